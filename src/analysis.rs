@@ -0,0 +1,579 @@
+//! Musical analysis of existing notes
+//!
+//! Estimates properties such as the key, computes summary metrics, and diffs
+//! note events, so imported or generated MIDI can be inspected and
+//! validated.
+
+use crate::import::midi_pitch_to_name;
+use crate::midi::reader::{MidiReadError, file_tempo, parse_midi};
+use crate::midi::{Note, NoteSequence};
+use crate::preset::Key;
+use midly::{Format, MetaMessage, Smf, Timing, TrackEventKind};
+use serde::Serialize;
+
+/// Krumhansl-Kessler major key profile (tonic first)
+const MAJOR_PROFILE: [f64; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+
+/// Krumhansl-Kessler minor key profile (tonic first)
+const MINOR_PROFILE: [f64; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// Estimate the key of some notes.
+///
+/// Builds a pitch-class histogram weighted by note duration and correlates
+/// it with the major and minor profiles rotated to each [`Key`]'s root,
+/// returning the best match. Defaults to C when there are no notes.
+pub fn detect_key(notes: &[Note]) -> Key {
+    let mut histogram = [0.0; 12];
+    for note in notes {
+        histogram[(note.pitch % 12) as usize] += note.duration.max(0.0);
+    }
+    if histogram.iter().all(|&weight| weight == 0.0) {
+        return Key::C;
+    }
+
+    Key::ALL
+        .into_iter()
+        .map(|key| {
+            let profile = if key.is_minor() {
+                &MINOR_PROFILE
+            } else {
+                &MAJOR_PROFILE
+            };
+            let root = (key.root() % 12) as usize;
+            let rotated: Vec<f64> = (0..12).map(|pc| profile[(pc + 12 - root) % 12]).collect();
+            (key, correlation(&histogram, &rotated))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(key, _)| key)
+        .unwrap_or(Key::C)
+}
+
+/// Pearson correlation of two equal-length series
+fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+
+    let mut covariance = 0.0;
+    let mut var_a = 0.0;
+    let mut var_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+
+    if var_a == 0.0 || var_b == 0.0 {
+        0.0
+    } else {
+        covariance / (var_a * var_b).sqrt()
+    }
+}
+
+/// Summary metrics for a set of sequences (usually a decoded MIDI file)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MidiStats {
+    /// Total number of notes
+    pub notes: usize,
+
+    /// Lowest and highest pitch, if there are notes
+    pub pitch_range: Option<(u8, u8)>,
+
+    /// Mean note velocity
+    pub mean_velocity: f64,
+
+    /// Median note velocity (mean of the middle two for even counts)
+    pub median_velocity: f64,
+
+    /// Time from the start to the end of the last note, in seconds
+    pub duration_secs: f64,
+
+    /// Notes per second over the whole duration
+    pub notes_per_second: f64,
+
+    /// Most notes sounding at once
+    pub peak_polyphony: usize,
+
+    /// Per-sequence breakdown, in file order
+    pub channels: Vec<ChannelStats>,
+}
+
+/// Metrics for one sequence (one channel of one track)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChannelStats {
+    /// MIDI channel (0-15)
+    pub channel: u8,
+
+    /// GM program number
+    pub program: u8,
+
+    /// Number of notes
+    pub notes: usize,
+
+    /// Lowest and highest pitch, if there are notes
+    pub pitch_range: Option<(u8, u8)>,
+
+    /// Mean note velocity
+    pub mean_velocity: f64,
+}
+
+/// Compute [`MidiStats`] over sequences. Times follow each sequence's
+/// tempo and tempo changes, so polyphony and density are measured in
+/// seconds even when tracks disagree about tempo.
+pub fn midi_stats(sequences: &[NoteSequence]) -> MidiStats {
+    let all: Vec<&Note> = sequences.iter().flat_map(|s| &s.notes).collect();
+
+    let mut velocities: Vec<u8> = all.iter().map(|n| n.velocity).collect();
+    velocities.sort_unstable();
+    let median_velocity = match velocities.len() {
+        0 => 0.0,
+        len if len % 2 == 1 => velocities[len / 2] as f64,
+        len => (velocities[len / 2 - 1] as f64 + velocities[len / 2] as f64) / 2.0,
+    };
+
+    // Sweep note starts (+1) and ends (-1); ends sort first at equal times
+    let mut events: Vec<(f64, i32)> = Vec::with_capacity(all.len() * 2);
+    for seq in sequences {
+        for note in &seq.notes {
+            events.push((seq.beats_to_seconds(note.offset), 1));
+            events.push((seq.beats_to_seconds(note.offset + note.duration), -1));
+        }
+    }
+    events.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    let mut sounding = 0;
+    let mut peak_polyphony = 0;
+    for (_, change) in &events {
+        sounding += change;
+        peak_polyphony = peak_polyphony.max(sounding as usize);
+    }
+
+    let duration_secs = events.last().map(|e| e.0).unwrap_or(0.0);
+    MidiStats {
+        notes: all.len(),
+        pitch_range: pitch_range(all.iter().copied()),
+        mean_velocity: mean_velocity(all.iter().copied()),
+        median_velocity,
+        duration_secs,
+        notes_per_second: if duration_secs > 0.0 {
+            all.len() as f64 / duration_secs
+        } else {
+            0.0
+        },
+        peak_polyphony,
+        channels: sequences
+            .iter()
+            .map(|seq| ChannelStats {
+                channel: seq.channel,
+                program: seq.instrument,
+                notes: seq.notes.len(),
+                pitch_range: pitch_range(&seq.notes),
+                mean_velocity: mean_velocity(&seq.notes),
+            })
+            .collect(),
+    }
+}
+
+fn pitch_range<'a>(notes: impl IntoIterator<Item = &'a Note>) -> Option<(u8, u8)> {
+    notes.into_iter().fold(None, |range, note| match range {
+        None => Some((note.pitch, note.pitch)),
+        Some((low, high)) => Some((low.min(note.pitch), high.max(note.pitch))),
+    })
+}
+
+fn mean_velocity<'a>(notes: impl IntoIterator<Item = &'a Note>) -> f64 {
+    let (sum, count) = notes.into_iter().fold((0u64, 0u64), |(sum, count), n| {
+        (sum + n.velocity as u64, count + 1)
+    });
+    if count == 0 {
+        0.0
+    } else {
+        sum as f64 / count as f64
+    }
+}
+
+/// Structure of a MIDI file: header, tracks, and notes per channel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MidiInfo {
+    /// SMF format: "single", "parallel", or "sequential"
+    pub format: String,
+
+    /// Ticks per quarter note
+    pub ticks_per_beat: u16,
+
+    /// Starting tempo in BPM
+    pub tempo: u16,
+
+    /// True when the file has no tempo event and the default was assumed
+    pub tempo_assumed: bool,
+
+    /// Tracks in file order
+    pub tracks: Vec<TrackInfo>,
+
+    /// Total number of notes
+    pub notes: usize,
+
+    /// Per-sequence breakdown, in file order
+    pub channels: Vec<ChannelInfo>,
+}
+
+/// One track of a MIDI file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrackInfo {
+    /// Number of events, including meta events
+    pub events: usize,
+
+    /// Track name meta event, if any
+    pub name: Option<String>,
+}
+
+/// Notes on one channel of one track
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChannelInfo {
+    /// MIDI channel (0-15)
+    pub channel: u8,
+
+    /// Number of notes
+    pub notes: usize,
+
+    /// GM program number
+    pub program: u8,
+
+    /// Length in beats
+    pub beats: f64,
+}
+
+/// Inspect MIDI file bytes. Velocity-0 note-ons count as note-offs, as in
+/// [`parse_midi`].
+pub fn midi_info(bytes: &[u8]) -> Result<MidiInfo, MidiReadError> {
+    let smf = Smf::parse(bytes).map_err(|e| MidiReadError::Parse(e.to_string()))?;
+    let ticks_per_beat = match smf.header.timing {
+        Timing::Metrical(tpb) => tpb.as_int(),
+        Timing::Timecode(..) => return Err(MidiReadError::UnsupportedTiming),
+    };
+    let format = match smf.header.format {
+        Format::SingleTrack => "single",
+        Format::Parallel => "parallel",
+        Format::Sequential => "sequential",
+    };
+    let tempo = file_tempo(&smf);
+    let tracks = smf
+        .tracks
+        .iter()
+        .map(|track| TrackInfo {
+            events: track.len(),
+            name: track.iter().find_map(|e| match e.kind {
+                TrackEventKind::Meta(MetaMessage::TrackName(name)) => {
+                    Some(String::from_utf8_lossy(name).into_owned())
+                }
+                _ => None,
+            }),
+        })
+        .collect();
+
+    let sequences = parse_midi(bytes)?;
+    Ok(MidiInfo {
+        format: format.to_string(),
+        ticks_per_beat,
+        tempo: tempo.bpm,
+        tempo_assumed: tempo.assumed,
+        tracks,
+        notes: sequences.iter().map(|s| s.notes.len()).sum(),
+        channels: sequences
+            .iter()
+            .map(|seq| ChannelInfo {
+                channel: seq.channel,
+                notes: seq.notes.len(),
+                program: seq.instrument,
+                beats: seq.duration_beats(),
+            })
+            .collect(),
+    })
+}
+
+/// A note with its channel, as compared by [`compare_notes`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct NoteEvent {
+    /// MIDI channel (0-15)
+    pub channel: u8,
+
+    /// Start in beats
+    pub offset: f64,
+
+    /// MIDI pitch
+    pub pitch: u8,
+
+    /// Length in beats
+    pub duration: f64,
+
+    /// Velocity (1-127)
+    pub velocity: u8,
+}
+
+impl std::fmt::Display for NoteEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "beat {:.3} ch {} {} ({}) for {:.3} vel {}",
+            self.offset,
+            self.channel,
+            midi_pitch_to_name(self.pitch),
+            self.pitch,
+            self.duration,
+            self.velocity
+        )
+    }
+}
+
+/// A position where two event lists disagree; `None` means that side ran out
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NoteDifference {
+    /// Index into the time-ordered event lists
+    pub index: usize,
+
+    /// Event in the first file
+    pub left: Option<NoteEvent>,
+
+    /// Event in the second file
+    pub right: Option<NoteEvent>,
+}
+
+impl NoteDifference {
+    /// Which fields differ, e.g. "pitch 60 -> 62"
+    pub fn changes(&self) -> Vec<String> {
+        let (Some(a), Some(b)) = (self.left, self.right) else {
+            return vec![
+                if self.left.is_some() {
+                    "missing in second"
+                } else {
+                    "missing in first"
+                }
+                .to_string(),
+            ];
+        };
+
+        let mut changes = Vec::new();
+        if a.channel != b.channel {
+            changes.push(format!("channel {} -> {}", a.channel, b.channel));
+        }
+        if a.offset != b.offset {
+            changes.push(format!("offset {:.3} -> {:.3}", a.offset, b.offset));
+        }
+        if a.pitch != b.pitch {
+            changes.push(format!("pitch {} -> {}", a.pitch, b.pitch));
+        }
+        if a.duration != b.duration {
+            changes.push(format!("duration {:.3} -> {:.3}", a.duration, b.duration));
+        }
+        if a.velocity != b.velocity {
+            changes.push(format!("velocity {} -> {}", a.velocity, b.velocity));
+        }
+        changes
+    }
+}
+
+/// All notes as events, ordered by offset, then channel, then pitch
+pub fn note_events(sequences: &[NoteSequence]) -> Vec<NoteEvent> {
+    let mut events: Vec<NoteEvent> = sequences
+        .iter()
+        .flat_map(|seq| {
+            seq.notes.iter().map(|n| NoteEvent {
+                channel: seq.channel,
+                offset: n.offset,
+                pitch: n.pitch,
+                duration: n.duration,
+                velocity: n.velocity,
+            })
+        })
+        .collect();
+    events.sort_by(|a, b| {
+        a.offset
+            .total_cmp(&b.offset)
+            .then(a.channel.cmp(&b.channel))
+            .then(a.pitch.cmp(&b.pitch))
+    });
+    events
+}
+
+/// Compare the note events of two sets of sequences position by position.
+/// An empty result means the notes are identical (track layout, programs
+/// and tempo are not compared).
+pub fn compare_notes(left: &[NoteSequence], right: &[NoteSequence]) -> Vec<NoteDifference> {
+    let left = note_events(left);
+    let right = note_events(right);
+    (0..left.len().max(right.len()))
+        .filter_map(|index| {
+            let (a, b) = (left.get(index).copied(), right.get(index).copied());
+            (a != b).then_some(NoteDifference {
+                index,
+                left: a,
+                right: b,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn melody(pitches: &[(u8, f64)]) -> Vec<Note> {
+        let mut offset = 0.0;
+        pitches
+            .iter()
+            .map(|&(pitch, duration)| {
+                let note = Note::new(pitch, duration, 80, offset);
+                offset += duration;
+                note
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_a_minor() {
+        // A natural minor, leaning on the tonic and fifth
+        let notes = melody(&[
+            (69, 2.0),
+            (71, 0.5),
+            (72, 1.0),
+            (74, 0.5),
+            (76, 2.0),
+            (77, 0.5),
+            (76, 0.5),
+            (74, 0.5),
+            (72, 1.0),
+            (71, 0.5),
+            (69, 2.0),
+            (64, 1.0),
+            (69, 2.0),
+        ]);
+        assert_eq!(detect_key(&notes), Key::Am);
+    }
+
+    #[test]
+    fn test_detect_major_keys() {
+        let c_major = melody(&[
+            (60, 2.0),
+            (62, 0.5),
+            (64, 1.0),
+            (65, 0.5),
+            (67, 2.0),
+            (69, 0.5),
+            (71, 0.5),
+            (72, 2.0),
+        ]);
+        assert_eq!(detect_key(&c_major), Key::C);
+
+        let transposed: Vec<Note> = c_major
+            .iter()
+            .map(|n| n.transposed(7).with_velocity(80))
+            .collect();
+        assert_eq!(detect_key(&transposed), Key::G);
+    }
+
+    #[test]
+    fn test_midi_stats() {
+        // At 120 BPM a beat is half a second
+        let melody = NoteSequence::new(melody(&[(60, 1.0), (64, 1.0), (67, 2.0)]), 0, 120);
+        let mut bass = NoteSequence::new(vec![Note::new(36, 4.0, 50, 0.0)], 32, 120);
+        bass.channel = 1;
+
+        let stats = midi_stats(&[melody, bass]);
+        assert_eq!(stats.notes, 4);
+        assert_eq!(stats.pitch_range, Some((36, 67)));
+        assert_eq!(stats.mean_velocity, 72.5);
+        assert_eq!(stats.median_velocity, 80.0);
+        assert_eq!(stats.duration_secs, 2.0);
+        assert_eq!(stats.notes_per_second, 2.0);
+        assert_eq!(stats.peak_polyphony, 2);
+        assert_eq!(stats.channels.len(), 2);
+        assert_eq!(stats.channels[1].pitch_range, Some((36, 36)));
+        assert_eq!(stats.channels[1].program, 32);
+    }
+
+    #[test]
+    fn test_midi_stats_back_to_back_notes_do_not_overlap() {
+        let stats = midi_stats(&[NoteSequence::new(
+            melody(&[(60, 1.0), (62, 1.0), (64, 1.0)]),
+            0,
+            60,
+        )]);
+        assert_eq!(stats.peak_polyphony, 1);
+        assert_eq!(stats.duration_secs, 3.0);
+    }
+
+    #[test]
+    fn test_midi_stats_empty() {
+        let stats = midi_stats(&[]);
+        assert_eq!(stats.notes, 0);
+        assert_eq!(stats.pitch_range, None);
+        assert_eq!(stats.notes_per_second, 0.0);
+    }
+
+    #[test]
+    fn test_midi_info() {
+        let melody =
+            NoteSequence::new(melody(&[(60, 1.0), (64, 1.0), (67, 2.0)]), 0, 90).named("Lead");
+        let mut bass = NoteSequence::new(vec![Note::new(36, 4.0, 50, 0.0)], 32, 90);
+        bass.channel = 1;
+        let bytes = crate::midi_bytes(&[melody, bass]).unwrap();
+
+        let info = midi_info(&bytes).unwrap();
+        assert_eq!(info.format, "parallel");
+        assert_eq!((info.tempo, info.tempo_assumed), (90, false));
+        // A tempo track, then one track per sequence
+        assert_eq!(info.tracks.len(), 3);
+        assert_eq!(info.tracks[1].name.as_deref(), Some("Lead"));
+        assert_eq!(info.notes, 4);
+        assert_eq!(info.channels.len(), 2);
+        assert_eq!(
+            (info.channels[1].channel, info.channels[1].program),
+            (1, 32)
+        );
+        assert_eq!(info.channels[0].beats, 4.0);
+
+        assert!(matches!(
+            midi_info(b"not midi"),
+            Err(MidiReadError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_compare_identical() {
+        let seq = NoteSequence::new(melody(&[(60, 1.0), (64, 1.0), (67, 2.0)]), 0, 120);
+        assert!(compare_notes(std::slice::from_ref(&seq), std::slice::from_ref(&seq)).is_empty());
+    }
+
+    #[test]
+    fn test_compare_transposed() {
+        let original = NoteSequence::new(melody(&[(60, 1.0), (64, 1.0), (67, 2.0)]), 0, 120);
+        let mut transposed = original.clone();
+        for note in &mut transposed.notes {
+            note.pitch += 2;
+        }
+
+        let diffs = compare_notes(&[original], &[transposed]);
+        assert_eq!(diffs.len(), 3);
+        assert_eq!(diffs[0].index, 0);
+        assert_eq!(diffs[0].changes(), ["pitch 60 -> 62"]);
+        assert_eq!(diffs[2].changes(), ["pitch 67 -> 69"]);
+    }
+
+    #[test]
+    fn test_compare_extra_note() {
+        let short = NoteSequence::new(melody(&[(60, 1.0)]), 0, 120);
+        let long = NoteSequence::new(melody(&[(60, 1.0), (62, 1.0)]), 0, 120);
+        let diffs = compare_notes(&[short], &[long]);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].left, None);
+        assert_eq!(diffs[0].changes(), ["missing in first"]);
+    }
+
+    #[test]
+    fn test_detect_key_without_notes() {
+        assert_eq!(detect_key(&[]), Key::C);
+    }
+}
@@ -0,0 +1,108 @@
+//! Crossfade stitching
+//!
+//! Joins two clips end to start, overlapping the tail of the first with the
+//! head of the second. The fade is equal-power (cosine/sine gains), so
+//! uncorrelated material keeps a steady level through the overlap.
+
+use super::{AudioError, WavData};
+
+/// Join `b` onto the end of `a`, overlapping them by `seconds`
+///
+/// Both clips must share a channel count and sample rate. The overlap is
+/// shortened to the length of the shorter clip; the result uses `a`'s format
+/// and is `seconds` shorter than the two clips back to back.
+pub fn crossfade(a: &WavData, b: &WavData, seconds: f64) -> Result<WavData, AudioError> {
+    if a.spec.channels != b.spec.channels || a.spec.sample_rate != b.spec.sample_rate {
+        return Err(AudioError::Stitch(format!(
+            "clips differ in format ({} ch at {} Hz vs {} ch at {} Hz)",
+            a.spec.channels, a.spec.sample_rate, b.spec.channels, b.spec.sample_rate
+        )));
+    }
+
+    let channels = a.spec.channels.max(1) as usize;
+    let (a_frames, b_frames) = (a.samples.len() / channels, b.samples.len() / channels);
+    let overlap = ((seconds.max(0.0) * a.spec.sample_rate as f64).round() as usize)
+        .min(a_frames)
+        .min(b_frames);
+    let start = (a_frames - overlap) * channels;
+
+    let mut samples = a.samples[..start].to_vec();
+    samples.reserve(b.samples.len());
+    for frame in 0..overlap {
+        let t = frame as f64 / overlap as f64 * std::f64::consts::FRAC_PI_2;
+        let (fade_out, fade_in) = (t.cos() as f32, t.sin() as f32);
+        for c in 0..channels {
+            let i = frame * channels + c;
+            samples.push(a.samples[start + i] * fade_out + b.samples[i] * fade_in);
+        }
+    }
+    samples.extend_from_slice(&b.samples[overlap * channels..]);
+
+    Ok(WavData {
+        spec: a.spec,
+        samples,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::{SampleFormat, WavSpec};
+
+    fn clip(channels: u16, samples: Vec<f32>) -> WavData {
+        WavData {
+            spec: WavSpec {
+                channels,
+                sample_rate: 1000,
+                bits_per_sample: 32,
+                sample_format: SampleFormat::Float,
+            },
+            samples,
+        }
+    }
+
+    #[test]
+    fn test_overlap_length_and_continuity() {
+        let a = clip(1, vec![0.5; 3000]);
+        let b = clip(1, vec![-0.5; 2000]);
+        let joined = crossfade(&a, &b, 1.0).unwrap();
+
+        // One second at 1 kHz overlaps 1000 samples
+        assert_eq!(joined.samples.len(), 3000 + 2000 - 1000);
+        assert_eq!(joined.samples[..2000], [0.5; 2000]);
+        assert_eq!(joined.samples[3000..], [-0.5; 1000]);
+
+        // No jump anywhere, including both edges of the fade
+        let max_step = joined
+            .samples
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0.0, f32::max);
+        assert!(max_step < 0.005, "{max_step}");
+    }
+
+    #[test]
+    fn test_equal_power_midpoint_and_channels() {
+        let a = clip(2, vec![1.0; 40]);
+        let b = clip(2, vec![1.0; 40]);
+        let joined = crossfade(&a, &b, 0.01).unwrap();
+        assert_eq!(joined.samples.len(), 60);
+        // Half way through, both gains are 1/sqrt(2) on both channels
+        let mid = &joined.samples[20 + 10..20 + 12];
+        assert!(
+            mid.iter()
+                .all(|&s| (s - std::f32::consts::SQRT_2).abs() < 1e-6),
+            "{mid:?}"
+        );
+    }
+
+    #[test]
+    fn test_overlap_capped_and_formats_checked() {
+        let joined = crossfade(&clip(1, vec![0.5; 10]), &clip(1, vec![0.5; 20]), 5.0).unwrap();
+        assert_eq!(joined.samples.len(), 20);
+        assert!(matches!(
+            crossfade(&clip(1, vec![]), &clip(2, vec![]), 1.0),
+            Err(AudioError::Stitch(_))
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+//! Loudness measurement and normalization (EBU R128)
+//!
+//! Integrated loudness follows ITU-R BS.1770: each channel is K-weighted,
+//! mean-square power is taken over 400 ms blocks overlapping by 75%, and
+//! blocks are gated at -70 LUFS and then 10 LU below the ungated mean.
+
+use super::{AudioError, WavData, peak, read_wav, write_wav};
+use std::path::Path;
+
+/// Blocks quieter than this are ignored entirely
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+/// Blocks more than this far below the mean of the louder ones are ignored
+const RELATIVE_GATE_LU: f64 = 10.0;
+
+/// Gating block length in seconds
+const BLOCK_SECS: f64 = 0.4;
+
+/// Gating blocks start this often (75% overlap)
+const BLOCK_STEP_SECS: f64 = 0.1;
+
+/// Second-order IIR filter (direct form I)
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+}
+
+impl Biquad {
+    fn run(&self, samples: impl Iterator<Item = f64>) -> impl Iterator<Item = f64> {
+        let Self { b, a } = *self;
+        let (mut x1, mut x2, mut y1, mut y2) = (0.0, 0.0, 0.0, 0.0);
+        samples.map(move |x| {
+            let y = b[0] * x + b[1] * x1 + b[2] * x2 - a[0] * y1 - a[1] * y2;
+            (x2, x1, y2, y1) = (x1, x, y1, y);
+            y
+        })
+    }
+}
+
+/// The two K-weighting stages for a sample rate: a high shelf that models
+/// the head, then a high-pass that discounts low frequencies
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    };
+
+    [shelf, high_pass]
+}
+
+/// Loudness of a mean-square power summed over channels
+fn lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// Integrated loudness in LUFS, or `None` for silence
+///
+/// All channels are weighted equally (no surround weighting). Clips shorter
+/// than one gating block are measured as a single block.
+pub fn integrated_loudness(data: &WavData) -> Option<f64> {
+    let channels = data.spec.channels.max(1) as usize;
+    let frames = data.samples.len() / channels;
+    if frames == 0 {
+        return None;
+    }
+
+    // Squared K-weighted samples, per channel
+    let [shelf, high_pass] = k_weighting(data.spec.sample_rate);
+    let squared: Vec<Vec<f64>> = (0..channels)
+        .map(|c| {
+            let channel = data
+                .samples
+                .iter()
+                .skip(c)
+                .step_by(channels)
+                .map(|&s| s as f64);
+            high_pass.run(shelf.run(channel)).map(|y| y * y).collect()
+        })
+        .collect();
+
+    let rate = data.spec.sample_rate as f64;
+    let block = ((BLOCK_SECS * rate).round() as usize).clamp(1, frames);
+    let step = ((BLOCK_STEP_SECS * rate).round() as usize).max(1);
+    let blocks: Vec<f64> = (0..=frames - block)
+        .step_by(step)
+        .map(|start| {
+            squared
+                .iter()
+                .map(|ch| ch[start..start + block].iter().sum::<f64>() / block as f64)
+                .sum()
+        })
+        .collect();
+
+    let mean = |threshold: f64| {
+        let gated: Vec<f64> = blocks
+            .iter()
+            .copied()
+            .filter(|&p| p > 0.0 && lufs(p) > threshold)
+            .collect();
+        (!gated.is_empty()).then(|| gated.iter().sum::<f64>() / gated.len() as f64)
+    };
+    let ungated = mean(ABSOLUTE_GATE_LUFS)?;
+    mean(lufs(ungated) - RELATIVE_GATE_LU).map(lufs)
+}
+
+/// Scale samples toward `target_lufs` integrated loudness
+///
+/// The gain is capped so the peak stays at or below full scale, so quiet
+/// material with loud transients may land under the target. Returns the
+/// applied gain, or `None` if the input is silent and was not changed.
+pub fn normalize_loudness(data: &mut WavData, target_lufs: f64) -> Option<f32> {
+    let measured = integrated_loudness(data)?;
+    let gain = (10f64.powf((target_lufs - measured) / 20.0) as f32).min(1.0 / peak(&data.samples));
+    for sample in data.samples.iter_mut() {
+        *sample *= gain;
+    }
+    Some(gain)
+}
+
+/// Loudness-normalize a WAV file in place (see [`normalize_loudness`])
+pub fn normalize_loudness_wav(path: &Path, target_lufs: f64) -> Result<Option<f32>, AudioError> {
+    let mut data = read_wav(path)?;
+    let gain = normalize_loudness(&mut data, target_lufs);
+    if gain.is_some() {
+        write_wav(path, &data)?;
+    }
+    Ok(gain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::{SampleFormat, WavSpec};
+
+    fn sine(amplitude: f32, frequency: f32, secs: f32) -> WavData {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 48000,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let len = (secs * 48000.0) as usize;
+        let samples = (0..len)
+            .map(|i| amplitude * (i as f32 * frequency * std::f32::consts::TAU / 48000.0).sin())
+            .collect();
+        WavData { spec, samples }
+    }
+
+    #[test]
+    fn test_full_scale_1khz_sine_reads_minus_3_lufs() {
+        // BS.1770 reference: a 0 dBFS 1 kHz sine in one channel is -3.01 LUFS
+        let loudness = integrated_loudness(&sine(1.0, 1000.0, 3.0)).unwrap();
+        assert!((loudness + 3.01).abs() < 0.05, "{loudness}");
+    }
+
+    #[test]
+    fn test_normalize_reaches_target_loudness() {
+        let mut data = sine(0.05, 440.0, 3.0);
+        let gain = normalize_loudness(&mut data, -14.0).unwrap();
+        assert!(gain > 1.0);
+        let loudness = integrated_loudness(&data).unwrap();
+        assert!((loudness + 14.0).abs() < 0.1, "{loudness}");
+    }
+
+    #[test]
+    fn test_gain_capped_at_full_scale_and_silence_ignored() {
+        let mut data = sine(0.5, 440.0, 1.0);
+        normalize_loudness(&mut data, 0.0).unwrap();
+        assert!(peak(&data.samples) <= 1.0);
+
+        let mut silent = sine(0.0, 440.0, 1.0);
+        assert_eq!(normalize_loudness(&mut silent, -14.0), None);
+    }
+}
@@ -0,0 +1,94 @@
+//! Stem mixing
+//!
+//! Stems rendered separately (for example, each layer with its own
+//! SoundFont) are summed sample by sample into one buffer.
+
+use super::{AudioError, WavData};
+
+/// Gain that keeps a sum of `stems` full-scale stems from clipping
+pub fn mix_gain(stems: usize) -> f32 {
+    1.0 / stems.max(1) as f32
+}
+
+/// Sum stems into one buffer as long as the longest, scaled by `gain`
+///
+/// All stems must share a channel count and sample rate; shorter stems are
+/// treated as silent past their end. The result uses the first stem's format.
+pub fn mix(stems: &[WavData], gain: f32) -> Result<WavData, AudioError> {
+    let Some(first) = stems.first() else {
+        return Err(AudioError::Mix("no stems to mix".to_string()));
+    };
+    if let Some(other) = stems.iter().find(|s| {
+        s.spec.channels != first.spec.channels || s.spec.sample_rate != first.spec.sample_rate
+    }) {
+        return Err(AudioError::Mix(format!(
+            "stems differ in format ({} ch at {} Hz vs {} ch at {} Hz)",
+            first.spec.channels,
+            first.spec.sample_rate,
+            other.spec.channels,
+            other.spec.sample_rate
+        )));
+    }
+
+    let len = stems.iter().map(|s| s.samples.len()).max().unwrap_or(0);
+    let mut samples = vec![0.0f32; len];
+    for stem in stems {
+        for (mixed, &sample) in samples.iter_mut().zip(&stem.samples) {
+            *mixed += sample * gain;
+        }
+    }
+
+    Ok(WavData {
+        spec: first.spec,
+        samples,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::peak;
+    use hound::{SampleFormat, WavSpec};
+
+    fn stem(samples: Vec<f32>) -> WavData {
+        WavData {
+            spec: WavSpec {
+                channels: 2,
+                sample_rate: 44100,
+                bits_per_sample: 16,
+                sample_format: SampleFormat::Int,
+            },
+            samples,
+        }
+    }
+
+    #[test]
+    fn test_mix_length_is_longest_stem() {
+        let mixed = mix(&[stem(vec![0.5; 4]), stem(vec![0.25; 10])], 1.0).unwrap();
+        assert_eq!(mixed.samples.len(), 10);
+        assert_eq!(mixed.samples[..4], [0.75; 4]);
+        assert_eq!(mixed.samples[4..], [0.25; 6]);
+        assert_eq!(mixed.spec, stem(Vec::new()).spec);
+    }
+
+    #[test]
+    fn test_two_full_scale_stems_do_not_clip() {
+        let a = stem(vec![1.0, -1.0, 0.5, -0.75]);
+        let b = stem(vec![1.0, -1.0, 0.25, 0.75]);
+        let mixed = mix(&[a, b], mix_gain(2)).unwrap();
+
+        assert!(peak(&mixed.samples) <= 1.0);
+        assert_eq!(mixed.samples, [1.0, -1.0, 0.375, 0.0]);
+    }
+
+    #[test]
+    fn test_mismatched_stems_rejected() {
+        let mut mono = stem(vec![0.0; 4]);
+        mono.spec.channels = 1;
+        assert!(matches!(
+            mix(&[stem(vec![0.0; 4]), mono], 1.0),
+            Err(AudioError::Mix(_))
+        ));
+        assert!(matches!(mix(&[], 1.0), Err(AudioError::Mix(_))));
+    }
+}
@@ -0,0 +1,102 @@
+//! Audio post-processing for rendered WAV files
+//!
+//! FluidSynth output is read back as floating-point samples, processed in
+//! Rust, and written with the original format. Files can be normalized by
+//! peak or by loudness (LUFS) and trimmed with a fade-out without ffmpeg.
+//! Separately rendered stems can be mixed, clips can be stitched together
+//! with a crossfade, and a small built-in synth can also render sequences
+//! directly.
+
+mod crossfade;
+mod loudness;
+mod mix;
+mod normalize;
+mod synth;
+mod trim;
+
+pub use crossfade::crossfade;
+pub use loudness::{integrated_loudness, normalize_loudness, normalize_loudness_wav};
+pub use mix::{mix, mix_gain};
+pub use normalize::{normalize_samples, normalize_wav, peak};
+pub use synth::{Synth, TestSynth, midi_to_frequency};
+pub use trim::{FADE_OUT_SECS, trim_samples, trim_wav};
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use std::path::Path;
+use thiserror::Error;
+
+/// Errors that can occur when processing audio files
+#[derive(Debug, Error)]
+pub enum AudioError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("WAV error: {0}")]
+    Wav(#[from] hound::Error),
+
+    #[error("Unsupported WAV format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Cannot mix stems: {0}")]
+    Mix(String),
+
+    #[error("Cannot stitch clips: {0}")]
+    Stitch(String),
+}
+
+/// Interleaved samples in the range -1.0..=1.0 with their WAV format
+#[derive(Debug, Clone)]
+pub struct WavData {
+    /// Format of the source file (reused when writing)
+    pub spec: WavSpec,
+
+    /// Interleaved samples
+    pub samples: Vec<f32>,
+}
+
+/// Read a WAV file into floating-point samples
+pub fn read_wav(path: &Path) -> Result<WavData, AudioError> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+
+    let samples = match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Float, 32) => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+        (SampleFormat::Int, bits @ 8..=32) => {
+            let scale = (1i64 << (bits - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|v| v as f32 / scale))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        (format, bits) => {
+            return Err(AudioError::UnsupportedFormat(format!(
+                "{format:?} {bits}-bit"
+            )));
+        }
+    };
+
+    Ok(WavData { spec, samples })
+}
+
+/// Write floating-point samples as a WAV file, clamping to avoid wraparound
+pub fn write_wav(path: &Path, data: &WavData) -> Result<(), AudioError> {
+    let spec = data.spec;
+    let mut writer = WavWriter::create(path, spec)?;
+
+    match spec.sample_format {
+        SampleFormat::Float => {
+            for &sample in &data.samples {
+                writer.write_sample(sample.clamp(-1.0, 1.0))?;
+            }
+        }
+        SampleFormat::Int => {
+            let max = ((1i64 << (spec.bits_per_sample - 1)) - 1) as f32;
+            for &sample in &data.samples {
+                writer.write_sample((sample.clamp(-1.0, 1.0) * max).round() as i32)?;
+            }
+        }
+    }
+
+    writer.finalize()?;
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! Peak normalization
+//!
+//! Scales a file so its loudest sample sits at a target level in dBFS.
+
+use super::{AudioError, read_wav, write_wav};
+use std::path::Path;
+
+/// Peaks below this level (-80 dBFS) are treated as silence and left alone
+const SILENCE_THRESHOLD: f32 = 1e-4;
+
+/// Largest absolute sample value
+pub fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0f32, |max, s| max.max(s.abs()))
+}
+
+/// Scale samples so the peak reaches `target_dbfs`
+///
+/// Targets above 0 dBFS are capped at 0 dBFS to avoid clipping. Returns the
+/// applied gain, or `None` if the input is near-silent and was not changed.
+pub fn normalize_samples(samples: &mut [f32], target_dbfs: f64) -> Option<f32> {
+    let current = peak(samples);
+    if current < SILENCE_THRESHOLD {
+        return None;
+    }
+
+    let target = 10f64.powf(target_dbfs.min(0.0) / 20.0) as f32;
+    let gain = target / current;
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+    Some(gain)
+}
+
+/// Peak-normalize a WAV file in place
+pub fn normalize_wav(path: &Path, target_dbfs: f64) -> Result<Option<f32>, AudioError> {
+    let mut data = read_wav(path)?;
+    let gain = normalize_samples(&mut data.samples, target_dbfs);
+    if gain.is_some() {
+        write_wav(path, &data)?;
+    }
+    Ok(gain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::WavData;
+    use hound::{SampleFormat, WavSpec};
+    use tempfile::tempdir;
+
+    fn spec_16bit() -> WavSpec {
+        WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        }
+    }
+
+    fn sine(amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (i as f32 * 440.0 * std::f32::consts::TAU / 44100.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_normalize_wav_reaches_target_peak() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("quiet.wav");
+        write_wav(
+            &path,
+            &WavData {
+                spec: spec_16bit(),
+                samples: sine(0.25, 4410),
+            },
+        )
+        .unwrap();
+
+        let gain = normalize_wav(&path, -1.0).unwrap();
+        assert!(gain.unwrap() > 1.0);
+
+        let data = read_wav(&path).unwrap();
+        let expected = 10f32.powf(-1.0 / 20.0);
+        assert!(
+            (peak(&data.samples) - expected).abs() < 1e-3,
+            "peak {}",
+            peak(&data.samples)
+        );
+    }
+
+    #[test]
+    fn test_normalize_caps_at_full_scale() {
+        let mut samples = sine(0.5, 1000);
+        normalize_samples(&mut samples, 6.0);
+        assert!((peak(&samples) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_normalize_leaves_silence_alone() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("silent.wav");
+        write_wav(
+            &path,
+            &WavData {
+                spec: spec_16bit(),
+                samples: vec![0.0; 1000],
+            },
+        )
+        .unwrap();
+
+        assert_eq!(normalize_wav(&path, -1.0).unwrap(), None);
+        assert_eq!(peak(&read_wav(&path).unwrap().samples), 0.0);
+    }
+}
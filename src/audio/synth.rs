@@ -0,0 +1,180 @@
+//! In-process synthesis
+//!
+//! [`Synth`] renders note sequences straight to samples without FluidSynth.
+//! [`TestSynth`] plays every note as a sine with a linear attack and release,
+//! so output is exactly reproducible and easy to reason about in tests.
+
+use super::WavData;
+use crate::midi::NoteSequence;
+use crate::midi::sequence::DRUM_CHANNEL;
+use hound::{SampleFormat, WavSpec};
+
+/// Renders note sequences to mono samples in the range -1.0..=1.0
+pub trait Synth {
+    /// Output sample rate in Hz
+    fn sample_rate(&self) -> u32;
+
+    /// Render sequences, following each one's tempo and tempo changes.
+    /// The buffer ends when the last note does.
+    fn render(&self, sequences: &[NoteSequence]) -> Vec<f32>;
+
+    /// Render to a mono 32-bit float [`WavData`], ready for `write_wav`
+    fn render_wav(&self, sequences: &[NoteSequence]) -> WavData {
+        WavData {
+            spec: WavSpec {
+                channels: 1,
+                sample_rate: self.sample_rate(),
+                bits_per_sample: 32,
+                sample_format: SampleFormat::Float,
+            },
+            samples: self.render(sequences),
+        }
+    }
+}
+
+/// Frequency of a MIDI pitch in equal temperament (A4 = 69 = 440 Hz)
+pub fn midi_to_frequency(pitch: u8) -> f64 {
+    440.0 * 2f64.powf((pitch as f64 - 69.0) / 12.0)
+}
+
+/// Deterministic sine synth for tests and previews
+///
+/// Each note is a sine at its pitch's frequency (detuned by its cents),
+/// scaled by velocity and `gain`, with linear ramps of `ramp_secs` at both
+/// ends (shortened for notes too brief to fit them). Drum-channel notes are skipped, since
+/// their pitches select sounds rather than frequencies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TestSynth {
+    /// Output sample rate in Hz
+    pub sample_rate: u32,
+
+    /// Attack and release time in seconds
+    pub ramp_secs: f64,
+
+    /// Level of a velocity-127 note
+    pub gain: f32,
+}
+
+impl Default for TestSynth {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100,
+            ramp_secs: 0.01,
+            gain: 0.25,
+        }
+    }
+}
+
+impl Synth for TestSynth {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn render(&self, sequences: &[NoteSequence]) -> Vec<f32> {
+        let rate = self.sample_rate as f64;
+        let to_sample = |seconds: f64| (seconds * rate).round().max(0.0) as usize;
+
+        let notes: Vec<(usize, usize, f64, f32)> = sequences
+            .iter()
+            .flat_map(|seq| {
+                let channel = seq.channel;
+                seq.notes
+                    .iter()
+                    .filter(move |n| n.channel.unwrap_or(channel) != DRUM_CHANNEL)
+                    .map(move |note| {
+                        let start = to_sample(seq.beats_to_seconds(note.offset));
+                        let end = to_sample(seq.beats_to_seconds(note.offset + note.duration));
+                        let amplitude = self.gain * note.velocity as f32 / 127.0;
+                        let frequency =
+                            midi_to_frequency(note.pitch) * 2f64.powf(note.cents as f64 / 1200.0);
+                        (start, end, frequency, amplitude)
+                    })
+            })
+            .collect();
+
+        let len = notes.iter().map(|n| n.1).max().unwrap_or(0);
+        let mut samples = vec![0.0f32; len];
+        for (start, end, frequency, amplitude) in notes {
+            let length = end.saturating_sub(start);
+            let ramp = to_sample(self.ramp_secs).min(length / 2).max(1) as f64;
+            let step = std::f64::consts::TAU * frequency / rate;
+
+            for (i, sample) in samples[start..end].iter_mut().enumerate() {
+                let envelope = (i as f64 / ramp).min((length - i) as f64 / ramp).min(1.0);
+                *sample += (amplitude as f64 * envelope * (step * i as f64).sin()) as f32;
+            }
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::Note;
+
+    /// Signal power at one frequency (Goertzel algorithm)
+    fn power_at(samples: &[f32], frequency: f64, sample_rate: u32) -> f64 {
+        let coeff = 2.0 * (std::f64::consts::TAU * frequency / sample_rate as f64).cos();
+        let (mut prev, mut prev2) = (0.0, 0.0);
+        for &sample in samples {
+            let current = sample as f64 + coeff * prev - prev2;
+            prev2 = prev;
+            prev = current;
+        }
+        prev2 * prev2 + prev * prev - coeff * prev * prev2
+    }
+
+    fn rms(samples: &[f32]) -> f64 {
+        (samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn test_one_second_a4() {
+        // One beat at 60 BPM is one second
+        let seq = NoteSequence::new(vec![Note::new(69, 1.0, 127, 0.0)], 0, 60);
+        let synth = TestSynth::default();
+        let samples = synth.render(&[seq]);
+
+        assert_eq!(samples.len(), 44100);
+        let a4 = power_at(&samples, 440.0, 44100);
+        assert!(a4 > 100.0 * power_at(&samples, 220.0, 44100));
+        assert!(a4 > 100.0 * power_at(&samples, 880.0, 44100));
+
+        // A full-scale sine has RMS amplitude / sqrt(2)
+        let expected = synth.gain as f64 / 2f64.sqrt();
+        assert!(
+            (rms(&samples) - expected).abs() < 0.01,
+            "rms {}",
+            rms(&samples)
+        );
+    }
+
+    #[test]
+    fn test_envelope_starts_and_ends_silent() {
+        let seq = NoteSequence::new(vec![Note::new(60, 0.5, 100, 0.5)], 0, 120);
+        let samples = TestSynth::default().render(&[seq]);
+
+        // Half a beat of silence at 120 BPM, then a quarter second of tone
+        assert_eq!(samples.len(), 22050);
+        assert!(samples[..11025].iter().all(|&s| s == 0.0));
+        assert!(samples[11025].abs() < 1e-3);
+        assert!(samples.last().unwrap().abs() < 0.05);
+    }
+
+    #[test]
+    fn test_drums_skipped_and_wav_spec() {
+        let mut drums = NoteSequence::new(vec![Note::new(36, 1.0, 100, 0.0)], 0, 120);
+        drums.channel = DRUM_CHANNEL;
+        let wav = TestSynth::default().render_wav(&[drums]);
+        assert!(wav.samples.is_empty());
+        assert_eq!(wav.spec.channels, 1);
+        assert_eq!(wav.spec.sample_rate, 44100);
+    }
+
+    #[test]
+    fn test_midi_to_frequency() {
+        assert_eq!(midi_to_frequency(69), 440.0);
+        assert!((midi_to_frequency(60) - 261.63).abs() < 0.01);
+    }
+}
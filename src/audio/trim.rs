@@ -0,0 +1,116 @@
+//! Trimming with a fade-out
+//!
+//! Cuts a rendered file to a target length and fades the end linearly to
+//! silence, so reverb tails don't run past the requested duration. Files
+//! shorter than the target are padded with silence. This is the fallback
+//! when ffmpeg isn't available.
+
+use super::{AudioError, WavData, read_wav, write_wav};
+use std::path::Path;
+
+/// Length of the fade-out at the end of a trimmed file, in seconds
+pub const FADE_OUT_SECS: f64 = 0.5;
+
+/// Cut or pad samples to exactly `duration` seconds and fade the last `fade`
+/// seconds out
+///
+/// Shorter input is padded with silence; any part of it that falls inside
+/// the fade window is still faded.
+pub fn trim_samples(data: &mut WavData, duration: f64, fade: f64) {
+    let channels = data.spec.channels.max(1) as usize;
+    let rate = data.spec.sample_rate as f64;
+    let end = (duration.max(0.0) * rate).round() as usize;
+    let fade_frames = ((fade.max(0.0) * rate).round() as usize).min(end);
+
+    data.samples.resize(end * channels, 0.0);
+    let fade_start = end - fade_frames;
+    for (frame, samples) in data
+        .samples
+        .chunks_mut(channels)
+        .enumerate()
+        .skip(fade_start)
+    {
+        let gain = (end - frame - 1) as f32 / fade_frames as f32;
+        for sample in samples {
+            *sample *= gain;
+        }
+    }
+}
+
+/// Trim a WAV file in place (see [`trim_samples`])
+pub fn trim_wav(path: &Path, duration: f64, fade: f64) -> Result<(), AudioError> {
+    let mut data = read_wav(path)?;
+    trim_samples(&mut data, duration, fade);
+    write_wav(path, &data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{Synth, TestSynth};
+    use crate::midi::{Note, NoteSequence};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_trim_generated_wav_to_two_seconds() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("long.wav");
+        // Four seconds of A4 at 60 BPM
+        let seq = NoteSequence::new(vec![Note::new(69, 4.0, 127, 0.0)], 0, 60);
+        let synth = TestSynth::default();
+        write_wav(&path, &synth.render_wav(&[seq])).unwrap();
+
+        trim_wav(&path, 2.0, FADE_OUT_SECS).unwrap();
+        let data = read_wav(&path).unwrap();
+        assert_eq!(data.samples.len(), 2 * synth.sample_rate as usize);
+
+        let level = |range: std::ops::Range<usize>| {
+            data.samples[range]
+                .iter()
+                .fold(0.0f32, |max, s| max.max(s.abs()))
+        };
+        let rate = synth.sample_rate as usize;
+        // Full level before the fade, about half way through it, silent at the end
+        assert!(level(rate..rate + 1000) > 0.24);
+        assert!((level(rate * 7 / 4 - 500..rate * 7 / 4 + 500) - 0.125).abs() < 0.01);
+        assert!(level(data.samples.len() - 100..data.samples.len()) < 0.001);
+        assert_eq!(*data.samples.last().unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_trim_fades_every_channel() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 10,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut data = WavData {
+            spec,
+            samples: vec![1.0; 40],
+        };
+        trim_samples(&mut data, 1.0, 0.5);
+        assert_eq!(data.samples.len(), 20);
+        assert_eq!(data.samples[..10], [1.0; 10]);
+        assert_eq!(
+            data.samples[10..],
+            [0.8, 0.8, 0.6, 0.6, 0.4, 0.4, 0.2, 0.2, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_padded_clip_includes_both_pads() {
+        // One second of A4 at 60 BPM, padded half a second before and after
+        let mut seq = NoteSequence::new(vec![Note::new(69, 1.0, 127, 0.0)], 0, 60);
+        crate::midi::sequence::pad_start(std::slice::from_mut(&mut seq), 0.5);
+        assert_eq!(seq.notes[0].offset, 0.5);
+
+        let synth = TestSynth::default();
+        let mut data = synth.render_wav(&[seq.clone()]);
+        trim_samples(&mut data, seq.duration_seconds() + 0.5, 0.0);
+        let rate = synth.sample_rate as usize;
+        assert_eq!(data.samples.len(), 2 * rate);
+        assert!(data.samples[..rate / 2].iter().all(|&s| s == 0.0));
+        assert!(data.samples[rate * 3 / 2..].iter().all(|&s| s == 0.0));
+    }
+}
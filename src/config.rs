@@ -0,0 +1,182 @@
+//! User configuration file
+//!
+//! `~/.config/midi-cli-rs/config.toml` (or the file given with `--config`)
+//! holds defaults for settings that would otherwise be passed on every
+//! invocation. Flags on the command line always win over the file.
+//!
+//! ```toml
+//! soundfont = "~/soundfonts/FluidR3_GM.sf2"
+//! fluidsynth_path = "/opt/homebrew/bin/fluidsynth"
+//! gain = 0.8
+//! sample_rate = 48000
+//! tempo = 100
+//! output_dir = "~/Music/renders"
+//! ```
+
+use crate::render::{GAIN_RANGE, RenderOptions, SAMPLE_RATE_RANGE};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Errors that can occur when loading a config file
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Cannot read config file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid config file {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+}
+
+/// Defaults read from the config file; every field is optional
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// SoundFont used when --soundfont is not given
+    pub soundfont: Option<PathBuf>,
+    /// FluidSynth binary, instead of searching PATH
+    pub fluidsynth_path: Option<PathBuf>,
+    /// FluidSynth output gain
+    pub gain: Option<f32>,
+    /// Output sample rate in Hz
+    pub sample_rate: Option<u32>,
+    /// Tempo in BPM when --tempo is not given
+    pub tempo: Option<u16>,
+    /// Directory for relative output paths
+    pub output_dir: Option<PathBuf>,
+}
+
+/// Default config file: `$XDG_CONFIG_HOME/midi-cli-rs/config.toml`, else
+/// `~/.config/midi-cli-rs/config.toml`
+pub fn config_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("midi-cli-rs").join("config.toml"))
+}
+
+/// Expand a leading `~/` to the home directory
+fn expand_home(path: PathBuf) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path,
+    }
+}
+
+impl Config {
+    /// Parse a config file's contents; `path` is only used in errors
+    pub fn parse(content: &str, path: &Path) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(content).map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        let invalid = |message: String| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message,
+        };
+        if let Some(gain) = config.gain.filter(|gain| !GAIN_RANGE.contains(gain)) {
+            return Err(invalid(format!("gain {gain} is outside 0-10")));
+        }
+        if let Some(rate) = config
+            .sample_rate
+            .filter(|rate| !SAMPLE_RATE_RANGE.contains(rate))
+        {
+            return Err(invalid(format!("sample_rate {rate} is outside 8000-96000")));
+        }
+        Ok(Self {
+            soundfont: config.soundfont.map(expand_home),
+            fluidsynth_path: config.fluidsynth_path.map(expand_home),
+            output_dir: config.output_dir.map(expand_home),
+            ..config
+        })
+    }
+
+    /// Load a config file, which must exist
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&content, path)
+    }
+
+    /// Load the default config file, or no defaults if there isn't one
+    pub fn load_default() -> Result<Self, ConfigError> {
+        match config_path() {
+            Some(path) if path.is_file() => Self::load(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Fill in render settings that weren't given on the command line
+    pub fn apply(&self, options: &mut RenderOptions) {
+        if options.soundfont.is_none() {
+            options.soundfont = self.soundfont.clone();
+        }
+        if options.fluidsynth.is_none() {
+            options.fluidsynth = self.fluidsynth_path.clone();
+        }
+        options.gain = options.gain.or(self.gain);
+        options.sample_rate = options.sample_rate.or(self.sample_rate);
+    }
+
+    /// Where an output path goes: relative paths land in `output_dir`
+    pub fn output_path(&self, output: PathBuf) -> PathBuf {
+        match &self.output_dir {
+            Some(dir) if output.is_relative() && output != Path::new("-") => dir.join(output),
+            _ => output,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(content: &str) -> Config {
+        Config::parse(content, Path::new("config.toml")).unwrap()
+    }
+
+    #[test]
+    fn test_config_gain_used_unless_given() {
+        let config = config("gain = 0.8\nsample_rate = 48000");
+
+        let mut options = RenderOptions::default();
+        config.apply(&mut options);
+        assert_eq!(options.gain(), 0.8);
+        assert_eq!(options.sample_rate(), 48000);
+
+        let mut options = RenderOptions {
+            gain: Some(0.5),
+            ..Default::default()
+        };
+        config.apply(&mut options);
+        assert_eq!(options.gain(), 0.5);
+    }
+
+    #[test]
+    fn test_output_dir_only_for_relative_paths() {
+        let config = config("output_dir = \"/renders\"");
+        assert_eq!(
+            config.output_path(PathBuf::from("intro.wav")),
+            Path::new("/renders/intro.wav")
+        );
+        assert_eq!(
+            config.output_path(PathBuf::from("/tmp/intro.wav")),
+            Path::new("/tmp/intro.wav")
+        );
+        assert_eq!(config.output_path(PathBuf::from("-")), Path::new("-"));
+    }
+
+    #[test]
+    fn test_unknown_field_and_bad_values_rejected() {
+        let err = Config::parse("gian = 0.8", Path::new("config.toml")).unwrap_err();
+        assert!(err.to_string().contains("config.toml"), "{err}");
+        assert!(Config::parse("gain = 20.0", Path::new("config.toml")).is_err());
+        assert!(Config::parse("sample_rate = 1000", Path::new("config.toml")).is_err());
+    }
+}
@@ -59,11 +59,9 @@ impl MelodyImporter for AbcParser {
                         // Reference number - marks start of tune
                         in_body = false;
                     }
-                    'T' => {
-                        // Title
-                        if melody.title.is_none() {
-                            melody.title = Some(value.to_string());
-                        }
+                    'T' if melody.title.is_none() => {
+                        // Title (the first one wins)
+                        melody.title = Some(value.to_string());
                     }
                     'M' => {
                         // Time signature (e.g., "4/4", "6/8", "C")
@@ -21,7 +21,7 @@ pub use abc::AbcParser;
 pub use error::ImportError;
 pub use musicxml::MusicXmlParser;
 
-use crate::midi::sequence::{resolve_instrument, NoteSequence};
+use crate::midi::sequence::{resolve_instrument, NoteSequence, TimeSignature};
 use crate::midi::Note;
 
 /// A single imported note with optional rest support
@@ -107,12 +107,8 @@ impl ImportedMelody {
             .notes
             .iter()
             .filter_map(|n| {
-                n.pitch.map(|pitch| Note {
-                    pitch,
-                    duration: n.duration,
-                    velocity: n.velocity,
-                    offset: n.offset,
-                })
+                n.pitch
+                    .map(|pitch| Note::new(pitch, n.duration, n.velocity, n.offset))
             })
             .collect();
 
@@ -120,7 +116,11 @@ impl ImportedMelody {
             return Err(ImportError::NoNotes);
         }
 
-        Ok(vec![NoteSequence::new(notes, instrument_num, final_tempo)])
+        let mut sequence = NoteSequence::new(notes, instrument_num, final_tempo);
+        let (numerator, denominator) = self.time_signature;
+        sequence.time_signature = TimeSignature::new(numerator, denominator);
+
+        Ok(vec![sequence])
     }
 
     /// Get total duration in beats
@@ -198,9 +198,9 @@ pub fn name_to_midi_pitch(name: &str) -> Option<u8> {
     let octave: i32 = octave_str.parse().ok()?;
 
     // Calculate MIDI pitch
-    let midi_pitch = (octave + 1) * 12 + base_pitch as i32 + pitch_offset as i32;
+    let midi_pitch = (octave + 1) * 12 + base_pitch + pitch_offset as i32;
 
-    if midi_pitch >= 0 && midi_pitch <= 127 {
+    if (0..=127).contains(&midi_pitch) {
         Some(midi_pitch as u8)
     } else {
         None
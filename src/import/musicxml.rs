@@ -69,20 +69,17 @@ impl MelodyImporter for MusicXmlParser {
         let mut key_fifths = 0i8; // positive = sharps, negative = flats
 
         // Get work title
-        if let Some(work) = root.children().find(|n| n.has_tag_name("work")) {
-            if let Some(title) = work.children().find(|n| n.has_tag_name("work-title")) {
-                melody.title = title.text().map(|s| s.to_string());
-            }
+        if let Some(work) = root.children().find(|n| n.has_tag_name("work"))
+            && let Some(title) = work.children().find(|n| n.has_tag_name("work-title"))
+        {
+            melody.title = title.text().map(|s| s.to_string());
         }
 
         // Also check movement-title
-        if melody.title.is_none() {
-            if let Some(movement) = root
-                .children()
-                .find(|n| n.has_tag_name("movement-title"))
-            {
-                melody.title = movement.text().map(|s| s.to_string());
-            }
+        if melody.title.is_none()
+            && let Some(movement) = root.children().find(|n| n.has_tag_name("movement-title"))
+        {
+            melody.title = movement.text().map(|s| s.to_string());
         }
 
         // Find first part
@@ -98,20 +95,17 @@ impl MelodyImporter for MusicXmlParser {
                     "attributes" => {
                         // Get divisions
                         if let Some(div) = element.children().find(|n| n.has_tag_name("divisions"))
+                            && let Some(text) = div.text()
                         {
-                            if let Some(text) = div.text() {
-                                divisions = text.parse().unwrap_or(1);
-                            }
+                            divisions = text.parse().unwrap_or(1);
                         }
 
                         // Get key signature
                         if let Some(key) = element.children().find(|n| n.has_tag_name("key")) {
-                            if let Some(fifths) =
-                                key.children().find(|n| n.has_tag_name("fifths"))
+                            if let Some(fifths) = key.children().find(|n| n.has_tag_name("fifths"))
+                                && let Some(text) = fifths.text()
                             {
-                                if let Some(text) = fifths.text() {
-                                    key_fifths = text.parse().unwrap_or(0);
-                                }
+                                key_fifths = text.parse().unwrap_or(0);
                             }
                             // Convert fifths to key name
                             melody.key = Some(fifths_to_key(key_fifths));
@@ -137,30 +131,21 @@ impl MelodyImporter for MusicXmlParser {
 
                     "direction" => {
                         // Check for tempo
-                        if let Some(sound) = element
-                            .descendants()
-                            .find(|n| n.has_tag_name("sound"))
+                        if let Some(sound) = element.descendants().find(|n| n.has_tag_name("sound"))
+                            && let Some(tempo) = sound.attribute("tempo")
                         {
-                            if let Some(tempo) = sound.attribute("tempo") {
-                                melody.tempo = tempo.parse().ok();
-                            }
+                            melody.tempo = tempo.parse().ok();
                         }
 
                         // Also check metronome
-                        if melody.tempo.is_none() {
-                            if let Some(metronome) = element
-                                .descendants()
-                                .find(|n| n.has_tag_name("metronome"))
-                            {
-                                if let Some(per_minute) = metronome
-                                    .children()
-                                    .find(|n| n.has_tag_name("per-minute"))
-                                {
-                                    if let Some(text) = per_minute.text() {
-                                        melody.tempo = text.parse().ok();
-                                    }
-                                }
-                            }
+                        if melody.tempo.is_none()
+                            && let Some(metronome) =
+                                element.descendants().find(|n| n.has_tag_name("metronome"))
+                            && let Some(per_minute) =
+                                metronome.children().find(|n| n.has_tag_name("per-minute"))
+                            && let Some(text) = per_minute.text()
+                        {
+                            melody.tempo = text.parse().ok();
                         }
                     }
 
@@ -287,12 +272,9 @@ fn extract_mxl(path: &Path) -> Result<String, ImportError> {
         zip::ZipArchive::new(file).map_err(|e| ImportError::Zip(e.to_string()))?;
 
     // First, try to find META-INF/container.xml which points to the main file
-    let root_file = if let Ok(container) = archive.by_name("META-INF/container.xml") {
-        let content: String = container
-            .bytes()
-            .filter_map(|b| b.ok())
-            .map(|b| b as char)
-            .collect();
+    let root_file = if let Ok(mut container) = archive.by_name("META-INF/container.xml") {
+        let mut content = String::new();
+        container.read_to_string(&mut content)?;
 
         // Parse container.xml to find rootfile
         if let Ok(doc) = roxmltree::Document::parse(&content) {
@@ -324,12 +306,13 @@ fn extract_mxl(path: &Path) -> Result<String, ImportError> {
 
     // Last resort: find any .xml file
     for i in 0..archive.len() {
-        if let Ok(mut file) = archive.by_index(i) {
-            if file.name().ends_with(".xml") && !file.name().contains("META-INF") {
-                let mut content = String::new();
-                file.read_to_string(&mut content)?;
-                return Ok(content);
-            }
+        if let Ok(mut file) = archive.by_index(i)
+            && file.name().ends_with(".xml")
+            && !file.name().contains("META-INF")
+        {
+            let mut content = String::new();
+            file.read_to_string(&mut content)?;
+            return Ok(content);
         }
     }
 
@@ -3,21 +3,50 @@
 //! This library provides programmatic MIDI generation with support for
 //! note sequences, instrument selection, and mood presets.
 
+pub mod analysis;
+pub mod audio;
+pub mod config;
 pub mod import;
 pub mod midi;
+pub mod playback;
 #[cfg(feature = "native-plugins")]
 pub mod plugin;
 pub mod preset;
+pub mod render;
 #[cfg(feature = "server")]
 pub mod server;
+pub mod soundfont;
 
+pub use analysis::{
+    ChannelInfo, ChannelStats, MidiInfo, MidiStats, NoteDifference, NoteEvent, TrackInfo,
+    compare_notes, detect_key, midi_info, midi_stats, note_events,
+};
+pub use midi::click::add_click;
+pub use midi::drums::{
+    DRUM_KITS, DRUM_NOTES, STANDARD_KIT, drum_kit_name, drum_note, resolve_drum_kit,
+};
+pub use midi::groove::{GROOVES, Groove};
 pub use midi::note::NoteError;
+pub use midi::reader::{
+    DEFAULT_TEMPO, FileTempo, MidiReadError, file_tempo, parse_midi, read_midi,
+};
 pub use midi::sequence::{
-    INSTRUMENT_MAP, JsonNoteInput, JsonSequenceInput, JsonTrackInput, resolve_instrument,
+    ArpPattern, Articulation, DRUM_CHANNEL, Envelope, INSTRUMENT_MAP, Instrument,
+    InstrumentCategory, JsonInputError, JsonNoteInput, JsonSequenceInput, JsonTempoChange,
+    JsonTrackInput, NoteSequenceBuilder, TempoChange, TimeSignature, instruments_in, overruns,
+    pad_start, resolve_instrument, suggest_instruments,
+};
+pub use midi::writer::{
+    Marker, MidiMetadata, MidiWriteError, PITCH_BEND_RANGE_CENTS, assign_channels,
+    assign_detune_channels, cents_to_bend, merge, midi_bytes, write_midi, write_midi_single,
+    write_midi_to, write_midi_to_with, write_midi_with, write_stems,
 };
-pub use midi::writer::{MidiWriteError, write_midi, write_midi_single};
 pub use midi::{Note, NoteSequence};
-pub use preset::{Key, Mood, MoodGenerator, PresetConfig, generate_mood};
+pub use preset::{
+    IntensityCurve, Key, Mood, MoodGenerator, MoodRegistry, Polyrhythm, PresetConfig,
+    PresetConfigError, PresetVariation, add_pickup, apply_intensity_curve, create_rng,
+    generate_mood,
+};
 
 // Re-export import types
 pub use import::{AbcParser, ImportError, ImportedMelody, ImportedNote, MusicXmlParser};
@@ -3,10 +3,21 @@
 //! Generate MIDI files and WAV audio from note specifications or mood presets.
 
 use chrono::{DateTime, Utc};
-use clap::{Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
 use midi_cli_rs::{
-    AbcParser, JsonSequenceInput, Key, Mood, MusicXmlParser, Note, NoteSequence, PresetConfig,
-    generate_mood, resolve_instrument, write_midi,
+    AbcParser, ArpPattern, Groove, IntensityCurve, Marker, MidiMetadata, NoteEvent, add_click, add_pickup, compare_notes, midi_info, midi_stats, note_events, overruns, pad_start, resolve_drum_kit, DRUM_CHANNEL, DRUM_KITS, DRUM_NOTES, STANDARD_KIT, JsonSequenceInput, Key, Mood, MoodRegistry, MusicXmlParser, Note, NoteSequence, Polyrhythm, PresetConfig,
+    TimeSignature, assign_channels, generate_mood, read_midi, resolve_instrument, suggest_instruments, instruments_in, InstrumentCategory, write_midi_to_with, write_midi_with, write_stems,
+};
+use midi_cli_rs::midi::output as midi_output;
+use midi_cli_rs::playback::{self, PlaybackBuffer};
+use midi_cli_rs::audio::{self, read_wav, write_wav};
+use midi_cli_rs::config::Config;
+use midi_cli_rs::soundfont;
+use midi_cli_rs::render::{find_soundfont, render_output, OutputFormat, RenderOptions, GAIN_RANGE};
+use midi_cli_rs::preset::{
+    expand_output_template, generate_seed_batch, generate_structure, markov_melody, parse_seed_range, progression_sequence,
+    seed_file_template, SongStructure,
 };
 #[cfg(feature = "server")]
 use midi_cli_rs::{lookup_plugin_mood, PluginMoodInfo};
@@ -14,7 +25,9 @@ use midi_cli_rs::{lookup_plugin_mood, PluginMoodInfo};
 use midi_cli_rs::server;
 #[cfg(feature = "native-plugins")]
 use midi_cli_rs::{generate_with_native_plugin, is_native_plugin_mood, list_native_plugin_moods};
-use std::io::{self, Read};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use std::process::{Command, ExitCode};
 
@@ -70,6 +83,7 @@ AI CODING AGENT INSTRUCTIONS:
   OUTPUT FORMATS:
     - .mid: MIDI file only (fast, no dependencies)
     - .wav: MIDI + audio render (requires FluidSynth)
+    - .mp3/.ogg: WAV render transcoded with ffmpeg
 
   COMBINING TRACKS (post-processing with external tools):
     ffmpeg -i track1.wav -i track2.wav -filter_complex amix=inputs=2 combined.wav
@@ -89,6 +103,22 @@ struct Cli {
     #[arg(short = 'V', long = "version", action = clap::ArgAction::SetTrue, global = true)]
     version: bool,
 
+    /// Download a SoundFont (FluidR3_GM by default) into the cache (~/.cache/midi-cli-rs) for rendering
+    #[arg(long, global = true)]
+    fetch_soundfont: bool,
+
+    /// SoundFont URL for --fetch-soundfont, instead of the default
+    #[arg(long, value_name = "URL", global = true, requires = "fetch_soundfont")]
+    soundfont_url: Option<String>,
+
+    /// Expected SHA-256 of the --fetch-soundfont download (required with --soundfont-url)
+    #[arg(long, value_name = "HEX", global = true, requires = "fetch_soundfont")]
+    soundfont_sha256: Option<String>,
+
+    /// Config file with default settings (default: ~/.config/midi-cli-rs/config.toml)
+    #[arg(long, value_name = "PATH", global = true)]
+    config: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -104,33 +134,16 @@ enum Commands {
         - C4:1:80 = Middle C, 1 beat, velocity 80\n  \
         - F#3:0.5:100@2 = F# octave 3, half beat, loud, starts at beat 2")]
     Generate {
-        /// Notes as "PITCH:DURATION:VELOCITY[@OFFSET],..." (e.g., "C4:1:80,E4:0.5:100@1")
-        #[arg(short, long)]
-        notes: Option<String>,
-
-        /// Read JSON note data from stdin (for complex multi-track sequences)
-        #[arg(short, long)]
-        json: bool,
-
-        /// Instrument name or GM program number 0-127 (use 'instruments' to list)
-        #[arg(short, long, default_value = "piano")]
-        instrument: String,
-
-        /// Tempo in BPM (beats per minute)
-        #[arg(short, long, default_value = "120")]
-        tempo: u16,
-
-        /// Output file path (.mid for MIDI only, .wav for audio)
-        #[arg(short, long)]
-        output: PathBuf,
+        #[command(flatten)]
+        args: GenerateArgs,
 
-        /// SoundFont file for WAV rendering (auto-detected if not specified)
-        #[arg(long)]
-        soundfont: Option<PathBuf>,
+        /// Output file path (.mid for MIDI only, .wav/.mp3/.ogg for audio, - for MIDI on stdout)
+        #[arg(short, long, required_unless_present = "stdout")]
+        output: Option<PathBuf>,
 
-        /// Show detailed generation info (parsed notes, instrument, tempo)
-        #[arg(short = 'v', long)]
-        verbose: bool,
+        /// Write MIDI to stdout for piping (same as -o -)
+        #[arg(long, conflicts_with = "output")]
+        stdout: bool,
     },
 
     /// Generate MIDI/audio using a mood preset (recommended for quick results)
@@ -146,41 +159,16 @@ enum Commands {
         --seed 0: Random seed (shown in output for replication)\n  \
         --seed N: Use specific seed N for exact reproduction")]
     Preset {
-        /// Mood preset: suspense, eerie, upbeat, calm, ambient, jazz
-        #[arg(short, long)]
-        mood: String,
-
-        /// Duration in seconds (typically 3-15 for intro/outro)
-        #[arg(short, long, default_value = "5")]
-        duration: f64,
-
-        /// Musical key: C, Cm, D, Dm, Eb, E, Em, F, Fm, G, Gm, A, Am, Bb, B, Bm
-        #[arg(short, long)]
-        key: Option<String>,
-
-        /// Intensity level 0-100 (affects layering and dynamics)
-        #[arg(long, default_value = "50")]
-        intensity: u8,
-
-        /// Tempo in BPM (beats per minute)
-        #[arg(short, long, default_value = "90")]
-        tempo: u16,
-
-        /// Random seed for reproducible output (default: 1, use 0 for random)
-        #[arg(short, long, default_value = "1")]
-        seed: i64,
-
-        /// Output file path (.mid for MIDI only, .wav for audio)
-        #[arg(short, long)]
-        output: PathBuf,
+        #[command(flatten)]
+        args: PresetArgs,
 
-        /// SoundFont file for WAV rendering (auto-detected if not specified)
-        #[arg(long)]
-        soundfont: Option<PathBuf>,
+        /// Output file path (.mid for MIDI only, .wav/.mp3/.ogg for audio, - for MIDI on stdout)
+        #[arg(short, long, required_unless_present_any = ["stdout", "dry_run"])]
+        output: Option<PathBuf>,
 
-        /// Show detailed generation info (layers, notes, instruments)
-        #[arg(short = 'v', long)]
-        verbose: bool,
+        /// Write MIDI to stdout for piping (same as -o -)
+        #[arg(long, conflicts_with_all = ["output", "dry_run"])]
+        stdout: bool,
     },
 
     /// Render existing MIDI file to WAV audio
@@ -189,17 +177,80 @@ enum Commands {
         #[arg(short, long)]
         input: PathBuf,
 
-        /// Output WAV file path
+        /// Output audio file path (.wav, .mp3 or .ogg)
         #[arg(short, long)]
         output: PathBuf,
 
         /// SoundFont file for rendering (auto-detected if not specified)
         #[arg(long)]
         soundfont: Option<PathBuf>,
+
+        /// SoundFont for one layer, numbered as with --stems (e.g. layer2=piano.sf2);
+        /// repeatable. Layers are rendered separately and mixed
+        #[arg(long, value_name = "LAYER=PATH", value_parser = parse_soundfont_map)]
+        soundfont_map: Vec<(usize, PathBuf)>,
+
+        /// Peak-normalize WAV output to a level in dBFS (default: -1)
+        #[arg(long, value_name = "DBFS", num_args = 0..=1, default_missing_value = "-1", allow_negative_numbers = true)]
+        normalize: Option<f64>,
+
+        /// Normalize WAV output to an integrated loudness in LUFS (e.g. -14)
+        #[arg(long, value_name = "LUFS", allow_negative_numbers = true, value_parser = parse_lufs, conflicts_with = "normalize")]
+        lufs: Option<f64>,
+
+        /// FluidSynth output gain, 0-10 (default: 1.0; lower it if dense output clips)
+        #[arg(long, value_parser = parse_gain)]
+        gain: Option<f32>,
+
+        /// Output sample rate in Hz, 8000-96000 (default: 44100)
+        #[arg(long, value_name = "HZ", value_parser = clap::value_parser!(u32).range(8000..=96000))]
+        sample_rate: Option<u32>,
+    },
+
+    /// Render and play through the default audio device (no file kept)
+    #[command(long_about = "Render and play through the default audio device.\n\n\
+        EXAMPLES:\n  \
+        midi-cli-rs play preset -m jazz -d 5\n  \
+        midi-cli-rs play generate --notes \"C4:1:80,E4:1:80,G4:2:80\"\n  \
+        midi-cli-rs play file intro.mid\n\n\
+        Audio is rendered to a temporary WAV with FluidSynth and removed afterwards.\n\
+        Requires a build with --features playback; use --no-play to render only (CI).")]
+    Play {
+        /// Render and load the audio but don't open the output device
+        #[arg(long, global = true)]
+        no_play: bool,
+
+        #[command(subcommand)]
+        source: PlaySource,
+    },
+
+    /// Send to a hardware or virtual synth in real time over a MIDI port
+    #[command(long_about = "Send to a hardware or virtual synth in real time over a MIDI port.\n\n\
+        EXAMPLES:\n  \
+        midi-cli-rs send --list-ports\n  \
+        midi-cli-rs send --port 0 preset -m calm -d 10\n  \
+        midi-cli-rs send --port 1 file intro.mid\n\n\
+        Requires a build with --features midi-out.")]
+    Send {
+        /// List available MIDI output ports and exit
+        #[arg(long)]
+        list_ports: bool,
+
+        /// Output port number (see --list-ports)
+        #[arg(long, default_value = "0", global = true)]
+        port: usize,
+
+        #[command(subcommand)]
+        source: Option<PlaySource>,
     },
 
     /// List available instruments (General MIDI names and program numbers)
-    Instruments,
+    Instruments {
+        /// Only list one family: piano, strings, woodwinds, brass, synth,
+        /// ambient, guitar or percussion
+        #[arg(long, value_parser = parse_category)]
+        category: Option<InstrumentCategory>,
+    },
 
     /// List available mood presets with descriptions
     Moods,
@@ -216,6 +267,49 @@ enum Commands {
     Info {
         /// MIDI file to inspect
         file: PathBuf,
+
+        /// Print the information as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Report musical metrics of a MIDI file (notes, range, velocity, density, polyphony)
+    Stats {
+        /// MIDI file to analyze
+        file: PathBuf,
+
+        /// Print the metrics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Compare the notes of two MIDI files (exits non-zero if they differ)
+    Compare {
+        /// First MIDI file
+        first: PathBuf,
+
+        /// Second MIDI file
+        second: PathBuf,
+    },
+
+    /// Join WAV files end to start, crossfading each into the next
+    #[command(long_about = "Join WAV files end to start, crossfading each into the next.\n\n\
+        EXAMPLES:\n  \
+        midi-cli-rs stitch --crossfade 1.0 intro.wav main.wav -o combined.wav\n\n\
+        The overlap uses an equal-power fade. All inputs must have the same\n\
+        channel count and sample rate (render them with the same settings).")]
+    Stitch {
+        /// WAV files in playing order (two or more)
+        #[arg(required = true, num_args = 2..)]
+        inputs: Vec<PathBuf>,
+
+        /// Output WAV file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overlap between consecutive files in seconds
+        #[arg(long, value_name = "SECS", default_value = "1.0", value_parser = parse_seconds)]
+        crossfade: f64,
     },
 
     /// Start the web UI server for interactive preset creation
@@ -227,13 +321,31 @@ enum Commands {
 
         /// Directory containing static web files (index.html, JS, WASM)
         /// Defaults to 'static' relative to the executable, then current directory
-        #[arg(short, long)]
+        #[arg(short, long, env = "MIDI_CLI_STATIC_DIR")]
         static_dir: Option<PathBuf>,
 
         /// Directory for data storage (presets, generated audio)
         /// Defaults to ~/.midi-cli-rs
-        #[arg(short, long)]
+        #[arg(short, long, env = "MIDI_CLI_DATA_DIR")]
         data_dir: Option<PathBuf>,
+
+        /// JSON file for saved presets and melodies
+        /// Defaults to storage.json in the data directory
+        #[arg(long, env = "MIDI_CLI_DATA_FILE")]
+        data_file: Option<PathBuf>,
+
+        /// Directory for generated audio
+        /// Defaults to 'generated' in the data directory (./generated without --data-dir)
+        #[arg(long, env = "MIDI_CLI_OUTPUT_DIR")]
+        output_dir: Option<PathBuf>,
+
+        /// Delete generated audio older than this many days
+        #[arg(long, default_value = "7")]
+        keep_days: u64,
+
+        /// Number of most recent generations kept per preset or melody
+        #[arg(long, default_value = "5")]
+        keep_latest: usize,
     },
 
     /// Import melody from ABC notation or MusicXML file
@@ -246,6 +358,402 @@ enum Commands {
         - ABC notation (.abc) - Text-based format used by folk music archives\n  \
         - MusicXML (.musicxml, .mxl) - Standard interchange format")]
     Import(ImportFormat),
+
+    /// Print a shell completion script (e.g. `midi-cli-rs completions bash > ~/.local/share/bash-completion/completions/midi-cli-rs`)
+    #[command(hide = true)]
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
+}
+
+/// Options for generating from explicit notes
+#[derive(Args)]
+struct GenerateArgs {
+    /// Notes as "PITCH:DURATION:VELOCITY[@OFFSET],..." (e.g., "C4:1:80,E4:0.5:100@1")
+    #[arg(short, long)]
+    notes: Option<String>,
+
+    /// Read JSON note data from stdin (for complex multi-track sequences)
+    #[arg(short, long)]
+    json: bool,
+
+    /// Read JSON note data from a file instead of stdin
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["json", "notes"])]
+    json_file: Option<PathBuf>,
+
+    /// Notes with scale degrees of --key as pitches, e.g. "1:1:80,b3:1:80,5+:2:90"
+    /// (b/# before the degree alters it, each + or - after shifts an octave)
+    #[arg(long, value_name = "DEGREES", group = "in_key", conflicts_with_all = ["notes", "json", "json_file"])]
+    degrees: Option<String>,
+
+    /// Chords from Roman numerals in --key, e.g. "i-iv-v-i" or "I-vi-ii7-V7"
+    /// (uppercase major, lowercase minor; °, +, 7 and maj7 suffixes)
+    #[arg(long, value_name = "NUMERALS", group = "in_key", conflicts_with_all = ["notes", "json", "json_file"])]
+    progression: Option<String>,
+
+    /// Length of each --progression chord in bars
+    #[arg(long, value_name = "BARS", default_value = "1", requires = "progression", value_parser = clap::value_parser!(u32).range(1..))]
+    bars_per_chord: u32,
+
+    /// Generate a melody from a built-in Markov model over the scale of --key
+    #[arg(long, group = "in_key", conflicts_with_all = ["notes", "json", "json_file"])]
+    markov: bool,
+
+    /// Key for --degrees, --progression and --markov (e.g. C, Am, Eb) [default: C]
+    #[arg(long, value_name = "KEY", value_parser = parse_key, requires = "in_key")]
+    key: Option<Key>,
+
+    /// Number of notes for --markov
+    #[arg(long, value_name = "NOTES", default_value = "16", requires = "markov", value_parser = clap::value_parser!(u32).range(1..))]
+    length: u32,
+
+    /// Random seed for --markov (use 0 for random)
+    #[arg(long, default_value = "1", requires = "markov")]
+    seed: u64,
+
+    /// Instrument name or GM program number 0-127 (use 'instruments' to list)
+    #[arg(short, long, default_value = "piano")]
+    instrument: String,
+
+    /// SoundFont bank to select on every non-drum track (0-16383)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(0..=16383))]
+    bank: Option<u16>,
+
+    /// SoundFont preset (program 0-127) for every non-drum track,
+    /// overriding --instrument and JSON instruments
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(0..=127))]
+    preset: Option<u8>,
+
+    /// Tempo in BPM (beats per minute) [default: 120, or the config file's tempo]
+    #[arg(short, long)]
+    tempo: Option<u16>,
+
+    /// Time signature (e.g. 4/4, 3/4, 6/8)
+    #[arg(long, default_value = "4/4")]
+    time_sig: String,
+
+    /// Assign one channel per instrument (drums stay on channel 9)
+    #[arg(long)]
+    auto_channels: bool,
+
+    /// Put every track on the drum channel (9) with no program change;
+    /// pitches then pick sounds, e.g. kick:1:100,snare:1:100@1
+    #[arg(long)]
+    drums: bool,
+
+    /// Drum kit for the drum channel: standard, room, power, electronic,
+    /// tr808, jazz, brush, orchestra, or a program number (implies --drums)
+    #[arg(long, value_name = "KIT", value_parser = parse_drum_kit)]
+    kit: Option<u8>,
+
+    /// Snap pitches to the nearest note in this key's scale (e.g. Am, Eb)
+    #[arg(long, value_name = "KEY", value_parser = parse_key)]
+    quantize_to: Option<Key>,
+
+    /// Arpeggiate chords: up, down, updown or random
+    #[arg(long, value_name = "PATTERN", value_parser = parse_arp_pattern)]
+    arp: Option<ArpPattern>,
+
+    /// Time between arpeggiated notes, in beats
+    #[arg(long, value_name = "BEATS", default_value = "0.25", requires = "arp")]
+    arp_rate: f64,
+
+    /// Reverse the notes in time, keeping the total length
+    #[arg(long)]
+    retrograde: bool,
+
+    /// Mirror pitches around this note (e.g. C4)
+    #[arg(long, value_name = "PITCH", value_parser = parse_pitch)]
+    invert: Option<u8>,
+
+    /// Swing off-beat eighth notes (0.5 = straight, 0.67 = triplet swing, max 0.75)
+    #[arg(long, value_name = "RATIO", value_parser = parse_swing)]
+    swing: Option<f64>,
+
+    /// Apply a groove template: straight, laid-back or pushed
+    #[arg(long, value_name = "NAME", value_parser = parse_groove)]
+    groove: Option<Groove>,
+
+    /// Grid spacing of the groove's slots, in beats
+    #[arg(long, value_name = "BEATS", default_value = "0.5", requires = "groove")]
+    groove_subdivision: f64,
+
+    /// Compress dynamics toward each track's mean velocity: RATIO 0-1
+    /// (0.5 halves the spread), with an optional threshold left untouched
+    #[arg(long, value_name = "RATIO[,THRESHOLD]", value_parser = parse_compress)]
+    compress: Option<(f64, u8)>,
+
+    /// Expand dynamics away from each track's mean velocity: RATIO 1-4
+    /// (2 doubles the spread), with an optional threshold left untouched
+    #[arg(long, value_name = "RATIO[,THRESHOLD]", value_parser = parse_expand)]
+    expand: Option<(f64, u8)>,
+
+    /// Humanize timing and velocity: max offset shift in beats and max
+    /// velocity change (default: 0.02,8)
+    #[arg(long, value_name = "BEATS,VELOCITY", num_args = 0..=1, default_missing_value = "0.02,8", value_parser = parse_humanize)]
+    humanize: Option<(f64, u8)>,
+
+    /// Random seed for --humanize (use 0 for random)
+    #[arg(long, default_value = "1", requires = "humanize")]
+    humanize_seed: u64,
+
+    /// Repeat the content this many times back-to-back
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    loops: Option<u32>,
+
+    /// Treat the first BEATS as a pickup: rest is added before it so the
+    /// note after it lands on the first beat of a bar
+    #[arg(long, value_name = "BEATS", value_parser = parse_pickup)]
+    pickup: Option<f64>,
+
+    /// Prepend a count-in click on the drum channel (bars, default: 1)
+    #[arg(long, value_name = "BARS", num_args = 0..=1, default_missing_value = "1")]
+    click: Option<u32>,
+
+    /// Keep the click going for the whole piece
+    #[arg(long, requires = "click")]
+    click_throughout: bool,
+
+    /// Shorten notes that overlap a later note of the same pitch on the same
+    /// channel, so synths don't retrigger or drop note-offs
+    #[arg(long)]
+    legato_fix: bool,
+
+    /// Seconds of silence before the first note
+    #[arg(long, value_name = "SECS", value_parser = parse_seconds)]
+    pad_start: Option<f64>,
+
+    /// Seconds of silence after the end (audio output)
+    #[arg(long, value_name = "SECS", value_parser = parse_seconds)]
+    pad_end: Option<f64>,
+
+    /// Embed a copyright notice (default: this tool's copyright line)
+    #[arg(long, value_name = "TEXT", num_args = 0..=1, default_missing_value = version_info::COPYRIGHT)]
+    copyright: Option<String>,
+
+    /// Add a section marker at a beat, e.g. "8:Chorus" (repeatable)
+    #[arg(long, value_name = "BEAT:TEXT", value_parser = parse_marker)]
+    marker: Vec<Marker>,
+
+    /// SoundFont file for WAV rendering (auto-detected if not specified)
+    #[arg(long)]
+    soundfont: Option<PathBuf>,
+
+    /// SoundFont for one layer, numbered as with --stems (e.g. layer2=piano.sf2);
+    /// repeatable. Layers are rendered separately and mixed
+    #[arg(long, value_name = "LAYER=PATH", value_parser = parse_soundfont_map)]
+    soundfont_map: Vec<(usize, PathBuf)>,
+
+    /// Peak-normalize WAV output to a level in dBFS (default: -1)
+    #[arg(long, value_name = "DBFS", num_args = 0..=1, default_missing_value = "-1", allow_negative_numbers = true)]
+    normalize: Option<f64>,
+
+    /// Normalize WAV output to an integrated loudness in LUFS (e.g. -14)
+    #[arg(long, value_name = "LUFS", allow_negative_numbers = true, value_parser = parse_lufs, conflicts_with = "normalize")]
+    lufs: Option<f64>,
+
+    /// FluidSynth output gain, 0-10 (default: 1.0; lower it if dense output clips)
+    #[arg(long, value_parser = parse_gain)]
+    gain: Option<f32>,
+
+    /// Output sample rate in Hz, 8000-96000 (default: 44100)
+    #[arg(long, value_name = "HZ", value_parser = clap::value_parser!(u32).range(8000..=96000))]
+    sample_rate: Option<u32>,
+
+    /// Show detailed generation info (parsed notes, instrument, tempo)
+    #[arg(short = 'v', long)]
+    verbose: bool,
+
+    /// Intended length in seconds, checked with --check or --verbose
+    #[arg(long, value_name = "SECS")]
+    duration: Option<f64>,
+
+    /// Warn if any layer's notes run past --duration
+    #[arg(long, requires = "duration")]
+    check: bool,
+
+    /// Print a JSON summary (tracks, notes, instruments, tempo, length) to
+    /// stdout when done, for scripts and agents
+    #[arg(long)]
+    summary_json: bool,
+}
+
+/// Options for mood preset generation
+#[derive(Args)]
+struct PresetArgs {
+    /// Mood preset: suspense, eerie, upbeat, calm, ambient, jazz
+    #[arg(short, long)]
+    mood: String,
+
+    /// Duration in seconds (typically 3-15 for intro/outro)
+    #[arg(short, long, default_value = "5")]
+    duration: f64,
+
+    /// Length in bars instead of seconds (e.g. --bars 4)
+    #[arg(long, conflicts_with = "duration")]
+    bars: Option<u32>,
+
+    /// Sections with their lengths in bars, e.g. intro:2,main:8,outro:2
+    #[arg(long, value_name = "NAME:BARS,...", value_parser = parse_structure, conflicts_with_all = ["duration", "bars", "seeds", "resolve_ending"])]
+    structure: Option<SongStructure>,
+
+    /// Musical key: C, Cm, D, Dm, Eb, E, Em, F, Fm, G, Gm, A, Am, Bb, B, Bm
+    #[arg(short, long)]
+    key: Option<String>,
+
+    /// Intensity level 0-100 (affects layering and dynamics)
+    #[arg(long, default_value = "50")]
+    intensity: u8,
+
+    /// Intensity ramp over the clip as START-END (e.g. 20-90 builds up);
+    /// overrides --intensity for built-in moods
+    #[arg(long, value_name = "START-END", value_parser = parse_intensity_curve)]
+    intensity_curve: Option<IntensityCurve>,
+
+    /// Tempo in BPM (beats per minute) [default: 90, or the mood's or config file's tempo]
+    #[arg(short, long)]
+    tempo: Option<u16>,
+
+    /// Time signature (e.g. 4/4, 3/4, 6/8); bars follow its length
+    #[arg(long, default_value = "4/4")]
+    time_sig: String,
+
+    /// Swing ratio for the jazz mood (0.5 = straight, 0.67 = triplet swing, max 0.75)
+    #[arg(long, value_name = "RATIO", value_parser = parse_swing)]
+    swing: Option<f64>,
+
+    /// Two phasing tone layers in this ratio for the ambient mood (e.g. 3:2)
+    #[arg(long, value_name = "A:B", value_parser = parse_polyrhythm)]
+    polyrhythm: Option<Polyrhythm>,
+
+    /// Random seed for reproducible output (default: 1, use 0 for random)
+    #[arg(short, long, default_value = "1")]
+    seed: i64,
+
+    /// Generate a range of seeds in parallel (e.g. 1-20); -o is then a
+    /// directory, or a path like out/name.wav to set the prefix and format
+    #[arg(long, conflicts_with = "seed")]
+    seeds: Option<String>,
+
+    /// Add the seed to the output file name (out.wav -> out_1234.wav). The
+    /// output may also use {mood}, {seed}, {key} and {tempo} placeholders
+    #[arg(long, conflicts_with = "seeds")]
+    seed_in_filename: bool,
+
+    /// Write each layer to its own file (basename_layer1.wav, ...)
+    #[arg(long, conflicts_with = "seeds")]
+    stems: bool,
+
+    /// Slow the final bar and end on a tonic chord (calm, ambient)
+    #[arg(long)]
+    resolve_ending: bool,
+
+    /// Repeat the content this many times back-to-back (use --bars for seamless loops)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "seeds")]
+    loops: Option<u32>,
+
+    /// Lead into the first bar with a pickup phrase this many beats long
+    #[arg(long, value_name = "BEATS", value_parser = parse_pickup, conflicts_with = "seeds")]
+    pickup: Option<f64>,
+
+    /// Prepend a count-in click on the drum channel (bars, default: 1)
+    #[arg(long, value_name = "BARS", num_args = 0..=1, default_missing_value = "1", conflicts_with = "seeds")]
+    click: Option<u32>,
+
+    /// Keep the click going for the whole piece
+    #[arg(long, requires = "click")]
+    click_throughout: bool,
+
+    /// Shorten notes that overlap a later note of the same pitch on the same
+    /// channel, so synths don't retrigger or drop note-offs
+    #[arg(long, conflicts_with = "seeds")]
+    legato_fix: bool,
+
+    /// Seconds of silence before the first note
+    #[arg(long, value_name = "SECS", value_parser = parse_seconds, conflicts_with = "seeds")]
+    pad_start: Option<f64>,
+
+    /// Seconds of silence after the end (audio output)
+    #[arg(long, value_name = "SECS", value_parser = parse_seconds, conflicts_with = "seeds")]
+    pad_end: Option<f64>,
+
+    /// Embed a copyright notice (default: this tool's copyright line)
+    #[arg(long, value_name = "TEXT", num_args = 0..=1, default_missing_value = version_info::COPYRIGHT)]
+    copyright: Option<String>,
+
+    /// Add a section marker at a beat, e.g. "8:Chorus" (repeatable)
+    #[arg(long, value_name = "BEAT:TEXT", value_parser = parse_marker)]
+    marker: Vec<Marker>,
+
+    /// SoundFont file for WAV rendering (auto-detected if not specified)
+    #[arg(long)]
+    soundfont: Option<PathBuf>,
+
+    /// SoundFont for one layer, numbered as with --stems (e.g. layer2=piano.sf2);
+    /// repeatable. Layers are rendered separately and mixed
+    #[arg(long, value_name = "LAYER=PATH", value_parser = parse_soundfont_map)]
+    soundfont_map: Vec<(usize, PathBuf)>,
+
+    /// Peak-normalize WAV output to a level in dBFS (default: -1)
+    #[arg(long, value_name = "DBFS", num_args = 0..=1, default_missing_value = "-1", allow_negative_numbers = true)]
+    normalize: Option<f64>,
+
+    /// Normalize WAV output to an integrated loudness in LUFS (e.g. -14)
+    #[arg(long, value_name = "LUFS", allow_negative_numbers = true, value_parser = parse_lufs, conflicts_with = "normalize")]
+    lufs: Option<f64>,
+
+    /// FluidSynth output gain, 0-10 (default: 1.0; lower it if dense output clips)
+    #[arg(long, value_parser = parse_gain)]
+    gain: Option<f32>,
+
+    /// Output sample rate in Hz, 8000-96000 (default: 44100)
+    #[arg(long, value_name = "HZ", value_parser = clap::value_parser!(u32).range(8000..=96000))]
+    sample_rate: Option<u32>,
+
+    /// Show detailed generation info (layers, notes, instruments)
+    #[arg(short = 'v', long)]
+    verbose: bool,
+
+    /// Warn if any layer's notes run past the requested duration (and so
+    /// get cut by the fade-out); also done with --verbose
+    #[arg(long)]
+    check: bool,
+
+    /// Print a JSON summary of the generated layers instead of rendering
+    /// (writes MIDI only if the output is a .mid file)
+    #[arg(long, conflicts_with_all = ["seeds", "stems"])]
+    dry_run: bool,
+
+    /// Record the settings used (mood, key, seed, tempo, ...) and the command
+    /// line in a .json file next to the output, for reproducing it later
+    #[arg(long, conflicts_with_all = ["seeds", "dry_run"])]
+    sidecar: bool,
+
+    /// Print a JSON summary (settings, layers, tempo, length) to stdout
+    /// when done, for scripts and agents
+    #[arg(long, conflicts_with_all = ["seeds", "dry_run"])]
+    summary_json: bool,
+}
+
+/// Music source for the play and send commands
+#[derive(Subcommand)]
+enum PlaySource {
+    /// Play a mood preset
+    Preset(PresetArgs),
+
+    /// Play explicit notes
+    Generate(GenerateArgs),
+
+    /// Play an existing MIDI or WAV file
+    File {
+        /// File to play (.mid is rendered first, .wav plays directly; send takes .mid only)
+        path: PathBuf,
+
+        /// SoundFont file for rendering MIDI (auto-detected if not specified; play only)
+        #[arg(long)]
+        soundfont: Option<PathBuf>,
+    },
 }
 
 /// Import format subcommands
@@ -256,7 +764,7 @@ enum ImportFormat {
         /// ABC notation file to import
         file: PathBuf,
 
-        /// Output file path (.mid for MIDI only, .wav for audio)
+        /// Output file path (.mid for MIDI only, .wav/.mp3/.ogg for audio)
         #[arg(short, long)]
         output: PathBuf,
 
@@ -286,7 +794,7 @@ enum ImportFormat {
         /// MusicXML file to import
         file: PathBuf,
 
-        /// Output file path (.mid for MIDI only, .wav for audio)
+        /// Output file path (.mid for MIDI only, .wav/.mp3/.ogg for audio)
         #[arg(short, long)]
         output: PathBuf,
 
@@ -321,13 +829,47 @@ fn main() -> ExitCode {
         return ExitCode::SUCCESS;
     }
 
+    if cli.fetch_soundfont {
+        let fetched = soundfont::soundfont_source(
+            cli.soundfont_url.as_deref(),
+            cli.soundfont_sha256.as_deref(),
+            soundfont::DEFAULT_SOUNDFONT,
+        )
+        .and_then(|(url, sha256)| {
+            let dir = soundfont::cache_dir().ok_or(soundfont::SoundFontError::NoCacheDir)?;
+            soundfont::fetch_soundfont(&dir, &url, &sha256, soundfont::curl_download)
+        });
+        match fetched {
+            Ok(path) => eprintln!("SoundFont ready: {}", path.display()),
+            Err(e) => {
+                eprintln!("ERROR: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+        if cli.command.is_none() {
+            return ExitCode::SUCCESS;
+        }
+    }
+
     // Require a subcommand if not showing version
     let Some(command) = cli.command else {
         eprintln!("ERROR: No command specified. Use --help for usage.");
         return ExitCode::FAILURE;
     };
 
-    match run(command) {
+    let config = match &cli.config {
+        Some(path) => Config::load(path),
+        None => Config::load_default(),
+    };
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("ERROR: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(command, &config) {
         Ok(()) => ExitCode::SUCCESS,
         Err(e) => {
             eprintln!("ERROR: {e}");
@@ -336,315 +878,69 @@ fn main() -> ExitCode {
     }
 }
 
-fn run(command: Commands) -> Result<(), Box<dyn std::error::Error>> {
+fn run(command: Commands, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     match command {
-        Commands::Generate {
-            notes,
-            json,
-            instrument,
-            tempo,
-            output,
-            soundfont,
-            verbose,
-        } => {
-            let sequences = if json {
-                // Read JSON from stdin
-                let mut input = String::new();
-                io::stdin().read_to_string(&mut input)?;
-                let json_input: JsonSequenceInput = serde_json::from_str(&input)?;
-                json_input.to_sequences()?
-            } else if let Some(notes_str) = notes {
-                // Parse notes from CLI argument
-                let parsed_notes = Note::parse_many(&notes_str)?;
-                let inst = resolve_instrument(&instrument).ok_or_else(|| {
-                    format!("Unknown instrument: {instrument}. Use 'instruments' command to list.")
-                })?;
-                vec![NoteSequence::new(parsed_notes, inst, tempo)]
-            } else {
-                return Err("Either --notes or --json must be specified".into());
-            };
-
-            if sequences.is_empty() {
-                return Err("No notes to generate".into());
-            }
-
-            // Verbose output
-            if verbose {
-                eprintln!("--- Generate Details ---");
-                eprintln!("Tempo: {} BPM", sequences[0].tempo);
-                eprintln!("Tracks: {}", sequences.len());
-                for (i, seq) in sequences.iter().enumerate() {
-                    let instrument_name = midi_cli_rs::INSTRUMENT_MAP
-                        .iter()
-                        .find(|(_, num)| *num == seq.instrument)
-                        .map(|(name, _)| *name)
-                        .unwrap_or("unknown");
-                    eprintln!(
-                        "  Track {}: {} notes, instrument {} ({})",
-                        i + 1,
-                        seq.notes.len(),
-                        seq.instrument,
-                        instrument_name
-                    );
-                    for note in &seq.notes {
-                        eprintln!(
-                            "    Note: pitch={}, duration={:.2}, velocity={}, offset={:.2}",
-                            note.pitch, note.duration, note.velocity, note.offset
-                        );
-                    }
-                }
-                eprintln!("------------------------");
-            }
-
-            // Determine output format from extension
-            let ext = output.extension().and_then(|s| s.to_str()).unwrap_or("mid");
-
-            let midi_path = if ext == "wav" {
-                output.with_extension("mid")
-            } else {
-                output.clone()
-            };
-
-            // Write MIDI file
-            write_midi(&sequences, &midi_path)?;
-            eprintln!("Generated MIDI: {}", midi_path.display());
-
-            // Render to WAV if requested
-            if ext == "wav" {
-                // For manual note generation, don't trim (let notes decay naturally)
-                render_wav(&midi_path, &output, soundfont.as_ref(), None)?;
-                eprintln!("Rendered WAV: {}", output.display());
-            }
-
-            Ok(())
+        Commands::Generate { args, output, stdout } => {
+            run_generate(args, config.output_path(output_path(output, stdout)), config)
         }
 
-        Commands::Preset {
-            mood,
-            duration,
-            key,
-            intensity,
-            tempo,
-            seed,
+        Commands::Preset { args, output, stdout } => run_preset(args, config.output_path(output_path(output, stdout)), config),
+
+        Commands::Render {
+            input,
             output,
             soundfont,
-            verbose,
+            soundfont_map,
+            normalize,
+            lufs,
+            gain,
+            sample_rate,
         } => {
-            // Get moods directory for plugin lookup
-            #[cfg(any(feature = "server", feature = "native-plugins"))]
-            let moods_dir = std::env::var("HOME")
-                .map(|h| std::path::PathBuf::from(h).join(".midi-cli-rs/moods"))
-                .unwrap_or_else(|_| std::path::PathBuf::from(".midi-cli-rs/moods"));
-
-            // Check if this is a native plugin mood
-            #[cfg(feature = "native-plugins")]
-            let is_native = is_native_plugin_mood(&mood, &moods_dir);
-            #[cfg(not(feature = "native-plugins"))]
-            let is_native = false;
-
-            // Try to parse as built-in mood first (skip if native plugin)
-            let (mood_enum, plugin_overrides): (Option<Mood>, Option<PluginMoodInfo>) =
-                if is_native {
-                    // Native plugin - no built-in mood enum needed
-                    (None, None)
-                } else if let Some(m) = Mood::parse(&mood) {
-                    (Some(m), None)
-                } else {
-                    // Check if it's a plugin mood with base_mood
-                    #[cfg(feature = "server")]
-                    {
-                        if let Some(plugin_mood) = lookup_plugin_mood(&mood) {
-                            if let Some(ref base) = plugin_mood.base_mood {
-                                if let Some(base_enum) = Mood::parse(base) {
-                                    (Some(base_enum), Some(plugin_mood))
-                                } else {
-                                    return Err(format!(
-                                        "Plugin mood '{}' has invalid base_mood '{}'. Valid base moods: suspense, eerie, upbeat, calm, ambient, jazz, show, orchestral.",
-                                        mood, base
-                                    ).into());
-                                }
-                            } else {
-                                return Err(format!(
-                                    "Plugin mood '{}' has no base_mood defined - cannot generate audio. Add 'base_mood = \"upbeat\"' (or another built-in mood) to the plugin TOML.",
-                                    mood
-                                ).into());
-                            }
-                        } else {
-                            return Err(format!(
-                                "Unknown mood: {mood}. Built-in moods: suspense, eerie, upbeat, calm, ambient, jazz, show, orchestral, chiptune. \
-                                Use 'midi-cli-rs moods' to see available plugin moods."
-                            ).into());
-                        }
-                    }
-                    #[cfg(not(feature = "server"))]
-                    {
-                        return Err(format!(
-                            "Unknown mood: {mood}. Built-in moods: suspense, eerie, upbeat, calm, ambient, jazz, show, orchestral, chiptune."
-                        ).into());
-                    }
-                };
-
-            // Parse key: use CLI arg > plugin default > mood default
-            let key_enum = if let Some(k) = key {
-                Key::parse(&k)
-                    .ok_or_else(|| format!("Unknown key: {k}. Examples: C, Am, F#m, Bb"))?
-            } else if let Some(ref plugin) = plugin_overrides {
-                Key::parse(&plugin.default_key).unwrap_or_else(|| {
-                    mood_enum.map(|m| m.default_key()).unwrap_or(Key::C)
-                })
-            } else if is_native {
-                // Native plugins default to Am for algorithmic moods
-                Key::Am
-            } else {
-                mood_enum.map(|m| m.default_key()).unwrap_or(Key::C)
+            // No target duration for render command - use full MIDI duration
+            let format = match OutputFormat::from_path(&output) {
+                OutputFormat::Midi => OutputFormat::Wav,
+                format => format,
             };
-
-            // Apply plugin tempo/intensity overrides if not specified on CLI
-            let final_tempo = if tempo != 90 {
-                tempo  // CLI override
-            } else if let Some(ref plugin) = plugin_overrides {
-                plugin.default_tempo
-            } else {
-                tempo
+            let options = RenderOptions {
+                soundfont,
+                soundfont_map: soundfont_map.into_iter().collect(),
+                normalize,
+                lufs,
+                gain,
+                sample_rate,
+                ..Default::default()
             };
+            render_output_audio(&input, &config.output_path(output), format, &options, config)?;
+            Ok(())
+        }
 
-            let final_intensity = if intensity != 50 {
-                intensity  // CLI override
-            } else if let Some(ref plugin) = plugin_overrides {
-                plugin.default_intensity.unwrap_or(intensity)
-            } else {
-                intensity
-            };
+        Commands::Play { no_play, source } => run_play(source, no_play, config),
 
-            // Handle seed: 0 or negative = random, positive = use that value
-            let actual_seed = if seed <= 0 {
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .map(|d| d.as_secs())
-                    .unwrap_or(42)
-            } else {
-                seed as u64
-            };
+        Commands::Send { list_ports, port, source } => run_send(source, port, list_ports, config),
 
-            // Create config
-            let config = PresetConfig {
-                duration_secs: duration,
-                key: key_enum,
-                intensity: final_intensity.min(100),
-                seed: actual_seed,
-                tempo: final_tempo,
+        Commands::Instruments { category } => {
+            println!("Available instruments:");
+            let categories = match category {
+                Some(category) => vec![category],
+                None => InstrumentCategory::ALL.to_vec(),
             };
-
-            // Generate sequences - use native plugin if available
-            let sequences = if is_native {
-                #[cfg(feature = "native-plugins")]
-                {
-                    generate_with_native_plugin(&mood, &config, &moods_dir).map_err(|e| {
-                        format!("Native plugin generation failed: {}", e)
-                    })?
+            for category in categories {
+                println!("\n[{}]", category.name());
+                println!("{:<20} GM PROGRAM", "NAME");
+                println!("{:-<32}", "");
+                for instrument in instruments_in(category) {
+                    println!("{:<20} {}", instrument.name, instrument.program);
                 }
-                #[cfg(not(feature = "native-plugins"))]
-                {
-                    return Err("Native plugins are not enabled. Rebuild with --features native-plugins".into());
-                }
-            } else if let Some(m) = mood_enum {
-                generate_mood(m, &config)
-            } else {
-                return Err("No mood generator available".into());
-            };
-
-            if sequences.is_empty() {
-                return Err("No sequences generated".into());
             }
-
-            // Verbose output
-            if verbose {
-                eprintln!("--- Preset Generation Details ---");
-                if is_native {
-                    eprintln!("Native Plugin Mood: {}", mood);
-                } else if plugin_overrides.is_some() {
-                    eprintln!("Plugin Mood: {} (base: {:?})", mood, mood_enum);
-                } else {
-                    eprintln!("Mood: {:?}", mood_enum);
-                }
-                eprintln!("Key: {:?} (root MIDI note: {})", key_enum, key_enum.root());
-                eprintln!("Duration: {:.1}s ({:.1} beats at {} BPM)", duration, duration * final_tempo as f64 / 60.0, final_tempo);
-                eprintln!("Intensity: {}/100", final_intensity);
-                eprintln!("Seed: {}{}", actual_seed, if seed <= 0 { " (random)" } else { "" });
-                eprintln!("Layers: {}", sequences.len());
-                for (i, seq) in sequences.iter().enumerate() {
-                    let instrument_name = midi_cli_rs::INSTRUMENT_MAP
-                        .iter()
-                        .find(|(_, num)| *num == seq.instrument)
-                        .map(|(name, _)| *name)
-                        .unwrap_or("unknown");
-                    eprintln!(
-                        "  Layer {}: {} notes, instrument {} ({})",
-                        i + 1,
-                        seq.notes.len(),
-                        seq.instrument,
-                        instrument_name
-                    );
+            if category.is_none() {
+                println!("\n[drum kits] (generate --kit NAME)");
+                println!("{:<20} GM PROGRAM", "NAME");
+                println!("{:-<32}", "");
+                for (name, program) in DRUM_KITS {
+                    println!("{name:<20} {program}");
                 }
-                eprintln!("---------------------------------");
-            }
-
-            // Determine output format from extension
-            let ext = output.extension().and_then(|s| s.to_str()).unwrap_or("mid");
-
-            let midi_path = if ext == "wav" {
-                output.with_extension("mid")
-            } else {
-                output.clone()
-            };
-
-            // Write MIDI file
-            write_midi(&sequences, &midi_path)?;
-            if is_native {
-                eprintln!(
-                    "Generated {} preset (native plugin, seed: {}, key: {:?}): {}",
-                    mood,
-                    config.seed,
-                    key_enum,
-                    midi_path.display()
-                );
-            } else {
-                eprintln!(
-                    "Generated {:?} preset (seed: {}, key: {:?}): {}",
-                    mood_enum.unwrap_or(Mood::Calm),
-                    config.seed,
-                    key_enum,
-                    midi_path.display()
-                );
-            }
-
-            // Render to WAV if requested
-            if ext == "wav" {
-                // Trim to requested duration with fade-out
-                render_wav(&midi_path, &output, soundfont.as_ref(), Some(duration))?;
-                eprintln!("Rendered WAV: {}", output.display());
-            }
-
-            Ok(())
-        }
-
-        Commands::Render {
-            input,
-            output,
-            soundfont,
-        } => {
-            // No target duration for render command - use full MIDI duration
-            render_wav(&input, &output, soundfont.as_ref(), None)?;
-            eprintln!("Rendered WAV: {}", output.display());
-            Ok(())
-        }
-
-        Commands::Instruments => {
-            println!("Available instruments:\n");
-            println!("{:<20} GM PROGRAM", "NAME");
-            println!("{:-<32}", "");
-            for (name, num) in midi_cli_rs::INSTRUMENT_MAP {
-                println!("{name:<20} {num}");
+                let notes: Vec<&str> = DRUM_NOTES.iter().map(|&(name, _)| name).collect();
+                println!("\nDrum note names (use as pitches with --drums): {}", notes.join(", "));
             }
             println!("\nYou can also use program numbers directly (0-127).");
             Ok(())
@@ -657,42 +953,10 @@ fn run(command: Commands) -> Result<(), Box<dyn std::error::Error>> {
 
             // Built-in moods
             println!("\n[Built-in]");
-            println!(
-                "{:<12} {:<8} Tense mood with low drones and tremolo strings",
-                "suspense", "Am"
-            );
-            println!(
-                "{:<12} {:<8} Creepy mood with sparse tones and diminished harmony",
-                "eerie", "Dm"
-            );
-            println!(
-                "{:<12} {:<8} Energetic mood with rhythmic patterns",
-                "upbeat", "C"
-            );
-            println!(
-                "{:<12} {:<8} Peaceful mood with sustained pads and arpeggios",
-                "calm", "G"
-            );
-            println!(
-                "{:<12} {:<8} Atmospheric mood with drones and pentatonic tones",
-                "ambient", "Em"
-            );
-            println!(
-                "{:<12} {:<8} Nightclub trio with walking bass and piano comping",
-                "jazz", "F"
-            );
-            println!(
-                "{:<12} {:<8} Broadway/Hollywood musical theater orchestration",
-                "show", "Bb"
-            );
-            println!(
-                "{:<12} {:<8} Full symphonic orchestra with all sections",
-                "orchestral", "C"
-            );
-            println!(
-                "{:<12} {:<8} 8-bit video game style with square wave arpeggios",
-                "chiptune", "C"
-            );
+            for generator in MoodRegistry::default().iter() {
+                let key = format!("{:?}", generator.default_key());
+                println!("{:<12} {:<8} {}", generator.name(), key, generator.description());
+            }
 
             // Plugin moods
             let moods_dir = std::env::var("HOME")
@@ -773,7 +1037,10 @@ fn run(command: Commands) -> Result<(), Box<dyn std::error::Error>> {
                     }
                     p
                 }
-                None => find_soundfont()?,
+                None => match &config.soundfont {
+                    Some(p) => p.clone(),
+                    None => find_soundfont()?,
+                },
             };
 
             println!("SoundFont: {}\n", sf_path.display());
@@ -813,25 +1080,107 @@ quit""#,
             Ok(())
         }
 
-        Commands::Info { file } => {
-            let content = std::fs::read(&file)?;
-            let smf = midly::Smf::parse(&content)?;
+        Commands::Info { file, json } => {
+            let info = midi_info(&std::fs::read(&file)?)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&info)?);
+                return Ok(());
+            }
+
+            println!("MIDI File: {}", file.display());
+            println!("Format: {}", info.format);
+            println!("Timing: {} ticks per beat", info.ticks_per_beat);
+            println!("Tempo: {}{}", info.tempo, if info.tempo_assumed { " (assumed)" } else { "" });
+            println!("Tracks: {}", info.tracks.len());
+            for (i, track) in info.tracks.iter().enumerate() {
+                match &track.name {
+                    Some(name) => println!("  Track {i}: {} events ({name})", track.events),
+                    None => println!("  Track {i}: {} events", track.events),
+                }
+            }
+
+            println!("Notes: {}", info.notes);
+            for channel in &info.channels {
+                println!(
+                    "  Channel {}: {} notes, program {}, {:.1} beats",
+                    channel.channel, channel.notes, channel.program, channel.beats
+                );
+            }
+
+            Ok(())
+        }
+
+        Commands::Stats { file, json } => {
+            let stats = midi_stats(&read_midi(&file)?);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+                return Ok(());
+            }
 
+            let range = |range: Option<(u8, u8)>| match range {
+                Some((low, high)) => format!("{low}-{high}"),
+                None => "-".to_string(),
+            };
             println!("MIDI File: {}", file.display());
-            println!("Format: {:?}", smf.header.format);
-            println!("Timing: {:?}", smf.header.timing);
-            println!("Tracks: {}", smf.tracks.len());
+            println!("Notes: {}", stats.notes);
+            println!("Pitch range: {}", range(stats.pitch_range));
+            println!("Velocity: mean {:.1}, median {:.1}", stats.mean_velocity, stats.median_velocity);
+            println!("Duration: {:.2}s", stats.duration_secs);
+            println!("Density: {:.2} notes/s", stats.notes_per_second);
+            println!("Peak polyphony: {}", stats.peak_polyphony);
+            println!("{:<8} {:<8} {:<6} {:<8} VELOCITY", "CHANNEL", "PROGRAM", "NOTES", "RANGE");
+            for channel in &stats.channels {
+                println!(
+                    "{:<8} {:<8} {:<6} {:<8} {:.1}",
+                    channel.channel,
+                    channel.program,
+                    channel.notes,
+                    range(channel.pitch_range),
+                    channel.mean_velocity
+                );
+            }
+            Ok(())
+        }
+
+        Commands::Compare { first, second } => {
+            let left = read_midi(&first)?;
+            let right = read_midi(&second)?;
+            let diffs = compare_notes(&left, &right);
+            let events = note_events(&left);
 
-            for (i, track) in smf.tracks.iter().enumerate() {
-                let events = track.len();
-                println!("  Track {i}: {events} events");
+            let Some(diff) = diffs.first() else {
+                println!("Identical: {} note events", events.len());
+                return Ok(());
+            };
+
+            println!("First difference at event {}: {}", diff.index, diff.changes().join(", "));
+            for event in &events[diff.index.saturating_sub(2)..diff.index.min(events.len())] {
+                println!("    {event}");
             }
+            let side = |event: Option<NoteEvent>| event.map(|e| e.to_string()).unwrap_or_else(|| "(none)".to_string());
+            println!("  - {}", side(diff.left));
+            println!("  + {}", side(diff.right));
+            Err(format!("Files differ: {} of {} note events", diffs.len(), events.len().max(note_events(&right).len())).into())
+        }
 
+        Commands::Stitch { inputs, output, crossfade } => {
+            let output = config.output_path(output);
+            if OutputFormat::from_path(&output) != OutputFormat::Wav {
+                return Err(format!("stitch writes WAV only, not {}", output.display()).into());
+            }
+            let mut joined = read_wav(&inputs[0])?;
+            for input in &inputs[1..] {
+                joined = audio::crossfade(&joined, &read_wav(input)?, crossfade)
+                    .map_err(|e| format!("{}: {e}", input.display()))?;
+            }
+            write_wav(&output, &joined)?;
+            let seconds = joined.samples.len() as f64 / joined.spec.channels.max(1) as f64 / joined.spec.sample_rate as f64;
+            eprintln!("Stitched {} files ({seconds:.2}s): {}", inputs.len(), output.display());
             Ok(())
         }
 
         #[cfg(feature = "server")]
-        Commands::Serve { port, static_dir, data_dir } => {
+        Commands::Serve { port, static_dir, data_dir, data_file, output_dir, keep_days, keep_latest } => {
             // Resolve static directory: explicit > exe-relative > cwd
             let static_path = static_dir.unwrap_or_else(|| {
                 if let Ok(exe) = std::env::current_exe()
@@ -853,52 +1202,1140 @@ quit""#,
             }
 
             let rt = tokio::runtime::Runtime::new()?;
-            rt.block_on(server::run_server(port, static_path, data_dir))?;
+            let retention = server::state::RetentionConfig {
+                max_age: std::time::Duration::from_secs(keep_days * 24 * 60 * 60),
+                keep_latest,
+            };
+            let storage = server::state::StorageConfig { data_dir, data_file, output_dir };
+            rt.block_on(server::run_server(port, static_path, storage, retention, config.clone()))?;
             Ok(())
         }
 
         Commands::Import(format) => {
-            handle_import(format)
+            handle_import(format, config)
+        }
+
+        Commands::Completions { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+            Ok(())
         }
     }
 }
 
-/// Handle import command for ABC and MusicXML files
-fn handle_import(format: ImportFormat) -> Result<(), Box<dyn std::error::Error>> {
-    let (melody, file, output, key, tempo, instrument, soundfont, verbose) = match format {
-        ImportFormat::Abc {
-            file,
-            output,
-            key,
-            tempo,
-            instrument,
-            soundfont,
-            verbose,
-        } => {
-            let melody = AbcParser::parse_file(&file)?;
-            (melody, file, output, key, tempo, instrument, soundfont, verbose)
-        }
-        ImportFormat::Musicxml {
-            file,
-            output,
-            key,
-            tempo,
-            instrument,
-            soundfont,
-            verbose,
-        } => {
-            let melody = MusicXmlParser::parse_file(&file)?;
-            (melody, file, output, key, tempo, instrument, soundfont, verbose)
-        }
+/// Generate MIDI/audio from explicit notes or JSON
+fn run_generate(args: GenerateArgs, output: PathBuf, user_config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let GenerateArgs {
+        notes,
+        json,
+        json_file,
+        degrees,
+        progression,
+        bars_per_chord,
+        markov,
+        key,
+        length,
+        seed,
+        instrument,
+        bank,
+        preset,
+        tempo,
+        time_sig,
+        auto_channels,
+        drums,
+        kit,
+        arp,
+        arp_rate,
+        retrograde,
+        invert,
+        quantize_to,
+        swing,
+        groove,
+        groove_subdivision,
+        compress,
+        expand,
+        humanize,
+        humanize_seed,
+        loops,
+        pickup,
+        click,
+        click_throughout,
+        legato_fix,
+        pad_start: start_pad,
+        pad_end: end_pad,
+        copyright,
+        marker,
+        soundfont,
+        soundfont_map,
+        normalize,
+        lufs,
+        gain,
+        sample_rate,
+        verbose,
+        duration,
+        check,
+        summary_json,
+    } = args;
+    if is_stdout(&output) && summary_json {
+        return Err("--summary-json prints to stdout, so the MIDI can't go there too".into());
+    }
+    let metadata = MidiMetadata { copyright, markers: marker };
+    let tempo = tempo.or(user_config.tempo).unwrap_or(120);
+
+    let time_signature = parse_time_signature(&time_sig)?;
+    let mut sequences = if let Some(path) = json_file {
+        let input = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read JSON file {}: {e}", path.display()))?;
+        let json_input: JsonSequenceInput = serde_json::from_str(&input)
+            .map_err(|e| format!("Invalid JSON in {}: {e}", path.display()))?;
+        json_input.to_sequences()?
+    } else if json {
+        // Read JSON from stdin
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        let json_input: JsonSequenceInput = serde_json::from_str(&input)?;
+        json_input.to_sequences()?
+    } else if let Some(notes_str) = notes {
+        // Parse notes from CLI argument
+        let parsed_notes = Note::parse_many(&notes_str)?;
+        let inst = resolve_generate_instrument(&instrument)?;
+        vec![NoteSequence::new(parsed_notes, inst, tempo)]
+    } else if let Some(degrees) = degrees {
+        let parsed_notes = Note::parse_degrees(&degrees, key.unwrap_or(Key::C))?;
+        let inst = resolve_generate_instrument(&instrument)?;
+        vec![NoteSequence::new(parsed_notes, inst, tempo)]
+    } else if let Some(progression) = progression {
+        let inst = resolve_generate_instrument(&instrument)?;
+        let beats = bars_per_chord as f64 * time_signature.beats_per_bar();
+        vec![progression_sequence(&progression, key.unwrap_or(Key::C), beats, inst, tempo)?]
+    } else if markov {
+        let inst = resolve_generate_instrument(&instrument)?;
+        let seed = if seed == 0 { rand::random() } else { seed };
+        eprintln!("Markov melody (seed: {seed})");
+        vec![markov_melody(key.unwrap_or(Key::C), length as usize, inst, tempo, seed)]
+    } else {
+        return Err("Either --notes, --degrees, --progression, --markov, --json or --json-file must be specified".into());
     };
 
-    // Verbose output
-    if verbose {
-        eprintln!("--- Import Details ---");
-        eprintln!("File: {}", file.display());
-        if let Some(title) = &melody.title {
-            eprintln!("Title: {}", title);
-        }
+    if sequences.is_empty() {
+        return Err("No notes to generate".into());
+    }
+    if drums || kit.is_some() {
+        for seq in &mut sequences {
+            seq.channel = DRUM_CHANNEL;
+            seq.instrument = kit.unwrap_or(STANDARD_KIT);
+        }
+    }
+    for seq in &mut sequences {
+        seq.time_signature = time_signature;
+        if seq.channel != DRUM_CHANNEL {
+            seq.bank = bank.or(seq.bank);
+            seq.instrument = preset.unwrap_or(seq.instrument);
+        }
+    }
+    if retrograde {
+        // Mirror every track around the end of the longest so they stay aligned
+        let length = sequences.iter().map(|s| s.duration_beats()).fold(0.0, f64::max);
+        for seq in &mut sequences {
+            let shift = length - seq.duration_beats();
+            seq.retrograde();
+            for note in &mut seq.notes {
+                note.offset += shift;
+            }
+        }
+    }
+    if let Some(axis) = invert {
+        for seq in &mut sequences {
+            seq.invert(axis);
+        }
+    }
+    if let Some(key) = quantize_to {
+        for seq in &mut sequences {
+            if seq.channel != DRUM_CHANNEL {
+                seq.quantize_to_scale(key);
+            }
+        }
+    }
+    if arp_rate <= 0.0 {
+        return Err("--arp-rate must be greater than 0".into());
+    }
+    if let Some(pattern) = arp {
+        for seq in &mut sequences {
+            seq.arpeggiate(pattern, arp_rate);
+        }
+    }
+    if let Some(beats) = pickup {
+        let rest = time_signature.pickup_rest(beats);
+        for seq in &mut sequences {
+            seq.shift(rest);
+        }
+    }
+    if let Some(ratio) = swing {
+        for seq in &mut sequences {
+            seq.apply_swing(ratio);
+        }
+    }
+    if groove_subdivision <= 0.0 {
+        return Err("--groove-subdivision must be greater than 0".into());
+    }
+    if let Some(groove) = &groove {
+        for seq in &mut sequences {
+            seq.apply_groove(groove, groove_subdivision);
+        }
+    }
+    if let Some((ratio, threshold)) = compress {
+        for seq in &mut sequences {
+            seq.compress_velocity(ratio, threshold);
+        }
+    }
+    if let Some((ratio, threshold)) = expand {
+        for seq in &mut sequences {
+            seq.expand_velocity(ratio, threshold);
+        }
+    }
+    if let Some((timing, velocity)) = humanize {
+        let seed = if humanize_seed == 0 { rand::random() } else { humanize_seed };
+        // Offset the seed per track so parts don't drift in lockstep
+        for (i, seq) in sequences.iter_mut().enumerate() {
+            seq.humanize(timing, velocity, seed.wrapping_add(i as u64));
+        }
+        eprintln!("Humanized (seed: {seed})");
+    }
+    if let Some(times) = loops {
+        let length = sequences.iter().map(|s| s.duration_beats()).fold(0.0, f64::max);
+        for seq in &mut sequences {
+            seq.repeat(times, length);
+        }
+    }
+    if let Some(bars) = click {
+        add_click(&mut sequences, bars, click_throughout);
+    }
+    let overlaps: usize = sequences.iter().map(|s| s.count_overlaps()).sum();
+    if legato_fix {
+        fix_overlaps(&mut sequences);
+    } else if overlaps > 0 {
+        eprintln!("Warning: same-pitch notes overlap on the same channel ({overlaps} to fix); use --legato-fix");
+    }
+    if auto_channels {
+        assign_channels(&mut sequences)?;
+    }
+    if let Some(limit) = duration.filter(|_| check || verbose) {
+        warn_overruns(&sequences, limit);
+    }
+    if let Some(secs) = start_pad {
+        pad_start(&mut sequences, secs);
+    }
+
+    // Verbose output
+    if verbose {
+        eprintln!("--- Generate Details ---");
+        eprintln!("Tempo: {} BPM", sequences[0].tempo);
+        eprintln!("Tracks: {}", sequences.len());
+        for (i, seq) in sequences.iter().enumerate() {
+            let instrument_name = instrument_name(seq.instrument);
+            eprintln!(
+                "  Track {}: {} notes, instrument {} ({})",
+                i + 1,
+                seq.notes.len(),
+                seq.instrument,
+                instrument_name
+            );
+            for note in &seq.notes {
+                eprintln!(
+                    "    Note: pitch={}, duration={:.2}, velocity={}, offset={:.2}",
+                    note.pitch, note.duration, note.velocity, note.offset
+                );
+            }
+        }
+        eprintln!("------------------------");
+    }
+
+    // Write MIDI file (next to the audio output if one was requested)
+    let (midi_path, format) = write_midi_output(&sequences, &metadata, &output)?;
+    eprintln!("Generated MIDI: {}", midi_path.display());
+
+    // Render audio if requested
+    // For manual note generation, don't trim (let notes decay naturally)
+    // unless an end pad fixes the length
+    let options = RenderOptions {
+        soundfont,
+        soundfont_map: soundfont_map.into_iter().collect(),
+        target_duration: end_pad.map(|secs| {
+            sequences.iter().map(|s| s.duration_seconds()).fold(0.0, f64::max) + secs
+        }),
+        normalize,
+        lufs,
+        gain,
+        sample_rate,
+        ..Default::default()
+    };
+    render_output_audio(&midi_path, &output, format, &options, user_config)?;
+    if summary_json {
+        let summary = serde_json::json!({ "tempo": sequences[0].tempo });
+        println!("{}", serde_json::to_string_pretty(&with_summary(summary, &sequences))?);
+    }
+
+    Ok(())
+}
+
+/// Apply --legato-fix to every sequence and report how many notes changed
+fn fix_overlaps(sequences: &mut [NoteSequence]) {
+    let fixed: usize = sequences.iter_mut().map(|s| s.resolve_overlaps()).sum();
+    if fixed > 0 {
+        eprintln!("Legato fix: shortened or removed {fixed} overlapping same-pitch notes");
+    }
+}
+
+/// Program number for --instrument, suggesting close names if unknown
+fn resolve_generate_instrument(instrument: &str) -> Result<u8, String> {
+    resolve_instrument(instrument).ok_or_else(|| match suggest_instruments(instrument)[..] {
+        [] => format!("Unknown instrument: {instrument}. Use 'instruments' command to list."),
+        ref names => format!("Unknown instrument: {instrument}. Did you mean: {}?", names.join(", ")),
+    })
+}
+
+/// Generate MIDI/audio from a mood preset
+fn run_preset(args: PresetArgs, output: PathBuf, user_config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let PresetArgs {
+        mood,
+        duration,
+        bars,
+        structure,
+        key,
+        intensity,
+        intensity_curve,
+        tempo,
+        time_sig,
+        swing,
+        polyrhythm,
+        seed,
+        seeds,
+        seed_in_filename,
+        stems,
+        resolve_ending,
+        loops,
+        pickup,
+        click,
+        click_throughout,
+        legato_fix,
+        pad_start: start_pad,
+        pad_end: end_pad,
+        copyright,
+        marker,
+        soundfont,
+        soundfont_map,
+        normalize,
+        lufs,
+        gain,
+        sample_rate,
+        verbose,
+        check,
+        dry_run,
+        sidecar,
+        summary_json,
+    } = args;
+    let mut metadata = MidiMetadata { copyright, markers: marker };
+    // A structure's length is the sum of its sections
+    let bars = bars.or(structure.as_ref().map(|s| s.total_bars()));
+    let soundfont_map: BTreeMap<usize, PathBuf> = soundfont_map.into_iter().collect();
+
+    if is_stdout(&output) && (seeds.is_some() || stems || sidecar) {
+        return Err("--seeds, --stems and --sidecar write several files and can't go to stdout".into());
+    }
+    if is_stdout(&output) && summary_json {
+        return Err("--summary-json prints to stdout, so the MIDI can't go there too".into());
+    }
+
+    // Get moods directory for plugin lookup
+    #[cfg(any(feature = "server", feature = "native-plugins"))]
+    let moods_dir = std::env::var("HOME")
+        .map(|h| std::path::PathBuf::from(h).join(".midi-cli-rs/moods"))
+        .unwrap_or_else(|_| std::path::PathBuf::from(".midi-cli-rs/moods"));
+
+    // Check if this is a native plugin mood
+    #[cfg(feature = "native-plugins")]
+    let is_native = is_native_plugin_mood(&mood, &moods_dir);
+    #[cfg(not(feature = "native-plugins"))]
+    let is_native = false;
+
+    // Try to parse as built-in mood first (skip if native plugin)
+    let (mood_enum, plugin_overrides): (Option<Mood>, Option<PluginMoodInfo>) =
+        if is_native {
+            // Native plugin - no built-in mood enum needed
+            (None, None)
+        } else if let Some(m) = Mood::parse(&mood) {
+            (Some(m), None)
+        } else {
+            // Check if it's a plugin mood with base_mood
+            #[cfg(feature = "server")]
+            {
+                if let Some(plugin_mood) = lookup_plugin_mood(&mood) {
+                    if let Some(ref base) = plugin_mood.base_mood {
+                        if let Some(base_enum) = Mood::parse(base) {
+                            (Some(base_enum), Some(plugin_mood))
+                        } else {
+                            return Err(format!(
+                                "Plugin mood '{}' has invalid base_mood '{}'. Valid base moods: suspense, eerie, upbeat, calm, ambient, jazz, show, orchestral.",
+                                mood, base
+                            ).into());
+                        }
+                    } else {
+                        return Err(format!(
+                            "Plugin mood '{}' has no base_mood defined - cannot generate audio. Add 'base_mood = \"upbeat\"' (or another built-in mood) to the plugin TOML.",
+                            mood
+                        ).into());
+                    }
+                } else {
+                    return Err(format!(
+                        "Unknown mood: {mood}. Built-in moods: {}. Use 'midi-cli-rs moods' to see available plugin moods.",
+                        MoodRegistry::default().names().join(", ")
+                    ).into());
+                }
+            }
+            #[cfg(not(feature = "server"))]
+            {
+                return Err(format!(
+                    "Unknown mood: {mood}. Built-in moods: {}.",
+                    MoodRegistry::default().names().join(", ")
+                ).into());
+            }
+        };
+
+    // Parse key: use CLI arg > plugin default > mood default
+    let key_enum = if let Some(k) = key {
+        Key::parse(&k)
+            .ok_or_else(|| format!("Unknown key: {k}. Examples: C, Am, F#m, Bb"))?
+    } else if let Some(ref plugin) = plugin_overrides {
+        Key::parse(&plugin.default_key).unwrap_or_else(|| {
+            mood_enum.map(|m| m.default_key()).unwrap_or(Key::C)
+        })
+    } else if is_native {
+        // Native plugins default to Am for algorithmic moods
+        Key::Am
+    } else {
+        mood_enum.map(|m| m.default_key()).unwrap_or(Key::C)
+    };
+
+    // Apply plugin tempo/intensity overrides if not specified on CLI
+    let final_tempo = tempo
+        .or(plugin_overrides.as_ref().map(|plugin| plugin.default_tempo))
+        .or(user_config.tempo)
+        .unwrap_or(90);
+
+    let final_intensity = if intensity != 50 {
+        intensity  // CLI override
+    } else if let Some(ref plugin) = plugin_overrides {
+        plugin.default_intensity.unwrap_or(intensity)
+    } else {
+        intensity
+    };
+
+    // Handle seed: 0 or negative = random, positive = use that value
+    let actual_seed = if seed <= 0 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(42)
+    } else {
+        seed as u64
+    };
+
+    // Create config
+    let mut config = PresetConfig {
+        duration_secs: duration,
+        key: key_enum,
+        intensity: final_intensity,
+        seed: actual_seed,
+        tempo: final_tempo,
+        resolve_ending,
+        bars,
+        time_signature: parse_time_signature(&time_sig)?,
+        intensity_curve,
+        swing,
+        polyrhythm,
+    };
+
+    config.validate()?;
+
+    // Bars set the length in beats; derive the nominal duration
+    // (native plugins only see seconds)
+    if bars.is_some() {
+        config.duration_secs = config.beats(final_tempo) * 60.0 / final_tempo as f64;
+    }
+    let duration = config.duration_secs;
+
+    // Fill in {mood}, {seed}, ... now that the seed is known
+    let output = if seed_in_filename && !is_stdout(&output) { seed_file_template(&output) } else { output };
+    if seeds.is_some() && output.to_string_lossy().contains("{seed}") {
+        return Err("--seeds already puts each seed in its file name; remove {seed} from the output".into());
+    }
+    let output = expand_output_template(&output, &mood, &config);
+
+    // Batch mode: one file per seed
+    if let Some(range) = seeds {
+        let seeds = parse_seed_range(&range)
+            .ok_or_else(|| format!("Invalid seed range: {range}. Example: 1-20"))?;
+        let mood_enum = match mood_enum {
+            Some(m) if !is_native => m,
+            _ => return Err("--seeds is only supported for built-in and TOML plugin moods".into()),
+        };
+        // Bars and ritardandos change the length per seed; let those ring out
+        let trim = if bars.is_none() && !resolve_ending { Some(duration) } else { None };
+        let (midi_paths, ext) = run_seed_batch(&mood, mood_enum, &config, &metadata, seeds, &output)?;
+
+        // FluidSynth renders run one at a time
+        let options = RenderOptions {
+            soundfont,
+            soundfont_map,
+            target_duration: trim,
+            normalize,
+            lufs,
+            gain,
+            sample_rate,
+            ..Default::default()
+        };
+        for midi_path in &midi_paths {
+            let output = midi_path.with_extension(&ext);
+            render_output_audio(midi_path, &output, OutputFormat::from_path(&output), &options, user_config)?;
+        }
+        return Ok(());
+    }
+
+    if structure.is_some() && is_native {
+        return Err("--structure is only supported for built-in and TOML plugin moods".into());
+    }
+
+    // Generate sequences - use native plugin if available
+    let mut sequences = if is_native {
+        #[cfg(feature = "native-plugins")]
+        {
+            let mut sequences = generate_with_native_plugin(&mood, &config, &moods_dir).map_err(|e| {
+                format!("Native plugin generation failed: {}", e)
+            })?;
+            for seq in &mut sequences {
+                seq.time_signature = config.time_signature;
+            }
+            sequences
+        }
+        #[cfg(not(feature = "native-plugins"))]
+        {
+            return Err("Native plugins are not enabled. Rebuild with --features native-plugins".into());
+        }
+    } else if let Some(m) = mood_enum {
+        match &structure {
+            Some(structure) => {
+                let (sequences, markers) = generate_structure(m, &config, structure);
+                metadata.markers.splice(0..0, markers);
+                sequences
+            }
+            None => generate_mood(m, &config),
+        }
+    } else {
+        return Err("No mood generator available".into());
+    };
+
+    if sequences.is_empty() {
+        return Err("No sequences generated".into());
+    }
+
+    // Verbose output
+    if verbose {
+        eprintln!("--- Preset Generation Details ---");
+        if is_native {
+            eprintln!("Native Plugin Mood: {}", mood);
+        } else if plugin_overrides.is_some() {
+            eprintln!("Plugin Mood: {} (base: {:?})", mood, mood_enum);
+        } else {
+            eprintln!("Mood: {:?}", mood_enum);
+        }
+        eprintln!("Key: {:?} (root MIDI note: {})", key_enum, key_enum.root());
+        eprintln!("Duration: {:.1}s ({:.1} beats at {} BPM)", duration, duration * final_tempo as f64 / 60.0, final_tempo);
+        eprintln!("Intensity: {}/100", final_intensity);
+        eprintln!("Seed: {}{}", actual_seed, if seed <= 0 { " (random)" } else { "" });
+        eprintln!("Layers: {}", sequences.len());
+        for (i, seq) in sequences.iter().enumerate() {
+            eprintln!(
+                "  Layer {}: {} notes, instrument {} ({})",
+                i + 1,
+                seq.notes.len(),
+                seq.instrument,
+                instrument_name(seq.instrument)
+            );
+        }
+        eprintln!("---------------------------------");
+    }
+
+    if let Some(times) = loops {
+        let length = config.beats(sequences[0].tempo);
+        for seq in &mut sequences {
+            seq.repeat(times, length);
+        }
+    }
+
+    // Trim audio to requested duration with fade-out. Bars follow the
+    // seed-varied tempo, and a ritardando stretches the ending.
+    let repeats = loops.unwrap_or(1) as f64;
+    let mut target = match bars {
+        Some(_) => sequences[0].beats_to_seconds(config.beats(sequences[0].tempo) * repeats),
+        None => duration * repeats,
+    };
+    if resolve_ending || structure.is_some() {
+        target = sequences.iter().map(|s| s.duration_seconds()).fold(target, f64::max);
+    }
+    if let Some(beats) = pickup {
+        let lead_in = add_pickup(&mut sequences, key_enum, beats);
+        target += sequences[0].beats_to_seconds(lead_in);
+    }
+    if let Some(bars) = click {
+        add_click(&mut sequences, bars, click_throughout);
+        target += sequences[0].beats_to_seconds(bars as f64 * config.time_signature.beats_per_bar());
+    }
+    if legato_fix {
+        fix_overlaps(&mut sequences);
+    }
+    if let Some(secs) = start_pad {
+        pad_start(&mut sequences, secs);
+        target += secs;
+    }
+
+    if check || verbose {
+        warn_overruns(&sequences, target);
+    }
+    target += end_pad.unwrap_or(0.0);
+
+    // What was generated, for --dry-run and --sidecar
+    let settings = serde_json::json!({
+        "mood": mood,
+        "key": format!("{key_enum:?}"),
+        "seed": actual_seed,
+        "tempo": sequences[0].tempo,
+        "intensity": final_intensity,
+        "duration_secs": target,
+    });
+
+    // Dry run: report what would be rendered, write MIDI only for .mid output
+    if dry_run {
+        let midi_path = match OutputFormat::from_path(&output) {
+            OutputFormat::Midi if !is_stdout(&output) => {
+                write_midi_with(&sequences, &metadata, &output)?;
+                Some(output.display().to_string())
+            }
+            _ => None,
+        };
+        let mut summary = with_summary(settings, &sequences);
+        summary["midi"] = midi_path.into();
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    // Stems: one file per layer, each with its mapped SoundFont
+    if stems {
+        let format = OutputFormat::from_path(&output);
+        let midi_paths = write_stems(&sequences, &metadata, &output)?;
+        eprintln!("Generated {} stems (seed: {}, key: {:?})", midi_paths.len(), config.seed, key_enum);
+        for (i, midi_path) in midi_paths.iter().enumerate() {
+            eprintln!("  {}", midi_path.display());
+            let stem_output = midi_path.with_extension(output.extension().unwrap_or_default());
+            let options = RenderOptions {
+                soundfont: soundfont_map.get(&(i + 1)).or(soundfont.as_ref()).cloned(),
+                target_duration: Some(target),
+                normalize,
+                lufs,
+                gain,
+                sample_rate,
+                ..Default::default()
+            };
+            render_output_audio(midi_path, &stem_output, format, &options, user_config)?;
+        }
+        if summary_json {
+            println!("{}", serde_json::to_string_pretty(&with_summary(settings.clone(), &sequences))?);
+        }
+        if sidecar {
+            write_sidecar(&output, settings)?;
+        }
+        return Ok(());
+    }
+
+    let options = RenderOptions {
+        soundfont,
+        soundfont_map,
+        target_duration: Some(target),
+        normalize,
+        lufs,
+        gain,
+        sample_rate,
+        ..Default::default()
+    };
+
+    // Write MIDI file (next to the audio output if one was requested)
+    let (midi_path, format) = write_midi_output(&sequences, &metadata, &output)?;
+    if is_native {
+        eprintln!(
+            "Generated {} preset (native plugin, seed: {}, key: {:?}): {}",
+            mood,
+            config.seed,
+            key_enum,
+            midi_path.display()
+        );
+    } else {
+        eprintln!(
+            "Generated {:?} preset (seed: {}, key: {:?}): {}",
+            mood_enum.unwrap_or(Mood::Calm),
+            config.seed,
+            key_enum,
+            midi_path.display()
+        );
+    }
+
+    // Render audio if requested
+    render_output_audio(&midi_path, &output, format, &options, user_config)?;
+    if summary_json {
+        println!("{}", serde_json::to_string_pretty(&with_summary(settings.clone(), &sequences))?);
+    }
+    if sidecar {
+        write_sidecar(&output, settings)?;
+    }
+
+    Ok(())
+}
+
+/// Add each layer (name, instrument, channel, notes, length) and the total
+/// length to a JSON summary, for --dry-run and --summary-json
+fn with_summary(mut summary: serde_json::Value, sequences: &[NoteSequence]) -> serde_json::Value {
+    let layers: Vec<serde_json::Value> = sequences
+        .iter()
+        .map(|seq| {
+            serde_json::json!({
+                "name": seq.name,
+                "instrument": seq.instrument,
+                "instrument_name": instrument_name(seq.instrument),
+                "channel": seq.channel,
+                "notes": seq.notes.len(),
+                "beats": seq.duration_beats(),
+                "seconds": seq.duration_seconds(),
+            })
+        })
+        .collect();
+    summary["layers"] = layers.into();
+    summary["total_beats"] = sequences.iter().map(|s| s.duration_beats()).fold(0.0, f64::max).into();
+    summary["total_seconds"] = sequences.iter().map(|s| s.duration_seconds()).fold(0.0, f64::max).into();
+    summary
+}
+
+/// Warn about each layer whose notes run past `target_secs`
+fn warn_overruns(sequences: &[NoteSequence], target_secs: f64) {
+    for (i, over) in overruns(sequences, target_secs) {
+        let seq = &sequences[i];
+        eprintln!(
+            "Warning: layer {} ({}) ends at {:.2}s, {:.2}s past the requested {:.2}s",
+            i + 1,
+            seq.name.as_deref().unwrap_or(instrument_name(seq.instrument)),
+            seq.duration_seconds(),
+            over,
+            target_secs
+        );
+    }
+}
+
+/// Write generation settings, this tool's version and the command line to
+/// a .json file next to the output
+fn write_sidecar(output: &Path, mut settings: serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+    let path = output.with_extension("json");
+    settings["version"] = env!("CARGO_PKG_VERSION").into();
+    settings["command"] = std::env::args().collect::<Vec<_>>().into();
+    std::fs::write(&path, serde_json::to_string_pretty(&settings)? + "\n")?;
+    eprintln!("Wrote settings: {}", path.display());
+    Ok(())
+}
+
+/// Render a play source to a temporary WAV and play it
+fn run_play(source: PlaySource, no_play: bool, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_wav = std::env::temp_dir().join(format!("midi-cli-rs-play-{}.wav", std::process::id()));
+    let temp_mid = temp_wav.with_extension("mid");
+
+    let rendered = match source {
+        PlaySource::Preset(args) => {
+            if args.seeds.is_some() || args.stems {
+                return Err("--seeds and --stems cannot be used with play".into());
+            }
+            run_preset(args, temp_wav.clone(), config)
+        }
+        PlaySource::Generate(args) => run_generate(args, temp_wav.clone(), config),
+        PlaySource::File { path, soundfont } => match OutputFormat::from_path(&path) {
+            OutputFormat::Wav => return play_wav(&path, no_play),
+            OutputFormat::Midi => {
+                let mut options = RenderOptions { soundfont, ..Default::default() };
+                config.apply(&mut options);
+                render_output(&path, &temp_wav, OutputFormat::Wav, &options).map_err(Into::into)
+            }
+            format => return Err(format!("Cannot play {} files; use .mid or .wav", format.name()).into()),
+        },
+    };
+
+    let result = rendered.and_then(|()| play_wav(&temp_wav, no_play));
+    let _ = std::fs::remove_file(&temp_wav);
+    let _ = std::fs::remove_file(&temp_mid);
+    result
+}
+
+/// Load a WAV and play it, or just report it when `no_play` is set
+fn play_wav(path: &Path, no_play: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let buffer = PlaybackBuffer::from_wav(path)?;
+    let seconds = buffer.duration().as_secs_f64();
+    if no_play {
+        eprintln!("Loaded {seconds:.2}s of audio (--no-play, skipping output device)");
+        return Ok(());
+    }
+    eprintln!("Playing {seconds:.2}s...");
+    playback::play(&buffer)?;
+    Ok(())
+}
+
+/// Send a source to a MIDI output port, or list the ports
+fn run_send(source: Option<PlaySource>, port: usize, list_ports: bool, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    if list_ports {
+        let ports = midi_output::list_ports()?;
+        if ports.is_empty() {
+            println!("No MIDI output ports found");
+        }
+        for (index, name) in ports.iter().enumerate() {
+            println!("{index:>3}  {name}");
+        }
+        return Ok(());
+    }
+
+    let Some(source) = source else {
+        return Err("Nothing to send: give a source (preset, generate, file) or --list-ports".into());
+    };
+
+    // Generate through the normal MIDI path so every option behaves the same
+    let temp_mid = std::env::temp_dir().join(format!("midi-cli-rs-send-{}.mid", std::process::id()));
+    let sequences = match source {
+        PlaySource::File { path, .. } => {
+            if OutputFormat::from_path(&path) != OutputFormat::Midi {
+                return Err("send needs a .mid file".into());
+            }
+            read_midi(&path)?
+        }
+        PlaySource::Preset(args) => {
+            if args.seeds.is_some() || args.stems {
+                return Err("--seeds and --stems cannot be used with send".into());
+            }
+            let result = run_preset(args, temp_mid.clone(), config).and_then(|()| Ok(read_midi(&temp_mid)?));
+            let _ = std::fs::remove_file(&temp_mid);
+            result?
+        }
+        PlaySource::Generate(args) => {
+            let result = run_generate(args, temp_mid.clone(), config).and_then(|()| Ok(read_midi(&temp_mid)?));
+            let _ = std::fs::remove_file(&temp_mid);
+            result?
+        }
+    };
+
+    let seconds = sequences.iter().map(|s| s.duration_seconds()).fold(0.0, f64::max);
+    eprintln!("Sending {seconds:.2}s to MIDI port {port}...");
+    midi_output::send(&sequences, port)?;
+    Ok(())
+}
+
+/// Generate a seed range into a directory (in parallel)
+///
+/// Returns the written MIDI paths and the requested output extension.
+fn run_seed_batch(
+    mood_name: &str,
+    mood: Mood,
+    config: &PresetConfig,
+    metadata: &MidiMetadata,
+    seeds: RangeInclusive<u64>,
+    output: &Path,
+) -> Result<(Vec<PathBuf>, String), Box<dyn std::error::Error>> {
+    // "out_dir/" -> out_dir/{mood}_seedN.mid, "out_dir/intro.wav" -> out_dir/intro_seedN.wav
+    let (dir, prefix, ext) = match output.extension().and_then(|s| s.to_str()) {
+        Some(ext) => (
+            output.parent().unwrap_or(Path::new(".")).to_path_buf(),
+            output.file_stem().and_then(|s| s.to_str()).unwrap_or(mood_name).to_string(),
+            ext.to_string(),
+        ),
+        None => (output.to_path_buf(), mood_name.to_string(), "mid".to_string()),
+    };
+
+    let count = seeds.clone().count();
+    let midi_paths = generate_seed_batch(mood, config, metadata, seeds, &dir, &prefix)?;
+    eprintln!("Generated {} {:?} presets in {}", count, mood, dir.display());
+
+    Ok((midi_paths, ext))
+}
+
+/// Write MIDI for an output path, choosing the format from its extension
+///
+/// Audio outputs get their MIDI file alongside (same name, .mid).
+fn write_midi_output(
+    sequences: &[NoteSequence],
+    metadata: &MidiMetadata,
+    output: &Path,
+) -> Result<(PathBuf, OutputFormat), Box<dyn std::error::Error>> {
+    if is_stdout(output) {
+        let mut stdout = io::stdout().lock();
+        write_midi_to_with(sequences, metadata, &mut stdout)?;
+        stdout.flush()?;
+        return Ok((output.to_path_buf(), OutputFormat::Midi));
+    }
+
+    let format = OutputFormat::from_path(output);
+    let midi_path = format.midi_path(output);
+    write_midi_with(sequences, metadata, &midi_path)?;
+    Ok((midi_path, format))
+}
+
+/// Output path from -o/--stdout; "-" means MIDI on stdout
+fn output_path(output: Option<PathBuf>, stdout: bool) -> PathBuf {
+    output.filter(|_| !stdout).unwrap_or_else(|| PathBuf::from("-"))
+}
+
+/// GM instrument name for a program number, or "unknown"
+fn instrument_name(program: u8) -> &'static str {
+    midi_cli_rs::INSTRUMENT_MAP
+        .iter()
+        .find(|i| i.program == program)
+        .map(|i| i.name)
+        .unwrap_or("unknown")
+}
+
+/// Whether an output path means stdout
+fn is_stdout(output: &Path) -> bool {
+    output == Path::new("-")
+}
+
+/// Render audio for an output path (no-op for MIDI) and report it
+fn render_output_audio(
+    midi_path: &Path,
+    output: &Path,
+    format: OutputFormat,
+    options: &RenderOptions,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if format.is_audio() {
+        // Settings not given on the command line come from the config file
+        let mut options = options.clone();
+        config.apply(&mut options);
+        render_output(midi_path, output, format, &options)?;
+        eprintln!("Rendered {}: {}", format.name(), output.display());
+    }
+    Ok(())
+}
+
+/// Parse a note name such as C4 or F#3 to a MIDI pitch
+fn parse_pitch(s: &str) -> Result<u8, String> {
+    Note::parse_pitch(s).map_err(|e| e.to_string())
+}
+
+/// Parse a --lufs loudness target (-70 to 0)
+fn parse_lufs(s: &str) -> Result<f64, String> {
+    s.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|lufs| (-70.0..=0.0).contains(lufs))
+        .ok_or_else(|| format!("Invalid loudness target: {s}. Expected LUFS from -70 to 0, e.g. -14"))
+}
+
+/// Parse a --gain for FluidSynth (0-10)
+fn parse_gain(s: &str) -> Result<f32, String> {
+    s.trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|gain| GAIN_RANGE.contains(gain))
+        .ok_or_else(|| format!("Invalid gain: {s}. Expected a number from 0 to 10, e.g. 0.6"))
+}
+
+/// Parse a --kit name such as "brush" to its drum-channel program
+fn parse_drum_kit(s: &str) -> Result<u8, String> {
+    resolve_drum_kit(s).ok_or_else(|| {
+        let names: Vec<&str> = DRUM_KITS.iter().map(|&(name, _)| name).collect();
+        format!("Unknown drum kit: {s}. Use one of: {}, or a program number 0-127", names.join(", "))
+    })
+}
+
+/// Parse an --intensity-curve such as "20-90"
+fn parse_intensity_curve(s: &str) -> Result<IntensityCurve, String> {
+    IntensityCurve::parse(s).ok_or_else(|| format!("Invalid intensity curve: {s}. Use START-END with values 0-100, e.g. 20-90"))
+}
+
+/// Parse a --structure such as "intro:2,main:8,outro:2"
+fn parse_structure(s: &str) -> Result<SongStructure, String> {
+    SongStructure::parse(s).ok_or_else(|| format!("Invalid structure: {s}. Use NAME:BARS,..., e.g. intro:2,main:8,outro:2"))
+}
+
+/// Parse a --marker such as "8:Chorus" (beat, then text)
+fn parse_marker(s: &str) -> Result<Marker, String> {
+    let (offset, text) = s.split_once(':').ok_or_else(|| format!("Invalid marker: {s}. Use BEAT:TEXT, e.g. 8:Chorus"))?;
+    let offset: f64 = offset
+        .trim()
+        .parse()
+        .ok()
+        .filter(|beat: &f64| *beat >= 0.0 && beat.is_finite())
+        .ok_or_else(|| format!("Invalid marker beat: {offset}. Expected a number of beats, 0 or more"))?;
+    Ok(Marker { offset, text: text.to_string() })
+}
+
+/// Parse a --pickup length in beats (greater than 0)
+fn parse_pickup(s: &str) -> Result<f64, String> {
+    s.trim()
+        .parse()
+        .ok()
+        .filter(|beats: &f64| *beats > 0.0 && beats.is_finite())
+        .ok_or_else(|| format!("Invalid pickup: {s}. Expected a number of beats greater than 0"))
+}
+
+/// Parse a length in seconds (0 or more), e.g. --pad-start or --crossfade
+fn parse_seconds(s: &str) -> Result<f64, String> {
+    s.trim()
+        .parse()
+        .ok()
+        .filter(|secs: &f64| *secs >= 0.0 && secs.is_finite())
+        .ok_or_else(|| format!("Invalid length: {s}. Expected a number of seconds, 0 or more"))
+}
+
+/// Parse a --soundfont-map entry such as "layer2=piano.sf2"
+fn parse_soundfont_map(s: &str) -> Result<(usize, PathBuf), String> {
+    let invalid = || format!("Invalid SoundFont mapping: {s}. Use layerN=PATH, e.g. layer2=piano.sf2");
+    let (layer, path) = s.split_once('=').ok_or_else(invalid)?;
+    let layer = layer
+        .trim()
+        .strip_prefix("layer")
+        .and_then(|n| n.parse().ok())
+        .filter(|&n: &usize| n > 0)
+        .ok_or_else(invalid)?;
+    if path.is_empty() {
+        return Err(invalid());
+    }
+    Ok((layer, PathBuf::from(path)))
+}
+
+/// Parse a key name such as Am or Eb
+fn parse_key(s: &str) -> Result<Key, String> {
+    Key::parse(s).ok_or_else(|| format!("Unknown key: {s}. Examples: C, Am, F#m, Bb"))
+}
+
+/// Parse a --category instrument family name
+fn parse_category(s: &str) -> Result<InstrumentCategory, String> {
+    InstrumentCategory::parse(s).ok_or_else(|| {
+        let names: Vec<&str> = InstrumentCategory::ALL.iter().map(|c| c.name()).collect();
+        format!("Unknown category: {s}. Use {}", names.join(", "))
+    })
+}
+
+/// Parse a --groove template name
+fn parse_groove(s: &str) -> Result<Groove, String> {
+    Groove::parse(s).ok_or_else(|| format!("Invalid groove: {s}. Use {}", midi_cli_rs::GROOVES.join(", ")))
+}
+
+/// Parse an --arp pattern name
+fn parse_arp_pattern(s: &str) -> Result<ArpPattern, String> {
+    ArpPattern::parse(s).ok_or_else(|| format!("Invalid arp pattern: {s}. Use up, down, updown or random"))
+}
+
+/// Parse a --polyrhythm ratio such as 3:2
+fn parse_polyrhythm(s: &str) -> Result<Polyrhythm, String> {
+    Polyrhythm::parse(s).ok_or_else(|| format!("Invalid polyrhythm: {s}. Use A:B with values 1-16, e.g. 3:2"))
+}
+
+/// Parse a --swing ratio between 0.5 (straight) and 0.75
+fn parse_swing(s: &str) -> Result<f64, String> {
+    s.trim()
+        .parse()
+        .ok()
+        .filter(|r: &f64| (0.5..=0.75).contains(r))
+        .ok_or_else(|| format!("Invalid swing ratio: {s}. Use 0.5 (straight) to 0.75, e.g. 0.67"))
+}
+
+/// Parse a --compress argument: "RATIO" or "RATIO,THRESHOLD" (e.g. "0.5,10")
+fn parse_compress(s: &str) -> Result<(f64, u8), String> {
+    parse_velocity_scale(s, 0.0..=1.0, "0.5")
+}
+
+/// Parse an --expand argument: "RATIO" or "RATIO,THRESHOLD" (e.g. "1.5,10")
+fn parse_expand(s: &str) -> Result<(f64, u8), String> {
+    parse_velocity_scale(s, 1.0..=4.0, "1.5")
+}
+
+fn parse_velocity_scale(s: &str, range: std::ops::RangeInclusive<f64>, example: &str) -> Result<(f64, u8), String> {
+    let (ratio, threshold) = s.split_once(',').unwrap_or((s, "0"));
+    let ratio: f64 = ratio
+        .trim()
+        .parse()
+        .ok()
+        .filter(|r| range.contains(r))
+        .ok_or_else(|| format!("Invalid ratio: {ratio}. Use {} to {}, e.g. {example}", range.start(), range.end()))?;
+    let threshold: u8 = threshold
+        .trim()
+        .parse()
+        .ok()
+        .filter(|t| *t <= 127)
+        .ok_or_else(|| format!("Invalid threshold: {threshold}. Use 0-127"))?;
+    Ok((ratio, threshold))
+}
+
+/// Parse a --humanize argument: "BEATS" or "BEATS,VELOCITY" (e.g. "0.03,10")
+fn parse_humanize(s: &str) -> Result<(f64, u8), String> {
+    let (timing, velocity) = s.split_once(',').unwrap_or((s, "8"));
+    let timing: f64 = timing
+        .trim()
+        .parse()
+        .ok()
+        .filter(|t: &f64| (0.0..=1.0).contains(t))
+        .ok_or_else(|| format!("Invalid timing jitter: {timing}. Use beats between 0 and 1, e.g. 0.02"))?;
+    let velocity: u8 = velocity
+        .trim()
+        .parse()
+        .ok()
+        .filter(|v| *v <= 127)
+        .ok_or_else(|| format!("Invalid velocity jitter: {velocity}. Use 0-127"))?;
+    Ok((timing, velocity))
+}
+
+/// Parse a --time-sig argument such as "3/4"
+fn parse_time_signature(s: &str) -> Result<TimeSignature, String> {
+    TimeSignature::parse(s).ok_or_else(|| format!("Invalid time signature: {s}. Examples: 4/4, 3/4, 6/8"))
+}
+
+/// Handle import command for ABC and MusicXML files
+fn handle_import(format: ImportFormat, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let (melody, file, output, key, tempo, instrument, soundfont, verbose) = match format {
+        ImportFormat::Abc {
+            file,
+            output,
+            key,
+            tempo,
+            instrument,
+            soundfont,
+            verbose,
+        } => {
+            let melody = AbcParser::parse_file(&file)?;
+            (melody, file, output, key, tempo, instrument, soundfont, verbose)
+        }
+        ImportFormat::Musicxml {
+            file,
+            output,
+            key,
+            tempo,
+            instrument,
+            soundfont,
+            verbose,
+        } => {
+            let melody = MusicXmlParser::parse_file(&file)?;
+            (melody, file, output, key, tempo, instrument, soundfont, verbose)
+        }
+    };
+    let output = config.output_path(output);
+
+    // Verbose output
+    if verbose {
+        eprintln!("--- Import Details ---");
+        eprintln!("File: {}", file.display());
+        if let Some(title) = &melody.title {
+            eprintln!("Title: {}", title);
+        }
         if let Some(k) = &melody.key {
             eprintln!("Key: {}", k);
         }
@@ -921,17 +2358,8 @@ fn handle_import(format: ImportFormat) -> Result<(), Box<dyn std::error::Error>>
         return Err("No notes to generate".into());
     }
 
-    // Determine output format from extension
-    let ext = output.extension().and_then(|s| s.to_str()).unwrap_or("mid");
-
-    let midi_path = if ext == "wav" {
-        output.with_extension("mid")
-    } else {
-        output.clone()
-    };
-
-    // Write MIDI file
-    write_midi(&sequences, &midi_path)?;
+    // Write MIDI file (next to the audio output if one was requested)
+    let (midi_path, format) = write_midi_output(&sequences, &MidiMetadata::default(), &output)?;
 
     let title_str = melody
         .title
@@ -957,160 +2385,9 @@ fn handle_import(format: ImportFormat) -> Result<(), Box<dyn std::error::Error>>
         midi_path.display()
     );
 
-    // Render to WAV if requested
-    if ext == "wav" {
-        render_wav(&midi_path, &output, soundfont.as_ref(), None)?;
-        eprintln!("Rendered WAV: {}", output.display());
-    }
+    // Render audio if requested
+    let options = RenderOptions { soundfont, ..Default::default() };
+    render_output_audio(&midi_path, &output, format, &options, config)?;
 
     Ok(())
 }
-
-/// Render MIDI file to WAV using FluidSynth
-fn render_wav(
-    midi_path: &Path,
-    wav_path: &Path,
-    soundfont: Option<&PathBuf>,
-    target_duration: Option<f64>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Find FluidSynth
-    let fluidsynth = find_fluidsynth()?;
-
-    // Find SoundFont
-    let sf = if let Some(sf) = soundfont {
-        sf.to_path_buf()
-    } else {
-        find_soundfont()?
-    };
-    eprintln!("Using SoundFont: {}", sf.display());
-
-    // Determine output path (use temp file if trimming needed)
-    let needs_trim = target_duration.is_some();
-    let render_path = if needs_trim {
-        wav_path.with_extension("tmp.wav")
-    } else {
-        wav_path.to_path_buf()
-    };
-
-    // Run FluidSynth
-    // Usage: fluidsynth [options] soundfont.sf2 midifile.mid
-    // -F option must come before soundfont and midi file
-    let status = Command::new(&fluidsynth)
-        .args([
-            "-ni", // Non-interactive, no shell
-            "-g",
-            "1.0", // Gain
-            "-r",
-            "44100", // Sample rate
-            "-F",
-            render_path.to_str().unwrap(), // Output WAV file
-            sf.to_str().unwrap(),          // SoundFont file
-            midi_path.to_str().unwrap(),   // Input MIDI file
-        ])
-        .status()?;
-
-    if !status.success() {
-        return Err(format!("FluidSynth failed with status: {status}").into());
-    }
-
-    // Trim to target duration if specified (removes reverb tail)
-    if let Some(duration) = target_duration {
-        let fade_duration = 0.5; // 500ms fade out for smooth ending
-        let trim_result = Command::new("ffmpeg")
-            .args([
-                "-y",                                      // Overwrite output
-                "-i", render_path.to_str().unwrap(),       // Input file
-                "-t", &format!("{:.2}", duration),         // Duration limit
-                "-af", &format!("afade=t=out:st={:.2}:d={:.2}", duration - fade_duration, fade_duration),
-                wav_path.to_str().unwrap(),                // Output file
-            ])
-            .output();
-
-        // Clean up temp file
-        let _ = std::fs::remove_file(&render_path);
-
-        match trim_result {
-            Ok(output) if output.status.success() => {}
-            Ok(output) => {
-                // ffmpeg failed, but we still have the untrimmed file
-                eprintln!("Warning: ffmpeg trim failed, using untrimmed audio");
-                eprintln!("{}", String::from_utf8_lossy(&output.stderr));
-            }
-            Err(_) => {
-                // ffmpeg not available, copy temp to final
-                eprintln!("Warning: ffmpeg not found, audio may be longer than requested");
-            }
-        }
-    }
-
-    Ok(())
-}
-
-/// Find FluidSynth binary
-fn find_fluidsynth() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    // Check if fluidsynth is in PATH
-    if Command::new("fluidsynth").arg("--version").output().is_ok() {
-        return Ok(PathBuf::from("fluidsynth"));
-    }
-
-    // Check common locations
-    let paths = [
-        "/opt/homebrew/bin/fluidsynth",
-        "/usr/local/bin/fluidsynth",
-        "/usr/bin/fluidsynth",
-    ];
-
-    for path in paths {
-        let p = PathBuf::from(path);
-        if p.exists() {
-            return Ok(p);
-        }
-    }
-
-    Err("FluidSynth not found. Install with:\n  macOS: brew install fluid-synth\n  Ubuntu: apt install fluidsynth".into())
-}
-
-/// Find a SoundFont file
-fn find_soundfont() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    // Check user's home directory first (~/.soundfonts/)
-    if let Some(home) = std::env::var_os("HOME") {
-        let home_path = PathBuf::from(home);
-        let user_soundfonts = [
-            home_path.join(".soundfonts/default.sf2"),
-            home_path.join(".soundfonts/GeneralUser_GS.sf2"),
-            home_path.join(".soundfonts/FluidR3_GM.sf2"),
-        ];
-        for p in user_soundfonts {
-            if p.exists() {
-                return Ok(p);
-            }
-        }
-    }
-
-    // Prioritize MIT-licensed soundfonts for clear commercial use rights
-    let paths = [
-        // Project local (preferred) - MIT licensed
-        "./soundfonts/FluidR3_GM.sf2",
-        "./soundfonts/GeneralUser_GS.sf2",
-        "./soundfonts/MuseScore_General.sf2",
-        "./soundfonts/default.sf2",
-        // macOS Homebrew - FluidR3_GM is MIT licensed
-        "/opt/homebrew/share/sounds/sf2/FluidR3_GM.sf2",
-        "/opt/homebrew/share/soundfonts/default.sf2",
-        "/usr/local/share/soundfonts/default.sf2",
-        // Linux - FluidR3_GM is MIT licensed
-        "/usr/share/sounds/sf2/FluidR3_GM.sf2",
-        "/usr/share/soundfonts/FluidR3_GM.sf2",
-        "/usr/share/soundfonts/default.sf2",
-        "/usr/share/soundfonts/freepats-general-midi.sf2",
-    ];
-
-    for path in paths {
-        let p = PathBuf::from(path);
-        if p.exists() {
-            return Ok(p);
-        }
-    }
-
-    Err("No SoundFont found. Install FluidR3_GM or specify --soundfont.\n  macOS: brew install fluid-synth (includes SoundFont)\n  Ubuntu: apt install fluid-soundfont-gm\n  Or place a .sf2 file in ~/.soundfonts/".into())
-}
@@ -0,0 +1,115 @@
+//! Metronome click and count-in tracks
+//!
+//! A click is a drum-channel sequence with an accented hit on the first beat
+//! of each bar. Beats follow the time signature's beat unit, so 6/8 clicks
+//! six eighths per bar.
+
+use super::sequence::DRUM_CHANNEL;
+use super::{Note, NoteSequence};
+
+/// GM percussion note for the first beat of a bar (cowbell)
+pub const CLICK_ACCENT: u8 = 56;
+
+/// GM percussion note for the other beats (side stick)
+pub const CLICK_BEAT: u8 = 37;
+
+/// Prepend `count_in_bars` bars of click before the content, shifting every
+/// sequence (and its tempo changes) later by the count-in length. With
+/// `throughout`, the click keeps going to the end of the last bar with notes.
+/// The tempo and time signature come from the first sequence; the click is
+/// appended as the last sequence.
+pub fn add_click(sequences: &mut Vec<NoteSequence>, count_in_bars: u32, throughout: bool) {
+    let Some(first) = sequences.first() else {
+        return;
+    };
+    let time_signature = first.time_signature;
+    let bar = time_signature.beats_per_bar();
+    let count_in = count_in_bars as f64 * bar;
+
+    for seq in sequences.iter_mut() {
+        seq.shift(count_in);
+    }
+
+    let end = if throughout {
+        let content_end = sequences
+            .iter()
+            .map(|s| s.duration_beats())
+            .fold(count_in, f64::max);
+        (content_end / bar - 1e-6).ceil().max(0.0) * bar
+    } else {
+        count_in
+    };
+
+    let beat = 4.0 / time_signature.denominator as f64;
+    let beats = (end / beat).round() as usize;
+    let notes = (0..beats)
+        .map(|i| {
+            let accent = i % time_signature.numerator as usize == 0;
+            let (pitch, velocity) = if accent {
+                (CLICK_ACCENT, 100)
+            } else {
+                (CLICK_BEAT, 80)
+            };
+            Note::new(pitch, beat / 2.0, velocity, i as f64 * beat)
+        })
+        .collect();
+
+    let first = &sequences[0];
+    let mut click = NoteSequence::new(notes, 0, first.tempo);
+    click.channel = DRUM_CHANNEL;
+    click.tempo_changes = first.tempo_changes.clone();
+    click.time_signature = time_signature;
+    sequences.push(click);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::sequence::TimeSignature;
+
+    fn melody() -> Vec<NoteSequence> {
+        let notes = vec![Note::new(60, 1.0, 80, 0.0), Note::new(64, 1.5, 80, 1.0)];
+        vec![NoteSequence::new(notes, 0, 120)]
+    }
+
+    #[test]
+    fn test_two_bar_count_in() {
+        let mut sequences = melody();
+        add_click(&mut sequences, 2, false);
+
+        assert_eq!(sequences.len(), 2);
+        let offsets: Vec<f64> = sequences[0].notes.iter().map(|n| n.offset).collect();
+        assert_eq!(offsets, [8.0, 9.0]);
+
+        let click = &sequences[1];
+        assert_eq!(click.channel, DRUM_CHANNEL);
+        assert_eq!(click.notes.len(), 8);
+        assert_eq!(click.notes[4].offset, 4.0);
+        let accents = click
+            .notes
+            .iter()
+            .filter(|n| n.pitch == CLICK_ACCENT)
+            .count();
+        assert_eq!(accents, 2);
+    }
+
+    #[test]
+    fn test_click_throughout_fills_last_bar() {
+        let mut sequences = melody();
+        add_click(&mut sequences, 1, true);
+        // 1 bar count-in + melody ending at beat 6.5 -> two bars of click
+        assert_eq!(sequences[1].notes.len(), 8);
+    }
+
+    #[test]
+    fn test_click_follows_beat_unit() {
+        let mut sequences = melody();
+        sequences[0].time_signature = TimeSignature::new(6, 8);
+        add_click(&mut sequences, 1, false);
+
+        assert_eq!(sequences[0].notes[0].offset, 3.0);
+        let click = &sequences[1].notes;
+        assert_eq!(click.len(), 6);
+        assert_eq!(click[1].offset, 0.5);
+    }
+}
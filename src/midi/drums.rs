@@ -0,0 +1,98 @@
+//! General MIDI drum kits and drum note names
+//!
+//! On the drum channel each pitch selects a sound rather than a note, and
+//! the program number selects the kit. Names here can be used wherever a
+//! pitch is expected (`kick:1:100`) and with `generate --kit`.
+
+/// The kit every GM synth starts with on the drum channel
+pub const STANDARD_KIT: u8 = 0;
+
+/// GM drum kits (name, program on the drum channel)
+pub const DRUM_KITS: &[(&str, u8)] = &[
+    ("standard", STANDARD_KIT),
+    ("room", 8),
+    ("power", 16),
+    ("electronic", 24),
+    ("tr808", 25),
+    ("jazz", 32),
+    ("brush", 40),
+    ("orchestra", 48),
+];
+
+/// GM percussion sounds (name, pitch on the drum channel)
+pub const DRUM_NOTES: &[(&str, u8)] = &[
+    ("kick", 36),
+    ("kick2", 35),
+    ("sidestick", 37),
+    ("rimshot", 37),
+    ("snare", 38),
+    ("clap", 39),
+    ("snare2", 40),
+    ("floor-tom", 41),
+    ("hihat", 42),
+    ("closed-hihat", 42),
+    ("pedal-hihat", 44),
+    ("low-tom", 45),
+    ("open-hihat", 46),
+    ("mid-tom", 47),
+    ("crash", 49),
+    ("high-tom", 50),
+    ("ride", 51),
+    ("china", 52),
+    ("ride-bell", 53),
+    ("tambourine", 54),
+    ("splash", 55),
+    ("cowbell", 56),
+    ("maracas", 70),
+    ("claves", 75),
+];
+
+/// Look up a drum kit by name, or a program number (0-127)
+pub fn resolve_drum_kit(name: &str) -> Option<u8> {
+    let lower = name.trim().to_lowercase();
+    let lower = lower.strip_suffix("-kit").unwrap_or(&lower);
+    DRUM_KITS
+        .iter()
+        .find(|(kit, _)| *kit == lower)
+        .map(|&(_, program)| program)
+        .or_else(|| lower.parse::<u8>().ok().filter(|&p| p <= 127))
+}
+
+/// Name of the kit selected by a drum-channel program, if it is a GM kit
+pub fn drum_kit_name(program: u8) -> Option<&'static str> {
+    DRUM_KITS
+        .iter()
+        .find(|&&(_, p)| p == program)
+        .map(|&(name, _)| name)
+}
+
+/// Pitch of a named percussion sound (kick, snare, hihat, ...)
+pub fn drum_note(name: &str) -> Option<u8> {
+    let lower = name.trim().to_lowercase();
+    DRUM_NOTES
+        .iter()
+        .find(|(drum, _)| *drum == lower)
+        .map(|&(_, pitch)| pitch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_drum_kit() {
+        assert_eq!(resolve_drum_kit("Brush"), Some(40));
+        assert_eq!(resolve_drum_kit("jazz-kit"), Some(32));
+        assert_eq!(resolve_drum_kit("25"), Some(25));
+        assert_eq!(resolve_drum_kit("marching"), None);
+        assert_eq!(drum_kit_name(STANDARD_KIT), Some("standard"));
+    }
+
+    #[test]
+    fn test_drum_notes_are_unique_and_in_gm_range() {
+        for (name, pitch) in DRUM_NOTES {
+            assert!((35..=81).contains(pitch), "{name}");
+            assert_eq!(drum_note(&name.to_uppercase()), Some(*pitch));
+        }
+    }
+}
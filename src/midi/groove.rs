@@ -0,0 +1,124 @@
+//! Groove templates
+//!
+//! A groove is a repeating pattern of micro-timing and accents, one entry per
+//! grid slot. Applying it snaps each note to the nearest slot, then nudges it
+//! by that slot's timing offset and adds its accent to the velocity.
+
+use super::NoteSequence;
+
+/// Names of the built-in grooves, for help text
+pub const GROOVES: &[&str] = &["straight", "laid-back", "pushed"];
+
+/// Micro-timing and accent pattern per grid slot
+#[derive(Debug, Clone, PartialEq)]
+pub struct Groove {
+    /// Offset in beats added to notes in each slot
+    pub timing: Vec<f64>,
+    /// Velocity change for notes in each slot
+    pub accents: Vec<i8>,
+}
+
+impl Groove {
+    /// A built-in groove by name: straight, laid-back (or laidback), pushed.
+    ///
+    /// Built-ins span one 4/4 bar of eighth-note slots, so use them with a
+    /// subdivision of 0.5 beats. Beats 2 and 4 fall on slots 2 and 6.
+    pub fn parse(name: &str) -> Option<Self> {
+        // Accent the downbeats, lighten the off-beats
+        let accents = vec![8, -6, 4, -6, 6, -6, 4, -6];
+        let timing = match name.to_lowercase().as_str() {
+            "straight" => vec![0.0; 8],
+            // Behind the beat, dragging most on the backbeat
+            "laid-back" | "laidback" => vec![0.0, 0.02, 0.04, 0.02, 0.01, 0.02, 0.04, 0.02],
+            // Ahead of the beat, rushing most on the backbeat
+            "pushed" => vec![0.0, -0.02, -0.04, -0.02, -0.01, -0.02, -0.04, -0.02],
+            _ => return None,
+        };
+        Some(Self { timing, accents })
+    }
+
+    /// Number of slots before the pattern repeats
+    pub fn len(&self) -> usize {
+        self.timing.len().max(self.accents.len())
+    }
+
+    /// Whether the groove has no slots
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl NoteSequence {
+    /// Apply a groove on a grid of `subdivision` beats: each note snaps to
+    /// the nearest slot, moves by the slot's timing offset, and has the
+    /// slot's accent added to its velocity (kept within 1-127). The pattern
+    /// repeats every `groove.len()` slots from the start of the sequence.
+    pub fn apply_groove(&mut self, groove: &Groove, subdivision: f64) {
+        if groove.is_empty() || subdivision <= 0.0 {
+            return;
+        }
+
+        for note in &mut self.notes {
+            let slot = (note.offset / subdivision).round().max(0.0);
+            let index = slot as usize % groove.len();
+            let timing = groove.timing.get(index).copied().unwrap_or(0.0);
+            let accent = groove.accents.get(index).copied().unwrap_or(0);
+
+            note.offset = (slot * subdivision + timing).max(0.0);
+            note.velocity = (note.velocity as i16 + accent as i16).clamp(1, 127) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::Note;
+
+    fn quarter_notes() -> NoteSequence {
+        let notes = (0..4).map(|i| Note::new(60, 1.0, 80, i as f64)).collect();
+        NoteSequence::new(notes, 0, 120)
+    }
+
+    #[test]
+    fn test_pushed_groove_advances_backbeat() {
+        let groove = Groove::parse("pushed").unwrap();
+        let mut seq = quarter_notes();
+        seq.apply_groove(&groove, 0.5);
+
+        let offsets: Vec<f64> = seq.notes.iter().map(|n| n.offset).collect();
+        assert_eq!(
+            offsets,
+            [
+                0.0,
+                1.0 + groove.timing[2],
+                2.0 + groove.timing[4],
+                3.0 + groove.timing[6]
+            ]
+        );
+        assert!(groove.timing[2] < 0.0);
+
+        let velocities: Vec<u8> = seq.notes.iter().map(|n| n.velocity).collect();
+        assert_eq!(velocities, [88, 84, 86, 84]);
+    }
+
+    #[test]
+    fn test_groove_snaps_to_nearest_slot() {
+        let mut seq = NoteSequence::new(
+            vec![Note::new(60, 0.5, 80, 0.47), Note::new(62, 0.5, 80, 1.04)],
+            0,
+            120,
+        );
+        seq.apply_groove(&Groove::parse("straight").unwrap(), 0.5);
+        let offsets: Vec<f64> = seq.notes.iter().map(|n| n.offset).collect();
+        assert_eq!(offsets, [0.5, 1.0]);
+    }
+
+    #[test]
+    fn test_builtin_names_parse() {
+        for name in GROOVES {
+            assert_eq!(Groove::parse(name).unwrap().len(), 8);
+        }
+        assert!(Groove::parse("shuffle").is_none());
+    }
+}
@@ -1,11 +1,18 @@
 //! MIDI generation module
 //!
-//! Provides note representation, sequence building, and MIDI file output.
+//! Provides note representation, sequence building, MIDI file input/output, and realtime output.
 
+pub mod click;
+pub mod drums;
+pub mod groove;
 pub mod note;
+pub mod output;
+pub mod reader;
+pub mod schedule;
 pub mod sequence;
 pub mod writer;
 
 pub use note::Note;
+pub use reader::read_midi;
 pub use sequence::NoteSequence;
 pub use writer::write_midi;
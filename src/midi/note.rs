@@ -5,7 +5,14 @@
 //! Examples:
 //! - `C4:1:80` - Middle C, 1 beat, velocity 80
 //! - `F#3:0.5:100@2` - F# below middle C, half beat, velocity 100, starting at beat 2
+//! - `A4+50:1:80` - A4 detuned 50 cents sharp
+//!
+//! With [`Note::parse_degrees`] the pitch is a scale degree in a key instead:
+//! - `5:1:80` - the fifth of the key, 1 beat
+//! - `b3+:2:90` - a flattened third an octave up, 2 beats
 
+use super::drums::drum_note;
+use crate::preset::{Key, degree_pitch};
 use std::str::FromStr;
 use thiserror::Error;
 
@@ -16,10 +23,18 @@ pub enum NoteError {
     BadFormat(String),
 
     #[error(
-        "Bad pitch: {0}. Expected note name (A-G) with optional accidental (#/b) and octave (0-10)"
+        "Bad pitch: {0}. Expected note name (A-G) with optional accidental (#/b) and octave (0-10), or a drum name"
     )]
     BadPitch(String),
 
+    #[error(
+        "Bad degree: {0}. Expected scale degree 1-7 with optional accidental (b/#) before and octave shifts (+/-) after"
+    )]
+    BadDegree(String),
+
+    #[error("Bad cents: {0}. Expected a detuning from -100 to +100 after the pitch, e.g. A4+50")]
+    BadCents(String),
+
     #[error("Bad duration: {0}. Expected positive number")]
     BadDuration(String),
 
@@ -44,6 +59,13 @@ pub struct Note {
 
     /// Start time in beats from sequence start
     pub offset: f64,
+
+    /// MIDI channel (0-15) for this note alone; `None` uses the sequence's
+    pub channel: Option<u8>,
+
+    /// Detuning in cents (-100 to 100, 0 = in tune), written as pitch bend
+    /// on a channel of its own
+    pub cents: i16,
 }
 
 impl Note {
@@ -54,15 +76,21 @@ impl Note {
             duration,
             velocity,
             offset,
+            channel: None,
+            cents: 0,
         }
     }
 
-    /// Parse a note name (like "C4", "F#3", "Bb5") to MIDI pitch number
+    /// Parse a note name (like "C4", "F#3", "Bb5") or a drum name (like
+    /// "kick", "snare") to MIDI pitch number
     pub fn parse_pitch(pitch_str: &str) -> Result<u8, NoteError> {
         let pitch_str = pitch_str.trim();
         if pitch_str.is_empty() {
             return Err(NoteError::BadPitch(pitch_str.to_string()));
         }
+        if let Some(pitch) = drum_note(pitch_str) {
+            return Ok(pitch);
+        }
 
         let mut chars = pitch_str.chars().peekable();
 
@@ -115,8 +143,75 @@ impl Note {
         Ok(midi_pitch as u8)
     }
 
+    /// Parse a pitch with an optional detuning in cents ("A4+50", "C4-25")
+    pub fn parse_pitch_cents(pitch_str: &str) -> Result<(u8, i16), NoteError> {
+        let pitch_str = pitch_str.trim();
+        // Drum names contain '-', so only a numeric suffix counts as cents
+        let split = pitch_str
+            .rfind(['+', '-'])
+            .filter(|&pos| pos > 0 && pitch_str[pos + 1..].chars().all(|c| c.is_ascii_digit()));
+        let Some(pos) = split else {
+            return Ok((Self::parse_pitch(pitch_str)?, 0));
+        };
+
+        let cents: i16 = pitch_str[pos..]
+            .trim_start_matches('+')
+            .parse()
+            .ok()
+            .filter(|c: &i16| (-100..=100).contains(c))
+            .ok_or_else(|| NoteError::BadCents(pitch_str.to_string()))?;
+        Ok((Self::parse_pitch(&pitch_str[..pos])?, cents))
+    }
+
+    /// Parse a scale degree (like "1", "b3", "#4", "5+", "7-") to a MIDI
+    /// pitch in `key`, where degree 1 is the key's root in octave 4
+    ///
+    /// Each trailing `+` raises the note an octave and each `-` lowers it.
+    pub fn parse_degree(degree_str: &str, key: Key) -> Result<u8, NoteError> {
+        let degree_str = degree_str.trim();
+        let bad = || NoteError::BadDegree(degree_str.to_string());
+
+        let (accidental, rest) = match degree_str.strip_prefix('b') {
+            Some(rest) => (-1, rest),
+            None => degree_str
+                .strip_prefix('#')
+                .map_or((0, degree_str), |rest| (1, rest)),
+        };
+        let digits = rest.trim_end_matches(['+', '-']);
+        let octaves = rest[digits.len()..]
+            .chars()
+            .map(|c| if c == '+' { 1 } else { -1 })
+            .sum::<i32>();
+        let degree: i32 = digits
+            .parse()
+            .ok()
+            .filter(|d| (1..=7).contains(d))
+            .ok_or_else(bad)?;
+
+        let pitch = degree_pitch(key, degree - 1) as i32 + accidental + 12 * octaves;
+        u8::try_from(pitch)
+            .ok()
+            .filter(|&p| p <= 127)
+            .ok_or_else(bad)
+    }
+
     /// Parse a note from string format: "PITCH:DURATION:VELOCITY[@OFFSET]"
     pub fn parse(s: &str) -> Result<Self, NoteError> {
+        Self::parse_with(s, Self::parse_pitch_cents)
+    }
+
+    /// Parse a note whose pitch is a scale degree in `key` (see
+    /// [`Note::parse_degree`]): "DEGREE:DURATION:VELOCITY[@OFFSET]"
+    pub fn parse_degree_note(s: &str, key: Key) -> Result<Self, NoteError> {
+        Self::parse_with(s, |degree| Ok((Self::parse_degree(degree, key)?, 0)))
+    }
+
+    /// Parse "PITCH:DURATION:VELOCITY[@OFFSET]" with a given parser for the
+    /// pitch and its detuning
+    fn parse_with(
+        s: &str,
+        parse_pitch: impl Fn(&str) -> Result<(u8, i16), NoteError>,
+    ) -> Result<Self, NoteError> {
         let s = s.trim();
 
         // Split on @ to get offset if present
@@ -139,7 +234,7 @@ impl Note {
             return Err(NoteError::BadFormat(s.to_string()));
         }
 
-        let pitch = Self::parse_pitch(parts[0])?;
+        let (pitch, cents) = parse_pitch(parts[0])?;
 
         let duration: f64 = parts[1]
             .parse()
@@ -155,7 +250,10 @@ impl Note {
             return Err(NoteError::BadVelocity(parts[2].to_string()));
         }
 
-        Ok(Self::new(pitch, duration, velocity, offset))
+        Ok(Self {
+            cents,
+            ..Self::new(pitch, duration, velocity, offset)
+        })
     }
 
     /// Parse multiple notes from comma-separated string
@@ -164,6 +262,45 @@ impl Note {
             .map(|note_str| Self::parse(note_str.trim()))
             .collect()
     }
+
+    /// Parse multiple comma-separated scale-degree notes in `key`
+    pub fn parse_degrees(s: &str, key: Key) -> Result<Vec<Self>, NoteError> {
+        s.split(',')
+            .map(|note_str| Self::parse_degree_note(note_str.trim(), key))
+            .collect()
+    }
+
+    /// Copy moved by `semitones`, clamped to the MIDI range (0-127)
+    pub fn transposed(&self, semitones: i16) -> Self {
+        Self {
+            pitch: (self.pitch as i16 + semitones).clamp(0, 127) as u8,
+            ..self.clone()
+        }
+    }
+
+    /// Copy starting at `offset` beats
+    pub fn at(&self, offset: f64) -> Self {
+        Self {
+            offset,
+            ..self.clone()
+        }
+    }
+
+    /// Copy with a different duration in beats
+    pub fn with_duration(&self, duration: f64) -> Self {
+        Self {
+            duration,
+            ..self.clone()
+        }
+    }
+
+    /// Copy with a different velocity, capped at 127
+    pub fn with_velocity(&self, velocity: u8) -> Self {
+        Self {
+            velocity: velocity.min(127),
+            ..self.clone()
+        }
+    }
 }
 
 impl FromStr for Note {
@@ -243,6 +380,16 @@ mod tests {
         assert!(Note::parse_pitch("").is_err());
     }
 
+    #[test]
+    fn test_parse_pitch_drum_names() {
+        assert_eq!(Note::parse_pitch("kick").unwrap(), 36);
+        assert_eq!(
+            Note::parse("Snare:0.5:100@1").unwrap(),
+            Note::new(38, 0.5, 100, 1.0)
+        );
+        assert!(Note::parse_pitch("cymbal").is_err());
+    }
+
     // ==================
     // Note Parsing Tests
     // ==================
@@ -352,6 +499,85 @@ mod tests {
         assert_eq!(notes[2].offset, 2.0);
     }
 
+    // ================
+    // Fluent Helpers
+    // ================
+
+    #[test]
+    fn test_parse_pitch_cents() {
+        assert_eq!(Note::parse_pitch_cents("A4+50").unwrap(), (69, 50));
+        assert_eq!(Note::parse_pitch_cents("C#4-25").unwrap(), (61, -25));
+        assert_eq!(Note::parse_pitch_cents("A4").unwrap(), (69, 0));
+        assert_eq!(Note::parse_pitch_cents("open-hihat").unwrap(), (46, 0));
+        assert_eq!(Note::parse("A4+100:1:80").unwrap().cents, 100);
+        assert!(matches!(
+            Note::parse_pitch_cents("A4+150"),
+            Err(NoteError::BadCents(_))
+        ));
+        assert!(matches!(
+            Note::parse_pitch_cents("A4+"),
+            Err(NoteError::BadCents(_))
+        ));
+    }
+
+    // ====================
+    // Scale Degree Tests
+    // ====================
+
+    #[test]
+    fn test_parse_degree_in_a_minor() {
+        assert_eq!(Note::parse_degree("1", Key::Am).unwrap(), 69); // A4
+        assert_eq!(Note::parse_degree("3", Key::Am).unwrap(), 72); // C5
+        assert_eq!(Note::parse_degree("5", Key::Am).unwrap(), 76); // E5
+    }
+
+    #[test]
+    fn test_parse_degree_accidentals_and_octaves() {
+        assert_eq!(Note::parse_degree("b3", Key::C).unwrap(), 63);
+        assert_eq!(Note::parse_degree("#4", Key::C).unwrap(), 66);
+        assert_eq!(Note::parse_degree("1+", Key::C).unwrap(), 72);
+        assert_eq!(Note::parse_degree("5--", Key::C).unwrap(), 43);
+        for bad in ["0", "8", "", "b", "3x", "1+++++++"] {
+            assert!(
+                matches!(
+                    Note::parse_degree(bad, Key::C),
+                    Err(NoteError::BadDegree(_))
+                ),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_degrees() {
+        let notes = Note::parse_degrees("1:1:80,3:1:80,5:2:90@2", Key::Am).unwrap();
+        let pitches: Vec<u8> = notes.iter().map(|n| n.pitch).collect();
+        assert_eq!(pitches, [69, 72, 76]);
+        assert_eq!(notes[2].offset, 2.0);
+        assert_eq!(notes[2].duration, 2.0);
+    }
+
+    #[test]
+    fn test_transposed() {
+        let note = Note::new(60, 1.0, 80, 0.0);
+        assert_eq!(note.transposed(12).pitch, 72);
+        assert_eq!(note.transposed(-3).pitch, 57);
+        assert_eq!(note.transposed(100).pitch, 127);
+        assert_eq!(note.transposed(-100).pitch, 0);
+    }
+
+    #[test]
+    fn test_helpers_change_only_one_field() {
+        let note = Note::new(60, 1.0, 80, 0.0);
+        assert_eq!(note.at(2.5), Note::new(60, 1.0, 80, 2.5));
+        assert_eq!(note.with_duration(0.5), Note::new(60, 0.5, 80, 0.0));
+        assert_eq!(note.with_velocity(200), Note::new(60, 1.0, 127, 0.0));
+        assert_eq!(
+            note.transposed(7).at(1.0).with_velocity(90),
+            Note::new(67, 1.0, 90, 1.0)
+        );
+    }
+
     // ================
     // FromStr Trait
     // ================
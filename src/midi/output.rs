@@ -0,0 +1,92 @@
+//! Realtime MIDI output to hardware or virtual synths
+//!
+//! Port access uses the optional `midi-out` feature (midir); the timing comes
+//! from [`super::schedule`], which is always available.
+
+use super::NoteSequence;
+#[cfg(feature = "midi-out")]
+use super::schedule::{all_notes_off, schedule};
+use thiserror::Error;
+
+/// Client name shown to other MIDI applications
+#[cfg(feature = "midi-out")]
+const CLIENT_NAME: &str = "midi-cli-rs";
+
+/// Errors that can occur when sending MIDI to an output port
+#[derive(Debug, Error)]
+pub enum MidiOutError {
+    #[error("MIDI output not compiled in (rebuild with --features midi-out)")]
+    NotEnabled,
+
+    #[error("No MIDI output port {index} ({available} available, see --list-ports)")]
+    NoSuchPort { index: usize, available: usize },
+
+    #[error("MIDI port error: {0}")]
+    Port(String),
+}
+
+/// Names of the available MIDI output ports, in port-index order
+#[cfg(feature = "midi-out")]
+pub fn list_ports() -> Result<Vec<String>, MidiOutError> {
+    let output =
+        midir::MidiOutput::new(CLIENT_NAME).map_err(|e| MidiOutError::Port(e.to_string()))?;
+    output
+        .ports()
+        .iter()
+        .map(|port| {
+            output
+                .port_name(port)
+                .map_err(|e| MidiOutError::Port(e.to_string()))
+        })
+        .collect()
+}
+
+/// Play sequences in real time on output port `index`, blocking until done
+///
+/// Finishes with "all notes off" on every channel used.
+#[cfg(feature = "midi-out")]
+pub fn send(sequences: &[NoteSequence], index: usize) -> Result<(), MidiOutError> {
+    use std::time::{Duration, Instant};
+
+    let output =
+        midir::MidiOutput::new(CLIENT_NAME).map_err(|e| MidiOutError::Port(e.to_string()))?;
+    let ports = output.ports();
+    let port = ports.get(index).ok_or(MidiOutError::NoSuchPort {
+        index,
+        available: ports.len(),
+    })?;
+    let mut connection = output
+        .connect(port, CLIENT_NAME)
+        .map_err(|e| MidiOutError::Port(e.to_string()))?;
+
+    let start = Instant::now();
+    for event in schedule(sequences) {
+        let due = start + Duration::from_millis(event.at_ms);
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+        connection
+            .send(&event.message)
+            .map_err(|e| MidiOutError::Port(e.to_string()))?;
+    }
+
+    for message in all_notes_off(sequences) {
+        connection
+            .send(&message)
+            .map_err(|e| MidiOutError::Port(e.to_string()))?;
+    }
+    connection.close();
+    Ok(())
+}
+
+/// Names of the available MIDI output ports (unavailable in this build)
+#[cfg(not(feature = "midi-out"))]
+pub fn list_ports() -> Result<Vec<String>, MidiOutError> {
+    Err(MidiOutError::NotEnabled)
+}
+
+/// Play sequences in real time on an output port (unavailable in this build)
+#[cfg(not(feature = "midi-out"))]
+pub fn send(_sequences: &[NoteSequence], _index: usize) -> Result<(), MidiOutError> {
+    Err(MidiOutError::NotEnabled)
+}
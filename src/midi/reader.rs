@@ -0,0 +1,355 @@
+//! MIDI file reader using midly crate
+//!
+//! Parses Standard MIDI Files back into note sequences (one per track and
+//! channel), so existing files can be inspected, transformed, and re-exported.
+
+use super::sequence::{TempoChange, TimeSignature};
+use super::{Note, NoteSequence};
+use crate::preset::Key;
+use midly::{MetaMessage, MidiMessage, Smf, Timing, TrackEventKind};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::Path;
+use thiserror::Error;
+
+/// Errors that can occur when reading MIDI files
+#[derive(Debug, Error)]
+pub enum MidiReadError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid MIDI file: {0}")]
+    Parse(String),
+
+    #[error("SMPTE timecode timing is not supported")]
+    UnsupportedTiming,
+}
+
+/// Tempo assumed when a file has no tempo event (the MIDI default)
+pub const DEFAULT_TEMPO: u16 = 120;
+
+/// The starting tempo of a file, and whether it had to be assumed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileTempo {
+    /// Tempo in BPM
+    pub bpm: u16,
+
+    /// True when the file has no tempo event at its start
+    pub assumed: bool,
+}
+
+impl std::fmt::Display for FileTempo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.bpm)?;
+        if self.assumed {
+            write!(f, " (assumed)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Read the starting tempo from a parsed file: the first tempo event at
+/// tick 0 in any track, or [`DEFAULT_TEMPO`] (marked as assumed) if none.
+pub fn file_tempo(smf: &Smf) -> FileTempo {
+    for track in &smf.tracks {
+        let mut tick = 0u64;
+        for event in track {
+            tick += event.delta.as_int() as u64;
+            if tick > 0 {
+                break;
+            }
+            if let TrackEventKind::Meta(MetaMessage::Tempo(us_per_beat)) = event.kind {
+                return FileTempo {
+                    bpm: tempo_bpm(us_per_beat.as_int()),
+                    assumed: false,
+                };
+            }
+        }
+    }
+    FileTempo {
+        bpm: DEFAULT_TEMPO,
+        assumed: true,
+    }
+}
+
+/// Convert microseconds per beat to whole BPM
+fn tempo_bpm(us_per_beat: u32) -> u16 {
+    (60_000_000.0 / us_per_beat.max(1) as f64).round() as u16
+}
+
+/// Notes collected for one channel of one track
+#[derive(Default)]
+struct ChannelNotes {
+    program: Option<u8>,
+    notes: Vec<Note>,
+    /// Sounding notes per pitch: (start tick, velocity), oldest first
+    active: HashMap<u8, VecDeque<(u64, u8)>>,
+}
+
+impl ChannelNotes {
+    fn release(&mut self, pitch: u8, tick: u64, ticks_per_beat: f64) {
+        if let Some((start, velocity)) = self.active.get_mut(&pitch).and_then(|q| q.pop_front()) {
+            self.notes.push(Note::new(
+                pitch,
+                (tick - start) as f64 / ticks_per_beat,
+                velocity,
+                start as f64 / ticks_per_beat,
+            ));
+        }
+    }
+}
+
+/// Read a MIDI file into note sequences
+pub fn read_midi(path: &Path) -> Result<Vec<NoteSequence>, MidiReadError> {
+    let content = std::fs::read(path)?;
+    parse_midi(&content)
+}
+
+/// Parse MIDI file bytes into note sequences
+///
+/// A note-on with velocity 0 is treated as a note-off. Overlapping notes of
+/// the same pitch are released oldest first, and notes still sounding at the
+/// end of a track are closed there. Tempo and time signature come from the
+/// first events of those kinds; later tempo events become tempo changes.
+/// A track's name is given to every sequence read from it.
+pub fn parse_midi(bytes: &[u8]) -> Result<Vec<NoteSequence>, MidiReadError> {
+    let smf = Smf::parse(bytes).map_err(|e| MidiReadError::Parse(e.to_string()))?;
+    let ticks_per_beat = match smf.header.timing {
+        Timing::Metrical(tpb) => tpb.as_int().max(1) as f64,
+        Timing::Timecode(..) => return Err(MidiReadError::UnsupportedTiming),
+    };
+
+    let mut tempo: Option<u16> = None;
+    let mut tempo_changes = Vec::new();
+    let mut time_signature: Option<TimeSignature> = None;
+    let mut key_signature: Option<Key> = None;
+    let mut tracks: Vec<(Option<String>, BTreeMap<u8, ChannelNotes>)> = Vec::new();
+
+    for track in &smf.tracks {
+        let mut tick = 0u64;
+        let mut name: Option<String> = None;
+        let mut channels: BTreeMap<u8, ChannelNotes> = BTreeMap::new();
+
+        for event in track {
+            tick += event.delta.as_int() as u64;
+            match event.kind {
+                TrackEventKind::Midi { channel, message } => {
+                    let state = channels.entry(channel.as_int()).or_default();
+                    match message {
+                        MidiMessage::NoteOn { key, vel } if vel.as_int() > 0 => {
+                            state
+                                .active
+                                .entry(key.as_int())
+                                .or_default()
+                                .push_back((tick, vel.as_int()));
+                        }
+                        // Velocity-0 note-on is the running-status form of note-off
+                        MidiMessage::NoteOn { key, .. } | MidiMessage::NoteOff { key, .. } => {
+                            state.release(key.as_int(), tick, ticks_per_beat);
+                        }
+                        MidiMessage::ProgramChange { program } => {
+                            state.program.get_or_insert(program.as_int());
+                        }
+                        _ => {}
+                    }
+                }
+                TrackEventKind::Meta(MetaMessage::Tempo(us_per_beat)) => {
+                    let bpm = tempo_bpm(us_per_beat.as_int());
+                    if tempo.is_none() && tick == 0 {
+                        tempo = Some(bpm);
+                    } else {
+                        tempo_changes.push(TempoChange {
+                            offset: tick as f64 / ticks_per_beat,
+                            bpm,
+                        });
+                    }
+                }
+                TrackEventKind::Meta(MetaMessage::TrackName(bytes)) => {
+                    name.get_or_insert_with(|| String::from_utf8_lossy(bytes).into_owned());
+                }
+                TrackEventKind::Meta(MetaMessage::TimeSignature(
+                    numerator,
+                    denominator_pow,
+                    _,
+                    _,
+                )) => {
+                    time_signature.get_or_insert(TimeSignature::new(
+                        numerator,
+                        1u8 << denominator_pow.min(7),
+                    ));
+                }
+                TrackEventKind::Meta(MetaMessage::KeySignature(sharps, minor)) => {
+                    key_signature = key_signature.or(Key::from_signature(sharps, minor));
+                }
+                _ => {}
+            }
+        }
+
+        // Close anything still sounding at the end of the track
+        for state in channels.values_mut() {
+            let pitches: Vec<u8> = state.active.keys().copied().collect();
+            for pitch in pitches {
+                while state.active.get(&pitch).is_some_and(|q| !q.is_empty()) {
+                    state.release(pitch, tick, ticks_per_beat);
+                }
+            }
+        }
+
+        tracks.push((name, channels));
+    }
+
+    tempo_changes.sort_by(|a, b| {
+        a.offset
+            .partial_cmp(&b.offset)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut sequences = Vec::new();
+    for (name, channels) in tracks {
+        for (channel, mut state) in channels {
+            if state.notes.is_empty() {
+                continue;
+            }
+            state.notes.sort_by(|a, b| {
+                a.offset
+                    .partial_cmp(&b.offset)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+            let mut seq = NoteSequence::new(
+                state.notes,
+                state.program.unwrap_or(0),
+                tempo.unwrap_or(DEFAULT_TEMPO),
+            );
+            seq.name = name.clone();
+            seq.channel = channel;
+            seq.tempo_changes = tempo_changes.clone();
+            seq.time_signature = time_signature.unwrap_or_default();
+            seq.key_signature = key_signature;
+            sequences.push(seq);
+        }
+    }
+
+    Ok(sequences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::write_midi;
+    use tempfile::tempdir;
+
+    /// Build a format-0 file with 480 ticks per beat around raw track bytes
+    fn smf_bytes(track: &[u8]) -> Vec<u8> {
+        let mut bytes = b"MThd".to_vec();
+        bytes.extend_from_slice(&[0, 0, 0, 6, 0, 0, 0, 1, 0x01, 0xE0]);
+        bytes.extend_from_slice(b"MTrk");
+        bytes.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(track);
+        bytes
+    }
+
+    #[test]
+    fn test_velocity_zero_note_on_is_note_off() {
+        // Running status: one 0x90 status byte, offs written as velocity-0 note-ons
+        let track = [
+            0x00, 0xC0, 0x28, // program change: violin
+            0x00, 0x90, 0x3C, 0x50, // C4 on
+            0x83, 0x60, 0x3C, 0x00, // +480: C4 off (vel 0, running status)
+            0x00, 0x40, 0x64, // E4 on
+            0x81, 0x70, 0x40, 0x00, // +240: E4 off
+            0x00, 0x43, 0x46, // G4 on
+            0x87, 0x40, 0x43, 0x00, // +960: G4 off
+            0x00, 0xFF, 0x2F, 0x00, // end of track
+        ];
+
+        let sequences = parse_midi(&smf_bytes(&track)).unwrap();
+        assert_eq!(sequences.len(), 1);
+        let seq = &sequences[0];
+        assert_eq!(seq.instrument, 40);
+
+        let notes: Vec<(u8, f64, u8, f64)> = seq
+            .notes
+            .iter()
+            .map(|n| (n.pitch, n.duration, n.velocity, n.offset))
+            .collect();
+        assert_eq!(
+            notes,
+            vec![(60, 1.0, 80, 0.0), (64, 0.5, 100, 1.0), (67, 2.0, 70, 1.5)]
+        );
+    }
+
+    #[test]
+    fn test_file_tempo_from_meta() {
+        let track = [
+            0x00, 0xFF, 0x51, 0x03, 0x0A, 0x2C, 0x2B, // tempo: 666667 us/beat = 90 BPM
+            0x00, 0x90, 0x3C, 0x50, 0x83, 0x60, 0x3C, 0x00, 0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let bytes = smf_bytes(&track);
+        let tempo = file_tempo(&Smf::parse(&bytes).unwrap());
+        assert_eq!(
+            tempo,
+            FileTempo {
+                bpm: 90,
+                assumed: false
+            }
+        );
+        assert_eq!(tempo.to_string(), "90");
+        assert_eq!(parse_midi(&bytes).unwrap()[0].tempo, 90);
+    }
+
+    #[test]
+    fn test_file_tempo_assumed_without_meta() {
+        let track = [
+            0x00, 0x90, 0x3C, 0x50, 0x83, 0x60, 0x3C, 0x00, 0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let bytes = smf_bytes(&track);
+        let tempo = file_tempo(&Smf::parse(&bytes).unwrap());
+        assert_eq!(
+            tempo,
+            FileTempo {
+                bpm: DEFAULT_TEMPO,
+                assumed: true
+            }
+        );
+        assert_eq!(tempo.to_string(), "120 (assumed)");
+    }
+
+    #[test]
+    fn test_overlapping_same_pitch_released_in_order() {
+        let track = [
+            0x00, 0x90, 0x3C, 0x50, // C4 on
+            0x83, 0x60, 0x3C, 0x50, // +480: C4 on again (still sounding)
+            0x83, 0x60, 0x3C, 0x00, // +480: first C4 off
+            0x83, 0x60, 0x3C, 0x00, // +480: second C4 off
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+
+        let seq = &parse_midi(&smf_bytes(&track)).unwrap()[0];
+        assert_eq!(seq.notes.len(), 2);
+        assert_eq!((seq.notes[0].offset, seq.notes[0].duration), (0.0, 2.0));
+        assert_eq!((seq.notes[1].offset, seq.notes[1].duration), (1.0, 2.0));
+    }
+
+    #[test]
+    fn test_round_trip_with_writer() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("round.mid");
+
+        let mut seq = NoteSequence::new(
+            vec![Note::new(60, 1.0, 80, 0.0), Note::new(64, 0.5, 90, 1.0)],
+            33,
+            100,
+        );
+        seq.channel = 2;
+        seq.time_signature = TimeSignature::new(3, 4);
+        write_midi(std::slice::from_ref(&seq), &path).unwrap();
+
+        let read = read_midi(&path).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].notes, seq.notes);
+        assert_eq!(read[0].instrument, 33);
+        assert_eq!(read[0].channel, 2);
+        assert_eq!(read[0].tempo, 100);
+        assert_eq!(read[0].time_signature, TimeSignature::new(3, 4));
+    }
+}
@@ -0,0 +1,167 @@
+//! Realtime event scheduling
+//!
+//! Flattens note sequences into raw MIDI messages stamped with wall-clock
+//! times (following each sequence's tempo map), for sending to a live port.
+
+use super::NoteSequence;
+use super::sequence::DRUM_CHANNEL;
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+const CONTROLLER: u8 = 0xB0;
+const PROGRAM_CHANGE: u8 = 0xC0;
+
+/// Controller number for "all notes off"
+const CC_ALL_NOTES_OFF: u8 = 123;
+
+/// A raw MIDI message and when to send it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledEvent {
+    /// Milliseconds from the start of playback
+    pub at_ms: u64,
+
+    /// Raw MIDI bytes (status byte first)
+    pub message: Vec<u8>,
+}
+
+impl ScheduledEvent {
+    /// Ordering within the same millisecond: setup, then note-offs, then note-ons
+    fn priority(&self) -> u8 {
+        match self.message[0] & 0xF0 {
+            NOTE_OFF => 1,
+            NOTE_ON => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// Convert a beat position to milliseconds using the sequence's tempo map
+pub fn beat_to_ms(seq: &NoteSequence, beats: f64) -> u64 {
+    (seq.beats_to_seconds(beats.max(0.0)) * 1000.0).round() as u64
+}
+
+/// Build the time-ordered list of messages for all sequences
+///
+/// Each sequence gets its program change (except on the drum channel) at
+/// time zero, followed by note-on/note-off pairs. Notes are at least one
+/// millisecond long so the note-off never precedes its note-on.
+pub fn schedule(sequences: &[NoteSequence]) -> Vec<ScheduledEvent> {
+    let mut events = Vec::new();
+
+    for seq in sequences {
+        let channel = seq.channel & 0x0F;
+        if seq.channel != DRUM_CHANNEL {
+            events.push(ScheduledEvent {
+                at_ms: 0,
+                message: vec![PROGRAM_CHANGE | channel, seq.instrument & 0x7F],
+            });
+        }
+
+        for note in &seq.notes {
+            let start = beat_to_ms(seq, note.offset);
+            let end = beat_to_ms(seq, note.offset + note.duration).max(start + 1);
+            events.push(ScheduledEvent {
+                at_ms: start,
+                message: vec![
+                    NOTE_ON | channel,
+                    note.pitch & 0x7F,
+                    note.velocity.clamp(1, 127),
+                ],
+            });
+            events.push(ScheduledEvent {
+                at_ms: end,
+                message: vec![NOTE_OFF | channel, note.pitch & 0x7F, 0],
+            });
+        }
+    }
+
+    // Stable, so events at the same time keep their sequence order
+    events.sort_by_key(|e| (e.at_ms, e.priority()));
+    events
+}
+
+/// "All notes off" messages for every channel used, to silence hanging notes
+pub fn all_notes_off(sequences: &[NoteSequence]) -> Vec<Vec<u8>> {
+    let mut channels: Vec<u8> = sequences.iter().map(|s| s.channel & 0x0F).collect();
+    channels.sort_unstable();
+    channels.dedup();
+    channels
+        .into_iter()
+        .map(|ch| vec![CONTROLLER | ch, CC_ALL_NOTES_OFF, 0])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::Note;
+    use crate::midi::sequence::TempoChange;
+
+    #[test]
+    fn test_beat_to_ms_follows_tempo() {
+        let mut seq = NoteSequence::new(vec![], 0, 120);
+        assert_eq!(beat_to_ms(&seq, 1.0), 500);
+        assert_eq!(beat_to_ms(&seq, 3.5), 1750);
+
+        // 60 BPM from beat 2: each later beat is a full second
+        seq.tempo_changes = vec![TempoChange {
+            offset: 2.0,
+            bpm: 60,
+        }];
+        assert_eq!(beat_to_ms(&seq, 2.0), 1000);
+        assert_eq!(beat_to_ms(&seq, 4.0), 3000);
+    }
+
+    #[test]
+    fn test_schedule_orders_and_times_events() {
+        let mut bass = NoteSequence::new(vec![Note::new(36, 2.0, 90, 0.0)], 32, 90);
+        bass.channel = 1;
+        let lead = NoteSequence::new(
+            vec![Note::new(64, 1.0, 80, 1.0), Note::new(60, 1.0, 70, 0.0)],
+            0,
+            90,
+        );
+
+        let events = schedule(&[lead, bass]);
+        let summary: Vec<(u64, Vec<u8>)> =
+            events.into_iter().map(|e| (e.at_ms, e.message)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, vec![0xC0, 0]),
+                (0, vec![0xC1, 32]),
+                (0, vec![0x90, 60, 70]),
+                (0, vec![0x91, 36, 90]),
+                (667, vec![0x80, 60, 0]),
+                (667, vec![0x90, 64, 80]),
+                (1333, vec![0x80, 64, 0]),
+                (1333, vec![0x81, 36, 0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drums_skip_program_change() {
+        let mut drums = NoteSequence::new(vec![Note::new(36, 0.0, 100, 0.0)], 0, 120);
+        drums.channel = DRUM_CHANNEL;
+
+        let events = schedule(&[drums]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].message[0], 0x99);
+        // Zero-length note still gets its note-off afterwards
+        assert_eq!((events[0].at_ms, events[1].at_ms), (0, 1));
+    }
+
+    #[test]
+    fn test_all_notes_off_per_channel() {
+        let mut a = NoteSequence::new(vec![], 0, 120);
+        a.channel = 3;
+        let b = NoteSequence::new(vec![], 0, 120);
+        let c = NoteSequence::new(vec![], 5, 120);
+
+        assert_eq!(
+            all_notes_off(&[a, b, c]),
+            vec![vec![0xB0, 123, 0], vec![0xB3, 123, 0]]
+        );
+    }
+}
@@ -3,56 +3,138 @@
 //! A sequence is a collection of notes with instrument and tempo settings.
 
 use super::Note;
+use super::note::NoteError;
+use crate::preset::Key;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
 use serde::Deserialize;
+use thiserror::Error;
+
+/// Instrument families, in the order `instruments` lists them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstrumentCategory {
+    Piano,
+    Strings,
+    Woodwinds,
+    Brass,
+    Synth,
+    Ambient,
+    Guitar,
+    Percussion,
+}
+
+impl InstrumentCategory {
+    /// Every category, in display order
+    pub const ALL: [InstrumentCategory; 8] = [
+        Self::Piano,
+        Self::Strings,
+        Self::Woodwinds,
+        Self::Brass,
+        Self::Synth,
+        Self::Ambient,
+        Self::Guitar,
+        Self::Percussion,
+    ];
+
+    /// Parse a category name, singular or plural (e.g. "strings", "pianos", "bells")
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "piano" | "pianos" | "keys" => Some(Self::Piano),
+            "strings" | "string" => Some(Self::Strings),
+            "woodwinds" | "woodwind" => Some(Self::Woodwinds),
+            "brass" => Some(Self::Brass),
+            "synth" | "synths" => Some(Self::Synth),
+            "ambient" => Some(Self::Ambient),
+            "guitar" | "guitars" => Some(Self::Guitar),
+            "percussion" | "bells" => Some(Self::Percussion),
+            _ => None,
+        }
+    }
+
+    /// Lowercase name, as accepted by [`parse`](Self::parse)
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Piano => "piano",
+            Self::Strings => "strings",
+            Self::Woodwinds => "woodwinds",
+            Self::Brass => "brass",
+            Self::Synth => "synth",
+            Self::Ambient => "ambient",
+            Self::Guitar => "guitar",
+            Self::Percussion => "percussion",
+        }
+    }
+}
+
+/// A named General MIDI instrument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instrument {
+    /// Name accepted by `--instrument` and JSON input
+    pub name: &'static str,
+    /// GM program number (0-127)
+    pub program: u8,
+    /// Family the instrument is listed under
+    pub category: InstrumentCategory,
+}
+
+impl Instrument {
+    const fn new(name: &'static str, program: u8, category: InstrumentCategory) -> Self {
+        Self {
+            name,
+            program,
+            category,
+        }
+    }
+}
 
 /// General MIDI instrument names mapped to program numbers
-pub const INSTRUMENT_MAP: &[(&str, u8)] = &[
-    // Pianos
-    ("piano", 0),
-    ("acoustic_piano", 0),
-    ("bright_piano", 1),
-    ("electric_piano", 4),
-    // Strings
-    ("strings", 48),
-    ("violin", 40),
-    ("viola", 41),
-    ("cello", 42),
-    ("contrabass", 43),
-    ("tremolo_strings", 44),
-    ("pizzicato_strings", 45),
-    ("harp", 46),
-    // Woodwinds
-    ("flute", 73),
-    ("oboe", 68),
-    ("clarinet", 71),
-    ("bassoon", 70),
-    // Brass
-    ("trumpet", 56),
-    ("trombone", 57),
-    ("french_horn", 60),
-    ("tuba", 58),
-    // Synth
-    ("synth_pad", 88),
-    ("synth_lead", 80),
-    ("pad_warm", 89),
-    ("pad_choir", 91),
-    // Ambient
-    ("atmosphere", 99),
-    ("soundtrack", 97),
-    // Guitar
-    ("acoustic_guitar", 25),
-    ("electric_guitar", 27),
-    ("bass", 33),
-    ("electric_bass", 33),
-    // Bells/Percussion
-    ("vibraphone", 11),
-    ("marimba", 12),
-    ("xylophone", 13),
-    ("tubular_bells", 14),
-    ("glockenspiel", 9),
-    ("celesta", 8),
+pub const INSTRUMENT_MAP: &[Instrument] = &[
+    Instrument::new("piano", 0, InstrumentCategory::Piano),
+    Instrument::new("acoustic_piano", 0, InstrumentCategory::Piano),
+    Instrument::new("bright_piano", 1, InstrumentCategory::Piano),
+    Instrument::new("electric_piano", 4, InstrumentCategory::Piano),
+    Instrument::new("strings", 48, InstrumentCategory::Strings),
+    Instrument::new("violin", 40, InstrumentCategory::Strings),
+    Instrument::new("viola", 41, InstrumentCategory::Strings),
+    Instrument::new("cello", 42, InstrumentCategory::Strings),
+    Instrument::new("contrabass", 43, InstrumentCategory::Strings),
+    Instrument::new("tremolo_strings", 44, InstrumentCategory::Strings),
+    Instrument::new("pizzicato_strings", 45, InstrumentCategory::Strings),
+    Instrument::new("harp", 46, InstrumentCategory::Strings),
+    Instrument::new("flute", 73, InstrumentCategory::Woodwinds),
+    Instrument::new("oboe", 68, InstrumentCategory::Woodwinds),
+    Instrument::new("clarinet", 71, InstrumentCategory::Woodwinds),
+    Instrument::new("bassoon", 70, InstrumentCategory::Woodwinds),
+    Instrument::new("trumpet", 56, InstrumentCategory::Brass),
+    Instrument::new("trombone", 57, InstrumentCategory::Brass),
+    Instrument::new("french_horn", 60, InstrumentCategory::Brass),
+    Instrument::new("tuba", 58, InstrumentCategory::Brass),
+    Instrument::new("synth_pad", 88, InstrumentCategory::Synth),
+    Instrument::new("synth_lead", 80, InstrumentCategory::Synth),
+    Instrument::new("pad_warm", 89, InstrumentCategory::Synth),
+    Instrument::new("pad_choir", 91, InstrumentCategory::Synth),
+    Instrument::new("atmosphere", 99, InstrumentCategory::Ambient),
+    Instrument::new("soundtrack", 97, InstrumentCategory::Ambient),
+    Instrument::new("acoustic_guitar", 25, InstrumentCategory::Guitar),
+    Instrument::new("electric_guitar", 27, InstrumentCategory::Guitar),
+    Instrument::new("bass", 33, InstrumentCategory::Guitar),
+    Instrument::new("electric_bass", 33, InstrumentCategory::Guitar),
+    Instrument::new("vibraphone", 11, InstrumentCategory::Percussion),
+    Instrument::new("marimba", 12, InstrumentCategory::Percussion),
+    Instrument::new("xylophone", 13, InstrumentCategory::Percussion),
+    Instrument::new("tubular_bells", 14, InstrumentCategory::Percussion),
+    Instrument::new("glockenspiel", 9, InstrumentCategory::Percussion),
+    Instrument::new("celesta", 8, InstrumentCategory::Percussion),
 ];
 
+/// Instruments in one category, in table order
+pub fn instruments_in(category: InstrumentCategory) -> impl Iterator<Item = &'static Instrument> {
+    INSTRUMENT_MAP
+        .iter()
+        .filter(move |i| i.category == category)
+}
+
 /// Resolve instrument name to GM program number
 pub fn resolve_instrument(name: &str) -> Option<u8> {
     let name_lower = name.to_lowercase();
@@ -67,24 +149,330 @@ pub fn resolve_instrument(name: &str) -> Option<u8> {
     // Try named instrument
     INSTRUMENT_MAP
         .iter()
-        .find(|(n, _)| *n == name_lower)
-        .map(|(_, num)| *num)
+        .find(|i| i.name == name_lower)
+        .map(|i| i.program)
+}
+
+/// Instrument names close to an unrecognized one, best first (at most 3).
+///
+/// Names that start with or contain `name` rank first, then names within a
+/// couple of typos of it.
+pub fn suggest_instruments(name: &str) -> Vec<&'static str> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return Vec::new();
+    }
+
+    let mut ranked: Vec<((u8, usize), &'static str)> = INSTRUMENT_MAP
+        .iter()
+        .filter_map(|instrument| {
+            let candidate = instrument.name;
+            let rank = if candidate.starts_with(&name) {
+                (0, candidate.len() - name.len())
+            } else if candidate.contains(&name) {
+                (1, candidate.len() - name.len())
+            } else {
+                let distance = edit_distance(&name, candidate);
+                if distance > 2 || distance >= name.len() {
+                    return None;
+                }
+                (2, distance)
+            };
+            Some((rank, candidate))
+        })
+        .collect();
+    ranked.sort();
+    ranked.dedup_by_key(|(_, candidate)| *candidate);
+    ranked
+        .into_iter()
+        .take(3)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Sequences whose notes run past `target_secs`, as (index, seconds over)
+///
+/// Overruns of a millisecond or less are rounding, not a real overrun.
+pub fn overruns(sequences: &[NoteSequence], target_secs: f64) -> Vec<(usize, f64)> {
+    sequences
+        .iter()
+        .enumerate()
+        .map(|(i, seq)| (i, seq.duration_seconds() - target_secs))
+        .filter(|&(_, over)| over > 1e-3)
+        .collect()
+}
+
+/// Delay every sequence by `seconds` of silence, converted to beats at each
+/// sequence's starting tempo (tempo changes move with the notes)
+pub fn pad_start(sequences: &mut [NoteSequence], seconds: f64) {
+    for seq in sequences {
+        seq.shift(seconds * seq.tempo as f64 / 60.0);
+    }
+}
+
+/// General MIDI percussion channel (channel 10, zero-based 9)
+pub const DRUM_CHANNEL: u8 = 9;
+
+/// Sound-controller envelope emitted at the start of a sequence
+///
+/// Values use the MIDI controller range (0-127, 64 = instrument default).
+/// `attack` is sent as CC73 (attack time); `decay` is sent as both CC75
+/// (decay time) and CC72 (release time).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    /// Attack time (CC73)
+    pub attack: u8,
+
+    /// Decay/release time (CC75 and CC72)
+    pub decay: u8,
+}
+
+/// Time signature (numerator / denominator)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSignature {
+    /// Beats per bar
+    pub numerator: u8,
+
+    /// Beat unit (power of two: 2, 4, 8, 16)
+    pub denominator: u8,
+}
+
+impl TimeSignature {
+    /// Create a time signature
+    pub fn new(numerator: u8, denominator: u8) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// Parse a time signature string such as "3/4" or "6/8"
+    pub fn parse(s: &str) -> Option<Self> {
+        let (num, den) = s.trim().split_once('/')?;
+        let numerator: u8 = num.trim().parse().ok()?;
+        let denominator: u8 = den.trim().parse().ok()?;
+        if !(1..=32).contains(&numerator)
+            || !(1..=32).contains(&denominator)
+            || !denominator.is_power_of_two()
+        {
+            return None;
+        }
+        Some(Self::new(numerator, denominator))
+    }
+
+    /// Bar length in quarter-note beats (3/4 = 3.0, 6/8 = 3.0)
+    pub fn beats_per_bar(&self) -> f64 {
+        self.numerator as f64 * 4.0 / self.denominator as f64
+    }
+
+    /// Rest to put before a pickup of `pickup_beats` so that it ends on a
+    /// bar line (a 1-beat pickup in 4/4 needs 3 beats of rest)
+    pub fn pickup_rest(&self, pickup_beats: f64) -> f64 {
+        let bar = self.beats_per_bar();
+        ((pickup_beats / bar - 1e-6).ceil().max(1.0) * bar - pickup_beats).max(0.0)
+    }
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        Self::new(4, 4)
+    }
+}
+
+impl std::fmt::Display for TimeSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+/// Order in which [`NoteSequence::arpeggiate`] plays a chord's notes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArpPattern {
+    /// Lowest to highest
+    Up,
+    /// Highest to lowest
+    Down,
+    /// Up then back down, without repeating the top and bottom notes
+    UpDown,
+    /// Shuffled, reproducibly for a given chord position
+    Random,
+}
+
+impl ArpPattern {
+    /// Parse a pattern name: up, down, updown (or up-down), random
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "up" => Some(Self::Up),
+            "down" => Some(Self::Down),
+            "updown" | "up-down" => Some(Self::UpDown),
+            "random" => Some(Self::Random),
+            _ => None,
+        }
+    }
+
+    /// Indices into `chord` in playing order
+    fn order(self, chord: &[Note], start: f64) -> Vec<usize> {
+        let mut up: Vec<usize> = (0..chord.len()).collect();
+        up.sort_by_key(|&i| chord[i].pitch);
+
+        match self {
+            Self::Up => up,
+            Self::Down => up.into_iter().rev().collect(),
+            Self::UpDown => {
+                let down = up[1..up.len() - 1]
+                    .iter()
+                    .rev()
+                    .copied()
+                    .collect::<Vec<_>>();
+                up.into_iter().chain(down).collect()
+            }
+            Self::Random => {
+                let mut rng = StdRng::seed_from_u64(start.to_bits());
+                up.shuffle(&mut rng);
+                up
+            }
+        }
+    }
+}
+
+/// How far (in beats) a note may sit from the off-beat eighth and still be swung
+pub const SWING_TOLERANCE: f64 = 0.05;
+
+/// Overlaps and start differences smaller than this (in beats) are rounding
+const OVERLAP_TOLERANCE: f64 = 1e-6;
+
+/// A tempo change at a position in the sequence
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoChange {
+    /// Position in beats
+    pub offset: f64,
+
+    /// New tempo in BPM
+    pub bpm: u16,
 }
 
 /// A sequence of notes with instrument and tempo settings
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct NoteSequence {
     /// Notes in the sequence
     pub notes: Vec<Note>,
 
+    /// Optional track name shown by DAWs (e.g. "Walking Bass")
+    pub name: Option<String>,
+
     /// GM instrument program number (0-127)
     pub instrument: u8,
 
+    /// Optional SoundFont bank (0-16383) sent as bank select before the
+    /// program change
+    pub bank: Option<u16>,
+
     /// MIDI channel (0-15, usually 0)
     pub channel: u8,
 
     /// Tempo in BPM
     pub tempo: u16,
+
+    /// Optional attack/decay envelope sent as controller events
+    pub envelope: Option<Envelope>,
+
+    /// Tempo changes after the initial tempo, in beat order
+    pub tempo_changes: Vec<TempoChange>,
+
+    /// Time signature (written from the first sequence)
+    pub time_signature: TimeSignature,
+
+    /// Key signature, if known (written from the first sequence)
+    pub key_signature: Option<Key>,
+}
+
+/// Builder for [`NoteSequence`], from [`NoteSequence::builder`]
+#[derive(Debug, Clone)]
+pub struct NoteSequenceBuilder {
+    seq: NoteSequence,
+}
+
+impl NoteSequenceBuilder {
+    /// Notes in the sequence
+    pub fn notes(mut self, notes: Vec<Note>) -> Self {
+        self.seq.notes = notes;
+        self
+    }
+
+    /// Track name
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.seq.name = Some(name.into());
+        self
+    }
+
+    /// GM instrument program number (0-127)
+    pub fn instrument(mut self, instrument: u8) -> Self {
+        self.seq.instrument = instrument;
+        self
+    }
+
+    /// SoundFont bank, sent as bank select
+    pub fn bank(mut self, bank: u16) -> Self {
+        self.seq.bank = Some(bank);
+        self
+    }
+
+    /// MIDI channel (0-15)
+    pub fn channel(mut self, channel: u8) -> Self {
+        self.seq.channel = channel;
+        self
+    }
+
+    /// Tempo in BPM
+    pub fn tempo(mut self, tempo: u16) -> Self {
+        self.seq.tempo = tempo;
+        self
+    }
+
+    /// Attack/decay envelope
+    pub fn envelope(mut self, envelope: Envelope) -> Self {
+        self.seq.envelope = Some(envelope);
+        self
+    }
+
+    /// Tempo changes after the initial tempo
+    pub fn tempo_changes(mut self, tempo_changes: Vec<TempoChange>) -> Self {
+        self.seq.tempo_changes = tempo_changes;
+        self
+    }
+
+    /// Time signature
+    pub fn time_signature(mut self, time_signature: TimeSignature) -> Self {
+        self.seq.time_signature = time_signature;
+        self
+    }
+
+    /// Set the key signature
+    pub fn key_signature(mut self, key: Key) -> Self {
+        self.seq.key_signature = Some(key);
+        self
+    }
+
+    /// Finish the sequence
+    pub fn build(self) -> NoteSequence {
+        self.seq
+    }
 }
 
 impl NoteSequence {
@@ -92,9 +480,15 @@ impl NoteSequence {
     pub fn new(notes: Vec<Note>, instrument: u8, tempo: u16) -> Self {
         Self {
             notes,
+            name: None,
             instrument,
+            bank: None,
             channel: 0,
             tempo,
+            envelope: None,
+            tempo_changes: Vec::new(),
+            time_signature: TimeSignature::default(),
+            key_signature: None,
         }
     }
 
@@ -103,6 +497,30 @@ impl NoteSequence {
         Self::new(notes, 0, 120)
     }
 
+    /// The same sequence with a track name
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Start building a sequence (piano on channel 0 at 120 BPM unless set)
+    pub fn builder() -> NoteSequenceBuilder {
+        NoteSequenceBuilder {
+            seq: Self::from_notes(Vec::new()),
+        }
+    }
+
+    /// Channels the sequence plays on: its own, then any set on single notes
+    pub fn channels(&self) -> Vec<u8> {
+        let mut channels = vec![self.channel];
+        for channel in self.notes.iter().filter_map(|n| n.channel) {
+            if !channels.contains(&channel) {
+                channels.push(channel);
+            }
+        }
+        channels
+    }
+
     /// Calculate total duration in beats
     pub fn duration_beats(&self) -> f64 {
         self.notes
@@ -113,11 +531,299 @@ impl NoteSequence {
 
     /// Calculate duration in seconds
     pub fn duration_seconds(&self) -> f64 {
-        let beats = self.duration_beats();
-        beats * 60.0 / self.tempo as f64
+        self.beats_to_seconds(self.duration_beats())
+    }
+
+    /// Convert a beat position to seconds, following any tempo changes
+    pub fn beats_to_seconds(&self, beats: f64) -> f64 {
+        let mut seconds = 0.0;
+        let mut position = 0.0;
+        let mut bpm = self.tempo as f64;
+
+        for change in &self.tempo_changes {
+            if change.offset >= beats {
+                break;
+            }
+            let offset = change.offset.max(position);
+            seconds += (offset - position) * 60.0 / bpm;
+            position = offset;
+            bpm = change.bpm.max(1) as f64;
+        }
+
+        seconds + (beats - position) * 60.0 / bpm
+    }
+
+    /// Spread chords (notes sharing an offset) out in time: the chord's
+    /// pitches are played one at a time, `rate_beats` apart, in `pattern`
+    /// order, repeating until the chord's longest note would have ended
+    /// (always at least one pass). Single notes are left as they are.
+    pub fn arpeggiate(&mut self, pattern: ArpPattern, rate_beats: f64) {
+        let rate = rate_beats.max(1.0 / 64.0);
+        let mut notes = std::mem::take(&mut self.notes);
+        notes.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+
+        let mut result = Vec::with_capacity(notes.len());
+        for chord in notes.chunk_by(|a, b| (a.offset - b.offset).abs() < 1e-6) {
+            if chord.len() < 2 {
+                result.extend_from_slice(chord);
+                continue;
+            }
+
+            let start = chord[0].offset;
+            let length = chord.iter().map(|n| n.duration).fold(0.0, f64::max);
+            let order = pattern.order(chord, start);
+            let steps = ((length / rate).round() as usize).max(order.len());
+
+            for (step, &index) in order.iter().cycle().take(steps).enumerate() {
+                let offset = start + step as f64 * rate;
+                // The last step stops with the chord; steps past its end
+                // (finishing the first pass) keep the full rate
+                let remaining = start + length - offset;
+                let duration = if remaining > 0.0 {
+                    rate.min(remaining)
+                } else {
+                    rate
+                };
+                result.push(chord[index].at(offset).with_duration(duration));
+            }
+        }
+
+        self.notes = result;
+    }
+
+    /// Repeat the notes `times` times back-to-back, each copy starting
+    /// `length_beats` after the previous one. Notes that would run past a
+    /// repeat boundary are cut at it (or dropped, if they start after it);
+    /// only the last copy may ring on.
+    /// Tempo changes repeat too, with the starting tempo restored at each
+    /// boundary.
+    pub fn repeat(&mut self, times: u32, length_beats: f64) {
+        if times < 2 || length_beats <= 0.0 {
+            return;
+        }
+
+        let notes = std::mem::take(&mut self.notes);
+        let changes = std::mem::take(&mut self.tempo_changes);
+        for i in 0..times {
+            let start = i as f64 * length_beats;
+            let last = i + 1 == times;
+            for note in notes.iter().filter(|n| last || n.offset < length_beats) {
+                let mut note = note.clone();
+                if !last {
+                    note.duration = note.duration.min(length_beats - note.offset);
+                }
+                note.offset += start;
+                self.notes.push(note);
+            }
+
+            if i > 0 && !changes.is_empty() {
+                self.tempo_changes.push(TempoChange {
+                    offset: start,
+                    bpm: self.tempo,
+                });
+            }
+            self.tempo_changes.extend(
+                changes
+                    .iter()
+                    .filter(|c| last || c.offset < length_beats)
+                    .map(|c| TempoChange {
+                        offset: c.offset + start,
+                        bpm: c.bpm,
+                    }),
+            );
+        }
+    }
+
+    /// Move every note and tempo change `beats` later
+    pub fn shift(&mut self, beats: f64) {
+        for note in &mut self.notes {
+            note.offset += beats;
+        }
+        for change in &mut self.tempo_changes {
+            change.offset += beats;
+        }
+    }
+
+    /// Play the sequence backwards: each note ends where the mirrored note
+    /// started, so the total length is unchanged.
+    pub fn retrograde(&mut self) {
+        let length = self.duration_beats();
+        for note in &mut self.notes {
+            note.offset = (length - note.offset - note.duration).max(0.0);
+        }
+        self.notes.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+    }
+
+    /// Mirror every pitch around `axis_pitch` (a note a third above the
+    /// axis ends up a third below it), clamped to the MIDI range.
+    pub fn invert(&mut self, axis_pitch: u8) {
+        for note in &mut self.notes {
+            note.pitch = (2 * axis_pitch as i16 - note.pitch as i16).clamp(0, 127) as u8;
+        }
     }
+
+    /// Shorten each note that overlaps a later note of the same pitch on the
+    /// same channel so it ends where that note starts. Many synths retrigger
+    /// oddly or drop the note-off otherwise. Of notes starting together on
+    /// the same pitch, only the longest is kept. Returns how many notes were
+    /// shortened or removed; notes of different pitches are never touched.
+    pub fn resolve_overlaps(&mut self) -> usize {
+        let channel = self.channel;
+        let key = |note: &Note| (note.channel.unwrap_or(channel), note.pitch);
+        let mut order: Vec<usize> = (0..self.notes.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&self.notes[a], &self.notes[b]);
+            key(a)
+                .cmp(&key(b))
+                .then(a.offset.total_cmp(&b.offset))
+                .then(b.duration.total_cmp(&a.duration))
+        });
+
+        let mut removed = vec![false; self.notes.len()];
+        let mut fixed = 0;
+        let mut previous: Option<usize> = None;
+        for i in order {
+            if let Some(p) = previous.filter(|&p| key(&self.notes[p]) == key(&self.notes[i])) {
+                let start = self.notes[i].offset;
+                let earlier = &mut self.notes[p];
+                if start - earlier.offset < OVERLAP_TOLERANCE {
+                    removed[i] = true;
+                    fixed += 1;
+                    continue;
+                }
+                if earlier.offset + earlier.duration > start + OVERLAP_TOLERANCE {
+                    earlier.duration = start - earlier.offset;
+                    fixed += 1;
+                }
+            }
+            previous = Some(i);
+        }
+
+        let mut index = 0;
+        self.notes.retain(|_| {
+            index += 1;
+            !removed[index - 1]
+        });
+        fixed
+    }
+
+    /// How many notes [`resolve_overlaps`](Self::resolve_overlaps) would change
+    pub fn count_overlaps(&self) -> usize {
+        self.clone().resolve_overlaps()
+    }
+
+    /// Snap each pitch to the nearest tone of `key`'s scale, staying in
+    /// the same register. Notes halfway between two scale tones go down.
+    pub fn quantize_to_scale(&mut self, key: Key) {
+        let root = key.root() % 12;
+        let in_scale = |pitch: i16| {
+            let degree = (pitch - root as i16).rem_euclid(12) as u8;
+            key.scale_intervals().contains(&degree)
+        };
+
+        for note in &mut self.notes {
+            let pitch = note.pitch as i16;
+            let snapped = (0..=6)
+                .flat_map(|d| [pitch - d, pitch + d])
+                .find(|&p| (0..=127).contains(&p) && in_scale(p))
+                .unwrap_or(pitch);
+            note.pitch = snapped as u8;
+        }
+    }
+
+    /// Swing the off-beat eighth notes: a note starting or ending on the
+    /// second eighth of a beat (within [`SWING_TOLERANCE`]) moves to
+    /// `ratio` of the way through the beat, so 0.5 is straight and 0.67 is
+    /// triplet swing. Durations stretch or shrink to keep the note ends on
+    /// the swung grid; notes elsewhere are untouched. `ratio` is clamped to
+    /// 0.5-0.75.
+    pub fn apply_swing(&mut self, ratio: f64) {
+        let ratio = ratio.clamp(0.5, 0.75);
+        let swing = |t: f64| {
+            let beat = t.floor();
+            if (t - beat - 0.5).abs() <= SWING_TOLERANCE {
+                beat + ratio + (t - beat - 0.5)
+            } else {
+                t
+            }
+        };
+
+        for note in &mut self.notes {
+            let start = swing(note.offset);
+            let end = swing(note.offset + note.duration);
+            note.offset = start;
+            note.duration = (end - start).max(0.0);
+        }
+    }
+
+    /// Nudge each note's offset by up to ±`timing_jitter_beats` and its
+    /// velocity by up to ±`velocity_jitter`, so played-in parts sound less
+    /// mechanical. The same seed always gives the same result. Offsets stay
+    /// at or after 0 and velocities within 1-127.
+    pub fn humanize(&mut self, timing_jitter_beats: f64, velocity_jitter: u8, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let timing = timing_jitter_beats.abs();
+        let velocity = velocity_jitter as i16;
+
+        for note in &mut self.notes {
+            if timing > 0.0 {
+                note.offset = (note.offset + rng.gen_range(-timing..=timing)).max(0.0);
+            }
+            if velocity > 0 {
+                note.velocity = (note.velocity as i16 + rng.gen_range(-velocity..=velocity))
+                    .clamp(1, 127) as u8;
+            }
+        }
+    }
+
+    /// Narrow the dynamics: each velocity's distance from the mean velocity,
+    /// beyond `threshold`, is scaled by `ratio` (0.0-1.0; 0.5 halves it).
+    /// Velocities within `threshold` of the mean are left alone.
+    pub fn compress_velocity(&mut self, ratio: f64, threshold: u8) {
+        self.scale_velocity(ratio.clamp(0.0, 1.0), threshold);
+    }
+
+    /// Widen the dynamics: the counterpart of [`compress_velocity`](Self::compress_velocity),
+    /// with `ratio` of 1.0 or more (2.0 doubles the distance from the mean).
+    /// Velocities stay within 1-127.
+    pub fn expand_velocity(&mut self, ratio: f64, threshold: u8) {
+        self.scale_velocity(ratio.max(1.0), threshold);
+    }
+
+    fn scale_velocity(&mut self, factor: f64, threshold: u8) {
+        if self.notes.is_empty() {
+            return;
+        }
+        let mean =
+            self.notes.iter().map(|n| n.velocity as f64).sum::<f64>() / self.notes.len() as f64;
+        let threshold = threshold as f64;
+
+        for note in &mut self.notes {
+            let distance = note.velocity as f64 - mean;
+            let excess = distance.abs() - threshold;
+            if excess > 0.0 {
+                let scaled = mean + distance.signum() * (threshold + excess * factor);
+                note.velocity = scaled.round().clamp(1.0, 127.0) as u8;
+            }
+        }
+    }
+}
+
+/// How long a note actually sounds relative to its written duration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Articulation {
+    /// Detached: sounds for half the written duration
+    Staccato,
+    /// Held for the full written duration
+    Tenuto,
+    /// Connected: held slightly into the next note
+    Legato,
 }
 
+/// How far (in beats) a legato note overlaps the note after it
+pub const LEGATO_OVERLAP: f64 = 0.05;
+
 /// JSON input format for note sequences
 #[derive(Debug, Deserialize)]
 pub struct JsonNoteInput {
@@ -126,6 +832,33 @@ pub struct JsonNoteInput {
     pub velocity: u8,
     #[serde(default)]
     pub offset: f64,
+    #[serde(default)]
+    pub articulation: Option<Articulation>,
+    /// Channel for this note alone, overriding the track's
+    #[serde(default)]
+    pub channel: Option<u8>,
+}
+
+/// JSON input format for a tempo change
+#[derive(Debug, Deserialize)]
+pub struct JsonTempoChange {
+    pub offset_beats: f64,
+    pub bpm: u16,
+}
+
+/// Errors converting JSON input to note sequences
+#[derive(Debug, Error, PartialEq)]
+pub enum JsonInputError {
+    #[error(transparent)]
+    Note(#[from] NoteError),
+
+    #[error(
+        "Bad tempo change: {bpm} BPM at beat {offset}. Expected a positive BPM at a non-negative beat"
+    )]
+    BadTempoChange { offset: f64, bpm: u16 },
+
+    #[error("Unknown key: {0}. Examples: C, Am, Eb, Bm")]
+    UnknownKey(String),
 }
 
 /// JSON input format for a single track
@@ -135,6 +868,8 @@ pub struct JsonTrackInput {
     pub instrument: String,
     #[serde(default)]
     pub channel: u8,
+    #[serde(default)]
+    pub name: Option<String>,
     pub notes: Vec<JsonNoteInput>,
 }
 
@@ -151,6 +886,12 @@ pub struct JsonSequenceInput {
     pub notes: Vec<JsonNoteInput>,
     #[serde(default)]
     pub tracks: Vec<JsonTrackInput>,
+    /// Tempo changes applied to every track, in any order
+    #[serde(default)]
+    pub tempo_changes: Vec<JsonTempoChange>,
+    /// Key signature written to the file (e.g. "Eb", "Bm")
+    #[serde(default)]
+    pub key: Option<String>,
 }
 
 fn default_tempo() -> u16 {
@@ -163,51 +904,492 @@ fn default_instrument() -> String {
 
 impl JsonSequenceInput {
     /// Convert to NoteSequences
-    pub fn to_sequences(&self) -> Result<Vec<NoteSequence>, super::note::NoteError> {
+    pub fn to_sequences(&self) -> Result<Vec<NoteSequence>, JsonInputError> {
         let mut sequences = Vec::new();
+        let (tempo, tempo_changes) = self.tempo_map()?;
+        let key = match &self.key {
+            Some(name) => {
+                Some(Key::parse(name).ok_or_else(|| JsonInputError::UnknownKey(name.clone()))?)
+            }
+            None => None,
+        };
 
         // If tracks are specified, use those
         if !self.tracks.is_empty() {
             for track in &self.tracks {
-                let notes = track
-                    .notes
-                    .iter()
-                    .map(|n| {
-                        let pitch = Note::parse_pitch(&n.pitch)?;
-                        Ok(Note::new(pitch, n.duration, n.velocity, n.offset))
-                    })
-                    .collect::<Result<Vec<_>, _>>()?;
+                let notes = json_notes(&track.notes)?;
 
                 let instrument = resolve_instrument(&track.instrument).unwrap_or(0);
-                let mut seq = NoteSequence::new(notes, instrument, self.tempo);
+                let mut seq = NoteSequence::new(notes, instrument, tempo);
                 seq.channel = track.channel;
+                seq.name = track.name.clone();
                 sequences.push(seq);
             }
         } else if !self.notes.is_empty() {
             // Use top-level notes
-            let notes = self
-                .notes
-                .iter()
-                .map(|n| {
-                    let pitch = Note::parse_pitch(&n.pitch)?;
-                    Ok(Note::new(pitch, n.duration, n.velocity, n.offset))
-                })
-                .collect::<Result<Vec<_>, _>>()?;
+            let notes = json_notes(&self.notes)?;
 
             let instrument = resolve_instrument(&self.instrument).unwrap_or(0);
-            let mut seq = NoteSequence::new(notes, instrument, self.tempo);
+            let mut seq = NoteSequence::new(notes, instrument, tempo);
             seq.channel = self.channel;
             sequences.push(seq);
         }
 
+        for seq in &mut sequences {
+            seq.tempo_changes = tempo_changes.clone();
+            seq.key_signature = key;
+        }
         Ok(sequences)
     }
+
+    /// The starting tempo and the changes after it, sorted by offset. A
+    /// change at beat 0 replaces `tempo` rather than following it.
+    fn tempo_map(&self) -> Result<(u16, Vec<TempoChange>), JsonInputError> {
+        let mut changes = self
+            .tempo_changes
+            .iter()
+            .map(|c| {
+                if c.bpm == 0 || !c.offset_beats.is_finite() || c.offset_beats < 0.0 {
+                    return Err(JsonInputError::BadTempoChange {
+                        offset: c.offset_beats,
+                        bpm: c.bpm,
+                    });
+                }
+                Ok(TempoChange {
+                    offset: c.offset_beats,
+                    bpm: c.bpm,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        changes.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+
+        let mut tempo = self.tempo;
+        while changes.first().is_some_and(|c| c.offset == 0.0) {
+            tempo = changes.remove(0).bpm;
+        }
+        Ok((tempo, changes))
+    }
+}
+
+/// Convert JSON notes, applying articulations: staccato halves the sounding
+/// length, and legato holds until [`LEGATO_OVERLAP`] past the start of the
+/// next note to begin after this one (a last note keeps its duration).
+fn json_notes(inputs: &[JsonNoteInput]) -> Result<Vec<Note>, NoteError> {
+    inputs
+        .iter()
+        .map(|n| {
+            let (pitch, cents) = Note::parse_pitch_cents(&n.pitch)?;
+            let duration = match n.articulation {
+                Some(Articulation::Staccato) => n.duration * 0.5,
+                Some(Articulation::Legato) => inputs
+                    .iter()
+                    .map(|next| next.offset)
+                    .filter(|&offset| offset > n.offset)
+                    .min_by(f64::total_cmp)
+                    .map_or(n.duration, |next| next - n.offset + LEGATO_OVERLAP),
+                Some(Articulation::Tenuto) | None => n.duration,
+            };
+            let mut note = Note::new(pitch, duration, n.velocity, n.offset);
+            note.channel = n.channel;
+            note.cents = cents;
+            Ok(note)
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn scale_run() -> NoteSequence {
+        let notes = (0..16)
+            .map(|i| Note::new(60 + i as u8, 0.5, 80, i as f64 * 0.5))
+            .collect();
+        NoteSequence::new(notes, 0, 120)
+    }
+
+    fn offsets(seq: &NoteSequence) -> Vec<f64> {
+        seq.notes
+            .iter()
+            .map(|n| (n.offset * 100.0).round() / 100.0)
+            .collect()
+    }
+
+    #[test]
+    fn test_builder_matches_manual_construction() {
+        let notes = vec![Note::new(36, 1.0, 90, 0.0), Note::new(38, 1.0, 70, 1.0)];
+        let mut manual = NoteSequence::new(notes.clone(), 0, 96);
+        manual.channel = DRUM_CHANNEL;
+
+        let built = NoteSequence::builder()
+            .notes(notes)
+            .instrument(0)
+            .channel(DRUM_CHANNEL)
+            .tempo(96)
+            .build();
+        assert_eq!(built, manual);
+        assert_eq!(
+            NoteSequence::builder().build(),
+            NoteSequence::from_notes(Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_pickup_rest_reaches_bar_line() {
+        let common = TimeSignature::default();
+        assert_eq!(common.pickup_rest(1.0), 3.0);
+        assert_eq!(common.pickup_rest(4.0), 0.0);
+        assert_eq!(common.pickup_rest(5.0), 3.0);
+        assert_eq!(TimeSignature::new(6, 8).pickup_rest(0.5), 2.5);
+    }
+
+    #[test]
+    fn test_shift_moves_notes_and_tempo_changes() {
+        let mut seq = NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], 0, 120);
+        seq.tempo_changes.push(TempoChange {
+            offset: 2.0,
+            bpm: 100,
+        });
+        seq.shift(3.0);
+        assert_eq!(seq.notes[0].offset, 3.0);
+        assert_eq!(seq.tempo_changes[0].offset, 5.0);
+    }
+
+    #[test]
+    fn test_repeat_shifts_by_length() {
+        let notes = vec![Note::new(60, 1.0, 80, 0.0), Note::new(64, 2.0, 80, 2.0)];
+        let mut seq = NoteSequence::new(notes, 0, 120);
+        seq.repeat(3, 4.0);
+
+        assert_eq!(seq.notes.len(), 6);
+        assert_eq!(offsets(&seq), [0.0, 2.0, 4.0, 6.0, 8.0, 10.0]);
+        assert_eq!(seq.duration_beats(), 12.0);
+    }
+
+    #[test]
+    fn test_repeat_cuts_notes_at_boundary() {
+        let notes = vec![Note::new(60, 4.0, 80, 0.0), Note::new(64, 3.0, 80, 2.0)];
+        let mut seq = NoteSequence::new(notes, 0, 120);
+        seq.tempo_changes.push(TempoChange {
+            offset: 3.0,
+            bpm: 90,
+        });
+        seq.repeat(2, 4.0);
+
+        let durations: Vec<f64> = seq.notes.iter().map(|n| n.duration).collect();
+        assert_eq!(durations, [4.0, 2.0, 4.0, 3.0]);
+        let changes: Vec<(f64, u16)> = seq
+            .tempo_changes
+            .iter()
+            .map(|c| (c.offset, c.bpm))
+            .collect();
+        assert_eq!(changes, [(3.0, 90), (4.0, 120), (7.0, 90)]);
+    }
+
+    #[test]
+    fn test_retrograde_mirrors_offsets() {
+        let notes = vec![
+            Note::new(60, 1.0, 80, 0.0),
+            Note::new(62, 0.5, 80, 1.0),
+            Note::new(64, 2.0, 80, 2.0),
+        ];
+        let mut seq = NoteSequence::new(notes, 0, 120);
+        seq.retrograde();
+        assert_eq!(pitches(&seq), [64, 62, 60]);
+        assert_eq!(offsets(&seq), [0.0, 2.5, 3.0]);
+        assert_eq!(seq.duration_beats(), 4.0);
+    }
+
+    #[test]
+    fn test_resolve_overlaps_same_pitch_only() {
+        let notes = vec![
+            Note::new(60, 2.0, 80, 0.0),
+            Note::new(60, 1.0, 80, 1.0),
+            Note::new(64, 3.0, 80, 0.0),
+            Note::new(67, 1.0, 80, 0.5),
+        ];
+        let mut seq = NoteSequence::new(notes, 0, 120);
+        assert_eq!(seq.count_overlaps(), 1);
+        assert_eq!(seq.resolve_overlaps(), 1);
+        // The first C4 now ends where the second starts; E4 and G4 are untouched
+        let durations: Vec<f64> = seq.notes.iter().map(|n| n.duration).collect();
+        assert_eq!(durations, [1.0, 1.0, 3.0, 1.0]);
+        assert_eq!(seq.count_overlaps(), 0);
+    }
+
+    #[test]
+    fn test_resolve_overlaps_by_channel_and_duplicates() {
+        let mut other_channel = Note::new(60, 2.0, 80, 1.0);
+        other_channel.channel = Some(1);
+        let notes = vec![
+            Note::new(60, 1.0, 80, 0.0),
+            Note::new(60, 4.0, 90, 0.0),
+            other_channel,
+        ];
+        let mut seq = NoteSequence::new(notes, 0, 120);
+        assert_eq!(seq.resolve_overlaps(), 1);
+        // The longer of the two notes starting together stays, untouched by
+        // the C4 on another channel
+        assert_eq!(seq.notes.len(), 2);
+        assert_eq!((seq.notes[0].duration, seq.notes[0].velocity), (4.0, 90));
+    }
+
+    #[test]
+    fn test_invert_around_c4() {
+        let notes = vec![
+            Note::new(60, 1.0, 80, 0.0),
+            Note::new(64, 1.0, 80, 1.0),
+            Note::new(55, 1.0, 80, 2.0),
+        ];
+        let mut seq = NoteSequence::new(notes, 0, 120);
+        seq.invert(60);
+        // C4 stays, E4 -> Ab3, G3 -> F4
+        assert_eq!(pitches(&seq), [60, 56, 65]);
+
+        let mut high = NoteSequence::new(vec![Note::new(10, 1.0, 80, 0.0)], 0, 120);
+        high.invert(100);
+        assert_eq!(pitches(&high), [127]);
+    }
+
+    #[test]
+    fn test_quantize_to_scale() {
+        let mut seq = NoteSequence::new(
+            (0..=12)
+                .map(|i| Note::new(60 + i, 1.0, 80, i as f64))
+                .collect(),
+            0,
+            120,
+        );
+        seq.quantize_to_scale(Key::C);
+        // Scale tones stay put; C#, D#, F#, G#, A# drop to the tone below
+        assert_eq!(
+            pitches(&seq),
+            [60, 60, 62, 62, 64, 65, 65, 67, 67, 69, 69, 71, 72]
+        );
+
+        // In C minor, E goes to Eb; F# is a semitone from both F and G
+        let mut seq = NoteSequence::new(
+            vec![Note::new(64, 1.0, 80, 0.0), Note::new(54, 1.0, 80, 1.0)],
+            0,
+            120,
+        );
+        seq.quantize_to_scale(Key::Cm);
+        assert_eq!(pitches(&seq), [63, 53]);
+    }
+
+    #[test]
+    fn test_swing_straight_eighths() {
+        let notes = (0..3)
+            .map(|i| Note::new(60, 0.5, 80, i as f64 * 0.5))
+            .collect();
+        let mut seq = NoteSequence::new(notes, 0, 120);
+        seq.apply_swing(0.67);
+
+        assert_eq!(offsets(&seq), [0.0, 0.67, 1.0]);
+        let durations: Vec<f64> = seq
+            .notes
+            .iter()
+            .map(|n| (n.duration * 100.0).round() / 100.0)
+            .collect();
+        assert_eq!(durations, [0.67, 0.33, 0.67]);
+    }
+
+    #[test]
+    fn test_swing_leaves_other_positions_alone() {
+        let notes = vec![
+            Note::new(60, 0.25, 80, 0.25), // sixteenth
+            Note::new(62, 1.0, 80, 1.0),   // quarter on the beat
+            Note::new(64, 0.5, 80, 2.52),  // slightly late off-beat: still swung
+        ];
+        let mut seq = NoteSequence::new(notes, 0, 120);
+        seq.apply_swing(0.67);
+        assert_eq!(offsets(&seq), [0.25, 1.0, 2.69]);
+
+        let mut straight = seq.clone();
+        straight.apply_swing(0.5);
+        assert_eq!(straight.notes, seq.notes);
+    }
+
+    fn c_major_chord(duration: f64) -> NoteSequence {
+        let notes = [67, 60, 64]
+            .iter()
+            .map(|&p| Note::new(p, duration, 80, 0.0))
+            .collect();
+        NoteSequence::new(notes, 0, 120)
+    }
+
+    fn pitches(seq: &NoteSequence) -> Vec<u8> {
+        seq.notes.iter().map(|n| n.pitch).collect()
+    }
+
+    #[test]
+    fn test_arpeggiate_up() {
+        let mut seq = c_major_chord(0.75);
+        seq.arpeggiate(ArpPattern::Up, 0.25);
+        assert_eq!(pitches(&seq), [60, 64, 67]);
+        assert_eq!(offsets(&seq), [0.0, 0.25, 0.5]);
+        assert!(seq.notes.iter().all(|n| n.duration == 0.25));
+    }
+
+    #[test]
+    fn test_arpeggiate_repeats_to_fill_chord() {
+        let mut seq = c_major_chord(2.0);
+        seq.arpeggiate(ArpPattern::UpDown, 0.5);
+        assert_eq!(pitches(&seq), [60, 64, 67, 64]);
+
+        let mut seq = c_major_chord(1.0);
+        seq.arpeggiate(ArpPattern::Down, 0.25);
+        assert_eq!(pitches(&seq), [67, 64, 60, 67]);
+    }
+
+    #[test]
+    fn test_arpeggiate_clips_last_step_to_chord() {
+        let mut seq = c_major_chord(1.25);
+        seq.arpeggiate(ArpPattern::Up, 0.5);
+        assert_eq!(offsets(&seq), [0.0, 0.5, 1.0]);
+        let durations: Vec<f64> = seq.notes.iter().map(|n| n.duration).collect();
+        assert_eq!(durations, [0.5, 0.5, 0.25]);
+
+        // A chord shorter than one pass still plays every note in full
+        let mut seq = c_major_chord(0.5);
+        seq.arpeggiate(ArpPattern::Up, 0.5);
+        assert!(seq.notes.iter().all(|n| n.duration == 0.5));
+    }
+
+    #[test]
+    fn test_arpeggiate_keeps_single_notes_and_random_is_stable() {
+        let mut notes = c_major_chord(1.0).notes;
+        notes.push(Note::new(72, 1.0, 90, 1.0));
+        let mut a = NoteSequence::new(notes.clone(), 0, 120);
+        let mut b = NoteSequence::new(notes, 0, 120);
+        a.arpeggiate(ArpPattern::Random, 1.0 / 3.0);
+        b.arpeggiate(ArpPattern::Random, 1.0 / 3.0);
+
+        assert_eq!(a.notes, b.notes);
+        let mut chord = pitches(&a)[..3].to_vec();
+        chord.sort();
+        assert_eq!(chord, [60, 64, 67]);
+        assert_eq!(a.notes[3], Note::new(72, 1.0, 90, 1.0));
+    }
+
+    #[test]
+    fn test_humanize_zero_jitter_is_identity() {
+        let mut seq = scale_run();
+        seq.humanize(0.0, 0, 42);
+        assert_eq!(seq.notes, scale_run().notes);
+    }
+
+    #[test]
+    fn test_humanize_is_seeded_and_bounded() {
+        let (mut a, mut b, mut c) = (scale_run(), scale_run(), scale_run());
+        a.humanize(0.05, 10, 7);
+        b.humanize(0.05, 10, 7);
+        c.humanize(0.05, 10, 8);
+        assert_eq!(a.notes, b.notes);
+        assert_ne!(a.notes, c.notes);
+
+        for (humanized, original) in a.notes.iter().zip(&scale_run().notes) {
+            assert!((humanized.offset - original.offset).abs() <= 0.05 + 1e-9);
+            assert!(humanized.offset >= 0.0);
+            assert!(humanized.velocity.abs_diff(original.velocity) <= 10);
+            assert_eq!(
+                (humanized.pitch, humanized.duration),
+                (original.pitch, original.duration)
+            );
+        }
+        assert!(
+            a.notes
+                .iter()
+                .zip(&scale_run().notes)
+                .any(|(h, o)| h.offset != o.offset)
+        );
+    }
+
+    #[test]
+    fn test_humanize_keeps_velocity_in_range() {
+        let notes = vec![Note::new(60, 1.0, 1, 0.0), Note::new(62, 1.0, 127, 1.0)];
+        for seed in 0..20 {
+            let mut seq = NoteSequence::new(notes.clone(), 0, 120);
+            seq.humanize(0.0, 50, seed);
+            assert!(seq.notes.iter().all(|n| (1..=127).contains(&n.velocity)));
+        }
+    }
+
+    fn velocities(seq: &NoteSequence) -> Vec<u8> {
+        seq.notes.iter().map(|n| n.velocity).collect()
+    }
+
+    fn dynamics(velocities: &[u8]) -> NoteSequence {
+        let notes = velocities
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| Note::new(60, 1.0, v, i as f64))
+            .collect();
+        NoteSequence::new(notes, 0, 120)
+    }
+
+    #[test]
+    fn test_compress_halves_distance_from_mean() {
+        // Mean velocity is 70
+        let mut seq = dynamics(&[40, 60, 80, 100]);
+        seq.compress_velocity(0.5, 0);
+        assert_eq!(velocities(&seq), [55, 65, 75, 85]);
+
+        // Only the part beyond the threshold is compressed
+        let mut seq = dynamics(&[40, 60, 80, 100]);
+        seq.compress_velocity(0.5, 10);
+        assert_eq!(velocities(&seq), [50, 60, 80, 90]);
+    }
+
+    #[test]
+    fn test_expand_widens_and_clamps() {
+        let mut seq = dynamics(&[40, 60, 80, 100]);
+        seq.expand_velocity(2.0, 0);
+        assert_eq!(velocities(&seq), [10, 50, 90, 127]);
+    }
+
+    #[test]
+    fn test_instruments_in_strings_category() {
+        let strings: Vec<&str> = instruments_in(InstrumentCategory::Strings)
+            .map(|i| i.name)
+            .collect();
+        assert_eq!(
+            strings,
+            [
+                "strings",
+                "violin",
+                "viola",
+                "cello",
+                "contrabass",
+                "tremolo_strings",
+                "pizzicato_strings",
+                "harp"
+            ]
+        );
+        assert_eq!(
+            InstrumentCategory::parse("Bells"),
+            Some(InstrumentCategory::Percussion)
+        );
+        assert_eq!(InstrumentCategory::parse("drums"), None);
+
+        // Every instrument is listed under exactly one category
+        let listed: usize = InstrumentCategory::ALL
+            .iter()
+            .map(|&c| instruments_in(c).count())
+            .sum();
+        assert_eq!(listed, INSTRUMENT_MAP.len());
+    }
+
+    #[test]
+    fn test_suggest_instruments_for_typos() {
+        let piano = suggest_instruments("pian");
+        assert_eq!(piano[0], "piano");
+        assert!(piano.iter().all(|name| name.contains("piano")), "{piano:?}");
+        assert_eq!(suggest_instruments("xylphone"), ["xylophone"]);
+        assert_eq!(suggest_instruments("Stringss"), ["strings"]);
+        assert!(suggest_instruments("kazoo").is_empty());
+    }
+
     #[test]
     fn test_resolve_instrument_by_name() {
         assert_eq!(resolve_instrument("piano"), Some(0));
@@ -246,6 +1428,32 @@ mod tests {
         assert_eq!(seq.duration_seconds(), 1.5); // 3 beats at 120 BPM = 1.5 seconds
     }
 
+    #[test]
+    fn test_time_signature_parse() {
+        assert_eq!(TimeSignature::parse("3/4"), Some(TimeSignature::new(3, 4)));
+        assert_eq!(
+            TimeSignature::parse(" 6/8 "),
+            Some(TimeSignature::new(6, 8))
+        );
+        assert_eq!(TimeSignature::parse("4/3"), None);
+        assert_eq!(TimeSignature::parse("0/4"), None);
+        assert_eq!(TimeSignature::parse("waltz"), None);
+        assert_eq!(TimeSignature::new(6, 8).beats_per_bar(), 3.0);
+        assert_eq!(TimeSignature::default().to_string(), "4/4");
+    }
+
+    #[test]
+    fn test_sequence_duration_with_tempo_changes() {
+        let notes = vec![Note::new(60, 4.0, 80, 0.0)];
+        let mut seq = NoteSequence::new(notes, 0, 120);
+        seq.tempo_changes.push(TempoChange {
+            offset: 2.0,
+            bpm: 60,
+        });
+        // 2 beats at 120 BPM + 2 beats at 60 BPM
+        assert_eq!(seq.duration_seconds(), 3.0);
+    }
+
     #[test]
     fn test_json_parsing() {
         let json = r#"{
@@ -289,4 +1497,86 @@ mod tests {
         assert_eq!(sequences[0].instrument, 0); // piano
         assert_eq!(sequences[1].instrument, 33); // bass
     }
+
+    #[test]
+    fn test_json_tempo_changes_sorted_and_validated() {
+        let json = r#"{"tempo": 120, "notes": [{"pitch": "C4", "duration": 8, "velocity": 80}],
+            "tempo_changes": [{"offset_beats": 4, "bpm": 90}, {"offset_beats": 0, "bpm": 100}]}"#;
+        let input: JsonSequenceInput = serde_json::from_str(json).unwrap();
+        let seq = &input.to_sequences().unwrap()[0];
+        assert_eq!(seq.tempo, 100);
+        assert_eq!(
+            seq.tempo_changes,
+            [TempoChange {
+                offset: 4.0,
+                bpm: 90
+            }]
+        );
+
+        let json = r#"{"notes": [], "tempo_changes": [{"offset_beats": 2, "bpm": 0}]}"#;
+        let input: JsonSequenceInput = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            input.to_sequences(),
+            Err(JsonInputError::BadTempoChange {
+                offset: 2.0,
+                bpm: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_unknown_key_rejected() {
+        let json = r#"{"key": "H", "notes": [{"pitch": "C4", "duration": 1, "velocity": 80}]}"#;
+        let input: JsonSequenceInput = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            input.to_sequences(),
+            Err(JsonInputError::UnknownKey("H".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_json_articulation() {
+        let json = r#"{
+            "notes": [
+                {"pitch": "C4", "duration": 1.0, "velocity": 80, "offset": 0, "articulation": "staccato"},
+                {"pitch": "D4", "duration": 0.9, "velocity": 80, "offset": 1, "articulation": "legato"},
+                {"pitch": "E4", "duration": 1.0, "velocity": 80, "offset": 2, "articulation": "tenuto"}
+            ]
+        }"#;
+        let input: JsonSequenceInput = serde_json::from_str(json).unwrap();
+        let notes = &input.to_sequences().unwrap()[0].notes;
+
+        // Staccato quarter note ends half a beat in
+        assert_eq!(notes[0].duration, 0.5);
+        // Legato overlaps the note that follows
+        assert!(notes[1].offset + notes[1].duration > notes[2].offset);
+        assert_eq!(notes[2].duration, 1.0);
+
+        let bad = r#"{"notes": [{"pitch": "C4", "duration": 1, "velocity": 80, "articulation": "marcato"}]}"#;
+        assert!(serde_json::from_str::<JsonSequenceInput>(bad).is_err());
+    }
+
+    #[test]
+    fn test_overruns_report_only_long_sequences() {
+        // 120 BPM: 8 beats is 4 seconds, 10 beats is 5
+        let short = NoteSequence::new(vec![Note::new(60, 8.0, 80, 0.0)], 0, 120);
+        let long = NoteSequence::new(vec![Note::new(60, 4.0, 80, 6.0)], 0, 120);
+        assert_eq!(overruns(&[short.clone(), long.clone()], 4.0), [(1, 1.0)]);
+        assert!(overruns(&[short, long], 5.0).is_empty());
+    }
+
+    #[test]
+    fn test_pad_start_shifts_by_seconds() {
+        // Half a second is one beat at 120 BPM and half a beat at 60
+        let fast = NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], 0, 120);
+        let slow = NoteSequence::new(vec![Note::new(64, 1.0, 80, 2.0)], 0, 60);
+        let mut sequences = [fast, slow];
+        pad_start(&mut sequences, 0.5);
+        assert_eq!(sequences[0].notes[0].offset, 1.0);
+        assert_eq!(sequences[1].notes[0].offset, 2.5);
+        assert_eq!(
+            sequences[0].beats_to_seconds(sequences[0].notes[0].offset),
+            0.5
+        );
+    }
 }
@@ -3,15 +3,44 @@
 //! Generates Standard MIDI Files (SMF) from note sequences.
 
 use super::NoteSequence;
+use super::drums::{STANDARD_KIT, drum_kit_name};
+use super::sequence::{DRUM_CHANNEL, INSTRUMENT_MAP};
 use midly::{Format, Header, MidiMessage, Smf, Timing, Track, TrackEvent, TrackEventKind};
 use std::fs::File;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 /// Ticks per quarter note (standard resolution)
 const TICKS_PER_BEAT: u16 = 480;
 
+/// Bank select, most significant 7 bits
+const CC_BANK_SELECT_MSB: u8 = 0;
+
+/// Bank select, least significant 7 bits
+const CC_BANK_SELECT_LSB: u8 = 32;
+
+/// Data entry for the selected registered parameter, coarse and fine
+const CC_DATA_ENTRY_MSB: u8 = 6;
+const CC_DATA_ENTRY_LSB: u8 = 38;
+
+/// Registered parameter number select, fine and coarse
+const CC_RPN_LSB: u8 = 100;
+const CC_RPN_MSB: u8 = 101;
+
+/// Pitch-bend range set on channels that play detuned notes (the GM
+/// default of two semitones), in cents
+pub const PITCH_BEND_RANGE_CENTS: i32 = 200;
+
+/// Sound controller 3: release time
+const CC_RELEASE_TIME: u8 = 72;
+
+/// Sound controller 4: attack time
+const CC_ATTACK_TIME: u8 = 73;
+
+/// Sound controller 6: decay time
+const CC_DECAY_TIME: u8 = 75;
+
 /// Errors that can occur when writing MIDI files
 #[derive(Debug, Error)]
 pub enum MidiWriteError {
@@ -20,6 +49,15 @@ pub enum MidiWriteError {
 
     #[error("No sequences provided")]
     EmptySequences,
+
+    #[error("Invalid MIDI channel {0} (must be 0-15)")]
+    InvalidChannel(u8),
+
+    #[error("Too many instruments for the 15 non-drum channels: {0}")]
+    TooManyInstruments(usize),
+
+    #[error("Not enough free MIDI channels for {0} detunings (each needs a channel of its own)")]
+    TooManyDetunings(usize),
 }
 
 /// Convert beats to MIDI ticks
@@ -27,41 +65,99 @@ fn beats_to_ticks(beats: f64) -> u32 {
     (beats * TICKS_PER_BEAT as f64) as u32
 }
 
+/// A named position in the piece, written as a Marker meta event
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    /// Position in beats
+    pub offset: f64,
+
+    /// Marker text (e.g. "Chorus")
+    pub text: String,
+}
+
+/// File-level meta events written to the conductor track
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MidiMetadata {
+    /// Copyright notice, written at the start of the file
+    pub copyright: Option<String>,
+
+    /// Section markers
+    pub markers: Vec<Marker>,
+}
+
 /// Write sequences to a MIDI file
+///
+/// The file is encoded in memory first, so invalid sequences don't leave an
+/// empty file behind.
 pub fn write_midi(sequences: &[NoteSequence], path: &Path) -> Result<(), MidiWriteError> {
+    write_midi_with(sequences, &MidiMetadata::default(), path)
+}
+
+/// Write sequences to a MIDI file with a copyright notice and markers
+pub fn write_midi_with(
+    sequences: &[NoteSequence],
+    metadata: &MidiMetadata,
+    path: &Path,
+) -> Result<(), MidiWriteError> {
+    let mut bytes = Vec::new();
+    write_midi_to_with(sequences, metadata, &mut bytes)?;
+    File::create(path)?.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Encode sequences as Standard MIDI File bytes, without touching the
+/// filesystem (used by the in-browser WASM build)
+pub fn midi_bytes(sequences: &[NoteSequence]) -> Result<Vec<u8>, MidiWriteError> {
+    let mut buffer = Vec::new();
+    write_midi_to(sequences, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Write sequences as a Standard MIDI File to any writer (a file, stdout,
+/// or an in-memory buffer)
+pub fn write_midi_to<W: Write>(
+    sequences: &[NoteSequence],
+    writer: W,
+) -> Result<(), MidiWriteError> {
+    write_midi_to_with(sequences, &MidiMetadata::default(), writer)
+}
+
+/// Write sequences with a copyright notice and markers to any writer
+pub fn write_midi_to_with<W: Write>(
+    sequences: &[NoteSequence],
+    metadata: &MidiMetadata,
+    writer: W,
+) -> Result<(), MidiWriteError> {
     if sequences.is_empty() {
         return Err(MidiWriteError::EmptySequences);
     }
+    if let Some(channel) = sequences
+        .iter()
+        .flat_map(|s| s.channels())
+        .find(|&c| c > 15)
+    {
+        return Err(MidiWriteError::InvalidChannel(channel));
+    }
 
-    // Use tempo from first sequence
-    let tempo = sequences[0].tempo;
+    // Pitch bend affects a whole channel, so detuned notes need their own
+    let detuned;
+    let sequences = if sequences
+        .iter()
+        .any(|s| s.notes.iter().any(|n| n.cents != 0))
+    {
+        let mut copy = sequences.to_vec();
+        assign_detune_channels(&mut copy)?;
+        detuned = copy;
+        &detuned[..]
+    } else {
+        sequences
+    };
 
     // Create MIDI file structure
     let mut tracks: Vec<Track> = Vec::new();
 
-    // Track 0: Tempo and time signature
-    let mut tempo_track: Track = Vec::new();
-
-    // Set tempo (microseconds per beat)
-    let microseconds_per_beat = 60_000_000 / tempo as u32;
-    tempo_track.push(TrackEvent {
-        delta: 0.into(),
-        kind: TrackEventKind::Meta(midly::MetaMessage::Tempo(microseconds_per_beat.into())),
-    });
-
-    // Time signature: 4/4
-    tempo_track.push(TrackEvent {
-        delta: 0.into(),
-        kind: TrackEventKind::Meta(midly::MetaMessage::TimeSignature(4, 2, 24, 8)),
-    });
-
-    // End of track
-    tempo_track.push(TrackEvent {
-        delta: 0.into(),
-        kind: TrackEventKind::Meta(midly::MetaMessage::EndOfTrack),
-    });
-
-    tracks.push(tempo_track);
+    // Track 0: Tempo and time signature (from first sequence)
+    tracks.push(build_tempo_track(&sequences[0], metadata));
 
     // Add a track for each sequence
     for seq in sequences {
@@ -78,56 +174,371 @@ pub fn write_midi(sequences: &[NoteSequence], path: &Path) -> Result<(), MidiWri
         tracks,
     };
 
-    // Write to file
-    let mut file = File::create(path)?;
-    let mut buffer = Vec::new();
-    smf.write_std(&mut buffer)
+    smf.write_std(writer)
         .map_err(|e| std::io::Error::other(format!("MIDI write error: {e}")))?;
-    file.write_all(&buffer)?;
 
     Ok(())
 }
 
+/// Spread sequences over the 16 MIDI channels
+///
+/// Sequences on the drum channel stay there. Every other sequence gets a
+/// channel per instrument (sequences sharing an instrument share a channel),
+/// skipping the drum channel, so program changes never overwrite each other.
+/// Fails if there are more than 15 distinct non-drum instruments.
+pub fn assign_channels(sequences: &mut [NoteSequence]) -> Result<(), MidiWriteError> {
+    let mut assigned: Vec<(u8, u8)> = Vec::new(); // (instrument, channel)
+    let mut free = (0..16u8).filter(|&c| c != DRUM_CHANNEL);
+
+    for seq in sequences.iter_mut().filter(|s| s.channel != DRUM_CHANNEL) {
+        let channel = match assigned
+            .iter()
+            .find(|(instrument, _)| *instrument == seq.instrument)
+        {
+            Some(&(_, channel)) => channel,
+            None => {
+                let channel = free
+                    .next()
+                    .ok_or(MidiWriteError::TooManyInstruments(assigned.len() + 1))?;
+                assigned.push((seq.instrument, channel));
+                channel
+            }
+        };
+        seq.channel = channel;
+    }
+
+    Ok(())
+}
+
+/// Overlay sequences (e.g. from separate generators) into one set ready for
+/// [`write_midi`]
+///
+/// Sequences keep their channel unless an earlier one already plays a
+/// different instrument or bank there; those move to the lowest channel no
+/// sequence uses. Drum-channel sequences always stay on the drum channel.
+/// Fails if the free channels run out.
+pub fn merge(
+    sequences: impl IntoIterator<Item = NoteSequence>,
+) -> Result<Vec<NoteSequence>, MidiWriteError> {
+    let mut merged: Vec<NoteSequence> = sequences.into_iter().collect();
+    let mut taken: Vec<(u8, u8, Option<u16>)> = Vec::new(); // (channel, instrument, bank)
+    let mut moved = Vec::new();
+
+    for (i, seq) in merged
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.channel != DRUM_CHANNEL)
+    {
+        match taken.iter().find(|(channel, _, _)| *channel == seq.channel) {
+            Some(&(_, instrument, bank)) if instrument != seq.instrument || bank != seq.bank => {
+                moved.push(i)
+            }
+            Some(_) => {}
+            None => taken.push((seq.channel, seq.instrument, seq.bank)),
+        }
+    }
+
+    let used: Vec<u8> = merged.iter().flat_map(|s| s.channels()).collect();
+    let mut free = (0..16u8).filter(|c| *c != DRUM_CHANNEL && !used.contains(c));
+    for (count, i) in moved.into_iter().enumerate() {
+        let seq = &mut merged[i];
+        // Sequences with the same instrument that were moved share a channel
+        let channel = match taken.iter().find(|&&(c, instrument, bank)| {
+            !used.contains(&c) && instrument == seq.instrument && bank == seq.bank
+        }) {
+            Some(&(channel, _, _)) => channel,
+            None => {
+                let channel = free
+                    .next()
+                    .ok_or(MidiWriteError::TooManyInstruments(taken.len() + count + 1))?;
+                taken.push((channel, seq.instrument, seq.bank));
+                channel
+            }
+        };
+        seq.channel = channel;
+    }
+
+    Ok(merged)
+}
+
+/// Pitch-bend value (-8192..=8191) that raises a note by `cents` with a
+/// bend range of [`PITCH_BEND_RANGE_CENTS`]
+pub fn cents_to_bend(cents: i16) -> i16 {
+    (cents as i32 * 8192 / PITCH_BEND_RANGE_CENTS).clamp(-8192, 8191) as i16
+}
+
+/// Move detuned notes onto channels of their own
+///
+/// Each distinct detuning within a sequence gets a channel that no sequence
+/// plays on yet, so its pitch bend leaves other notes alone. Drum-channel
+/// notes stay put and are not bent. Fails when the free channels run out.
+pub fn assign_detune_channels(sequences: &mut [NoteSequence]) -> Result<(), MidiWriteError> {
+    let used: Vec<u8> = sequences.iter().flat_map(|s| s.channels()).collect();
+    let mut free = (0..16u8).filter(|c| *c != DRUM_CHANNEL && !used.contains(c));
+    let mut assigned: Vec<(usize, i16, u8)> = Vec::new(); // (sequence, cents, channel)
+
+    for (i, seq) in sequences.iter_mut().enumerate() {
+        let sequence_channel = seq.channel;
+        for note in seq.notes.iter_mut() {
+            if note.cents == 0 || note.channel.unwrap_or(sequence_channel) == DRUM_CHANNEL {
+                continue;
+            }
+            let channel = match assigned
+                .iter()
+                .find(|&&(s, cents, _)| s == i && cents == note.cents)
+            {
+                Some(&(_, _, channel)) => channel,
+                None => {
+                    let channel = free
+                        .next()
+                        .ok_or(MidiWriteError::TooManyDetunings(assigned.len() + 1))?;
+                    assigned.push((i, note.cents, channel));
+                    channel
+                }
+            };
+            note.channel = Some(channel);
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the conductor track (copyright, tempo, time signature, tempo
+/// changes and markers)
+fn build_tempo_track<'a>(seq: &NoteSequence, metadata: &'a MidiMetadata) -> Track<'a> {
+    let mut tempo_track: Track = Vec::new();
+
+    // Copyright notice goes first, at time zero
+    if let Some(copyright) = &metadata.copyright {
+        tempo_track.push(TrackEvent {
+            delta: 0.into(),
+            kind: TrackEventKind::Meta(midly::MetaMessage::Copyright(copyright.as_bytes())),
+        });
+    }
+
+    // Set tempo (microseconds per beat)
+    let microseconds_per_beat = 60_000_000 / seq.tempo as u32;
+    tempo_track.push(TrackEvent {
+        delta: 0.into(),
+        kind: TrackEventKind::Meta(midly::MetaMessage::Tempo(microseconds_per_beat.into())),
+    });
+
+    // Time signature (denominator as a power of two, metronome click per beat unit)
+    let time_signature = seq.time_signature;
+    tempo_track.push(TrackEvent {
+        delta: 0.into(),
+        kind: TrackEventKind::Meta(midly::MetaMessage::TimeSignature(
+            time_signature.numerator,
+            time_signature.denominator.max(1).trailing_zeros() as u8,
+            96 / time_signature.denominator.clamp(1, 96),
+            8,
+        )),
+    });
+
+    // Key signature (sharps or flats, major or minor)
+    if let Some(key) = seq.key_signature {
+        tempo_track.push(TrackEvent {
+            delta: 0.into(),
+            kind: TrackEventKind::Meta(midly::MetaMessage::KeySignature(
+                key.sharps(),
+                key.is_minor(),
+            )),
+        });
+    }
+
+    // Tempo changes (e.g. a closing ritardando) and section markers, in
+    // time order; the sort is stable, so tempo changes come first on a tie
+    let mut events: Vec<(u32, midly::MetaMessage)> = seq
+        .tempo_changes
+        .iter()
+        .map(|change| {
+            let microseconds_per_beat = 60_000_000 / change.bpm.max(1) as u32;
+            (
+                beats_to_ticks(change.offset),
+                midly::MetaMessage::Tempo(microseconds_per_beat.into()),
+            )
+        })
+        .chain(metadata.markers.iter().map(|marker| {
+            (
+                beats_to_ticks(marker.offset),
+                midly::MetaMessage::Marker(marker.text.as_bytes()),
+            )
+        }))
+        .collect();
+    events.sort_by_key(|&(tick, _)| tick);
+
+    let mut last_tick = 0u32;
+    for (tick, message) in events {
+        tempo_track.push(TrackEvent {
+            delta: tick.saturating_sub(last_tick).into(),
+            kind: TrackEventKind::Meta(message),
+        });
+        last_tick = tick;
+    }
+
+    // End of track
+    tempo_track.push(TrackEvent {
+        delta: 0.into(),
+        kind: TrackEventKind::Meta(midly::MetaMessage::EndOfTrack),
+    });
+
+    tempo_track
+}
+
 /// Build a MIDI track from a note sequence
-fn build_track(seq: &NoteSequence) -> Track<'static> {
+fn build_track(seq: &NoteSequence) -> Track<'_> {
     let mut track: Track = Vec::new();
-    let channel = seq.channel.into();
 
-    // Program change (instrument selection)
-    track.push(TrackEvent {
-        delta: 0.into(),
-        kind: TrackEventKind::Midi {
-            channel,
-            message: MidiMessage::ProgramChange {
-                program: seq.instrument.into(),
+    // Names shown by DAWs: the sequence's own, and its GM instrument
+    if let Some(name) = &seq.name {
+        track.push(TrackEvent {
+            delta: 0.into(),
+            kind: TrackEventKind::Meta(midly::MetaMessage::TrackName(name.as_bytes())),
+        });
+    }
+    let instrument = if seq.channel == DRUM_CHANNEL {
+        Some(
+            drum_kit_name(seq.instrument)
+                .filter(|&kit| kit != "standard")
+                .unwrap_or("drums"),
+        )
+    } else {
+        INSTRUMENT_MAP
+            .iter()
+            .find(|i| i.program == seq.instrument)
+            .map(|i| i.name)
+    };
+    if let Some(instrument) = instrument {
+        track.push(TrackEvent {
+            delta: 0.into(),
+            kind: TrackEventKind::Meta(midly::MetaMessage::InstrumentName(instrument.as_bytes())),
+        });
+    }
+
+    // Bank select and program change (instrument selection) on every
+    // channel the track plays on. The drum channel starts on the standard
+    // kit and a program change there only confuses some synths, so drum
+    // tracks get one just to select another kit
+    let drum_kit = seq.channel == DRUM_CHANNEL && seq.instrument != STANDARD_KIT;
+    for channel in seq
+        .channels()
+        .into_iter()
+        .filter(|&c| c != DRUM_CHANNEL || drum_kit)
+    {
+        let drums = channel == DRUM_CHANNEL;
+        let channel = channel.into();
+        if let Some(bank) = seq.bank.filter(|_| !drums) {
+            for (controller, value) in [
+                (CC_BANK_SELECT_MSB, (bank >> 7) & 0x7f),
+                (CC_BANK_SELECT_LSB, bank & 0x7f),
+            ] {
+                track.push(TrackEvent {
+                    delta: 0.into(),
+                    kind: TrackEventKind::Midi {
+                        channel,
+                        message: MidiMessage::Controller {
+                            controller: controller.into(),
+                            value: (value as u8).into(),
+                        },
+                    },
+                });
+            }
+        }
+        track.push(TrackEvent {
+            delta: 0.into(),
+            kind: TrackEventKind::Midi {
+                channel,
+                message: MidiMessage::ProgramChange {
+                    program: seq.instrument.into(),
+                },
             },
-        },
-    });
+        });
+    }
+
+    // Bend range and pitch bend on channels holding detuned notes
+    for channel in seq.channels().into_iter().filter(|&c| c != DRUM_CHANNEL) {
+        let Some(cents) = seq
+            .notes
+            .iter()
+            .find(|n| n.cents != 0 && n.channel.unwrap_or(seq.channel) == channel)
+            .map(|n| n.cents)
+        else {
+            continue;
+        };
+        let channel = channel.into();
+        let semitones = (PITCH_BEND_RANGE_CENTS / 100) as u8;
+        for (controller, value) in [
+            (CC_RPN_MSB, 0),
+            (CC_RPN_LSB, 0),
+            (CC_DATA_ENTRY_MSB, semitones),
+            (CC_DATA_ENTRY_LSB, 0),
+        ] {
+            track.push(TrackEvent {
+                delta: 0.into(),
+                kind: TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::Controller {
+                        controller: controller.into(),
+                        value: value.into(),
+                    },
+                },
+            });
+        }
+        track.push(TrackEvent {
+            delta: 0.into(),
+            kind: TrackEventKind::Midi {
+                channel,
+                message: MidiMessage::PitchBend {
+                    bend: midly::PitchBend::from_int(cents_to_bend(cents)),
+                },
+            },
+        });
+    }
+
+    // Envelope controllers (attack, decay, release) on every channel the
+    // track plays on, including per-note and detune channels
+    if let Some(envelope) = seq.envelope {
+        for channel in seq.channels() {
+            for (controller, value) in [
+                (CC_ATTACK_TIME, envelope.attack),
+                (CC_DECAY_TIME, envelope.decay),
+                (CC_RELEASE_TIME, envelope.decay),
+            ] {
+                track.push(TrackEvent {
+                    delta: 0.into(),
+                    kind: TrackEventKind::Midi {
+                        channel: channel.into(),
+                        message: MidiMessage::Controller {
+                            controller: controller.into(),
+                            value: value.min(127).into(),
+                        },
+                    },
+                });
+            }
+        }
+    }
 
     // Build events list: collect all note-on and note-off events
-    let mut events: Vec<(u32, bool, u8, u8)> = Vec::new(); // (tick, is_note_on, pitch, velocity)
+    let mut events: Vec<(u32, bool, u8, u8, u8)> = Vec::new(); // (tick, is_note_on, pitch, velocity, channel)
 
     for note in &seq.notes {
         let start_tick = beats_to_ticks(note.offset);
-        let end_tick = beats_to_ticks(note.offset + note.duration);
+        // At least one tick long, so the note-off can't sort before its note-on
+        let end_tick = beats_to_ticks(note.offset + note.duration).max(start_tick + 1);
+        let channel = note.channel.unwrap_or(seq.channel);
 
-        events.push((start_tick, true, note.pitch, note.velocity));
-        events.push((end_tick, false, note.pitch, 0));
+        events.push((start_tick, true, note.pitch, note.velocity, channel));
+        events.push((end_tick, false, note.pitch, 0, channel));
     }
 
-    // Sort by tick, note-offs before note-ons at same tick
-    events.sort_by(|a, b| {
-        if a.0 != b.0 {
-            a.0.cmp(&b.0)
-        } else {
-            // Note-off (false) before note-on (true)
-            a.1.cmp(&b.1)
-        }
-    });
+    // Notes may be in any order (e.g. grace notes pushed after their main
+    // note), so sort by absolute tick before computing deltas. The sort is
+    // stable; at the same tick note-offs (false) come before note-ons (true).
+    events.sort_by_key(|&(tick, is_note_on, _, _, _)| (tick, is_note_on));
 
     // Convert to delta times and add to track
     let mut last_tick = 0u32;
-    for (tick, is_note_on, pitch, velocity) in events {
+    for (tick, is_note_on, pitch, velocity, channel) in events {
         let delta = tick.saturating_sub(last_tick);
         last_tick = tick;
 
@@ -145,7 +556,10 @@ fn build_track(seq: &NoteSequence) -> Track<'static> {
 
         track.push(TrackEvent {
             delta: delta.into(),
-            kind: TrackEventKind::Midi { channel, message },
+            kind: TrackEventKind::Midi {
+                channel: channel.into(),
+                message,
+            },
         });
     }
 
@@ -159,10 +573,47 @@ fn build_track(seq: &NoteSequence) -> Track<'static> {
 }
 
 /// Write a single sequence to a MIDI file
+///
+/// Produces the same file as `write_midi(&[seq], path)`: a conductor track
+/// followed by one track for the sequence, built by the shared track builder.
+/// Convenient for stems and tests.
 pub fn write_midi_single(seq: &NoteSequence, path: &Path) -> Result<(), MidiWriteError> {
     write_midi(std::slice::from_ref(seq), path)
 }
 
+/// Path of one stem for an output path: `out/intro.wav` -> `out/intro_layer1.mid`
+pub fn stem_path(output: &Path, layer: usize) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("stem");
+    output.with_file_name(format!("{stem}_layer{layer}.mid"))
+}
+
+/// Write each sequence to its own MIDI file (layers numbered from 1), each
+/// with the same copyright notice and markers
+///
+/// Returns the written paths in sequence order.
+pub fn write_stems(
+    sequences: &[NoteSequence],
+    metadata: &MidiMetadata,
+    output: &Path,
+) -> Result<Vec<PathBuf>, MidiWriteError> {
+    if sequences.is_empty() {
+        return Err(MidiWriteError::EmptySequences);
+    }
+
+    sequences
+        .iter()
+        .enumerate()
+        .map(|(i, seq)| {
+            let path = stem_path(output, i + 1);
+            write_midi_with(std::slice::from_ref(seq), metadata, &path)?;
+            Ok(path)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,6 +628,52 @@ mod tests {
         assert_eq!(beats_to_ticks(0.25), 120);
     }
 
+    #[test]
+    fn test_write_midi_to_buffer_parses_back() {
+        let melody = NoteSequence::new(
+            vec![Note::new(60, 1.0, 80, 0.0), Note::new(64, 1.0, 90, 1.0)],
+            0,
+            100,
+        );
+        let bass = NoteSequence::new(vec![Note::new(36, 2.0, 70, 0.0)], 32, 100);
+
+        let mut buffer: Vec<u8> = Vec::new();
+        write_midi_to(&[melody, bass], &mut buffer).unwrap();
+
+        let smf = Smf::parse(&buffer).unwrap();
+        assert_eq!(smf.header.format, Format::Parallel);
+        assert_eq!(smf.header.timing, Timing::Metrical(TICKS_PER_BEAT.into()));
+        assert_eq!(smf.tracks.len(), 3); // conductor + one per sequence
+
+        let note_ons = smf.tracks[1]
+            .iter()
+            .filter(|e| matches!(e.kind, TrackEventKind::Midi { message: MidiMessage::NoteOn { vel, .. }, .. } if vel > 0))
+            .count();
+        assert_eq!(note_ons, 2);
+    }
+
+    #[test]
+    fn test_write_midi_to_rejects_empty() {
+        let mut buffer: Vec<u8> = Vec::new();
+        assert!(matches!(
+            write_midi_to(&[], &mut buffer),
+            Err(MidiWriteError::EmptySequences)
+        ));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_midi_bytes_match_file() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("test.mid");
+        let seq = NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], 0, 120);
+
+        let bytes = midi_bytes(std::slice::from_ref(&seq)).unwrap();
+        write_midi_single(&seq, &path).unwrap();
+        assert!(bytes.starts_with(b"MThd"));
+        assert_eq!(bytes, std::fs::read(&path).unwrap());
+    }
+
     #[test]
     fn test_write_simple_midi() {
         let temp = tempdir().unwrap();
@@ -239,6 +736,751 @@ mod tests {
         assert_eq!(smf.tracks.len(), 3);
     }
 
+    /// Absolute (tick, is_note_on, key) for each note event in a track
+    fn note_events(track: &[TrackEvent]) -> Vec<(u32, bool, u8)> {
+        let mut tick = 0u32;
+        track
+            .iter()
+            .filter_map(|e| {
+                tick += e.delta.as_int();
+                match e.kind {
+                    TrackEventKind::Midi {
+                        message: MidiMessage::NoteOn { key, .. },
+                        ..
+                    } => Some((tick, true, key.as_int())),
+                    TrackEventKind::Midi {
+                        message: MidiMessage::NoteOff { key, .. },
+                        ..
+                    } => Some((tick, false, key.as_int())),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_out_of_order_notes_written_in_time_order() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("grace.mid");
+
+        // Main note first, then a grace note leading into it, then a pickup at 0
+        let notes = vec![
+            Note::new(48, 1.0, 90, 1.0),
+            Note::new(47, 0.25, 60, 0.75),
+            Note::new(43, 0.75, 80, 0.0),
+        ];
+        write_midi_single(&NoteSequence::new(notes, 32, 120), &path).unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let smf = Smf::parse(&content).unwrap();
+
+        assert_eq!(
+            note_events(&smf.tracks[1]),
+            vec![
+                (0, true, 43),
+                (360, false, 43),
+                (360, true, 47),
+                (480, false, 47),
+                (480, true, 48),
+                (960, false, 48),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_program_change_on_drum_channel() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("drums.mid");
+
+        let piano = NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], 0, 120);
+        let mut drums = NoteSequence::new(vec![Note::new(38, 0.5, 80, 0.0)], 0, 120);
+        drums.channel = DRUM_CHANNEL;
+        write_midi(&[piano, drums], &path).unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let smf = Smf::parse(&content).unwrap();
+        let has_program_change = |track: &[TrackEvent]| {
+            track.iter().any(|e| {
+                matches!(
+                    e.kind,
+                    TrackEventKind::Midi {
+                        message: MidiMessage::ProgramChange { .. },
+                        ..
+                    }
+                )
+            })
+        };
+
+        assert!(has_program_change(&smf.tracks[1]));
+        assert!(!has_program_change(&smf.tracks[2]));
+    }
+
+    #[test]
+    fn test_drum_kit_selected_by_program_change() {
+        let mut drums = NoteSequence::new(vec![Note::new(38, 0.5, 80, 0.0)], 40, 120);
+        drums.channel = DRUM_CHANNEL;
+        drums.bank = Some(128);
+        let smf_bytes = midi_bytes(&[drums]).unwrap();
+        let smf = Smf::parse(&smf_bytes).unwrap();
+
+        let messages: Vec<MidiMessage> = smf.tracks[1]
+            .iter()
+            .filter_map(|e| match e.kind {
+                TrackEventKind::Midi { channel, message } if channel == DRUM_CHANNEL => {
+                    Some(message)
+                }
+                _ => None,
+            })
+            .filter(|m| !matches!(m, MidiMessage::NoteOn { .. } | MidiMessage::NoteOff { .. }))
+            .collect();
+        assert_eq!(
+            messages,
+            [MidiMessage::ProgramChange { program: 40.into() }]
+        );
+    }
+
+    #[test]
+    fn test_detuned_note_bent_on_own_channel() {
+        let mut sharp = Note::new(69, 1.0, 80, 0.0);
+        sharp.cents = 100;
+        let seq = NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0), sharp], 0, 120);
+        let smf_bytes = midi_bytes(&[seq]).unwrap();
+        let smf = Smf::parse(&smf_bytes).unwrap();
+
+        let events: Vec<(u8, MidiMessage)> = smf.tracks[1]
+            .iter()
+            .filter_map(|e| match e.kind {
+                TrackEventKind::Midi { channel, message } => Some((channel.as_int(), message)),
+                _ => None,
+            })
+            .collect();
+        // +100 cents is one semitone: half the +-2 semitone range
+        let bends: Vec<_> = events
+            .iter()
+            .filter_map(|(channel, m)| match m {
+                MidiMessage::PitchBend { bend } => Some((*channel, bend.as_int())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(bends, [(1, 4096)]);
+        assert!(events.contains(&(
+            1,
+            MidiMessage::Controller {
+                controller: 6.into(),
+                value: 2.into()
+            }
+        )));
+        assert!(events.contains(&(1, MidiMessage::ProgramChange { program: 0.into() })));
+
+        let note_on = |pitch: u8| {
+            events.iter().find_map(|(channel, m)| match m {
+                MidiMessage::NoteOn { key, .. } if key.as_int() == pitch => Some(*channel),
+                _ => None,
+            })
+        };
+        assert_eq!(note_on(60), Some(0));
+        assert_eq!(note_on(69), Some(1));
+    }
+
+    #[test]
+    fn test_merge_keeps_distinct_channels() {
+        let melody = NoteSequence::new(vec![Note::new(72, 1.0, 90, 0.0)], 73, 120);
+        let mut bass = NoteSequence::new(vec![Note::new(36, 2.0, 80, 0.0)], 33, 120);
+        bass.channel = 1;
+        let merged = merge([melody.clone(), bass.clone()]).unwrap();
+        assert_eq!(merged, [melody.clone(), bass]);
+
+        // A second channel-0 melody on another instrument moves out of the way
+        let strings = NoteSequence::new(vec![Note::new(60, 4.0, 60, 0.0)], 48, 120);
+        let merged = merge(vec![melody.clone(), strings.clone(), melody.clone()]).unwrap();
+        let channels: Vec<u8> = merged.iter().map(|s| s.channel).collect();
+        assert_eq!(channels, [0, 1, 0]);
+        assert_eq!(merged[1].notes, strings.notes);
+    }
+
+    #[test]
+    fn test_merge_runs_out_of_channels() {
+        let sequences =
+            (0..17u8).map(|i| NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], i, 120));
+        assert!(matches!(
+            merge(sequences),
+            Err(MidiWriteError::TooManyInstruments(_))
+        ));
+    }
+
+    #[test]
+    fn test_cents_to_bend() {
+        assert_eq!(cents_to_bend(0), 0);
+        assert_eq!(cents_to_bend(100), 4096);
+        assert_eq!(cents_to_bend(-50), -2048);
+        assert_eq!(cents_to_bend(200), 8191);
+    }
+
+    #[test]
+    fn test_invalid_channel_rejected() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("bad_channel.mid");
+
+        let mut seq = NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], 0, 120);
+        seq.channel = 16;
+
+        assert!(matches!(
+            write_midi_single(&seq, &path),
+            Err(MidiWriteError::InvalidChannel(16))
+        ));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_assign_channels_many_sequences() {
+        // 16 melodic sequences over 12 instruments, plus 2 drum sequences
+        let mut sequences: Vec<NoteSequence> = (0..16u8)
+            .map(|i| NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], i % 12, 120))
+            .collect();
+        for _ in 0..2 {
+            let mut drums = NoteSequence::new(vec![Note::new(38, 1.0, 80, 0.0)], 0, 120);
+            drums.channel = DRUM_CHANNEL;
+            sequences.push(drums);
+        }
+
+        assign_channels(&mut sequences).unwrap();
+
+        let (melodic, drums) = sequences.split_at(16);
+        assert!(drums.iter().all(|s| s.channel == DRUM_CHANNEL));
+        assert!(
+            melodic
+                .iter()
+                .all(|s| s.channel != DRUM_CHANNEL && s.channel < 16)
+        );
+        for a in melodic {
+            for b in melodic {
+                assert_eq!(a.instrument == b.instrument, a.channel == b.channel);
+            }
+        }
+
+        let temp = tempdir().unwrap();
+        write_midi(&sequences, &temp.path().join("many.mid")).unwrap();
+    }
+
+    #[test]
+    fn test_assign_channels_too_many_instruments() {
+        let mut sequences: Vec<NoteSequence> = (0..16u8)
+            .map(|i| NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], i, 120))
+            .collect();
+
+        assert!(matches!(
+            assign_channels(&mut sequences),
+            Err(MidiWriteError::TooManyInstruments(16))
+        ));
+    }
+
+    #[test]
+    fn test_zero_length_note_still_closes() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("zero.mid");
+
+        write_midi_single(
+            &NoteSequence::new(vec![Note::new(60, 0.0, 80, 1.0)], 0, 120),
+            &path,
+        )
+        .unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let smf = Smf::parse(&content).unwrap();
+        assert_eq!(
+            note_events(&smf.tracks[1]),
+            vec![(480, true, 60), (481, false, 60)]
+        );
+    }
+
+    #[test]
+    fn test_build_track_events() {
+        let notes = vec![Note::new(60, 1.0, 80, 0.0), Note::new(64, 0.5, 90, 0.5)];
+        let seq = NoteSequence::new(notes, 40, 100);
+        let track = build_track(&seq);
+
+        // Program change, then the notes in time order, then end of track
+        let messages: Vec<(u32, MidiMessage)> = track
+            .iter()
+            .filter_map(|e| match e.kind {
+                TrackEventKind::Midi { channel, message } => {
+                    assert_eq!(channel.as_int(), 0);
+                    Some((e.delta.as_int(), message))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (0, MidiMessage::ProgramChange { program: 40.into() }),
+                (
+                    0,
+                    MidiMessage::NoteOn {
+                        key: 60.into(),
+                        vel: 80.into()
+                    }
+                ),
+                (
+                    240,
+                    MidiMessage::NoteOn {
+                        key: 64.into(),
+                        vel: 90.into()
+                    }
+                ),
+                (
+                    240,
+                    MidiMessage::NoteOff {
+                        key: 60.into(),
+                        vel: 0.into()
+                    }
+                ),
+                (
+                    0,
+                    MidiMessage::NoteOff {
+                        key: 64.into(),
+                        vel: 0.into()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            track.last().unwrap().kind,
+            TrackEventKind::Meta(midly::MetaMessage::EndOfTrack)
+        );
+    }
+
+    #[test]
+    fn test_write_stems_one_file_per_layer() {
+        let temp = tempdir().unwrap();
+        let output = temp.path().join("intro.wav");
+
+        let config = crate::preset::PresetConfig::default();
+        let sequences = crate::preset::generate_mood(crate::preset::Mood::Calm, &config);
+        let metadata = MidiMetadata {
+            copyright: Some("(c) Test".to_string()),
+            markers: Vec::new(),
+        };
+        let paths = write_stems(&sequences, &metadata, &output).unwrap();
+
+        assert_eq!(paths.len(), sequences.len());
+        assert_eq!(paths[0], temp.path().join("intro_layer1.mid"));
+        let written = std::fs::read_dir(temp.path()).unwrap().count();
+        assert_eq!(written, sequences.len());
+        for path in &paths {
+            let content = std::fs::read(path).unwrap();
+            let smf = Smf::parse(&content).unwrap();
+            assert_eq!(smf.tracks.len(), 2); // tempo + one layer
+            assert!(
+                smf.tracks[0]
+                    .iter()
+                    .any(|e| e.kind
+                        == TrackEventKind::Meta(midly::MetaMessage::Copyright(b"(c) Test"))),
+                "{} has no copyright",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_envelope_controllers_written() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("envelope.mid");
+
+        let mut seq = NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], 0, 120);
+        seq.envelope = Some(crate::midi::sequence::Envelope {
+            attack: 20,
+            decay: 100,
+        });
+
+        write_midi_single(&seq, &path).unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let smf = Smf::parse(&content).unwrap();
+        let controllers: Vec<(u8, u8)> = smf.tracks[1]
+            .iter()
+            .filter_map(|e| match e.kind {
+                TrackEventKind::Midi {
+                    message: MidiMessage::Controller { controller, value },
+                    ..
+                } => Some((controller.as_int(), value.as_int())),
+                _ => None,
+            })
+            .collect();
+
+        assert!(controllers.contains(&(73, 20)));
+        assert!(controllers.contains(&(75, 100)));
+        assert!(controllers.contains(&(72, 100)));
+    }
+
+    #[test]
+    fn test_envelope_on_every_channel() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("envelope.mid");
+
+        // One note on its own channel, one detuned (moved to a free channel)
+        let mut seq = NoteSequence::new(
+            vec![
+                Note::new(60, 1.0, 80, 0.0),
+                Note {
+                    channel: Some(3),
+                    ..Note::new(64, 1.0, 80, 0.0)
+                },
+                Note {
+                    cents: 20,
+                    ..Note::new(67, 1.0, 80, 0.0)
+                },
+            ],
+            0,
+            120,
+        );
+        seq.envelope = Some(crate::midi::sequence::Envelope {
+            attack: 20,
+            decay: 100,
+        });
+
+        write_midi_single(&seq, &path).unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let smf = Smf::parse(&content).unwrap();
+        let mut note_channels = Vec::new();
+        let mut attack_channels = Vec::new();
+        for event in &smf.tracks[1] {
+            match event.kind {
+                TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::NoteOn { .. },
+                } => note_channels.push(channel.as_int()),
+                TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::Controller { controller, value },
+                } if controller.as_int() == 73 && value.as_int() == 20 => {
+                    attack_channels.push(channel.as_int())
+                }
+                _ => {}
+            }
+        }
+
+        assert_eq!(note_channels.len(), 3);
+        for channel in note_channels {
+            assert!(
+                attack_channels.contains(&channel),
+                "no envelope on channel {}",
+                channel
+            );
+        }
+    }
+
+    /// Copyright and marker texts with their absolute ticks, from the conductor track
+    fn conductor_texts(bytes: &[u8]) -> Vec<(u32, String)> {
+        let smf = Smf::parse(bytes).unwrap();
+        let mut tick = 0;
+        smf.tracks[0]
+            .iter()
+            .filter_map(|e| {
+                tick += e.delta.as_int();
+                match e.kind {
+                    TrackEventKind::Meta(
+                        midly::MetaMessage::Copyright(text) | midly::MetaMessage::Marker(text),
+                    ) => Some((tick, String::from_utf8_lossy(text).into_owned())),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_copyright_only_when_set() {
+        let seq = NoteSequence::new(vec![Note::new(60, 4.0, 80, 0.0)], 0, 120);
+        assert!(conductor_texts(&midi_bytes(std::slice::from_ref(&seq)).unwrap()).is_empty());
+
+        let metadata = MidiMetadata {
+            copyright: Some("Copyright (c) 2026 Example".to_string()),
+            ..Default::default()
+        };
+        let mut bytes = Vec::new();
+        write_midi_to_with(&[seq], &metadata, &mut bytes).unwrap();
+
+        let smf = Smf::parse(&bytes).unwrap();
+        assert!(matches!(
+            smf.tracks[0][0].kind,
+            TrackEventKind::Meta(midly::MetaMessage::Copyright(b"Copyright (c) 2026 Example"))
+        ));
+    }
+
+    #[test]
+    fn test_markers_interleave_with_tempo_changes() {
+        let mut seq = NoteSequence::new(vec![Note::new(60, 8.0, 80, 0.0)], 0, 120);
+        seq.tempo_changes = vec![crate::midi::sequence::TempoChange {
+            offset: 2.0,
+            bpm: 100,
+        }];
+        let metadata = MidiMetadata {
+            copyright: None,
+            markers: vec![
+                Marker {
+                    offset: 4.0,
+                    text: "Chorus".to_string(),
+                },
+                Marker {
+                    offset: 0.0,
+                    text: "Intro".to_string(),
+                },
+            ],
+        };
+        let mut bytes = Vec::new();
+        write_midi_to_with(&[seq], &metadata, &mut bytes).unwrap();
+
+        assert_eq!(
+            conductor_texts(&bytes),
+            [(0, "Intro".to_string()), (1920, "Chorus".to_string())]
+        );
+        let sequences = crate::midi::reader::parse_midi(&bytes).unwrap();
+        assert_eq!(sequences[0].tempo_changes.len(), 1);
+    }
+
+    #[test]
+    fn test_track_and_instrument_names_written() {
+        let seq =
+            NoteSequence::new(vec![Note::new(36, 1.0, 80, 0.0)], 33, 120).named("Walking Bass");
+        let bytes = midi_bytes(std::slice::from_ref(&seq)).unwrap();
+
+        let smf = Smf::parse(&bytes).unwrap();
+        let names: Vec<&[u8]> = smf.tracks[1]
+            .iter()
+            .filter_map(|e| match e.kind {
+                TrackEventKind::Meta(midly::MetaMessage::TrackName(name)) => Some(name),
+                TrackEventKind::Meta(midly::MetaMessage::InstrumentName(name)) => Some(name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, [b"Walking Bass".as_slice(), b"bass"]);
+
+        // Names survive a round trip
+        assert_eq!(
+            crate::midi::reader::parse_midi(&bytes).unwrap()[0]
+                .name
+                .as_deref(),
+            Some("Walking Bass")
+        );
+    }
+
+    #[test]
+    fn test_unnamed_sequence_has_no_track_name() {
+        let seq = NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], 0, 120);
+        let bytes = midi_bytes(&[seq]).unwrap();
+        let smf = Smf::parse(&bytes).unwrap();
+        assert!(!smf.tracks[1].iter().any(|e| matches!(
+            e.kind,
+            TrackEventKind::Meta(midly::MetaMessage::TrackName(_))
+        )));
+    }
+
+    #[test]
+    fn test_bank_select_before_program_change() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("bank.mid");
+
+        let mut seq = NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], 5, 120);
+        seq.bank = Some(130);
+        write_midi_single(&seq, &path).unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let smf = Smf::parse(&content).unwrap();
+        let setup: Vec<(u8, u8)> = smf.tracks[1]
+            .iter()
+            .take_while(|e| {
+                !matches!(
+                    e.kind,
+                    TrackEventKind::Midi {
+                        message: MidiMessage::NoteOn { .. },
+                        ..
+                    }
+                )
+            })
+            .filter_map(|e| match e.kind {
+                TrackEventKind::Midi {
+                    message: MidiMessage::Controller { controller, value },
+                    ..
+                } => Some((controller.as_int(), value.as_int())),
+                TrackEventKind::Midi {
+                    message: MidiMessage::ProgramChange { program },
+                    ..
+                } => Some((0xC0, program.as_int())),
+                _ => None,
+            })
+            .collect();
+
+        // 130 = MSB 1, LSB 2
+        assert_eq!(setup, [(0, 1), (32, 2), (0xC0, 5)]);
+    }
+
+    #[test]
+    fn test_per_note_channels_in_one_track() {
+        let json = r#"{"notes": [
+            {"pitch": "C4", "duration": 1, "velocity": 80, "offset": 0},
+            {"pitch": "C2", "duration": 1, "velocity": 100, "offset": 0, "channel": 9},
+            {"pitch": "E4", "duration": 1, "velocity": 80, "offset": 1}
+        ]}"#;
+        let input: crate::midi::sequence::JsonSequenceInput = serde_json::from_str(json).unwrap();
+        let bytes = midi_bytes(&input.to_sequences().unwrap()).unwrap();
+        let smf = Smf::parse(&bytes).unwrap();
+
+        assert_eq!(smf.tracks.len(), 2);
+        let note_ons: Vec<(u8, u8)> = smf.tracks[1]
+            .iter()
+            .filter_map(|e| match e.kind {
+                TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::NoteOn { key, .. },
+                } => Some((channel.as_int(), key.as_int())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(note_ons, [(0, 60), (9, 36), (0, 64)]);
+
+        // Only the melodic channel gets a program change
+        let programs = smf.tracks[1]
+            .iter()
+            .filter(|e| {
+                matches!(
+                    e.kind,
+                    TrackEventKind::Midi {
+                        message: MidiMessage::ProgramChange { .. },
+                        ..
+                    }
+                )
+            })
+            .count();
+        assert_eq!(programs, 1);
+    }
+
+    #[test]
+    fn test_no_envelope_no_controllers() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("plain.mid");
+
+        let seq = NoteSequence::new(vec![Note::new(60, 1.0, 80, 0.0)], 0, 120);
+        write_midi_single(&seq, &path).unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let smf = Smf::parse(&content).unwrap();
+        let has_controller = smf.tracks[1].iter().any(|e| {
+            matches!(
+                e.kind,
+                TrackEventKind::Midi {
+                    message: MidiMessage::Controller { .. },
+                    ..
+                }
+            )
+        });
+        assert!(!has_controller);
+    }
+
+    #[test]
+    fn test_tempo_changes_written() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("ritardando.mid");
+
+        let mut seq = NoteSequence::new(vec![Note::new(60, 4.0, 80, 0.0)], 0, 120);
+        seq.tempo_changes = vec![
+            crate::midi::sequence::TempoChange {
+                offset: 3.0,
+                bpm: 80,
+            },
+            crate::midi::sequence::TempoChange {
+                offset: 2.0,
+                bpm: 100,
+            },
+        ];
+        write_midi_single(&seq, &path).unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let smf = Smf::parse(&content).unwrap();
+        let mut tick = 0u32;
+        let tempos: Vec<(u32, u32)> = smf.tracks[0]
+            .iter()
+            .filter_map(|e| {
+                tick += e.delta.as_int();
+                match e.kind {
+                    TrackEventKind::Meta(midly::MetaMessage::Tempo(t)) => Some((tick, t.as_int())),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        assert_eq!(tempos, vec![(0, 500_000), (960, 600_000), (1440, 750_000)]);
+    }
+
+    #[test]
+    fn test_json_tempo_map_written() {
+        let json = r#"{"tempo": 120, "notes": [{"pitch": "C4", "duration": 8, "velocity": 80}],
+            "tempo_changes": [{"offset_beats": 4, "bpm": 140}, {"offset_beats": 0, "bpm": 100}]}"#;
+        let input: crate::midi::sequence::JsonSequenceInput = serde_json::from_str(json).unwrap();
+        let bytes = midi_bytes(&input.to_sequences().unwrap()).unwrap();
+        let smf = Smf::parse(&bytes).unwrap();
+
+        let mut tick = 0u32;
+        let tempos: Vec<(u32, u32)> = smf.tracks[0]
+            .iter()
+            .filter_map(|e| {
+                tick += e.delta.as_int();
+                match e.kind {
+                    TrackEventKind::Meta(midly::MetaMessage::Tempo(t)) => Some((tick, t.as_int())),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        // The change at beat 0 becomes the starting tempo
+        assert_eq!(tempos, vec![(0, 600_000), (1920, 428_571)]);
+    }
+
+    #[test]
+    fn test_json_key_signature_written() {
+        let json = r#"{"key": "Eb", "notes": [{"pitch": "Eb4", "duration": 1, "velocity": 80}]}"#;
+        let input: crate::midi::sequence::JsonSequenceInput = serde_json::from_str(json).unwrap();
+        let bytes = midi_bytes(&input.to_sequences().unwrap()).unwrap();
+        let smf = Smf::parse(&bytes).unwrap();
+
+        let keys: Vec<(i8, bool)> = smf.tracks[0]
+            .iter()
+            .filter_map(|e| match e.kind {
+                TrackEventKind::Meta(midly::MetaMessage::KeySignature(sharps, minor)) => {
+                    Some((sharps, minor))
+                }
+                _ => None,
+            })
+            .collect();
+        // E-flat major: three flats
+        assert_eq!(keys, [(-3, false)]);
+        assert_eq!(
+            crate::parse_midi(&bytes).unwrap()[0].key_signature,
+            Some(crate::preset::Key::Eb)
+        );
+    }
+
+    #[test]
+    fn test_time_signature_written() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("waltz.mid");
+
+        let mut seq = NoteSequence::new(vec![Note::new(60, 3.0, 80, 0.0)], 0, 120);
+        seq.time_signature = crate::midi::sequence::TimeSignature::new(3, 4);
+        write_midi_single(&seq, &path).unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let smf = Smf::parse(&content).unwrap();
+        let signature = smf.tracks[0].iter().find_map(|e| match e.kind {
+            TrackEventKind::Meta(midly::MetaMessage::TimeSignature(num, den, _, _)) => {
+                Some((num, den))
+            }
+            _ => None,
+        });
+
+        assert_eq!(signature, Some((3, 2)));
+    }
+
     #[test]
     fn test_round_trip_parse() {
         let temp = tempdir().unwrap();
@@ -0,0 +1,133 @@
+//! Audition rendered audio on the system output device
+//!
+//! Decoding into a [`PlaybackBuffer`] is always available so it can be tested
+//! headless; opening the device needs the optional `playback` feature (rodio).
+
+use crate::audio::{AudioError, read_wav};
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Errors that can occur when playing audio
+#[derive(Debug, Error)]
+pub enum PlaybackError {
+    #[error("Audio error: {0}")]
+    Audio(#[from] AudioError),
+
+    #[error("Playback support not compiled in (rebuild with --features playback)")]
+    NotEnabled,
+
+    #[error("Audio device error: {0}")]
+    Device(String),
+}
+
+/// Interleaved samples ready to stream to an output device
+#[derive(Debug, Clone)]
+pub struct PlaybackBuffer {
+    /// Interleaved samples in the range -1.0..=1.0
+    pub samples: Vec<f32>,
+
+    /// Number of interleaved channels
+    pub channels: u16,
+
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+}
+
+impl PlaybackBuffer {
+    /// Load a rendered WAV file
+    pub fn from_wav(path: &Path) -> Result<Self, PlaybackError> {
+        let data = read_wav(path)?;
+        Ok(Self {
+            samples: data.samples,
+            channels: data.spec.channels.max(1),
+            sample_rate: data.spec.sample_rate,
+        })
+    }
+
+    /// Playing time of the buffer
+    pub fn duration(&self) -> Duration {
+        if self.sample_rate == 0 {
+            return Duration::ZERO;
+        }
+        let frames = self.samples.len() as f64 / self.channels as f64;
+        Duration::from_secs_f64(frames / self.sample_rate as f64)
+    }
+
+    /// Build a rodio source without opening a device
+    #[cfg(feature = "playback")]
+    pub fn to_source(&self) -> rodio::buffer::SamplesBuffer<f32> {
+        rodio::buffer::SamplesBuffer::new(self.channels, self.sample_rate, self.samples.clone())
+    }
+}
+
+/// Play a buffer on the default output device, blocking until it finishes
+#[cfg(feature = "playback")]
+pub fn play(buffer: &PlaybackBuffer) -> Result<(), PlaybackError> {
+    let (_stream, handle) =
+        rodio::OutputStream::try_default().map_err(|e| PlaybackError::Device(e.to_string()))?;
+    let sink = rodio::Sink::try_new(&handle).map_err(|e| PlaybackError::Device(e.to_string()))?;
+    sink.append(buffer.to_source());
+    sink.sleep_until_end();
+    Ok(())
+}
+
+/// Play a buffer on the default output device (unavailable in this build)
+#[cfg(not(feature = "playback"))]
+pub fn play(_buffer: &PlaybackBuffer) -> Result<(), PlaybackError> {
+    Err(PlaybackError::NotEnabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{WavData, write_wav};
+    use hound::{SampleFormat, WavSpec};
+    use tempfile::tempdir;
+
+    fn stereo_wav(path: &Path, frames: usize) {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 22050,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        write_wav(
+            path,
+            &WavData {
+                spec,
+                samples: vec![0.25; frames * 2],
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_buffer_from_wav() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("audition.wav");
+        stereo_wav(&path, 11025);
+
+        let buffer = PlaybackBuffer::from_wav(&path).unwrap();
+        assert_eq!(buffer.channels, 2);
+        assert_eq!(buffer.sample_rate, 22050);
+        assert_eq!(buffer.samples.len(), 22050);
+        assert!((buffer.duration().as_secs_f64() - 0.5).abs() < 1e-9);
+    }
+
+    #[cfg(feature = "playback")]
+    #[test]
+    fn test_source_constructed_without_device() {
+        use rodio::Source;
+
+        let buffer = PlaybackBuffer {
+            samples: vec![0.0; 4410 * 2],
+            channels: 2,
+            sample_rate: 44100,
+        };
+        let source = buffer.to_source();
+        assert_eq!(source.channels(), 2);
+        assert_eq!(source.sample_rate(), 44100);
+        assert_eq!(source.total_duration().map(|d| d.as_millis()), Some(100));
+    }
+}
@@ -97,10 +97,10 @@ pub fn discover_native_plugins(moods_dir: &Path) -> Vec<NativePluginInfo> {
     if let Ok(entries) = std::fs::read_dir(moods_dir) {
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.extension().map(|e| e == "toml").unwrap_or(false) {
-                if let Some(info) = parse_native_plugin(&path) {
-                    plugins.push(info);
-                }
+            if path.extension().map(|e| e == "toml").unwrap_or(false)
+                && let Some(info) = parse_native_plugin(&path)
+            {
+                plugins.push(info);
             }
         }
     }
@@ -189,6 +189,7 @@ mod tests {
     #[test]
     fn test_add_platform_extension_with_lib_prefix() {
         let path = PathBuf::from("/usr/lib/libmyplugin");
+        #[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
         let resolved = add_platform_extension(&path);
         #[cfg(target_os = "macos")]
         assert!(resolved.to_str().unwrap().ends_with("libmyplugin.dylib"));
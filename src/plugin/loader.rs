@@ -427,6 +427,7 @@ mod tests {
             intensity: 75,
             seed: 42,
             tempo: 140,
+            ..Default::default()
         };
         let plugin = preset_config_to_plugin_config(&preset);
         assert_eq!(plugin.duration_secs, 10.0);
@@ -1,8 +1,13 @@
 //! Ambient mood preset
 //!
 //! Characteristics: Textural, non-rhythmic, drones, evolving, pentatonic
+//!
+//! With a polyrhythm set, the sporadic tones give way to two pulsing tone
+//! layers in that ratio, drifting in and out of phase over the clip.
 
-use super::{create_rng, MoodGenerator, PresetConfig, PresetVariation};
+use super::{
+    Key, MoodGenerator, Polyrhythm, PresetConfig, PresetVariation, create_rng, resolve_ending,
+};
 use crate::midi::{Note, NoteSequence};
 use rand::Rng;
 
@@ -35,7 +40,7 @@ impl MoodGenerator for AmbientPreset {
         let mut sequences = Vec::new();
 
         let effective_tempo = variation.effective_tempo(config.tempo);
-        let beats = config.duration_secs * effective_tempo as f64 / 60.0;
+        let beats = config.beats(effective_tempo);
 
         // Choose instruments
         let drone_inst = variation.pick_instrument(0, DRONE_INSTRUMENTS);
@@ -50,21 +55,73 @@ impl MoodGenerator for AmbientPreset {
         };
 
         // Layer 1: Primary drone (always)
-        sequences.push(generate_drone_layer(config, &variation, beats, effective_tempo, drone_inst, 0, &mut rng));
+        sequences.push(
+            generate_drone_layer(
+                config,
+                &variation,
+                beats,
+                effective_tempo,
+                drone_inst,
+                0,
+                &mut rng,
+            )
+            .named("Drone"),
+        );
 
         // Layer 2: Second drone (high probability)
         if variation.layer_probs[1] > 0.25 {
-            sequences.push(generate_drone_layer(config, &variation, beats, effective_tempo, drone_inst, second_interval, &mut rng));
+            sequences.push(
+                generate_drone_layer(
+                    config,
+                    &variation,
+                    beats,
+                    effective_tempo,
+                    drone_inst,
+                    second_interval,
+                    &mut rng,
+                )
+                .named("Second Drone"),
+            );
         }
 
-        // Layer 3: Sporadic tones (high probability)
-        if variation.layer_probs[2] > 0.2 {
-            sequences.push(generate_sporadic_tones(config, &variation, beats, effective_tempo, bell_inst, &mut rng));
+        // Layer 3: Phasing polyrhythm tones when requested, else sporadic tones (high probability)
+        if let Some(ratio) = config.polyrhythm {
+            let (first, second) = generate_polyrhythm(
+                config,
+                &variation,
+                beats,
+                effective_tempo,
+                bell_inst,
+                ratio,
+                &mut rng,
+            );
+            sequences.push(first.named(format!("Pulse {}", ratio.first)));
+            sequences.push(second.named(format!("Pulse {}", ratio.second)));
+        } else if variation.layer_probs[2] > 0.2 {
+            sequences.push(
+                generate_sporadic_tones(
+                    config,
+                    &variation,
+                    beats,
+                    effective_tempo,
+                    bell_inst,
+                    &mut rng,
+                )
+                .named("Sporadic Tones"),
+            );
         }
 
         // Layer 4: Sub-bass rumble
         if variation.layer_probs[3] > 0.5 {
-            sequences.push(generate_sub_rumble(config, &variation, beats, effective_tempo, &mut rng));
+            sequences.push(
+                generate_sub_rumble(config, &variation, beats, effective_tempo, &mut rng)
+                    .named("Sub-Bass Rumble"),
+            );
+        }
+
+        // Optional ritardando into a tonic chord
+        if config.resolve_ending {
+            resolve_ending(&mut sequences, config, beats);
         }
 
         sequences
@@ -77,6 +134,10 @@ impl MoodGenerator for AmbientPreset {
     fn description(&self) -> &'static str {
         "Atmospheric, textural mood with drones and sparse pentatonic tones"
     }
+
+    fn default_key(&self) -> Key {
+        Key::Em
+    }
 }
 
 /// Generate drone layer with variation
@@ -210,6 +271,63 @@ fn generate_sporadic_tones(
     NoteSequence::new(notes, instrument, tempo)
 }
 
+/// Length of one polyrhythm cycle (where the two layers line up), in beats
+const POLYRHYTHM_CYCLE_BEATS: f64 = 8.0;
+
+/// Generate two pentatonic tone layers pulsing in `ratio`.
+///
+/// The clip is split into whole cycles of roughly [`POLYRHYTHM_CYCLE_BEATS`];
+/// each layer spaces `ratio.first` or `ratio.second` notes evenly per cycle,
+/// so the layers coincide at every cycle start and phase apart in between.
+fn generate_polyrhythm(
+    config: &PresetConfig,
+    variation: &PresetVariation,
+    beats: f64,
+    tempo: u16,
+    instrument: u8,
+    ratio: Polyrhythm,
+    rng: &mut impl Rng,
+) -> (NoteSequence, NoteSequence) {
+    let root = config.key.root();
+    let pentatonic: &[u8] = if config.key.is_minor() {
+        &[0, 3, 5, 7, 10]
+    } else {
+        &[0, 2, 4, 7, 9]
+    };
+    let cycles = (beats / POLYRHYTHM_CYCLE_BEATS).round().max(1.0);
+
+    let mut layer = |pulses: u8, octave: u8| {
+        let count = (cycles * pulses as f64) as usize;
+        let spacing = beats / count as f64;
+        let mut scale_idx = (variation.scale_offset as usize) % pentatonic.len();
+        let notes = (0..count)
+            .map(|i| {
+                let pitch = root + octave + pentatonic[scale_idx];
+                // Wander gently through the scale, a step at a time
+                scale_idx = match rng.gen_range(0..3) {
+                    0 => (scale_idx + 1) % pentatonic.len(),
+                    1 => (scale_idx + pentatonic.len() - 1) % pentatonic.len(),
+                    _ => scale_idx,
+                };
+                let velocity = variation.adjust_velocity(25 + rng.gen_range(0..15));
+                // Let each tone ring into the next, but not past the clip
+                let offset = i as f64 * spacing;
+                Note::new(
+                    pitch,
+                    (spacing * 1.5).min(4.0).min(beats - offset),
+                    velocity,
+                    offset,
+                )
+            })
+            .collect();
+        NoteSequence::new(notes, instrument, tempo)
+    };
+
+    let first = layer(ratio.first, 12);
+    let second = layer(ratio.second, 0);
+    (first, second)
+}
+
 /// Generate sub-bass rumble
 fn generate_sub_rumble(
     config: &PresetConfig,
@@ -246,6 +364,49 @@ mod tests {
         assert!(!sequences.is_empty());
     }
 
+    #[test]
+    fn test_polyrhythm_layers_in_proportion() {
+        let config = PresetConfig {
+            tempo: 60,
+            bars: Some(8),
+            polyrhythm: Some(Polyrhythm {
+                first: 3,
+                second: 2,
+            }),
+            ..Default::default()
+        };
+        let sequences = AmbientPreset.generate(&config);
+        let count = |name: &str| {
+            sequences
+                .iter()
+                .find(|s| s.name.as_deref() == Some(name))
+                .unwrap()
+                .notes
+                .len()
+        };
+
+        // 32 beats is four 8-beat cycles
+        assert_eq!((count("Pulse 3"), count("Pulse 2")), (12, 8));
+        assert!(
+            sequences
+                .iter()
+                .all(|s| s.name.as_deref() != Some("Sporadic Tones"))
+        );
+    }
+
+    #[test]
+    fn test_polyrhythm_parse() {
+        assert_eq!(
+            Polyrhythm::parse("3:2"),
+            Some(Polyrhythm {
+                first: 3,
+                second: 2
+            })
+        );
+        assert_eq!(Polyrhythm::parse("3"), None);
+        assert_eq!(Polyrhythm::parse("0:2"), None);
+    }
+
     #[test]
     fn test_ambient_seeds_vary_across_range() {
         // Test that seeds produce variation across a range (not just adjacent)
@@ -0,0 +1,163 @@
+//! Batch generation across a range of seeds
+//!
+//! Every seed is independent, so sequence generation and MIDI writes run
+//! in parallel with rayon.
+
+use super::{Mood, PresetConfig, generate_mood};
+use crate::midi::writer::{MidiMetadata, MidiWriteError, write_midi_with};
+use rayon::prelude::*;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+/// Parse a seed range such as "1-20" (a single seed "7" is a range of one)
+pub fn parse_seed_range(s: &str) -> Option<RangeInclusive<u64>> {
+    let s = s.trim();
+    let (start, end) = match s.split_once('-') {
+        Some((start, end)) => (start.trim().parse().ok()?, end.trim().parse().ok()?),
+        None => {
+            let seed = s.parse().ok()?;
+            (seed, seed)
+        }
+    };
+
+    if start > end {
+        return None;
+    }
+    Some(start..=end)
+}
+
+/// File path for one seed of a batch: `{dir}/{prefix}_seed{seed}.mid`
+pub fn batch_file_path(dir: &Path, prefix: &str, seed: u64) -> PathBuf {
+    dir.join(format!("{prefix}_seed{seed}.mid"))
+}
+
+/// Output path with `{mood}`, `{seed}`, `{key}` and `{tempo}` filled in from
+/// the preset settings (e.g. `out_{mood}_{seed}.wav`); other text, including
+/// unknown placeholders, is kept as is
+pub fn expand_output_template(output: &Path, mood: &str, config: &PresetConfig) -> PathBuf {
+    let fields = [
+        ("{mood}", mood.to_string()),
+        ("{seed}", config.seed.to_string()),
+        ("{key}", format!("{:?}", config.key)),
+        ("{tempo}", config.tempo.to_string()),
+    ];
+    let template = output.to_string_lossy();
+    let expanded = fields
+        .iter()
+        .fold(template.to_string(), |path, (field, value)| {
+            path.replace(field, value)
+        });
+    PathBuf::from(expanded)
+}
+
+/// Template that adds the seed to a file name: `out.wav` -> `out_{seed}.wav`
+/// (unchanged if it already has a `{seed}`)
+pub fn seed_file_template(output: &Path) -> PathBuf {
+    if output.to_string_lossy().contains("{seed}") {
+        return output.to_path_buf();
+    }
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    match output.extension() {
+        Some(ext) => output.with_file_name(format!("{stem}_{{seed}}.{}", ext.to_string_lossy())),
+        None => output.with_file_name(format!("{stem}_{{seed}}")),
+    }
+}
+
+/// Generate one MIDI file per seed into `dir`
+///
+/// `config.seed` is replaced by each seed in the range; all other settings,
+/// and the copyright notice and markers, are shared. Returns the written
+/// paths in seed order.
+pub fn generate_seed_batch(
+    mood: Mood,
+    config: &PresetConfig,
+    metadata: &MidiMetadata,
+    seeds: RangeInclusive<u64>,
+    dir: &Path,
+    prefix: &str,
+) -> Result<Vec<PathBuf>, MidiWriteError> {
+    std::fs::create_dir_all(dir)?;
+
+    seeds
+        .into_par_iter()
+        .map(|seed| {
+            let seed_config = PresetConfig {
+                seed,
+                ..config.clone()
+            };
+            let sequences = generate_mood(mood, &seed_config);
+            let path = batch_file_path(dir, prefix, seed);
+            write_midi_with(&sequences, metadata, &path)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_seed_range() {
+        assert_eq!(parse_seed_range("1-20"), Some(1..=20));
+        assert_eq!(parse_seed_range(" 5 - 6 "), Some(5..=6));
+        assert_eq!(parse_seed_range("7"), Some(7..=7));
+        assert_eq!(parse_seed_range("20-1"), None);
+        assert_eq!(parse_seed_range("a-b"), None);
+    }
+
+    #[test]
+    fn test_batch_writes_distinct_files() {
+        let temp = tempdir().unwrap();
+        let config = PresetConfig::default();
+
+        let paths = generate_seed_batch(
+            Mood::Jazz,
+            &config,
+            &MidiMetadata::default(),
+            1..=20,
+            temp.path(),
+            "jazz",
+        )
+        .unwrap();
+
+        assert_eq!(paths.len(), 20);
+        assert_eq!(paths[0], temp.path().join("jazz_seed1.mid"));
+        let contents: HashSet<Vec<u8>> = paths.iter().map(|p| std::fs::read(p).unwrap()).collect();
+        assert_eq!(
+            contents.len(),
+            20,
+            "each seed should produce a different file"
+        );
+    }
+
+    #[test]
+    fn test_output_template_expands_fields() {
+        let config = PresetConfig {
+            seed: 42,
+            tempo: 96,
+            ..Default::default()
+        };
+        let path = expand_output_template(
+            Path::new("renders/out_{mood}_{seed}_{key}_{tempo}.wav"),
+            "jazz",
+            &config,
+        );
+        assert_eq!(path, Path::new("renders/out_jazz_42_Am_96.wav"));
+        assert_eq!(
+            expand_output_template(Path::new("{other}.mid"), "jazz", &config),
+            Path::new("{other}.mid")
+        );
+
+        assert_eq!(
+            seed_file_template(Path::new("dir/out.wav")),
+            Path::new("dir/out_{seed}.wav")
+        );
+        assert_eq!(
+            seed_file_template(Path::new("{seed}.wav")),
+            Path::new("{seed}.wav")
+        );
+    }
+}
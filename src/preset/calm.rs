@@ -2,7 +2,7 @@
 //!
 //! Characteristics: Major/modal, slow, sustained pads, gentle arpeggios
 
-use super::{create_rng, MoodGenerator, PresetConfig, PresetVariation};
+use super::{create_rng, resolve_ending, Key, MoodGenerator, PresetConfig, PresetVariation};
 use crate::midi::{Note, NoteSequence};
 use rand::Rng;
 
@@ -42,7 +42,7 @@ impl MoodGenerator for CalmPreset {
         let mut sequences = Vec::new();
 
         let effective_tempo = variation.effective_tempo(config.tempo);
-        let beats = config.duration_secs * effective_tempo as f64 / 60.0;
+        let beats = config.beats(effective_tempo);
 
         // Choose instruments
         let pad_inst = variation.pick_instrument(0, PAD_INSTRUMENTS);
@@ -50,21 +50,30 @@ impl MoodGenerator for CalmPreset {
         let bass_inst = variation.pick_instrument(2, BASS_INSTRUMENTS);
 
         // Layer 1: Sustained pad chord (always)
-        sequences.push(generate_pad_chord(config, &variation, beats, effective_tempo, pad_inst, &mut rng));
+        sequences.push(generate_pad_chord(config, &variation, beats, effective_tempo, pad_inst, &mut rng)
+            .named("Pad Chord"));
 
         // Layer 2: Gentle arpeggio (high probability)
         if variation.layer_probs[1] > 0.2 {
-            sequences.push(generate_arpeggio(config, &variation, beats, effective_tempo, arp_inst, &mut rng));
+            sequences.push(generate_arpeggio(config, &variation, beats, effective_tempo, arp_inst, &mut rng)
+                .named("Arpeggio"));
         }
 
         // Layer 3: Optional bass drone
         if variation.layer_probs[2] > 0.5 {
-            sequences.push(generate_bass_drone(config, &variation, beats, effective_tempo, bass_inst, &mut rng));
+            sequences.push(generate_bass_drone(config, &variation, beats, effective_tempo, bass_inst, &mut rng)
+                .named("Bass Drone"));
         }
 
         // Layer 4: Optional high shimmer
         if variation.layer_probs[3] > 0.6 {
-            sequences.push(generate_high_shimmer(config, &variation, beats, effective_tempo, &mut rng));
+            sequences.push(generate_high_shimmer(config, &variation, beats, effective_tempo, &mut rng)
+                .named("High Shimmer"));
+        }
+
+        // Optional ritardando into a tonic chord
+        if config.resolve_ending {
+            resolve_ending(&mut sequences, config, beats);
         }
 
         sequences
@@ -77,6 +86,10 @@ impl MoodGenerator for CalmPreset {
     fn description(&self) -> &'static str {
         "Peaceful, serene mood with sustained pads and gentle arpeggios"
     }
+
+    fn default_key(&self) -> Key {
+        Key::G
+    }
 }
 
 /// Generate sustained pad chord with variation
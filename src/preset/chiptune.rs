@@ -4,7 +4,7 @@
 //! affecting tempo, key, melody style, bass patterns, percussion, and intensity.
 
 use crate::midi::{Note, NoteSequence};
-use crate::preset::{MoodGenerator, PresetConfig, PresetVariation, create_rng};
+use crate::preset::{Key, MoodGenerator, PresetConfig, PresetVariation, create_rng};
 use rand::Rng;
 
 /// GM program numbers for chip-like sounds
@@ -52,7 +52,7 @@ impl MoodGenerator for ChiptunePreset {
             _ => vec![0, 2, 4, 5, 7, 9, 10],  // Mixolydian
         };
 
-        let beats = config.duration_secs * effective_tempo as f64 / 60.0;
+        let beats = config.beats(effective_tempo);
 
         // INTENSITY varies with seed (base intensity ± 20)
         let intensity_offset: i16 = ((variation.velocity_offset as i16) * 2).clamp(-20, 20);
@@ -71,14 +71,14 @@ impl MoodGenerator for ChiptunePreset {
         // Layer 1: Lead melody (always present)
         sequences.push(generate_lead_melody(
             root, &scale, beats, effective_tempo, base_velocity, lead_instrument, &variation, &mut rng
-        ));
+        ).named("Lead Melody"));
 
         // Layer 2: Bass line (varies with seed - sometimes absent)
         let include_bass = variation.layer_probs[1] > 0.3;
         if include_bass {
             sequences.push(generate_chip_bass(
                 root, beats, effective_tempo, base_velocity.saturating_sub(10), &variation, &mut rng
-            ));
+            ).named("Bass Line"));
         }
 
         // Layer 3: Counter-melody (seed-based probability)
@@ -86,7 +86,7 @@ impl MoodGenerator for ChiptunePreset {
         if effective_intensity > counter_threshold {
             sequences.push(generate_counter_melody(
                 root, &scale, beats, effective_tempo, base_velocity.saturating_sub(20), &variation, &mut rng
-            ));
+            ).named("Counter-Melody"));
         }
 
         // Layer 4: Percussion (seed-based probability - varies dramatically)
@@ -94,7 +94,7 @@ impl MoodGenerator for ChiptunePreset {
         if effective_intensity > drum_threshold && variation.density_factor > 0.7 {
             sequences.push(generate_chip_drums(
                 beats, effective_tempo, base_velocity.saturating_sub(15), &variation, &mut rng
-            ));
+            ).named("Percussion"));
         }
 
         sequences
@@ -107,6 +107,10 @@ impl MoodGenerator for ChiptunePreset {
     fn description(&self) -> &'static str {
         "8-bit video game style with dramatic seed-based variation"
     }
+
+    fn default_key(&self) -> Key {
+        Key::C
+    }
 }
 
 /// Generate lead melody with many seed-varied styles
@@ -300,7 +304,7 @@ fn generate_chip_bass(
     let mut pos = 0;
 
     // BASS INSTRUMENT varies
-    let bass_instrument = if variation.instrument_indices[1] % 2 == 0 {
+    let bass_instrument = if variation.instrument_indices[1].is_multiple_of(2) {
         SYNTH_BASS_1
     } else {
         SYNTH_BASS_2
@@ -469,6 +473,7 @@ mod tests {
             intensity: 70,
             seed: 42,
             tempo: 140,
+            ..Default::default()
         };
 
         let sequences = preset.generate(&config);
@@ -490,6 +495,7 @@ mod tests {
                 intensity: 70,
                 seed: seed1,
                 tempo: 140,
+                ..Default::default()
             };
             let config2 = PresetConfig {
                 duration_secs: 3.0,
@@ -497,6 +503,7 @@ mod tests {
                 intensity: 70,
                 seed: seed2,
                 tempo: 140,
+                ..Default::default()
             };
 
             let seq1 = preset.generate(&config1);
@@ -2,7 +2,7 @@
 //!
 //! Characteristics: Sparse, wide intervals, diminished harmony, ethereal
 
-use super::{create_rng, MoodGenerator, PresetConfig, PresetVariation};
+use super::{create_rng, Key, MoodGenerator, PresetConfig, PresetVariation};
 use crate::midi::{Note, NoteSequence};
 use rand::Rng;
 
@@ -43,7 +43,7 @@ impl MoodGenerator for EeriePreset {
         let mut sequences = Vec::new();
 
         let effective_tempo = variation.effective_tempo(config.tempo);
-        let beats = config.duration_secs * effective_tempo as f64 / 60.0;
+        let beats = config.beats(effective_tempo);
 
         // Choose instruments
         let pad_inst = variation.pick_instrument(0, PAD_INSTRUMENTS);
@@ -51,21 +51,24 @@ impl MoodGenerator for EeriePreset {
         let texture_inst = variation.pick_instrument(2, TEXTURE_INSTRUMENTS);
 
         // Layer 1: Pad (always, but chord type varies)
-        sequences.push(generate_pad(config, &variation, beats, effective_tempo, pad_inst, &mut rng));
+        sequences.push(generate_pad(config, &variation, beats, effective_tempo, pad_inst, &mut rng).named("Pad"));
 
         // Layer 2: Bell tones (high probability)
         if variation.layer_probs[1] > 0.2 {
-            sequences.push(generate_bell_tones(config, &variation, beats, effective_tempo, bell_inst, &mut rng));
+            sequences.push(generate_bell_tones(config, &variation, beats, effective_tempo, bell_inst, &mut rng)
+                .named("Bell Tones"));
         }
 
         // Layer 3: Breath/texture (varies with intensity + variation)
         if variation.layer_probs[2] > (0.6 - config.intensity as f64 / 150.0) {
-            sequences.push(generate_breath_texture(config, &variation, beats, effective_tempo, texture_inst, &mut rng));
+            sequences.push(generate_breath_texture(config, &variation, beats, effective_tempo, texture_inst, &mut rng)
+                .named("Breath Texture"));
         }
 
         // Layer 4: Dissonant stabs (random)
         if variation.layer_probs[3] > 0.6 {
-            sequences.push(generate_stabs(config, &variation, beats, effective_tempo, &mut rng));
+            sequences.push(generate_stabs(config, &variation, beats, effective_tempo, &mut rng)
+                .named("Dissonant Stabs"));
         }
 
         sequences
@@ -78,6 +81,10 @@ impl MoodGenerator for EeriePreset {
     fn description(&self) -> &'static str {
         "Creepy, unsettling mood with sparse tones and diminished harmony"
     }
+
+    fn default_key(&self) -> Key {
+        Key::Dm
+    }
 }
 
 /// Generate pad chord with variation
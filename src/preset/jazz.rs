@@ -2,8 +2,13 @@
 //!
 //! Characteristics: Swing feel, walking bass, piano comping with flourishes,
 //! brushed drums (ride cymbal, soft hi-hat, gentle snare)
+//!
+//! Bass and piano follow a repeating ii–V–I in the chosen key, one chord per
+//! bar: the bass lands on each chord's root on the downbeat and the piano
+//! voices whichever chord is sounding.
 
-use super::{create_rng, MoodGenerator, PresetConfig, PresetVariation};
+use super::{Key, MoodGenerator, PresetConfig, PresetVariation, create_rng};
+use crate::midi::sequence::DRUM_CHANNEL;
 use crate::midi::{Note, NoteSequence};
 use rand::Rng;
 
@@ -42,6 +47,122 @@ enum CompStyle {
     Dense,   // More active comping
 }
 
+/// Seventh-chord qualities used in the progression
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ChordQuality {
+    Major7,
+    Minor7,
+    Dominant7,
+    HalfDiminished,
+}
+
+impl ChordQuality {
+    /// Root, 3rd, 5th and 7th in semitones above the chord root
+    fn tones(self) -> [u8; 4] {
+        match self {
+            ChordQuality::Major7 => [0, 4, 7, 11],
+            ChordQuality::Minor7 => [0, 3, 7, 10],
+            ChordQuality::Dominant7 => [0, 4, 7, 10],
+            ChordQuality::HalfDiminished => [0, 3, 6, 10],
+        }
+    }
+
+    /// Piano voicings in semitones above the chord root (mostly rootless,
+    /// since the bass has the root)
+    fn voicings(self) -> [&'static [i8]; 6] {
+        match self {
+            ChordQuality::Major7 => [
+                &[4, 7, 11, 14],  // maj9 (3rd, 5th, 7th, 9th)
+                &[4, 11, 14],     // maj7 spread
+                &[11, 14, 16],    // maj9 upper
+                &[-1, 4, 7, 11],  // maj7 with 7th below
+                &[4, 7, 11],      // maj7 basic
+                &[7, 11, 14, 18], // maj9#11 upper
+            ],
+            ChordQuality::Minor7 => [
+                &[3, 7, 10, 14],  // m9 (3rd, 5th, 7th, 9th)
+                &[3, 10, 14],     // m7 spread
+                &[10, 14, 17],    // m9 upper
+                &[-2, 3, 7, 10],  // m7 with 7th below
+                &[3, 7, 10],      // m7 basic
+                &[7, 10, 14, 17], // m11 voicing
+            ],
+            ChordQuality::Dominant7 => [
+                &[4, 10, 14],      // 9 (3rd, 7th, 9th)
+                &[-2, 4, 9],       // 13 (7th, 3rd, 13th)
+                &[4, 10, 14, 21],  // 13 spread
+                &[10, 14, 16, 21], // 13 upper
+                &[4, 7, 10],       // 7 basic
+                &[-2, 4, 7, 10],   // 7 with 7th below
+            ],
+            ChordQuality::HalfDiminished => [
+                &[3, 6, 10],      // m7b5 basic
+                &[-2, 3, 6],      // m7b5 with 7th below
+                &[6, 10, 15],     // m7b5 upper
+                &[3, 6, 10, 15],  // m7b5 with doubled 3rd
+                &[-2, 3, 6, 10],  // m7b5 spread
+                &[6, 10, 12, 15], // m7b5 with root on top
+            ],
+        }
+    }
+}
+
+/// A chord in the progression
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Chord {
+    /// Root in semitones above the key's tonic
+    root: u8,
+    quality: ChordQuality,
+}
+
+/// ii–V–I in `key`, one chord per bar, with the I held for a second bar so
+/// the turnaround comes round every four bars
+fn ii_v_i(key: &Key) -> [Chord; 4] {
+    let (ii, v, i) = if key.is_minor() {
+        (
+            ChordQuality::HalfDiminished,
+            ChordQuality::Dominant7,
+            ChordQuality::Minor7,
+        )
+    } else {
+        (
+            ChordQuality::Minor7,
+            ChordQuality::Dominant7,
+            ChordQuality::Major7,
+        )
+    };
+    [
+        Chord {
+            root: 2,
+            quality: ii,
+        },
+        Chord {
+            root: 7,
+            quality: v,
+        },
+        Chord {
+            root: 0,
+            quality: i,
+        },
+        Chord {
+            root: 0,
+            quality: i,
+        },
+    ]
+}
+
+/// The chord sounding at beat `t`
+fn chord_at(progression: &[Chord], bar: f64, t: f64) -> Chord {
+    let index = (t.max(0.0) / bar + 1e-9).floor() as usize;
+    progression[index % progression.len()]
+}
+
+/// Whether beat `t` starts a bar
+fn is_downbeat(bar: f64, t: f64) -> bool {
+    let pos = t.rem_euclid(bar);
+    pos < 1e-9 || bar - pos < 1e-9
+}
+
 impl MoodGenerator for JazzPreset {
     fn generate(&self, config: &PresetConfig) -> Vec<NoteSequence> {
         // Use centralized variation for consistent seed-based differences
@@ -50,7 +171,7 @@ impl MoodGenerator for JazzPreset {
         let mut sequences = Vec::new();
 
         let effective_tempo = variation.effective_tempo(config.tempo);
-        let beats = config.duration_secs * effective_tempo as f64 / 60.0;
+        let beats = config.beats(effective_tempo);
 
         // Choose styles from variation
         let bass_style = match variation.pick_style(0, 4) {
@@ -74,18 +195,21 @@ impl MoodGenerator for JazzPreset {
         // Layer 1: Walking Bass on channel 1 (always included, prominent)
         let mut bass_seq = generate_walking_bass(config, &variation, beats, effective_tempo, bass_inst, bass_style, &mut rng);
         bass_seq.channel = 1; // Separate channel so bass instrument isn't overwritten
-        sequences.push(bass_seq);
+        sequences.push(bass_seq.named("Walking Bass"));
 
         // Layer 2: Piano comping on channel 0 (almost always included)
         if variation.layer_probs[1] > 0.05 {
             let mut piano_seq = generate_piano_comping(config, &variation, beats, effective_tempo, keys_inst, comp_style, &mut rng);
             piano_seq.channel = 0;
-            sequences.push(piano_seq);
+            sequences.push(piano_seq.named("Piano Comping"));
         }
 
         // Layer 3: Brushed drums on channel 9 (GM drum channel)
         if variation.layer_probs[2] > 0.1 {
-            sequences.push(generate_brush_drums(config, &variation, beats, effective_tempo, &mut rng));
+            sequences.push(
+                generate_brush_drums(config, &variation, beats, effective_tempo, &mut rng)
+                    .named("Brushed Drums"),
+            );
         }
 
         sequences
@@ -98,6 +222,10 @@ impl MoodGenerator for JazzPreset {
     fn description(&self) -> &'static str {
         "Nightclub trio style with walking bass, piano comping, and brushed drums"
     }
+
+    fn default_key(&self) -> Key {
+        Key::F
+    }
 }
 
 /// Generate walking bass line - the foundation of jazz trio
@@ -136,18 +264,14 @@ fn generate_walking_bass(
     scale_notes.sort();
     scale_notes.dedup();
 
-    // Chord tones (root, 3rd, 5th, 7th) - these are "strong" notes for downbeats
-    let chord_tones: Vec<u8> = if config.key.is_minor() {
-        vec![bass_root, bass_root + 3, bass_root + 7, bass_root + 10] // m7
-    } else {
-        vec![bass_root, bass_root + 4, bass_root + 7, bass_root + 11] // maj7
-    };
+    let bar = config.beats_per_bar();
+    let progression = ii_v_i(&config.key);
 
     let mut t = 0.0;
     let mut last_pitch = bass_root;
 
     // Determine step size and duration based on style
-    let (step, base_duration) = match style {
+    let (step, base_duration): (f64, f64) = match style {
         BassStyle::Walking => (1.0, 0.95),
         BassStyle::TwoFeel => (2.0, 1.9),
         BassStyle::Syncopated => (1.0, 0.8),
@@ -175,9 +299,18 @@ fn generate_walking_bass(
     };
 
     while t < beats {
-        // For syncopated style, use seed-based rest probability
-        if matches!(style, BassStyle::Syncopated) && variation.should_rest(rng) {
-            t += 0.5;
+        // Chord tones (root, 3rd, 5th, 7th) of the current chord - "strong" notes
+        let chord = chord_at(&progression, bar, t);
+        let chord_root = bass_root + chord.root;
+        let chord_tones = chord.quality.tones().map(|interval| chord_root + interval);
+        let downbeat = is_downbeat(bar, t);
+        // Steps never carry past the next bar line, so every bar gets its root
+        let next_bar = ((t / bar + 1e-9).floor() + 1.0) * bar;
+        let step = step.min(next_bar - t);
+
+        // For syncopated style, use seed-based rest probability (never on a downbeat)
+        if matches!(style, BassStyle::Syncopated) && !downbeat && variation.should_rest(rng) {
+            t = (t + 0.5).min(next_bar);
             phrase_pos += 1;
             continue;
         }
@@ -186,9 +319,17 @@ fn generate_walking_bass(
         let contour_dir = contour[phrase_pos % contour.len()];
 
         // Walking bass: contour-guided motion with occasional leaps
-        let pitch = if t == 0.0 {
-            // Start on root
-            bass_root
+        let pitch = if downbeat {
+            // Land on the chord root at the top of every bar
+            chord_root
+        } else if is_downbeat(bar, t + step) && rng.gen_bool(0.5) {
+            // Chromatic approach into the next chord's root
+            let next_root = bass_root + chord_at(&progression, bar, t + step).root;
+            if rng.gen_bool(0.5) {
+                next_root - 1
+            } else {
+                next_root + 1
+            }
         } else if rng.gen_bool(0.55) {
             // Follow contour direction for stepwise motion
             let direction = match contour_dir {
@@ -206,7 +347,7 @@ fn generate_walking_bass(
             if rng.gen_bool(0.5) {
                 target.saturating_sub(1).max(28)
             } else {
-                target.saturating_add(1).min(bass_root + 12)
+                target.saturating_add(1).min(chord_root + 12)
             }
         };
 
@@ -222,16 +363,19 @@ fn generate_walking_bass(
             .saturating_add(rng.gen_range(0..8))
             .min(127);
 
-        // Swing timing: slightly delay offbeat notes for swing feel
+        // Swing timing: delay offbeat notes for swing feel
         let swing_offset = if (t * 2.0) as i32 % 2 == 1 {
-            rng.gen_range(0.02..0.08) // Swing the offbeats
+            match config.swing {
+                Some(ratio) => ratio - 0.5,        // Offbeat lands at the swing ratio
+                None => rng.gen_range(0.02..0.08), // Swing the offbeats
+            }
         } else {
             rng.gen_range(-0.02..0.02) // Slight humanization on downbeats
         };
         let actual_time = (t + swing_offset).max(0.0);
 
         // Duration with slight variation
-        let duration: f64 = base_duration + rng.gen_range(-0.05..0.05);
+        let duration: f64 = base_duration.min(step * 0.95) + rng.gen_range(-0.05..0.05);
 
         // Occasional grace note slide into the main note (chromatic approach)
         if rng.gen_bool(0.15) && actual_time > 0.1 {
@@ -255,7 +399,7 @@ fn generate_walking_bass(
         if matches!(style, BassStyle::Syncopated) && rng.gen_bool(0.25) {
             let ghost_pitch = chord_tones[rng.gen_range(0..chord_tones.len())];
             let ghost_time = t + 0.5 + rng.gen_range(0.0..0.05); // Slight timing variation
-            if ghost_time < beats {
+            if ghost_time < beats.min(next_bar - 0.1) {
                 notes.push(Note::new(ghost_pitch, 0.2, vel_base - 35, ghost_time));
             }
         }
@@ -278,27 +422,9 @@ fn generate_piano_comping(
 ) -> NoteSequence {
     let root = config.key.root();
     let mut notes = Vec::new();
-
-    // Jazz voicings in comfortable piano range (around middle C)
-    let voicings: Vec<Vec<i8>> = if config.key.is_minor() {
-        vec![
-            vec![3, 7, 10, 14],    // m9 (3rd, 5th, 7th, 9th)
-            vec![3, 10, 14],       // m7 spread
-            vec![10, 14, 17],      // m9 upper
-            vec![-2, 3, 7, 10],    // m7 with 9th below
-            vec![3, 7, 10],        // m7 basic
-            vec![7, 10, 14, 17],   // m11 voicing
-        ]
-    } else {
-        vec![
-            vec![4, 7, 11, 14],    // maj9 (3rd, 5th, 7th, 9th)
-            vec![4, 11, 14],       // maj7 spread
-            vec![11, 14, 16],      // maj9 upper
-            vec![-1, 4, 7, 11],    // maj7 with 7th below
-            vec![4, 7, 11],        // maj7 basic
-            vec![7, 11, 14, 18],   // maj9#11 upper
-        ]
-    };
+    let bar = config.beats_per_bar();
+    let progression = ii_v_i(&config.key);
+    let voicing_count = ChordQuality::Major7.voicings().len();
 
     // Skip probability based on style (less skipping = more comping)
     let skip_prob = match style {
@@ -317,7 +443,7 @@ fn generate_piano_comping(
     // Get contour for voicing selection variation
     let phrase_len = variation.phrase_length as usize;
     let contour = variation.get_contour(phrase_len);
-    let mut voicing_idx = (variation.scale_offset as usize) % voicings.len();
+    let mut voicing_idx = (variation.scale_offset as usize) % voicing_count;
     let mut phrase_pos = 0;
 
     let mut t = 0.0;
@@ -330,9 +456,6 @@ fn generate_piano_comping(
             continue;
         }
 
-        // Choose voicing based on contour-guided index
-        let voicing = &voicings[voicing_idx % voicings.len()];
-
         // Swing feel: slightly late on offbeats
         let swing_offset = if rng.gen_bool(0.4) {
             rng.gen_range(0.1..0.4)
@@ -345,6 +468,17 @@ fn generate_piano_comping(
             break;
         }
 
+        // Voice the chord sounding at this point, choosing the voicing by the
+        // contour-guided index. Chords rooted above a fourth sit an octave
+        // lower so voicings stay around middle C.
+        let chord = chord_at(&progression, bar, chord_time);
+        let chord_root = if chord.root > 5 {
+            root + chord.root - 12
+        } else {
+            root + chord.root
+        };
+        let voicing = chord.quality.voicings()[voicing_idx % voicing_count];
+
         // Varied chord durations (staccato to legato)
         let duration = if rng.gen_bool(0.3) {
             0.2 // Staccato stabs
@@ -360,7 +494,7 @@ fn generate_piano_comping(
         let vel_base = variation.adjust_velocity(vel_base);
 
         for (i, &interval) in voicing.iter().enumerate() {
-            let pitch = ((root as i8 + interval) as u8).clamp(48, 84); // Keep in piano sweet spot
+            let pitch = ((chord_root as i8 + interval) as u8).clamp(48, 84); // Keep in piano sweet spot
             // Top notes slightly louder
             let vel = vel_base.saturating_add(i as u8 * 2).saturating_add(rng.gen_range(0..10));
             notes.push(Note::new(pitch, duration, vel.min(110), chord_time));
@@ -374,8 +508,14 @@ fn generate_piano_comping(
         // Move voicing selection based on contour
         let direction = contour[phrase_pos % contour.len()];
         match direction {
-            1 => voicing_idx = (voicing_idx + 1) % voicings.len(),
-            -1 => voicing_idx = if voicing_idx > 0 { voicing_idx - 1 } else { voicings.len() - 1 },
+            1 => voicing_idx = (voicing_idx + 1) % voicing_count,
+            -1 => {
+                voicing_idx = if voicing_idx > 0 {
+                    voicing_idx - 1
+                } else {
+                    voicing_count - 1
+                }
+            }
             _ => {} // Stay on current voicing
         }
         phrase_pos += 1;
@@ -392,7 +532,7 @@ fn add_piano_flourish(
     notes: &mut Vec<Note>,
     root: u8,
     start_time: f64,
-    key: &super::Key,
+    key: &Key,
     rng: &mut impl Rng,
 ) {
     let intervals = key.scale_intervals();
@@ -423,11 +563,10 @@ fn generate_brush_drums(
     tempo: u16,
     rng: &mut impl Rng,
 ) -> NoteSequence {
-    let _ = config; // May use intensity later
     let mut notes = Vec::new();
 
-    // Swing ratio: 0.67 = classic swing feel
-    let swing_ratio = rng.gen_range(0.62..0.72);
+    // Swing ratio: 0.67 = classic swing feel; loosely varied unless set
+    let swing_ratio = config.swing.unwrap_or_else(|| rng.gen_range(0.62..0.72));
 
     let mut t = 0.0;
 
@@ -480,15 +619,16 @@ fn generate_brush_drums(
     }
 
     // Create drum sequence on channel 9 (GM drums)
-    let mut seq = NoteSequence::new(notes, 0, tempo);
-    seq.channel = 9; // GM drum channel
-    seq
+    NoteSequence::builder()
+        .notes(notes)
+        .tempo(tempo)
+        .channel(DRUM_CHANNEL)
+        .build()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::preset::Key;
 
     #[test]
     fn test_jazz_generates_sequences() {
@@ -678,4 +818,95 @@ mod tests {
             assert!(note.pitch < 72, "Bass notes should be in lower register");
         }
     }
+
+    #[test]
+    fn test_jazz_bass_downbeats_follow_ii_v_i() {
+        for (key, roots) in [(Key::C, [62, 67, 60, 60]), (Key::Am, [71, 64, 69, 69])] {
+            for seed in 1..=10 {
+                let config = PresetConfig {
+                    key,
+                    seed,
+                    bars: Some(8),
+                    ..Default::default()
+                };
+                let bass = &JazzPreset.generate(&config)[0];
+                for bar in 0..8 {
+                    let downbeat = bar as f64 * config.beats_per_bar();
+                    let note = bass
+                        .notes
+                        .iter()
+                        .find(|n| (n.offset - downbeat).abs() < 0.05)
+                        .unwrap_or_else(|| {
+                            panic!("{key:?} seed {seed}: no bass note on bar {bar}")
+                        });
+                    assert_eq!(
+                        note.pitch % 12,
+                        roots[bar % 4] % 12,
+                        "{key:?} seed {seed}, bar {bar}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_jazz_piano_voices_current_chord() {
+        let progression = ii_v_i(&Key::C);
+        let config = PresetConfig {
+            key: Key::C,
+            bars: Some(8),
+            ..Default::default()
+        };
+        for seq in JazzPreset
+            .generate(&config)
+            .iter()
+            .filter(|s| s.name.as_deref() == Some("Piano Comping"))
+        {
+            // Chord notes are at least 0.2 beats; shorter notes are flourishes
+            for note in seq.notes.iter().filter(|n| n.duration >= 0.2) {
+                let chord = chord_at(&progression, 4.0, note.offset);
+                let pitch_class = (note.pitch as i32 - 60 - chord.root as i32).rem_euclid(12);
+                // Voicings add 9ths, 11ths and 13ths to the chord tones
+                let allowed = [0, 2, 3, 4, 5, 6, 7, 9, 10, 11];
+                assert!(
+                    allowed.contains(&pitch_class),
+                    "pitch {} over {chord:?}",
+                    note.pitch
+                );
+            }
+        }
+    }
+
+    /// Positions within the beat of the offbeat ride hits
+    fn ride_offbeats(swing: Option<f64>) -> Vec<f64> {
+        let config = PresetConfig {
+            swing,
+            ..Default::default()
+        };
+        let variation = PresetVariation::from_seed(config.seed);
+        let drums =
+            generate_brush_drums(&config, &variation, 8.0, 120, &mut create_rng(config.seed));
+        drums
+            .notes
+            .iter()
+            .filter(|n| {
+                matches!(n.pitch, DRUM_RIDE_CYMBAL | DRUM_RIDE_BELL) && n.offset.fract() != 0.0
+            })
+            .map(|n| n.offset.fract())
+            .collect()
+    }
+
+    #[test]
+    fn test_swing_ratio_places_ride_offbeats() {
+        let straight = ride_offbeats(Some(0.5));
+        assert!(!straight.is_empty());
+        assert!(straight.iter().all(|&pos| pos == 0.5), "{straight:?}");
+
+        let swung = ride_offbeats(Some(0.7));
+        assert!(!swung.is_empty());
+        assert!(
+            swung.iter().all(|&pos| (pos - 0.7).abs() < 1e-9),
+            "{swung:?}"
+        );
+    }
 }
@@ -0,0 +1,178 @@
+//! Markov-chain melodies over scale degrees
+//!
+//! Each note is chosen from the one before it: the chain mostly moves by
+//! step, sometimes repeats, and occasionally leaps, preferring chord tones
+//! when it does. Pitches come from the key's scale, between a fourth below
+//! the root and an octave and a fourth above it, and the melody ends on the
+//! tonic.
+
+use super::{Key, create_rng};
+use crate::midi::Note;
+use crate::midi::sequence::NoteSequence;
+use rand::Rng;
+
+/// Transition weights by movement in scale steps
+const STEP_WEIGHTS: &[(i32, u32)] = &[
+    (-4, 1),
+    (-3, 2),
+    (-2, 6),
+    (-1, 20),
+    (0, 5),
+    (1, 20),
+    (2, 6),
+    (3, 2),
+    (4, 1),
+];
+
+/// Lowest and highest reachable degree (0 = root at the key's octave)
+const DEGREE_RANGE: (i32, i32) = (-3, 10);
+
+/// Note lengths in beats and their weights
+const DURATIONS: &[(f64, u32)] = &[(0.5, 3), (1.0, 6), (2.0, 1)];
+
+/// Pick from `(item, weight)` pairs
+fn weighted<T: Copy>(rng: &mut impl Rng, choices: &[(T, u32)]) -> T {
+    let mut roll = rng.gen_range(0..choices.iter().map(|&(_, w)| w).sum::<u32>());
+    for &(item, weight) in choices {
+        if roll < weight {
+            return item;
+        }
+        roll -= weight;
+    }
+    choices[choices.len() - 1].0
+}
+
+/// Next scale degree after `degree`
+///
+/// Leaps of a third or more are twice as likely to land on a tonic-triad
+/// degree, so wide intervals outline the harmony.
+fn next_degree(rng: &mut impl Rng, degree: i32) -> i32 {
+    let choices: Vec<(i32, u32)> = STEP_WEIGHTS
+        .iter()
+        .map(|&(step, weight)| (degree + step, weight))
+        .filter(|&(next, _)| (DEGREE_RANGE.0..=DEGREE_RANGE.1).contains(&next))
+        .map(|(next, weight)| {
+            let chord_tone = matches!(next.rem_euclid(7), 0 | 2 | 4);
+            let leap = (next - degree).abs() >= 2;
+            (
+                next,
+                if leap && chord_tone {
+                    weight * 2
+                } else {
+                    weight
+                },
+            )
+        })
+        .collect();
+    weighted(rng, &choices)
+}
+
+/// MIDI pitch of a scale degree (0 = the key's root, 7 = an octave up)
+pub fn degree_pitch(key: Key, degree: i32) -> u8 {
+    let intervals = key.scale_intervals();
+    let octave = degree.div_euclid(7);
+    let interval = intervals[degree.rem_euclid(7) as usize] as i32;
+    (key.root() as i32 + 12 * octave + interval).clamp(0, 127) as u8
+}
+
+/// Build a `length`-note melody in `key`, reproducible for a given seed
+///
+/// Notes are back to back, starting on the root; the last note is the
+/// tonic, held for two beats.
+pub fn markov_melody(
+    key: Key,
+    length: usize,
+    instrument: u8,
+    tempo: u16,
+    seed: u64,
+) -> NoteSequence {
+    let mut rng = create_rng(seed);
+    let mut degree = 0;
+    let mut offset = 0.0;
+    let mut notes = Vec::with_capacity(length);
+
+    for i in 0..length {
+        if i > 0 {
+            degree = next_degree(&mut rng, degree);
+        }
+        let last = i + 1 == length;
+        if last && length > 1 {
+            // Resolve to the nearest tonic
+            degree = (degree as f64 / 7.0).round() as i32 * 7;
+        }
+        let duration = if last {
+            2.0
+        } else {
+            weighted(&mut rng, DURATIONS)
+        };
+        let velocity = rng.gen_range(70..=90);
+        notes.push(Note::new(
+            degree_pitch(key, degree),
+            duration,
+            velocity,
+            offset,
+        ));
+        offset += duration;
+    }
+
+    NoteSequence::new(notes, instrument, tempo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_melody() {
+        let a = markov_melody(Key::C, 16, 0, 120, 7);
+        let b = markov_melody(Key::C, 16, 0, 120, 7);
+        let c = markov_melody(Key::C, 16, 0, 120, 8);
+        let pitches = |seq: &NoteSequence| {
+            seq.notes
+                .iter()
+                .map(|n| (n.pitch, n.duration))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(pitches(&a), pitches(&b));
+        assert_ne!(pitches(&a), pitches(&c));
+        assert_eq!(a.notes.len(), 16);
+    }
+
+    #[test]
+    fn test_pitches_stay_in_key_and_end_on_tonic() {
+        for key in [Key::C, Key::Am, Key::Eb] {
+            let root_class = key.root() % 12;
+            for seed in 1..20 {
+                let melody = markov_melody(key, 32, 0, 120, seed);
+                for note in &melody.notes {
+                    assert!(
+                        key.scale_intervals()
+                            .contains(&((note.pitch + 12 - root_class) % 12)),
+                        "{key:?} {}",
+                        note.pitch
+                    );
+                }
+                assert_eq!(melody.notes.last().unwrap().pitch % 12, root_class);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mostly_stepwise() {
+        let melody = markov_melody(Key::C, 200, 0, 120, 3);
+        let leaps = melody
+            .notes
+            .windows(2)
+            .filter(|w| w[0].pitch.abs_diff(w[1].pitch) > 4)
+            .count();
+        assert!(leaps < 50, "{leaps} leaps");
+    }
+
+    #[test]
+    fn test_degree_pitch() {
+        assert_eq!(degree_pitch(Key::Am, 0), 69);
+        assert_eq!(degree_pitch(Key::Am, 2), 72);
+        assert_eq!(degree_pitch(Key::C, -1), 59);
+        assert_eq!(degree_pitch(Key::C, 7), 72);
+    }
+}
@@ -4,33 +4,67 @@
 //! suitable for video intro/outro stingers.
 
 mod ambient;
+mod batch;
 mod calm;
 mod chiptune;
 mod eerie;
 mod jazz;
+mod markov;
 mod orchestral;
+mod progression;
+mod registry;
 mod show;
+mod structure;
 mod suspense;
 mod upbeat;
 
 pub use ambient::AmbientPreset;
+pub use batch::{
+    batch_file_path, expand_output_template, generate_seed_batch, parse_seed_range,
+    seed_file_template,
+};
 pub use calm::CalmPreset;
 pub use chiptune::ChiptunePreset;
 pub use eerie::EeriePreset;
 pub use jazz::JazzPreset;
+pub use markov::{degree_pitch, markov_melody};
 pub use orchestral::OrchestralPreset;
+pub use progression::{chord_pitches, progression_sequence};
+pub use registry::MoodRegistry;
 pub use show::ShowPreset;
+pub use structure::{Section, SongStructure, generate_structure};
 pub use suspense::SuspensePreset;
 pub use upbeat::UpbeatPreset;
 
-use crate::midi::sequence::NoteSequence;
+use crate::midi::Note;
+use crate::midi::sequence::{DRUM_CHANNEL, NoteSequence, TempoChange, TimeSignature};
 use rand::Rng;
 use rand::SeedableRng;
 use rand::rngs::StdRng;
+use thiserror::Error;
 
 /// Centralized variation parameters generated from seed
 /// This ensures different seeds produce noticeably different outputs
-#[derive(Debug, Clone)]
+///
+/// The built-in presets draw all their seed-dependent choices from here, and
+/// custom [`MoodGenerator`]s can do the same: every field is a pure function
+/// of the seed, and the methods that take an RNG only consume it, so a
+/// generator that seeds its RNG with [`create_rng`] is fully reproducible.
+///
+/// ```
+/// use midi_cli_rs::preset::{create_rng, PresetVariation};
+///
+/// let variation = PresetVariation::from_seed(42);
+/// let mut rng = create_rng(42);
+/// let mut degree = variation.scale_offset as i32;
+/// for direction in variation.get_contour(variation.phrase_length as usize) {
+///     if !variation.should_rest(&mut rng) {
+///         degree += direction as i32 * variation.get_interval(&mut rng) as i32;
+///     }
+/// }
+/// # let _ = degree;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
 pub struct PresetVariation {
     /// Tempo multiplier (0.85 to 1.15 = ±15%)
     pub tempo_factor: f64,
@@ -175,6 +209,10 @@ impl PresetVariation {
 
     /// Get melodic contour for a phrase (sequence of up/down/same movements)
     /// Returns an array of direction changes: 1=up, 0=same, -1=down
+    ///
+    /// One of 16 patterns is chosen by `contour_pattern` and repeated to
+    /// `phrase_len` steps, so the same seed always gives the same contour
+    /// and a longer phrase extends a shorter one.
     pub fn get_contour(&self, phrase_len: usize) -> Vec<i8> {
         // 16 different contour patterns based on contour_pattern
         let patterns: &[&[i8]] = &[
@@ -204,12 +242,15 @@ impl PresetVariation {
         result
     }
 
-    /// Check if this position should be a rest
+    /// Check if this position should be a rest, with probability
+    /// `rest_probability` (draws one value from `rng`)
     pub fn should_rest(&self, rng: &mut impl Rng) -> bool {
         rng.gen_bool(self.rest_probability)
     }
 
-    /// Get interval size based on interval_style
+    /// Get interval size in scale degrees (always positive; combine with a
+    /// contour direction) based on interval_style: 1-2 for stepwise, 1-3 for
+    /// small leaps, 2-5 for large leaps, or a 60/40 mix of 1-2 and 3-5
     pub fn get_interval(&self, rng: &mut impl Rng) -> i8 {
         match self.interval_style {
             0 => rng.gen_range(1..=2),  // stepwise (1-2 scale degrees)
@@ -283,6 +324,28 @@ pub enum Key {
 }
 
 impl Key {
+    /// Every key, major and minor, in chromatic order of the root
+    pub const ALL: [Key; 18] = [
+        Key::C,
+        Key::Cm,
+        Key::D,
+        Key::Dm,
+        Key::Eb,
+        Key::Ebm,
+        Key::E,
+        Key::Em,
+        Key::F,
+        Key::Fm,
+        Key::G,
+        Key::Gm,
+        Key::A,
+        Key::Am,
+        Key::Bb,
+        Key::Bbm,
+        Key::B,
+        Key::Bm,
+    ];
+
     /// Parse key from string (e.g., "Am", "C", "F#m", "Bb", "Eb")
     pub fn parse(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
@@ -308,6 +371,31 @@ impl Key {
         }
     }
 
+    /// Key signature as sharps (positive) or flats (negative)
+    pub fn sharps(&self) -> i8 {
+        match self {
+            Key::C | Key::Am => 0,
+            Key::G | Key::Em => 1,
+            Key::D | Key::Bm => 2,
+            Key::A => 3,
+            Key::E => 4,
+            Key::B => 5,
+            Key::F | Key::Dm => -1,
+            Key::Bb | Key::Gm => -2,
+            Key::Eb | Key::Cm => -3,
+            Key::Fm => -4,
+            Key::Bbm => -5,
+            Key::Ebm => -6,
+        }
+    }
+
+    /// The key with this signature, if it's one of [`Key::ALL`]
+    pub fn from_signature(sharps: i8, minor: bool) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|k| k.sharps() == sharps && k.is_minor() == minor)
+    }
+
     /// Get the root note MIDI number (octave 4)
     pub fn root(&self) -> u8 {
         match self {
@@ -368,6 +456,19 @@ pub enum Mood {
 }
 
 impl Mood {
+    /// Every built-in mood
+    pub const ALL: [Mood; 9] = [
+        Mood::Suspense,
+        Mood::Eerie,
+        Mood::Upbeat,
+        Mood::Calm,
+        Mood::Ambient,
+        Mood::Jazz,
+        Mood::Show,
+        Mood::Orchestral,
+        Mood::Chiptune,
+    ];
+
     /// Parse mood from string
     pub fn parse(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
@@ -386,16 +487,21 @@ impl Mood {
 
     /// Get default key for this mood
     pub fn default_key(&self) -> Key {
+        self.generator().default_key()
+    }
+
+    /// The preset that generates this mood
+    fn generator(self) -> &'static dyn MoodGenerator {
         match self {
-            Mood::Suspense => Key::Am,
-            Mood::Eerie => Key::Dm,
-            Mood::Upbeat => Key::C,
-            Mood::Calm => Key::G,
-            Mood::Ambient => Key::Em,
-            Mood::Jazz => Key::F,        // Common jazz key
-            Mood::Show => Key::Bb,       // Broadway standard key
-            Mood::Orchestral => Key::C,  // Classical orchestral key
-            Mood::Chiptune => Key::C,    // Classic game music key
+            Mood::Suspense => &SuspensePreset,
+            Mood::Eerie => &EeriePreset,
+            Mood::Upbeat => &UpbeatPreset,
+            Mood::Calm => &CalmPreset,
+            Mood::Ambient => &AmbientPreset,
+            Mood::Jazz => &JazzPreset,
+            Mood::Show => &ShowPreset,
+            Mood::Orchestral => &OrchestralPreset,
+            Mood::Chiptune => &ChiptunePreset,
         }
     }
 }
@@ -413,6 +519,19 @@ pub struct PresetConfig {
     pub seed: u64,
     /// Tempo in BPM
     pub tempo: u16,
+    /// Slow the final bar and land on a tonic chord
+    pub resolve_ending: bool,
+    /// Length in bars; overrides `duration_secs` when set
+    pub bars: Option<u32>,
+    /// Time signature written to the MIDI file
+    pub time_signature: TimeSignature,
+    /// Intensity that changes over the clip; overrides `intensity` when set
+    pub intensity_curve: Option<IntensityCurve>,
+    /// Swing ratio for moods with a swing feel (jazz): 0.5 is straight,
+    /// 0.75 is hard swing. `None` keeps the mood's own feel.
+    pub swing: Option<f64>,
+    /// Two phasing tone layers in this ratio (ambient)
+    pub polyrhythm: Option<Polyrhythm>,
 }
 
 impl Default for PresetConfig {
@@ -423,6 +542,142 @@ impl Default for PresetConfig {
             intensity: 50,
             seed: 42,
             tempo: 90,
+            resolve_ending: false,
+            bars: None,
+            time_signature: TimeSignature::default(),
+            intensity_curve: None,
+            swing: None,
+            polyrhythm: None,
+        }
+    }
+}
+
+/// Intensity moving linearly from `start` to `end` over a preset's length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntensityCurve {
+    /// Intensity at the start (0-100)
+    pub start: u8,
+    /// Intensity at the end (0-100)
+    pub end: u8,
+}
+
+impl IntensityCurve {
+    /// Parse "START-END", e.g. "20-90" (values 0-100)
+    pub fn parse(s: &str) -> Option<Self> {
+        let (start, end) = s.trim().split_once('-')?;
+        let start: u8 = start.trim().parse().ok()?;
+        let end: u8 = end.trim().parse().ok()?;
+        (start <= 100 && end <= 100).then_some(Self { start, end })
+    }
+
+    /// Intensity at a position from 0.0 (start) to 1.0 (end)
+    pub fn at(&self, position: f64) -> f64 {
+        let position = position.clamp(0.0, 1.0);
+        self.start as f64 + (self.end as f64 - self.start as f64) * position
+    }
+
+    /// The highest intensity on the curve
+    pub fn peak(&self) -> u8 {
+        self.start.max(self.end)
+    }
+}
+
+/// Two pulses sounding against each other, e.g. 3:2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Polyrhythm {
+    /// Notes per cycle in the first layer
+    pub first: u8,
+    /// Notes per cycle in the second layer
+    pub second: u8,
+}
+
+impl Polyrhythm {
+    /// Parse "A:B", e.g. "3:2" (each 1-16)
+    pub fn parse(s: &str) -> Option<Self> {
+        let (first, second) = s.trim().split_once(':')?;
+        let first: u8 = first.trim().parse().ok()?;
+        let second: u8 = second.trim().parse().ok()?;
+        let valid = |n: u8| (1..=16).contains(&n);
+        (valid(first) && valid(second)).then_some(Self { first, second })
+    }
+}
+
+/// Velocity scale for an intensity level: 40% at 0, full at 100
+fn intensity_velocity_scale(intensity: f64) -> f64 {
+    0.4 + 0.6 * intensity / 100.0
+}
+
+/// Scale note velocities along an intensity curve spanning `beats`.
+///
+/// Layers are generated at the curve's peak intensity, so notes at the
+/// peak keep their velocity and notes elsewhere are scaled down in
+/// proportion to the curve.
+pub fn apply_intensity_curve(sequences: &mut [NoteSequence], curve: IntensityCurve, beats: f64) {
+    if beats <= 0.0 {
+        return;
+    }
+    let peak = intensity_velocity_scale(curve.peak() as f64);
+    for seq in sequences {
+        for note in &mut seq.notes {
+            let scale = intensity_velocity_scale(curve.at(note.offset / beats)) / peak;
+            note.velocity = (note.velocity as f64 * scale).round().clamp(1.0, 127.0) as u8;
+        }
+    }
+}
+
+/// Tempo range accepted by [`PresetConfig::validate`], in BPM
+pub const PRESET_TEMPO_RANGE: std::ops::RangeInclusive<u16> = 20..=300;
+
+/// A [`PresetConfig`] field out of range
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PresetConfigError {
+    #[error("Duration must be greater than 0 seconds, got {0}")]
+    Duration(f64),
+
+    #[error("Length must be at least 1 bar")]
+    Bars,
+
+    #[error("Tempo must be 20-300 BPM, got {0}")]
+    Tempo(u16),
+
+    #[error("Intensity must be 0-100, got {0}")]
+    Intensity(u8),
+}
+
+impl PresetConfig {
+    /// Check that the length, tempo and intensity are in range
+    ///
+    /// The duration only matters (and is only checked) when `bars` is unset.
+    pub fn validate(&self) -> Result<(), PresetConfigError> {
+        match self.bars {
+            Some(0) => return Err(PresetConfigError::Bars),
+            None if !(self.duration_secs > 0.0 && self.duration_secs.is_finite()) => {
+                return Err(PresetConfigError::Duration(self.duration_secs));
+            }
+            _ => {}
+        }
+        if !PRESET_TEMPO_RANGE.contains(&self.tempo) {
+            return Err(PresetConfigError::Tempo(self.tempo));
+        }
+        if self.intensity > 100 {
+            return Err(PresetConfigError::Intensity(self.intensity));
+        }
+        Ok(())
+    }
+
+    /// Bar length in quarter-note beats
+    pub fn beats_per_bar(&self) -> f64 {
+        self.time_signature.beats_per_bar()
+    }
+
+    /// Length in beats at the given tempo
+    ///
+    /// Bars map directly to beats; otherwise the duration in seconds is
+    /// converted using the tempo.
+    pub fn beats(&self, tempo: u16) -> f64 {
+        match self.bars {
+            Some(bars) => bars as f64 * self.beats_per_bar(),
+            None => self.duration_secs * tempo as f64 / 60.0,
         }
     }
 }
@@ -437,21 +692,213 @@ pub trait MoodGenerator {
 
     /// Get a description of this mood
     fn description(&self) -> &'static str;
+
+    /// Key used when the caller doesn't pick one
+    fn default_key(&self) -> Key {
+        Key::C
+    }
 }
 
 /// Generate sequences for a given mood
+///
+/// The config is not checked; call [`PresetConfig::validate`] first for
+/// user-supplied settings.
 pub fn generate_mood(mood: Mood, config: &PresetConfig) -> Vec<NoteSequence> {
-    match mood {
-        Mood::Suspense => SuspensePreset.generate(config),
-        Mood::Eerie => EeriePreset.generate(config),
-        Mood::Upbeat => UpbeatPreset.generate(config),
-        Mood::Calm => CalmPreset.generate(config),
-        Mood::Ambient => AmbientPreset.generate(config),
-        Mood::Jazz => JazzPreset.generate(config),
-        Mood::Show => ShowPreset.generate(config),
-        Mood::Orchestral => OrchestralPreset.generate(config),
-        Mood::Chiptune => ChiptunePreset.generate(config),
+    generate_with(mood.generator(), config)
+}
+
+/// A built-in mood generates with its preset, so the default registry can
+/// hold the moods themselves
+impl MoodGenerator for Mood {
+    fn generate(&self, config: &PresetConfig) -> Vec<NoteSequence> {
+        self.generator().generate(config)
+    }
+
+    fn name(&self) -> &'static str {
+        self.generator().name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.generator().description()
+    }
+
+    fn default_key(&self) -> Key {
+        self.generator().default_key()
+    }
+}
+
+/// Run a generator and apply the config's time signature and intensity curve
+fn generate_with(generator: &dyn MoodGenerator, config: &PresetConfig) -> Vec<NoteSequence> {
+    // With a curve, generate every layer the peak needs and shape velocity after
+    let peak_config;
+    let config = match config.intensity_curve {
+        Some(curve) => {
+            peak_config = PresetConfig {
+                intensity: curve.peak(),
+                ..config.clone()
+            };
+            &peak_config
+        }
+        None => config,
+    };
+
+    let mut sequences = generator.generate(config);
+
+    for seq in &mut sequences {
+        seq.time_signature = config.time_signature;
+    }
+    if let (Some(curve), Some(first)) = (config.intensity_curve, sequences.first()) {
+        let beats = config.beats(first.tempo);
+        apply_intensity_curve(&mut sequences, curve, beats);
+    }
+    sequences
+}
+
+/// Beats the final tonic chord is held
+const RESOLUTION_CHORD_BEATS: f64 = 2.0;
+
+/// Add a ritardando and tonic chord ending to generated sequences
+///
+/// The last bar of the config's time signature slows down one beat at a
+/// time, from 90% to 60% of the tempo, and the final two beats are replaced
+/// by a tonic chord in the config's key: the first melodic layer plays the
+/// full triad, the other melodic layers play the root in their own register.
+/// Drum tracks (channel 9) are only cut off at the chord.
+pub fn resolve_ending(sequences: &mut [NoteSequence], config: &PresetConfig, beats: f64) {
+    if beats <= 0.0 {
+        return;
+    }
+
+    let key = config.key;
+    let bar_beats = config.beats_per_bar();
+    let ritardando_steps = bar_beats.ceil().max(1.0) as usize;
+    let ritardando_start = (beats - bar_beats).max(0.0);
+    let chord_start = (beats - RESOLUTION_CHORD_BEATS).max(0.0);
+    let chord_duration = beats - chord_start;
+    let root_class = key.root() % 12;
+    let third = if key.is_minor() { 3 } else { 4 };
+    let mut full_chord_placed = false;
+
+    for seq in sequences.iter_mut() {
+        // Tempo ramp: 90% down to 60% of the sequence tempo (90, 80, 70, 60
+        // in 4/4)
+        let slowdown = 0.3 / (ritardando_steps - 1).max(1) as f64;
+        seq.tempo_changes = (0..ritardando_steps)
+            .map(|step| (ritardando_start + step as f64, step))
+            .filter(|(offset, _)| *offset < beats)
+            .map(|(offset, step)| TempoChange {
+                offset,
+                bpm: ((seq.tempo as f64 * (0.9 - slowdown * step as f64)).round() as u16).max(1),
+            })
+            .collect();
+
+        if seq.notes.is_empty() {
+            continue;
+        }
+
+        let avg_pitch =
+            seq.notes.iter().map(|n| n.pitch as u32).sum::<u32>() / seq.notes.len() as u32;
+        let avg_velocity =
+            seq.notes.iter().map(|n| n.velocity as u32).sum::<u32>() / seq.notes.len() as u32;
+
+        // Clear the way for the final chord
+        seq.notes.retain(|n| n.offset < chord_start);
+        for note in &mut seq.notes {
+            if note.offset + note.duration > chord_start {
+                note.duration = chord_start - note.offset;
+            }
+        }
+
+        if seq.channel == DRUM_CHANNEL {
+            continue;
+        }
+
+        // Root at or just below the layer's average register
+        let avg_pitch = avg_pitch as u8;
+        let root = avg_pitch.saturating_sub((avg_pitch + 12 - root_class) % 12);
+        let intervals: &[u8] = if full_chord_placed {
+            &[0]
+        } else {
+            &[0, third, 7]
+        };
+        full_chord_placed = true;
+
+        for &interval in intervals {
+            seq.notes.push(Note::new(
+                (root + interval).min(127),
+                chord_duration,
+                avg_velocity as u8,
+                chord_start,
+            ));
+        }
+    }
+}
+
+/// Length of each note in a preset lead-in phrase, in beats
+const PICKUP_NOTE_BEATS: f64 = 0.5;
+
+/// Add a lead-in phrase of `pickup_beats` before the first bar
+///
+/// Everything moves later by a bar's worth of rest plus the pickup (see
+/// [`TimeSignature::pickup_rest`]), so the original start becomes the
+/// downbeat of a full bar. The first melodic layer then climbs up the scale
+/// in eighth notes to its first note. Returns the beats added at the start.
+pub fn add_pickup(sequences: &mut [NoteSequence], key: Key, pickup_beats: f64) -> f64 {
+    let Some(first) = sequences.first() else {
+        return 0.0;
+    };
+    let rest = first.time_signature.pickup_rest(pickup_beats);
+    let lead_in = rest + pickup_beats;
+    for seq in sequences.iter_mut() {
+        seq.shift(lead_in);
+    }
+
+    let melodic = sequences
+        .iter_mut()
+        .filter(|seq| seq.channel != DRUM_CHANNEL)
+        .find(|seq| !seq.notes.is_empty());
+    let Some(seq) = melodic else {
+        return lead_in;
+    };
+
+    // Highest note sounding at the start
+    let start = seq
+        .notes
+        .iter()
+        .map(|n| n.offset)
+        .fold(f64::INFINITY, f64::min);
+    let Some(target) = seq
+        .notes
+        .iter()
+        .filter(|n| n.offset - start < 1e-6)
+        .max_by_key(|n| n.pitch)
+        .cloned()
+    else {
+        return lead_in;
+    };
+
+    let count = ((pickup_beats / PICKUP_NOTE_BEATS).round() as usize).max(1);
+    let step = pickup_beats / count as f64;
+    let root_class = key.root() % 12;
+    let steps_below = (0..target.pitch)
+        .rev()
+        .filter(|pitch| {
+            key.scale_intervals()
+                .contains(&((pitch + 12 - root_class) % 12))
+        })
+        .take(count);
+    let velocity = (target.velocity as u32 * 4 / 5).max(1) as u8;
+
+    for (i, pitch) in steps_below.enumerate() {
+        seq.notes.push(Note::new(
+            pitch,
+            step,
+            velocity,
+            start - (i + 1) as f64 * step,
+        ));
     }
+    seq.notes.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+    lead_in
 }
 
 /// Create a seeded RNG for reproducible generation
@@ -478,6 +925,16 @@ mod tests {
         assert_eq!(Key::Am.root(), 69);
     }
 
+    #[test]
+    fn test_key_signature_round_trip() {
+        assert_eq!(Key::Eb.sharps(), -3);
+        assert_eq!(Key::Bm.sharps(), 2);
+        for key in Key::ALL {
+            assert_eq!(Key::from_signature(key.sharps(), key.is_minor()), Some(key));
+        }
+        assert_eq!(Key::from_signature(6, false), None);
+    }
+
     #[test]
     fn test_key_is_minor() {
         assert!(Key::Am.is_minor());
@@ -498,22 +955,14 @@ mod tests {
     fn test_mood_default_key() {
         assert_eq!(Mood::Suspense.default_key(), Key::Am);
         assert_eq!(Mood::Upbeat.default_key(), Key::C);
+        assert_eq!(Mood::Jazz.default_key(), Key::F);
+        assert_eq!(Mood::Show.default_key(), Key::Bb);
     }
 
     #[test]
     fn test_generate_mood_produces_sequences() {
         let config = PresetConfig::default();
-        for mood in [
-            Mood::Suspense,
-            Mood::Eerie,
-            Mood::Upbeat,
-            Mood::Calm,
-            Mood::Ambient,
-            Mood::Jazz,
-            Mood::Show,
-            Mood::Orchestral,
-            Mood::Chiptune,
-        ] {
+        for mood in Mood::ALL {
             let sequences = generate_mood(mood, &config);
             assert!(
                 !sequences.is_empty(),
@@ -553,6 +1002,120 @@ mod tests {
         assert_eq!(var1.style_choices, var2.style_choices);
     }
 
+    #[test]
+    fn test_validate_each_field() {
+        let valid = PresetConfig::default();
+        assert_eq!(valid.validate(), Ok(()));
+
+        let check = |config: PresetConfig| config.validate().unwrap_err();
+        assert_eq!(
+            check(PresetConfig {
+                duration_secs: 0.0,
+                ..valid.clone()
+            }),
+            PresetConfigError::Duration(0.0)
+        );
+        assert_eq!(
+            check(PresetConfig {
+                duration_secs: -2.0,
+                ..valid.clone()
+            }),
+            PresetConfigError::Duration(-2.0)
+        );
+        assert!(matches!(
+            check(PresetConfig {
+                duration_secs: f64::NAN,
+                ..valid.clone()
+            }),
+            PresetConfigError::Duration(_)
+        ));
+        assert_eq!(
+            check(PresetConfig {
+                bars: Some(0),
+                ..valid.clone()
+            }),
+            PresetConfigError::Bars
+        );
+        assert_eq!(
+            check(PresetConfig {
+                tempo: 0,
+                ..valid.clone()
+            }),
+            PresetConfigError::Tempo(0)
+        );
+        assert_eq!(
+            check(PresetConfig {
+                tempo: 301,
+                ..valid.clone()
+            }),
+            PresetConfigError::Tempo(301)
+        );
+        assert_eq!(
+            check(PresetConfig {
+                intensity: 101,
+                ..valid.clone()
+            }),
+            PresetConfigError::Intensity(101)
+        );
+
+        // Bars replace the duration, so it isn't checked
+        assert_eq!(
+            PresetConfig {
+                bars: Some(4),
+                duration_secs: 0.0,
+                ..valid
+            }
+            .validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_contour_reproducible_and_extends() {
+        let variation = PresetVariation::from_seed(7);
+        assert_eq!(
+            variation.get_contour(12),
+            PresetVariation::from_seed(7).get_contour(12)
+        );
+        assert_eq!(variation.get_contour(12)[..5], variation.get_contour(5)[..]);
+        for seed in 0..64 {
+            let contour = PresetVariation::from_seed(seed).get_contour(9);
+            assert_eq!(contour.len(), 9);
+            assert!(contour.iter().all(|d| (-1..=1).contains(d)));
+        }
+    }
+
+    #[test]
+    fn test_rests_and_intervals_reproducible() {
+        let variation = PresetVariation::from_seed(11);
+        assert!((0.0..=0.35).contains(&variation.rest_probability));
+        assert!((3..=8).contains(&variation.phrase_length));
+        assert!(variation.scale_offset < 7 && variation.phrase_transform < 5);
+
+        let draw = |seed| {
+            let mut rng = create_rng(seed);
+            (0..2000)
+                .map(|_| {
+                    (
+                        variation.should_rest(&mut rng),
+                        variation.get_interval(&mut rng),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let draws = draw(3);
+        assert_eq!(draws, draw(3));
+
+        // The rest rate tracks rest_probability
+        let rests = draws.iter().filter(|(rest, _)| *rest).count() as f64 / draws.len() as f64;
+        assert!((rests - variation.rest_probability).abs() < 0.05, "{rests}");
+        assert!(
+            draws
+                .iter()
+                .all(|&(_, interval)| (1..=5).contains(&interval))
+        );
+    }
+
     #[test]
     fn test_variation_differs_by_seed() {
         let var42 = PresetVariation::from_seed(42);
@@ -593,4 +1156,195 @@ mod tests {
         let picked = var.pick_instrument(0, &instruments);
         assert!(instruments.contains(&picked));
     }
+
+    #[test]
+    fn test_pickup_climbs_to_first_downbeat() {
+        let melody = NoteSequence::new(vec![Note::new(69, 2.0, 100, 0.0)], 0, 90);
+        let mut drums = NoteSequence::new(vec![Note::new(36, 1.0, 90, 0.0)], 0, 90);
+        drums.channel = DRUM_CHANNEL;
+        let mut sequences = vec![melody, drums];
+
+        let lead_in = add_pickup(&mut sequences, Key::Am, 1.0);
+        assert_eq!(lead_in, 4.0);
+
+        let melody: Vec<(u8, f64)> = sequences[0]
+            .notes
+            .iter()
+            .map(|n| (n.pitch, n.offset))
+            .collect();
+        assert_eq!(melody, [(65, 3.0), (67, 3.5), (69, 4.0)]);
+        assert_eq!(sequences[1].notes.len(), 1);
+        assert_eq!(sequences[1].notes[0].offset, 4.0);
+    }
+
+    #[test]
+    fn test_resolve_ending_lands_on_tonic_chord() {
+        for mood in [Mood::Calm, Mood::Ambient] {
+            let config = PresetConfig {
+                key: Key::C,
+                duration_secs: 8.0,
+                resolve_ending: true,
+                ..Default::default()
+            };
+            let sequences = generate_mood(mood, &config);
+            let end = sequences
+                .iter()
+                .map(|s| s.duration_beats())
+                .fold(0.0, f64::max);
+
+            // Every pitch class of the C major triad sounds in the last two beats
+            let final_classes: Vec<u8> = sequences
+                .iter()
+                .flat_map(|s| s.notes.iter())
+                .filter(|n| n.offset >= end - RESOLUTION_CHORD_BEATS - 0.001)
+                .map(|n| n.pitch % 12)
+                .collect();
+            for class in [0, 4, 7] {
+                assert!(
+                    final_classes.contains(&class),
+                    "{:?} ending missing pitch class {}",
+                    mood,
+                    class
+                );
+            }
+            assert!(final_classes.iter().all(|c| [0, 4, 7].contains(c)));
+        }
+    }
+
+    #[test]
+    fn test_resolve_ending_tempo_decreases() {
+        let config = PresetConfig {
+            resolve_ending: true,
+            ..Default::default()
+        };
+        let sequences = generate_mood(Mood::Calm, &config);
+        let seq = &sequences[0];
+
+        assert_eq!(seq.tempo_changes.len(), 4);
+        assert!(seq.tempo_changes[0].bpm < seq.tempo);
+        for pair in seq.tempo_changes.windows(2) {
+            assert!(pair[1].offset > pair[0].offset);
+            assert!(pair[1].bpm < pair[0].bpm);
+        }
+    }
+
+    #[test]
+    fn test_resolve_ending_spans_final_bar() {
+        let config = PresetConfig {
+            time_signature: TimeSignature::new(3, 4),
+            bars: Some(4),
+            resolve_ending: true,
+            ..Default::default()
+        };
+        let sequences = generate_mood(Mood::Calm, &config);
+        let seq = &sequences[0];
+
+        let offsets: Vec<f64> = seq.tempo_changes.iter().map(|c| c.offset).collect();
+        assert_eq!(offsets, [9.0, 10.0, 11.0]);
+        assert_eq!(
+            seq.tempo_changes[2].bpm,
+            (seq.tempo as f64 * 0.6).round() as u16
+        );
+    }
+
+    #[test]
+    fn test_bars_length() {
+        let config = PresetConfig {
+            bars: Some(4),
+            tempo: 120,
+            ..Default::default()
+        };
+        assert_eq!(config.beats(120), 16.0);
+
+        // Generated material spans the requested bars, ~8 seconds at 120 BPM
+        let sequences = generate_mood(Mood::Calm, &config);
+        let seq = &sequences[0];
+        let seconds = seq.beats_to_seconds(config.beats(seq.tempo));
+        let nominal = config.beats(120) * 60.0 / 120.0;
+        assert_eq!(nominal, 8.0);
+        assert!(seq.duration_beats() <= 16.0 + 0.001);
+        assert!((seconds - 8.0).abs() < 8.0 * 0.2, "got {seconds}s");
+    }
+
+    #[test]
+    fn test_time_signature_applied() {
+        let config = PresetConfig {
+            time_signature: TimeSignature::new(3, 4),
+            bars: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(config.beats(90), 6.0);
+
+        let sequences = generate_mood(Mood::Upbeat, &config);
+        assert!(sequences.iter().all(|s| s.time_signature.numerator == 3));
+    }
+
+    #[test]
+    fn test_resolve_ending_off_by_default() {
+        let sequences = generate_mood(Mood::Calm, &PresetConfig::default());
+        assert!(sequences.iter().all(|s| s.tempo_changes.is_empty()));
+    }
+
+    #[test]
+    fn test_intensity_curve_parse() {
+        assert_eq!(
+            IntensityCurve::parse("20-90"),
+            Some(IntensityCurve { start: 20, end: 90 })
+        );
+        assert_eq!(
+            IntensityCurve::parse("80 - 10"),
+            Some(IntensityCurve { start: 80, end: 10 })
+        );
+        assert_eq!(IntensityCurve::parse("20-120"), None);
+        assert_eq!(IntensityCurve::parse("50"), None);
+    }
+
+    #[test]
+    fn test_rising_intensity_curve_builds_velocity() {
+        let config = PresetConfig {
+            bars: Some(8),
+            intensity_curve: Some(IntensityCurve {
+                start: 10,
+                end: 100,
+            }),
+            ..Default::default()
+        };
+        let sequences = generate_mood(Mood::Upbeat, &config);
+        let half = config.beats(90) / 2.0;
+
+        let mean = |first_half: bool| {
+            let velocities: Vec<f64> = sequences
+                .iter()
+                .flat_map(|s| &s.notes)
+                .filter(|n| (n.offset < half) == first_half)
+                .map(|n| n.velocity as f64)
+                .collect();
+            velocities.iter().sum::<f64>() / velocities.len() as f64
+        };
+        assert!(
+            mean(false) > mean(true) + 10.0,
+            "early {} late {}",
+            mean(true),
+            mean(false)
+        );
+    }
+
+    #[test]
+    fn test_flat_curve_at_peak_matches_static_intensity() {
+        let config = PresetConfig {
+            intensity: 70,
+            ..Default::default()
+        };
+        let curved = PresetConfig {
+            intensity_curve: Some(IntensityCurve { start: 70, end: 70 }),
+            ..config.clone()
+        };
+        let velocities = |c: &PresetConfig| -> Vec<u8> {
+            generate_mood(Mood::Jazz, c)
+                .iter()
+                .flat_map(|s| s.notes.iter().map(|n| n.velocity))
+                .collect()
+        };
+        assert_eq!(velocities(&config), velocities(&curved));
+    }
 }
@@ -8,7 +8,7 @@
 //! - Harp for color
 
 use crate::midi::{Note, NoteSequence};
-use crate::preset::{MoodGenerator, PresetConfig, PresetVariation, create_rng};
+use crate::preset::{Key, MoodGenerator, PresetConfig, PresetVariation, create_rng};
 use rand::Rng;
 
 /// GM program numbers for orchestral instruments
@@ -27,37 +27,49 @@ impl MoodGenerator for OrchestralPreset {
         let variation = PresetVariation::from_seed(config.seed);
         let mut sequences = Vec::new();
 
-        let beats = config.duration_secs * (config.tempo as f64 / 60.0);
+        let beats = config.beats(config.tempo);
         let root = config.key.root();
         let scale = config.key.scale_intervals();
         let tempo = config.tempo;
 
         // Layer 0: String section (always present)
-        sequences.push(generate_strings(&mut rng, &variation, beats, root, scale, tempo));
+        sequences.push(
+            generate_strings(&mut rng, &variation, beats, root, scale, tempo).named("Strings"),
+        );
 
         // Layer 1: Bass section (high probability)
         if variation.include_layer(1, config.intensity, 20) {
-            sequences.push(generate_bass(&mut rng, &variation, beats, root, scale, tempo));
+            sequences.push(
+                generate_bass(&mut rng, &variation, beats, root, scale, tempo)
+                    .named("Bass Section"),
+            );
         }
 
         // Layer 2: Woodwinds (intensity dependent)
         if variation.include_layer(2, config.intensity, 35) {
-            sequences.push(generate_woodwinds(&mut rng, &variation, beats, root, scale, tempo));
+            sequences.push(
+                generate_woodwinds(&mut rng, &variation, beats, root, scale, tempo)
+                    .named("Woodwinds"),
+            );
         }
 
         // Layer 3: Brass (moderate probability)
         if variation.include_layer(3, config.intensity, 45) {
-            sequences.push(generate_brass(&mut rng, &variation, beats, root, scale, tempo));
+            sequences.push(
+                generate_brass(&mut rng, &variation, beats, root, scale, tempo).named("Brass"),
+            );
         }
 
         // Layer 4: Harp (adds color)
         if variation.include_layer(4, config.intensity, 55) {
-            sequences.push(generate_harp(&mut rng, &variation, beats, root, scale, tempo));
+            sequences
+                .push(generate_harp(&mut rng, &variation, beats, root, scale, tempo).named("Harp"));
         }
 
         // Layer 5: Timpani
         if variation.include_layer(5, config.intensity, 50) {
-            sequences.push(generate_timpani(&mut rng, &variation, beats, root, tempo));
+            sequences
+                .push(generate_timpani(&mut rng, &variation, beats, root, tempo).named("Timpani"));
         }
 
         sequences
@@ -68,6 +80,10 @@ impl MoodGenerator for OrchestralPreset {
     fn description(&self) -> &'static str {
         "Full symphonic orchestra with strings, woodwinds, brass, and percussion"
     }
+
+    fn default_key(&self) -> Key {
+        Key::C
+    }
 }
 
 fn generate_strings(
@@ -389,7 +405,14 @@ mod tests {
 
     #[test]
     fn test_orchestral_generates_sequences() {
-        let config = PresetConfig { duration_secs: 8.0, key: Key::C, intensity: 50, seed: 42, tempo: 80 };
+        let config = PresetConfig {
+            duration_secs: 8.0,
+            key: Key::C,
+            intensity: 50,
+            seed: 42,
+            tempo: 80,
+            ..Default::default()
+        };
         let sequences = OrchestralPreset.generate(&config);
         assert!(!sequences.is_empty());
     }
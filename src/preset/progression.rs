@@ -0,0 +1,130 @@
+//! Roman-numeral chord progressions
+//!
+//! Numerals name chords by scale degree in a key: uppercase is a major
+//! triad and lowercase a minor one (`I`, `ii`, `V`, `vi`). A numeral may be
+//! preceded by `b` or `#` to alter its root and followed by `°`/`o`
+//! (diminished), `+` (augmented), `7` (dominant or minor seventh) or `maj7`.
+//! Chords are voiced in close position with the root in octave 3.
+
+use super::{Key, degree_pitch};
+use crate::midi::Note;
+use crate::midi::sequence::NoteSequence;
+
+/// Velocity of progression chords, kept under a melody
+const CHORD_VELOCITY: u8 = 70;
+
+const NUMERALS: [&str; 7] = ["vii", "vi", "v", "iv", "iii", "ii", "i"];
+
+/// Pitches of one chord numeral in `key`, or `None` if it isn't one
+pub fn chord_pitches(numeral: &str, key: Key) -> Option<Vec<u8>> {
+    let numeral = numeral.trim();
+    let (alteration, rest) = match numeral.strip_prefix('b') {
+        Some(rest) => (-1, rest),
+        None => numeral
+            .strip_prefix('#')
+            .map_or((0, numeral), |rest| (1, rest)),
+    };
+
+    // Longest numeral first, so "vi" isn't read as "v"
+    let (index, name) = NUMERALS
+        .iter()
+        .enumerate()
+        .find(|(_, name)| rest.to_lowercase().starts_with(**name))?;
+    let degree = 7 - index as i32;
+    let (written, suffix) = rest.split_at(name.len());
+    let major = if written.chars().all(|c| c.is_ascii_uppercase()) {
+        true
+    } else if written.chars().all(|c| c.is_ascii_lowercase()) {
+        false
+    } else {
+        return None;
+    };
+
+    let (triad, seventh): ([u8; 3], Option<u8>) = match suffix {
+        "" => (if major { [0, 4, 7] } else { [0, 3, 7] }, None),
+        "7" => (if major { [0, 4, 7] } else { [0, 3, 7] }, Some(10)),
+        "maj7" => (if major { [0, 4, 7] } else { [0, 3, 7] }, Some(11)),
+        "°" | "o" | "dim" => ([0, 3, 6], None),
+        "°7" | "o7" | "dim7" => ([0, 3, 6], Some(9)),
+        "+" | "aug" => ([0, 4, 8], None),
+        _ => return None,
+    };
+
+    let root = degree_pitch(key, degree - 1) as i32 - 12 + alteration;
+    let pitches = triad
+        .into_iter()
+        .chain(seventh)
+        .map(|interval| (root + interval as i32) as u8)
+        .collect();
+    Some(pitches)
+}
+
+/// One chord per numeral in `progression` ("i-iv-v-i", "I V vi IV" or
+/// "ii7,V7,Imaj7"), each held for `beats_per_chord` beats
+pub fn progression_sequence(
+    progression: &str,
+    key: Key,
+    beats_per_chord: f64,
+    instrument: u8,
+    tempo: u16,
+) -> Result<NoteSequence, String> {
+    let numerals: Vec<&str> = progression
+        .split(['-', ',', ' '])
+        .filter(|s| !s.trim().is_empty())
+        .collect();
+    if numerals.is_empty() {
+        return Err("Empty chord progression".to_string());
+    }
+
+    let mut notes = Vec::new();
+    for (i, numeral) in numerals.iter().enumerate() {
+        let pitches = chord_pitches(numeral, key).ok_or_else(|| {
+            format!("Unknown chord numeral: {numeral}. Expected I-VII or i-vii, optionally with b/# and °, +, 7 or maj7")
+        })?;
+        let offset = i as f64 * beats_per_chord;
+        notes.extend(
+            pitches
+                .into_iter()
+                .map(|pitch| Note::new(pitch, beats_per_chord, CHORD_VELOCITY, offset)),
+        );
+    }
+    Ok(NoteSequence::new(notes, instrument, tempo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minor_tonic_and_dominant_seventh() {
+        // A3 C4 E4
+        assert_eq!(chord_pitches("i", Key::Am), Some(vec![57, 60, 64]));
+        // E4 G#4 B4 D5
+        assert_eq!(chord_pitches("V7", Key::Am), Some(vec![64, 68, 71, 74]));
+    }
+
+    #[test]
+    fn test_qualities_and_alterations() {
+        assert_eq!(chord_pitches("IV", Key::C), Some(vec![53, 57, 60]));
+        assert_eq!(chord_pitches("vi", Key::C), Some(vec![57, 60, 64]));
+        assert_eq!(chord_pitches("vii°", Key::C), Some(vec![59, 62, 65]));
+        assert_eq!(chord_pitches("Imaj7", Key::C), Some(vec![48, 52, 55, 59]));
+        assert_eq!(chord_pitches("bVII", Key::C), Some(vec![58, 62, 65]));
+        assert_eq!(chord_pitches("Vi", Key::C), None);
+        assert_eq!(chord_pitches("VIII", Key::C), None);
+        assert_eq!(chord_pitches("x", Key::C), None);
+    }
+
+    #[test]
+    fn test_progression_sequence_timing() {
+        let seq = progression_sequence("i-iv-v-i", Key::Am, 8.0, 0, 90).unwrap();
+        assert_eq!(seq.notes.len(), 12);
+        assert_eq!(seq.duration_beats(), 32.0);
+        assert_eq!(seq.notes[3].offset, 8.0);
+        assert!(
+            progression_sequence("i-q", Key::Am, 4.0, 0, 90)
+                .unwrap_err()
+                .contains("q")
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Mood registry
+//!
+//! Maps mood names to generators so moods can be added without touching the
+//! [`Mood`] enum. The default registry holds the built-in presets, which also
+//! answer to their aliases ("happy" for upbeat, "spooky" for eerie, ...);
+//! registered moods are looked up by name, case-insensitively.
+
+use super::{Mood, MoodGenerator, PresetConfig, generate_with};
+use crate::midi::NoteSequence;
+
+/// Named mood generators, in registration order
+pub struct MoodRegistry {
+    generators: Vec<Box<dyn MoodGenerator>>,
+}
+
+impl MoodRegistry {
+    /// A registry with no moods
+    pub fn empty() -> Self {
+        Self {
+            generators: Vec::new(),
+        }
+    }
+
+    /// Add a generator under its [`MoodGenerator::name`], replacing any mood
+    /// already registered with that name
+    pub fn register(&mut self, generator: Box<dyn MoodGenerator>) -> &mut Self {
+        match self.position(generator.name()) {
+            Some(i) => self.generators[i] = generator,
+            None => self.generators.push(generator),
+        }
+        self
+    }
+
+    /// Look up a mood by name or built-in alias
+    pub fn get(&self, name: &str) -> Option<&dyn MoodGenerator> {
+        let index = self.position(name).or_else(|| {
+            let canonical = format!("{:?}", Mood::parse(name)?);
+            self.position(&canonical)
+        })?;
+        Some(self.generators[index].as_ref())
+    }
+
+    /// Generate a mood by name, applying the config's time signature and
+    /// intensity curve as [`generate_mood`](super::generate_mood) does
+    pub fn generate(&self, name: &str, config: &PresetConfig) -> Option<Vec<NoteSequence>> {
+        Some(generate_with(self.get(name)?, config))
+    }
+
+    /// Registered generators, in registration order
+    pub fn iter(&self) -> impl Iterator<Item = &dyn MoodGenerator> {
+        self.generators.iter().map(|g| g.as_ref())
+    }
+
+    /// Registered mood names, in registration order
+    pub fn names(&self) -> Vec<&'static str> {
+        self.iter().map(|g| g.name()).collect()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.generators
+            .iter()
+            .position(|g| g.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl Default for MoodRegistry {
+    /// The built-in presets
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        for mood in Mood::ALL {
+            registry.register(Box::new(mood));
+        }
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::Note;
+    use crate::preset::{CalmPreset, Key, generate_mood};
+
+    /// One note on the key's root per beat
+    struct Pulse;
+
+    impl MoodGenerator for Pulse {
+        fn generate(&self, config: &PresetConfig) -> Vec<NoteSequence> {
+            let beats = config.beats(config.tempo) as usize;
+            let notes = (0..beats)
+                .map(|i| Note::new(config.key.root(), 1.0, 80, i as f64))
+                .collect();
+            vec![NoteSequence::new(notes, 0, config.tempo)]
+        }
+
+        fn name(&self) -> &'static str {
+            "pulse"
+        }
+
+        fn description(&self) -> &'static str {
+            "A steady pulse on the root"
+        }
+
+        fn default_key(&self) -> Key {
+            Key::D
+        }
+    }
+
+    #[test]
+    fn test_custom_generator_by_name() {
+        let mut registry = MoodRegistry::default();
+        assert!(registry.get("pulse").is_none());
+        registry.register(Box::new(Pulse));
+
+        let config = PresetConfig {
+            duration_secs: 4.0,
+            tempo: 60,
+            ..Default::default()
+        };
+        let sequences = registry.generate("Pulse", &config).unwrap();
+        assert_eq!(sequences.len(), 1);
+        assert_eq!(sequences[0].notes.len(), 4);
+        assert_eq!(sequences[0].time_signature, config.time_signature);
+        assert_eq!(registry.get("pulse").unwrap().default_key(), Key::D);
+        assert_eq!(registry.names().last(), Some(&"pulse"));
+    }
+
+    #[test]
+    fn test_builtins_registered_with_aliases() {
+        let registry = MoodRegistry::default();
+        assert_eq!(registry.names().len(), 9);
+        assert_eq!(registry.get("spooky").unwrap().name(), "eerie");
+        assert_eq!(
+            registry.get("jazz").unwrap().default_key(),
+            Mood::Jazz.default_key()
+        );
+        assert!(MoodRegistry::empty().get("calm").is_none());
+
+        let config = PresetConfig::default();
+        let from_registry = registry.generate("calm", &config).unwrap();
+        let from_enum = generate_mood(Mood::Calm, &config);
+        let layer_sizes =
+            |seqs: &[NoteSequence]| seqs.iter().map(|s| s.notes.len()).collect::<Vec<_>>();
+        assert_eq!(layer_sizes(&from_registry), layer_sizes(&from_enum));
+    }
+
+    #[test]
+    fn test_register_replaces_same_name() {
+        let mut registry = MoodRegistry::empty();
+        registry
+            .register(Box::new(CalmPreset))
+            .register(Box::new(CalmPreset));
+        assert_eq!(registry.names(), ["calm"]);
+    }
+}
@@ -4,7 +4,7 @@
 //! brass fanfares, piano accompaniment, and singable melodies.
 //! Big dynamic builds, clear phrase structures, theatrical flair.
 
-use super::{create_rng, MoodGenerator, PresetConfig, PresetVariation};
+use super::{create_rng, Key, MoodGenerator, PresetConfig, PresetVariation};
 use crate::midi::{Note, NoteSequence};
 use rand::Rng;
 
@@ -64,7 +64,7 @@ impl MoodGenerator for ShowPreset {
         let mut sequences = Vec::new();
 
         let effective_tempo = variation.effective_tempo(config.tempo);
-        let beats = config.duration_secs * effective_tempo as f64 / 60.0;
+        let beats = config.beats(effective_tempo);
 
         // Choose instruments using variation system
         let string_inst = variation.pick_instrument(0, STRING_INSTRUMENTS);
@@ -78,13 +78,13 @@ impl MoodGenerator for ShowPreset {
         // Layer 1: String pad (foundation - always included)
         let mut strings = generate_string_pad(config, &variation, beats, effective_tempo, string_inst, &mut rng);
         strings.channel = 0;
-        sequences.push(strings);
+        sequences.push(strings.named("String Pad"));
 
         // Layer 2: Piano accompaniment (high probability)
         if variation.layer_probs[1] > 0.15 {
             let mut piano = generate_piano_accomp(config, &variation, beats, effective_tempo, piano_inst, pattern_idx, &mut rng);
             piano.channel = 1;
-            sequences.push(piano);
+            sequences.push(piano.named("Piano Accompaniment"));
         }
 
         // Layer 3: Melody line (probability based on intensity)
@@ -92,21 +92,21 @@ impl MoodGenerator for ShowPreset {
         if variation.layer_probs[2] > melody_threshold {
             let mut melody = generate_melody_line(config, &variation, beats, effective_tempo, melody_inst, &mut rng);
             melody.channel = 2;
-            sequences.push(melody);
+            sequences.push(melody.named("Melody"));
         }
 
         // Layer 4: Brass fanfare/accents (probability)
         if variation.layer_probs[3] > 0.4 {
             let mut brass = generate_brass_accents(config, &variation, beats, effective_tempo, brass_inst, &mut rng);
             brass.channel = 3;
-            sequences.push(brass);
+            sequences.push(brass.named("Brass Accents"));
         }
 
         // Layer 5: Percussion/timpani (lower probability)
         if variation.layer_probs[4] > 0.6 {
             let mut perc = generate_theatrical_percussion(config, &variation, beats, effective_tempo, &mut rng);
             perc.channel = 9; // GM drum channel
-            sequences.push(perc);
+            sequences.push(perc.named("Percussion"));
         }
 
         sequences
@@ -119,6 +119,10 @@ impl MoodGenerator for ShowPreset {
     fn description(&self) -> &'static str {
         "Broadway/musical theater style with strings, brass fanfares, and singable melodies"
     }
+
+    fn default_key(&self) -> Key {
+        Key::Bb
+    }
 }
 
 /// Generate lush string pad with swells
@@ -0,0 +1,191 @@
+//! Song structure: several sections in one preset
+//!
+//! A structure such as `intro:2,main:8,outro:2` generates each section as its
+//! own preset (same mood, key, seed and tempo) at an intensity suited to the
+//! section, then stitches the layers end to end with a marker at the start
+//! of each section. An outro resolves to the tonic.
+
+use super::{Mood, PresetConfig, generate_mood, resolve_ending};
+use crate::midi::sequence::{NoteSequence, TempoChange};
+use crate::midi::writer::Marker;
+
+/// One section of a song structure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Section name, used as its marker text
+    pub name: String,
+
+    /// Length in bars
+    pub bars: u32,
+}
+
+impl Section {
+    /// Share of the preset intensity this section is generated at: intros
+    /// and outros are sparser, breaks and bridges a little lighter
+    pub fn intensity_factor(&self) -> f64 {
+        match self.name.to_lowercase().as_str() {
+            "intro" => 0.6,
+            "outro" | "ending" => 0.7,
+            "break" | "bridge" => 0.8,
+            _ => 1.0,
+        }
+    }
+
+    /// Whether this section ends on a ritardando and tonic chord
+    pub fn resolves(&self) -> bool {
+        matches!(self.name.to_lowercase().as_str(), "outro" | "ending")
+    }
+}
+
+/// Sections in playing order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SongStructure {
+    /// Sections, first to last
+    pub sections: Vec<Section>,
+}
+
+impl SongStructure {
+    /// Parse "NAME:BARS,..." such as "intro:2,main:8,outro:2"
+    pub fn parse(s: &str) -> Option<Self> {
+        let sections = s
+            .split(',')
+            .map(|part| {
+                let (name, bars) = part.split_once(':')?;
+                let name = name.trim();
+                let bars: u32 = bars.trim().parse().ok()?;
+                (!name.is_empty() && bars > 0).then(|| Section {
+                    name: name.to_string(),
+                    bars,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { sections })
+    }
+
+    /// Total length in bars
+    pub fn total_bars(&self) -> u32 {
+        self.sections.iter().map(|s| s.bars).sum()
+    }
+}
+
+/// Generate every section and stitch them into one set of layers
+///
+/// Layers are matched across sections by name, channel and instrument; a
+/// layer missing from a section is silent there. Notes still sounding at a
+/// section boundary are cut, except in the last section. Returns the
+/// layers and a marker for each section start.
+pub fn generate_structure(
+    mood: Mood,
+    config: &PresetConfig,
+    structure: &SongStructure,
+) -> (Vec<NoteSequence>, Vec<Marker>) {
+    let bar = config.beats_per_bar();
+    let mut layers: Vec<NoteSequence> = Vec::new();
+    let mut tempo_changes: Vec<TempoChange> = Vec::new();
+    let mut markers = Vec::with_capacity(structure.sections.len());
+    let mut start = 0.0;
+
+    for (i, section) in structure.sections.iter().enumerate() {
+        let last = i + 1 == structure.sections.len();
+        let length = section.bars as f64 * bar;
+        let section_config = PresetConfig {
+            bars: Some(section.bars),
+            intensity: (config.intensity as f64 * section.intensity_factor()).round() as u8,
+            resolve_ending: false,
+            ..config.clone()
+        };
+
+        let mut sequences = generate_mood(mood, &section_config);
+        if section.resolves() {
+            resolve_ending(&mut sequences, &section_config, length);
+        }
+
+        markers.push(Marker {
+            offset: start,
+            text: section.name.clone(),
+        });
+        if let Some(first) = sequences.first() {
+            tempo_changes.extend(first.tempo_changes.iter().map(|c| TempoChange {
+                offset: c.offset + start,
+                bpm: c.bpm,
+            }));
+            if !last && !first.tempo_changes.is_empty() {
+                tempo_changes.push(TempoChange {
+                    offset: start + length,
+                    bpm: first.tempo,
+                });
+            }
+        }
+
+        for mut seq in sequences {
+            if !last {
+                seq.notes.retain(|n| n.offset < length);
+                for note in &mut seq.notes {
+                    note.duration = note.duration.min(length - note.offset);
+                }
+            }
+            seq.shift(start);
+
+            let layer = layers.iter_mut().find(|l| {
+                l.name == seq.name && l.channel == seq.channel && l.instrument == seq.instrument
+            });
+            match layer {
+                Some(layer) => layer.notes.append(&mut seq.notes),
+                None => layers.push(seq),
+            }
+        }
+        start += length;
+    }
+
+    for layer in &mut layers {
+        layer.tempo_changes = tempo_changes.clone();
+    }
+    (layers, markers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preset::Key;
+
+    #[test]
+    fn test_parse_structure() {
+        let structure = SongStructure::parse("intro:2, main:8 ,outro:2").unwrap();
+        let bars: Vec<u32> = structure.sections.iter().map(|s| s.bars).collect();
+        assert_eq!(bars, [2, 8, 2]);
+        assert_eq!(structure.total_bars(), 12);
+        assert!(SongStructure::parse("intro:0").is_none());
+        assert!(SongStructure::parse("intro").is_none());
+        assert!(SongStructure::parse(":4").is_none());
+    }
+
+    #[test]
+    fn test_three_sections_markers_and_length() {
+        let config = PresetConfig {
+            key: Key::C,
+            intensity: 80,
+            seed: 7,
+            tempo: 100,
+            ..Default::default()
+        };
+        let structure = SongStructure::parse("intro:2,main:8,outro:2").unwrap();
+        let (sequences, markers) = generate_structure(Mood::Calm, &config, &structure);
+
+        let offsets: Vec<f64> = markers.iter().map(|m| m.offset).collect();
+        assert_eq!(offsets, [0.0, 8.0, 40.0]);
+        let names: Vec<&str> = markers.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(names, ["intro", "main", "outro"]);
+
+        // The outro's tonic chord ends exactly on the last bar line
+        let length = sequences
+            .iter()
+            .map(|s| s.duration_beats())
+            .fold(0.0, f64::max);
+        assert!((length - 48.0).abs() < 1e-9, "length {length}");
+        assert!(
+            sequences
+                .iter()
+                .all(|s| s.tempo_changes.iter().all(|c| c.offset >= 44.0))
+        );
+    }
+}
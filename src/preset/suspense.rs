@@ -2,7 +2,7 @@
 //!
 //! Characteristics: Minor key, low drones, tremolo strings, dissonance
 
-use super::{create_rng, MoodGenerator, PresetConfig, PresetVariation};
+use super::{create_rng, Key, MoodGenerator, PresetConfig, PresetVariation};
 use crate::midi::{Note, NoteSequence};
 use rand::Rng;
 
@@ -42,7 +42,7 @@ impl MoodGenerator for SuspensePreset {
         let mut sequences = Vec::new();
 
         let effective_tempo = variation.effective_tempo(config.tempo);
-        let beats = config.duration_secs * effective_tempo as f64 / 60.0;
+        let beats = config.beats(effective_tempo);
 
         // Choose instruments using variation system
         let drone_inst = variation.pick_instrument(0, DRONE_INSTRUMENTS);
@@ -50,23 +50,27 @@ impl MoodGenerator for SuspensePreset {
         let hit_inst = variation.pick_instrument(2, HIT_INSTRUMENTS);
 
         // Layer 1: Low drone (always, but style varies)
-        sequences.push(generate_drone(config, &variation, beats, effective_tempo, drone_inst, &mut rng));
+        sequences.push(generate_drone(config, &variation, beats, effective_tempo, drone_inst, &mut rng)
+            .named("Low Drone"));
 
         // Layer 2: High tremolo strings (probability + intensity based)
         let tremolo_threshold = 0.6 - config.intensity as f64 / 200.0;
         if variation.layer_probs[1] > tremolo_threshold {
-            sequences.push(generate_tremolo(config, &variation, beats, effective_tempo, tremolo_inst, &mut rng));
+            sequences.push(generate_tremolo(config, &variation, beats, effective_tempo, tremolo_inst, &mut rng)
+                .named("Tremolo Strings"));
         }
 
         // Layer 3: Sparse hits (probability + intensity based)
         let hit_threshold = 0.7 - config.intensity as f64 / 150.0;
         if variation.layer_probs[2] > hit_threshold {
-            sequences.push(generate_sparse_hits(config, &variation, beats, effective_tempo, hit_inst, &mut rng));
+            sequences.push(generate_sparse_hits(config, &variation, beats, effective_tempo, hit_inst, &mut rng)
+                .named("Sparse Hits"));
         }
 
         // Layer 4: Sub-bass rumble (random chance)
         if variation.layer_probs[3] > 0.6 {
-            sequences.push(generate_sub_bass(config, &variation, beats, effective_tempo, &mut rng));
+            sequences.push(generate_sub_bass(config, &variation, beats, effective_tempo, &mut rng)
+                .named("Sub-Bass Rumble"));
         }
 
         sequences
@@ -79,6 +83,10 @@ impl MoodGenerator for SuspensePreset {
     fn description(&self) -> &'static str {
         "Tense, anxious mood with low drones and tremolo strings"
     }
+
+    fn default_key(&self) -> Key {
+        Key::Am
+    }
 }
 
 /// Generate low sustained drone with variation
@@ -2,7 +2,7 @@
 //!
 //! Characteristics: Major key, rhythmic, energetic, clear pulse
 
-use super::{create_rng, MoodGenerator, PresetConfig, PresetVariation};
+use super::{Key, MoodGenerator, PresetConfig, PresetVariation, create_rng};
 use crate::midi::{Note, NoteSequence};
 use rand::Rng;
 
@@ -46,6 +46,37 @@ const RHYTHM_PATTERNS: &[&[f64]] = &[
     &[0.0, 0.5, 1.0, 2.0, 2.5, 3.0, 3.5],            // Pop pattern
 ];
 
+/// Rhythm patterns (offsets within a 3-beat bar)
+const RHYTHM_PATTERNS_3: &[&[f64]] = &[
+    &[0.0, 1.0, 2.0],           // Waltz
+    &[0.0, 1.0, 1.5, 2.0, 2.5], // Oom-pah-pah with pickups
+    &[0.0, 0.5, 1.5, 2.0, 2.5], // Syncopated
+];
+
+/// Rhythm patterns that fit the bar, with their length in beats: 3-beat
+/// patterns for 3/4 (and 6/8), 4-beat patterns otherwise
+fn rhythm_patterns(beats_per_bar: f64) -> (&'static [&'static [f64]], f64) {
+    if beats_per_bar == 3.0 {
+        (RHYTHM_PATTERNS_3, 3.0)
+    } else {
+        (RHYTHM_PATTERNS, 4.0)
+    }
+}
+
+/// Offsets of a pattern within one bar, repeating or cutting the pattern
+/// when the bar is a different length (e.g. 4-beat patterns in 5/4)
+fn bar_offsets(pattern: &[f64], pattern_len: f64, beats_per_bar: f64) -> Vec<f64> {
+    let repeats = (beats_per_bar / pattern_len).ceil() as usize;
+    (0..repeats)
+        .flat_map(|r| {
+            pattern
+                .iter()
+                .map(move |offset| offset + r as f64 * pattern_len)
+        })
+        .filter(|&offset| offset < beats_per_bar)
+        .collect()
+}
+
 impl MoodGenerator for UpbeatPreset {
     fn generate(&self, config: &PresetConfig) -> Vec<NoteSequence> {
         let variation = PresetVariation::from_seed(config.seed);
@@ -53,33 +84,69 @@ impl MoodGenerator for UpbeatPreset {
         let mut sequences = Vec::new();
 
         let effective_tempo = variation.effective_tempo(config.tempo);
-        let beats = config.duration_secs * effective_tempo as f64 / 60.0;
+        let beats = config.beats(effective_tempo);
 
         // Choose instruments using variation system
         let rhythm_inst = variation.pick_instrument(0, RHYTHM_INSTRUMENTS);
         let bass_inst = variation.pick_instrument(1, BASS_INSTRUMENTS);
         let lead_inst = variation.pick_instrument(2, LEAD_INSTRUMENTS);
 
-        // Choose pattern based on seed
-        let pattern_idx = variation.pick_style(0, RHYTHM_PATTERNS.len());
+        // Choose pattern based on seed, phrased to the time signature
+        let beats_per_bar = config.beats_per_bar();
+        let pattern_idx = variation.pick_style(0, rhythm_patterns(beats_per_bar).0.len());
 
         // Layer 1: Rhythmic chord pattern (always)
-        sequences.push(generate_rhythm_pattern(config, &variation, beats, effective_tempo, rhythm_inst, pattern_idx, &mut rng));
+        sequences.push(
+            generate_rhythm_pattern(
+                config,
+                &variation,
+                beats,
+                beats_per_bar,
+                effective_tempo,
+                rhythm_inst,
+                pattern_idx,
+                &mut rng,
+            )
+            .named("Rhythm Chords"),
+        );
 
         // Layer 2: Bass line (high probability)
         if variation.layer_probs[1] > 0.1 {
-            sequences.push(generate_bass_line(config, &variation, beats, effective_tempo, bass_inst, &mut rng));
+            sequences.push(
+                generate_bass_line(
+                    config,
+                    &variation,
+                    beats,
+                    effective_tempo,
+                    bass_inst,
+                    &mut rng,
+                )
+                .named("Bass Line"),
+            );
         }
 
         // Layer 3: Melody hint (probability + intensity)
         let melody_threshold = 0.7 - (config.intensity as f64 / 150.0);
         if variation.layer_probs[2] > melody_threshold {
-            sequences.push(generate_melody_hint(config, &variation, beats, effective_tempo, lead_inst, &mut rng));
+            sequences.push(
+                generate_melody_hint(
+                    config,
+                    &variation,
+                    beats,
+                    effective_tempo,
+                    lead_inst,
+                    &mut rng,
+                )
+                .named("Melody Hint"),
+            );
         }
 
         // Layer 4: Percussion accent
         if variation.layer_probs[3] > 0.6 {
-            sequences.push(generate_percussion_accent(config, &variation, beats, effective_tempo, &mut rng));
+            sequences.push(
+                generate_percussion_accent(config, &variation, beats, effective_tempo, &mut rng)
+                    .named("Percussion Accent"),
+            );
         }
 
         sequences
@@ -92,13 +159,19 @@ impl MoodGenerator for UpbeatPreset {
     fn description(&self) -> &'static str {
         "Energetic, happy mood with rhythmic patterns and major harmony"
     }
+
+    fn default_key(&self) -> Key {
+        Key::C
+    }
 }
 
-/// Generate rhythmic chord pattern with variation
+/// Generate rhythmic chord pattern with variation, one pattern per bar
+#[allow(clippy::too_many_arguments)]
 fn generate_rhythm_pattern(
     config: &PresetConfig,
     variation: &PresetVariation,
     beats: f64,
+    beats_per_bar: f64,
     tempo: u16,
     instrument: u8,
     pattern_idx: usize,
@@ -107,8 +180,8 @@ fn generate_rhythm_pattern(
     let chord = config.key.chord_tones();
     let mut notes = Vec::new();
 
-    let pattern = RHYTHM_PATTERNS[pattern_idx];
-    let pattern_len = 4.0;
+    let (patterns, pattern_len) = rhythm_patterns(beats_per_bar);
+    let pattern = bar_offsets(patterns[pattern_idx], pattern_len, beats_per_bar);
 
     // Velocity variation style from seed
     let accent_style = variation.pick_style(1, 3);
@@ -153,7 +226,7 @@ fn generate_rhythm_pattern(
                 }
             }
         }
-        t += pattern_len;
+        t += beats_per_bar;
     }
 
     NoteSequence::new(notes, instrument, tempo)
@@ -465,4 +538,62 @@ mod tests {
         let unique: std::collections::HashSet<_> = instruments.iter().collect();
         assert!(unique.len() > 1, "Instruments should vary across seeds");
     }
+    #[test]
+    fn test_bar_offsets_fit_the_bar() {
+        let pop = RHYTHM_PATTERNS[1];
+        assert_eq!(bar_offsets(pop, 4.0, 4.0), pop);
+        assert_eq!(bar_offsets(pop, 4.0, 2.0), [0.0, 1.0]);
+        assert_eq!(bar_offsets(pop, 4.0, 5.0), [0.0, 1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_three_four_phrases_in_three() {
+        use crate::midi::sequence::TimeSignature;
+
+        for seed in 1..=12 {
+            let config = PresetConfig {
+                seed,
+                bars: Some(4),
+                time_signature: TimeSignature::new(3, 4),
+                ..Default::default()
+            };
+            let rhythm = &UpbeatPreset.generate(&config)[0];
+            let onsets = |bar: usize| -> Vec<f64> {
+                let mut offsets: Vec<f64> = rhythm
+                    .notes
+                    .iter()
+                    .map(|n| n.offset - bar as f64 * 3.0)
+                    .filter(|o| (0.0..3.0).contains(o))
+                    .collect();
+                offsets.dedup();
+                offsets
+            };
+
+            // Every bar starts on its downbeat and repeats the same pattern
+            assert_eq!(onsets(0)[0], 0.0);
+            for bar in 1..4 {
+                assert_eq!(onsets(bar), onsets(0), "seed {seed} bar {bar}");
+            }
+
+            // With first-beat accents, the loud hits land on multiples of 3
+            if PresetVariation::from_seed(seed).pick_style(1, 3) == 0 {
+                let loudest = rhythm.notes.iter().map(|n| n.velocity).max().unwrap();
+                let accent = rhythm.notes.iter().find(|n| n.velocity == loudest).unwrap();
+                assert_eq!(accent.offset % 3.0, 0.0, "seed {seed}");
+                let quietest_accent = rhythm
+                    .notes
+                    .iter()
+                    .filter(|n| n.offset % 3.0 == 0.0)
+                    .map(|n| n.velocity)
+                    .min();
+                let loudest_other = rhythm
+                    .notes
+                    .iter()
+                    .filter(|n| n.offset % 3.0 != 0.0)
+                    .map(|n| n.velocity)
+                    .max();
+                assert!(quietest_accent > loudest_other, "seed {seed}");
+            }
+        }
+    }
 }
@@ -0,0 +1,865 @@
+//! Audio rendering with external tools
+//!
+//! MIDI files are rendered to WAV with FluidSynth, optionally trimmed with
+//! ffmpeg (or in Rust when it's missing), post-processed in Rust, and
+//! transcoded to compressed formats.
+//! Layers can be rendered with their own SoundFonts and mixed back together.
+//! Command lines are built by separate functions so they can be inspected
+//! without running the tools.
+
+use crate::audio::{
+    AudioError, FADE_OUT_SECS, mix, mix_gain, normalize_loudness_wav, normalize_wav, read_wav,
+    trim_wav, write_wav,
+};
+use midly::{Format, Header, Smf};
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use thiserror::Error;
+
+/// Errors that can occur when rendering audio
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(
+        "FluidSynth not found. Install with:\n  macOS: brew install fluid-synth\n  Ubuntu: apt install fluidsynth"
+    )]
+    FluidSynthNotFound,
+
+    #[error(
+        "No SoundFont found. Install FluidR3_GM or specify --soundfont.\n  macOS: brew install fluid-synth (includes SoundFont)\n  Ubuntu: apt install fluid-soundfont-gm\n  Or place a .sf2 file in ~/.soundfonts/\n  Or download one with --fetch-soundfont"
+    )]
+    SoundFontNotFound,
+
+    #[error("{var} is set to {path}, which does not exist")]
+    EnvPathNotFound { var: &'static str, path: PathBuf },
+
+    #[error("Invalid MIDI file: {0}")]
+    InvalidMidi(String),
+
+    #[error("No layer {layer} to map a SoundFont to (the file has {layers} layers)")]
+    NoSuchLayer { layer: usize, layers: usize },
+
+    #[error("FluidSynth failed with status: {0}")]
+    FluidSynthFailed(std::process::ExitStatus),
+
+    #[error(
+        "ffmpeg not found (required for {0} output). Install with:\n  macOS: brew install ffmpeg\n  Ubuntu: apt install ffmpeg"
+    )]
+    FfmpegNotFound(String),
+
+    #[error("ffmpeg failed: {0}")]
+    FfmpegFailed(String),
+
+    #[error("Audio processing error: {0}")]
+    Audio(#[from] AudioError),
+}
+
+/// Output format chosen from the output file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Standard MIDI file only
+    Midi,
+    /// MIDI + FluidSynth render
+    Wav,
+    /// WAV transcoded with libmp3lame
+    Mp3,
+    /// WAV transcoded with libvorbis
+    Ogg,
+}
+
+impl OutputFormat {
+    /// Pick the format from a path's extension (anything unknown is MIDI)
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase())
+            .as_deref()
+        {
+            Some("wav") => OutputFormat::Wav,
+            Some("mp3") => OutputFormat::Mp3,
+            Some("ogg") => OutputFormat::Ogg,
+            _ => OutputFormat::Midi,
+        }
+    }
+
+    /// Whether this format needs an audio render
+    pub fn is_audio(&self) -> bool {
+        !matches!(self, OutputFormat::Midi)
+    }
+
+    /// Display name used in messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputFormat::Midi => "MIDI",
+            OutputFormat::Wav => "WAV",
+            OutputFormat::Mp3 => "MP3",
+            OutputFormat::Ogg => "OGG",
+        }
+    }
+
+    /// Where the MIDI file goes for a given output path
+    pub fn midi_path(&self, output: &Path) -> PathBuf {
+        if self.is_audio() {
+            output.with_extension("mid")
+        } else {
+            output.to_path_buf()
+        }
+    }
+
+    /// Where the rendered WAV goes for a given output path
+    pub fn wav_path(&self, output: &Path) -> PathBuf {
+        match self {
+            OutputFormat::Wav => output.to_path_buf(),
+            _ => output.with_extension("wav"),
+        }
+    }
+}
+
+/// Settings for the audio render step
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// SoundFont file (auto-detected if not specified)
+    pub soundfont: Option<PathBuf>,
+    /// SoundFonts for individual layers, numbered from 1 as with stems;
+    /// other layers use `soundfont`
+    pub soundfont_map: BTreeMap<usize, PathBuf>,
+    /// Trim to this many seconds with a fade-out (None = full length)
+    pub target_duration: Option<f64>,
+    /// Peak-normalize to this level in dBFS
+    pub normalize: Option<f64>,
+    /// Normalize integrated loudness to this level in LUFS
+    pub lufs: Option<f64>,
+    /// FluidSynth binary (found automatically if not specified)
+    pub fluidsynth: Option<PathBuf>,
+    /// FluidSynth output gain (default: 1.0)
+    pub gain: Option<f32>,
+    /// Output sample rate in Hz (default: 44100)
+    pub sample_rate: Option<u32>,
+}
+
+/// FluidSynth gain used when none is given
+pub const DEFAULT_GAIN: f32 = 1.0;
+
+/// Sample rate used when none is given
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+/// FluidSynth gains accepted from users (FluidSynth's own limit is 10)
+pub const GAIN_RANGE: RangeInclusive<f32> = 0.0..=10.0;
+
+/// Sample rates FluidSynth can render at
+pub const SAMPLE_RATE_RANGE: RangeInclusive<u32> = 8000..=96000;
+
+impl RenderOptions {
+    /// The FluidSynth gain to render with
+    pub fn gain(&self) -> f32 {
+        self.gain.unwrap_or(DEFAULT_GAIN)
+    }
+
+    /// The sample rate to render at
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE)
+    }
+}
+
+/// Render a MIDI file to the requested audio format
+///
+/// Does nothing for MIDI output. Compressed formats are rendered to a
+/// scratch WAV next to the output first, which is removed after transcoding.
+pub fn render_output(
+    midi_path: &Path,
+    output: &Path,
+    format: OutputFormat,
+    options: &RenderOptions,
+) -> Result<(), RenderError> {
+    if !format.is_audio() {
+        return Ok(());
+    }
+
+    if format == OutputFormat::Wav {
+        return render_wav(midi_path, output, options);
+    }
+
+    let wav_path = temp_path(&format.wav_path(output), "transcode");
+    render_wav(midi_path, &wav_path, options)?;
+
+    let encoded = temp_path(output, "transcode");
+    let result = transcode(&wav_path, &encoded, format)
+        .and_then(|()| Ok(std::fs::rename(&encoded, output)?));
+    let _ = std::fs::remove_file(&wav_path);
+    if result.is_err() {
+        let _ = std::fs::remove_file(&encoded);
+    }
+    result
+}
+
+/// Render a MIDI file to WAV with FluidSynth
+///
+/// With a SoundFont map, each layer is rendered on its own and the results
+/// are mixed (see [`render_layers`]).
+pub fn render_wav(
+    midi_path: &Path,
+    wav_path: &Path,
+    options: &RenderOptions,
+) -> Result<(), RenderError> {
+    // Find FluidSynth
+    let fluidsynth = match &options.fluidsynth {
+        Some(path) => path.clone(),
+        None => find_fluidsynth()?,
+    };
+
+    if !options.soundfont_map.is_empty() {
+        let layers = split_layers(&std::fs::read(midi_path)?)?;
+        if let Some(&layer) = options
+            .soundfont_map
+            .keys()
+            .find(|&&layer| layer == 0 || layer > layers.len())
+        {
+            return Err(RenderError::NoSuchLayer {
+                layer,
+                layers: layers.len(),
+            });
+        }
+        let mut soundfonts = Vec::with_capacity(layers.len());
+        for layer in 1..=layers.len() {
+            let sf = match options
+                .soundfont_map
+                .get(&layer)
+                .or(options.soundfont.as_ref())
+            {
+                Some(sf) => sf.clone(),
+                None => find_soundfont()?,
+            };
+            eprintln!("Using SoundFont for layer {layer}: {}", sf.display());
+            soundfonts.push(sf);
+        }
+        return finish_render(wav_path, options, |render_path| {
+            render_layers(&fluidsynth, &layers, &soundfonts, render_path, options)
+        });
+    }
+
+    // Find SoundFont
+    let sf = match &options.soundfont {
+        Some(sf) => sf.clone(),
+        None => find_soundfont()?,
+    };
+    eprintln!("Using SoundFont: {}", sf.display());
+
+    finish_render(wav_path, options, |render_path| {
+        let status =
+            fluidsynth_command(&fluidsynth, &sf, midi_path, render_path, options).status()?;
+        if !status.success() {
+            return Err(RenderError::FluidSynthFailed(status));
+        }
+        Ok(())
+    })
+}
+
+/// Split a MIDI file into one file per layer
+///
+/// In a multi-track file the first track holds the tempo map, so each layer
+/// is that track plus one of the others, as written by `write_midi`. A
+/// single-track file is one layer.
+pub fn split_layers(bytes: &[u8]) -> Result<Vec<Vec<u8>>, RenderError> {
+    let smf = Smf::parse(bytes).map_err(|e| RenderError::InvalidMidi(e.to_string()))?;
+    if smf.tracks.len() < 2 {
+        return Ok(vec![bytes.to_vec()]);
+    }
+
+    smf.tracks[1..]
+        .iter()
+        .map(|track| {
+            let layer = Smf {
+                header: Header::new(Format::Parallel, smf.header.timing),
+                tracks: vec![smf.tracks[0].clone(), track.clone()],
+            };
+            let mut buffer = Vec::new();
+            layer.write_std(&mut buffer)?;
+            Ok(buffer)
+        })
+        .collect()
+}
+
+/// Render each layer with its SoundFont and mix the stems into `wav_path`
+///
+/// The mix is scaled by [`mix_gain`] so layers can't clip when summed.
+/// Scratch files are removed whether or not rendering succeeds.
+pub fn render_layers(
+    fluidsynth: &Path,
+    layers: &[Vec<u8>],
+    soundfonts: &[PathBuf],
+    wav_path: &Path,
+    options: &RenderOptions,
+) -> Result<(), RenderError> {
+    let mut scratch = Vec::new();
+    let mut render = || {
+        let mut stems = Vec::with_capacity(layers.len());
+        for (i, (layer, soundfont)) in layers.iter().zip(soundfonts).enumerate() {
+            let tag = format!("layer{}", i + 1);
+            let midi_path = temp_path(&wav_path.with_extension("mid"), &tag);
+            let stem_path = temp_path(wav_path, &tag);
+            scratch.extend([midi_path.clone(), stem_path.clone()]);
+
+            std::fs::write(&midi_path, layer)?;
+            let status = fluidsynth_command(fluidsynth, soundfont, &midi_path, &stem_path, options)
+                .status()?;
+            if !status.success() {
+                return Err(RenderError::FluidSynthFailed(status));
+            }
+            stems.push(read_wav(&stem_path)?);
+        }
+        write_wav(wav_path, &mix(&stems, mix_gain(stems.len()))?)?;
+        Ok(())
+    };
+    let result = render();
+    for path in scratch {
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+/// Synthesize into a scratch WAV, trim and normalize it, then rename it over
+/// `wav_path`
+///
+/// Every intermediate file is unique to this call, so concurrent renders of
+/// the same output (parallel server requests) never touch each other's
+/// partial files; each rename installs a complete file.
+fn finish_render(
+    wav_path: &Path,
+    options: &RenderOptions,
+    synthesize: impl FnOnce(&Path) -> Result<(), RenderError>,
+) -> Result<(), RenderError> {
+    let render_path = temp_path(wav_path, "render");
+    if let Err(e) = synthesize(&render_path) {
+        let _ = std::fs::remove_file(&render_path);
+        return Err(e);
+    }
+    let mut finished = render_path;
+
+    // Trim or pad to target duration if specified (removes reverb tail)
+    if let Some(duration) = options.target_duration {
+        let trimmed = temp_path(wav_path, "trim");
+        match trim_command(&finished, &trimmed, duration).output() {
+            Ok(output) if output.status.success() => {
+                let _ = std::fs::remove_file(&finished);
+                finished = trimmed;
+            }
+            result => {
+                // Without a working ffmpeg, trim the untrimmed file in Rust
+                let _ = std::fs::remove_file(&trimmed);
+                if let Ok(output) = result {
+                    eprintln!("Warning: ffmpeg trim failed, trimming without it");
+                    eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+                }
+                if let Err(e) = trim_wav(&finished, duration, FADE_OUT_SECS) {
+                    let _ = std::fs::remove_file(&finished);
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+
+    // Peak-normalize if requested
+    if let Some(target_dbfs) = options.normalize {
+        match normalize_wav(&finished, target_dbfs) {
+            Ok(Some(gain)) => eprintln!(
+                "Normalized peak to {:.1} dBFS (gain {:.2}x)",
+                target_dbfs.min(0.0),
+                gain
+            ),
+            Ok(None) => eprintln!("Warning: audio is near-silent, skipping normalization"),
+            Err(e) => {
+                let _ = std::fs::remove_file(&finished);
+                return Err(e.into());
+            }
+        }
+    }
+
+    // Loudness-normalize if requested
+    if let Some(target_lufs) = options.lufs {
+        match normalize_loudness_wav(&finished, target_lufs) {
+            Ok(Some(gain)) => eprintln!(
+                "Normalized loudness to {:.1} LUFS (gain {:.2}x)",
+                target_lufs, gain
+            ),
+            Ok(None) => eprintln!("Warning: audio is silent, skipping loudness normalization"),
+            Err(e) => {
+                let _ = std::fs::remove_file(&finished);
+                return Err(e.into());
+            }
+        }
+    }
+
+    std::fs::rename(&finished, wav_path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&finished);
+    })?;
+    Ok(())
+}
+
+/// Scratch file in the same directory as `output` (so it can be renamed into
+/// place), unique per process and call: `intro.wav` becomes
+/// `intro.4242-1a2b3c4d.render.wav`
+pub fn temp_path(output: &Path, tag: &str) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = output.extension().unwrap_or_default().to_string_lossy();
+    let unique = format!("{}-{:08x}", std::process::id(), rand::random::<u32>());
+    output.with_file_name(format!("{stem}.{unique}.{tag}.{ext}"))
+}
+
+/// Transcode a WAV file to a compressed format with ffmpeg
+pub fn transcode(wav_path: &Path, output: &Path, format: OutputFormat) -> Result<(), RenderError> {
+    let Some(mut command) = transcode_command(wav_path, output, format) else {
+        return Ok(());
+    };
+
+    let result = command.output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => RenderError::FfmpegNotFound(format.name().to_string()),
+        _ => RenderError::Io(e),
+    })?;
+
+    if !result.status.success() {
+        return Err(RenderError::FfmpegFailed(
+            String::from_utf8_lossy(&result.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// FluidSynth command line for rendering a MIDI file to WAV
+///
+/// Usage: fluidsynth [options] soundfont.sf2 midifile.mid
+/// (-F must come before the soundfont and MIDI file)
+pub fn fluidsynth_command(
+    fluidsynth: &Path,
+    soundfont: &Path,
+    midi_path: &Path,
+    wav_path: &Path,
+    options: &RenderOptions,
+) -> Command {
+    let mut command = Command::new(fluidsynth);
+    command
+        .arg("-ni") // Non-interactive
+        .args(["-g", &format!("{:?}", options.gain())])
+        .args(["-r", &options.sample_rate().to_string()])
+        .arg("-F")
+        .arg(wav_path)
+        .arg(soundfont)
+        .arg(midi_path);
+    command
+}
+
+/// ffmpeg command line for cutting a WAV to exactly `duration` seconds (padding
+/// short input with silence) with a fade-out of [`FADE_OUT_SECS`]
+pub fn trim_command(input: &Path, output: &Path, duration: f64) -> Command {
+    let fade_duration = FADE_OUT_SECS;
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-y") // Overwrite output
+        .arg("-i")
+        .arg(input)
+        .args(["-t", &format!("{:.2}", duration)])
+        .args([
+            "-af",
+            &format!(
+                "apad,afade=t=out:st={:.2}:d={:.2}",
+                duration - fade_duration,
+                fade_duration
+            ),
+        ])
+        .arg(output);
+    command
+}
+
+/// ffmpeg command line for transcoding a WAV (None for MIDI/WAV output)
+pub fn transcode_command(wav_path: &Path, output: &Path, format: OutputFormat) -> Option<Command> {
+    let codec = match format {
+        OutputFormat::Mp3 => ["-c:a", "libmp3lame", "-q:a", "2"],
+        OutputFormat::Ogg => ["-c:a", "libvorbis", "-q:a", "5"],
+        OutputFormat::Midi | OutputFormat::Wav => return None,
+    };
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-y")
+        .arg("-i")
+        .arg(wav_path)
+        .args(codec)
+        .arg(output);
+    Some(command)
+}
+
+/// Whether ffmpeg is available on the PATH (needed for MP3/OGG and trimming)
+pub fn ffmpeg_available() -> bool {
+    Command::new("ffmpeg").arg("-version").output().is_ok()
+}
+
+/// Environment variable naming the FluidSynth binary to use
+pub const FLUIDSYNTH_ENV: &str = "MIDI_CLI_FLUIDSYNTH";
+
+/// Environment variable naming the SoundFont to use when --soundfont is not given
+pub const SOUNDFONT_ENV: &str = "MIDI_CLI_SOUNDFONT";
+
+/// Path from an environment variable's `value`, if set (and non-empty); an
+/// error if it names a file that doesn't exist, so a typo isn't silently
+/// ignored
+fn env_path(
+    var: &'static str,
+    value: Option<std::ffi::OsString>,
+) -> Result<Option<PathBuf>, RenderError> {
+    match value.filter(|value| !value.is_empty()) {
+        Some(value) => {
+            let path = PathBuf::from(value);
+            if path.exists() {
+                Ok(Some(path))
+            } else {
+                Err(RenderError::EnvPathNotFound { var, path })
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+/// Find FluidSynth binary
+///
+/// `MIDI_CLI_FLUIDSYNTH` takes precedence over PATH and common locations.
+pub fn find_fluidsynth() -> Result<PathBuf, RenderError> {
+    if let Some(path) = env_path(FLUIDSYNTH_ENV, std::env::var_os(FLUIDSYNTH_ENV))? {
+        return Ok(path);
+    }
+
+    // Check if fluidsynth is in PATH
+    if Command::new("fluidsynth").arg("--version").output().is_ok() {
+        return Ok(PathBuf::from("fluidsynth"));
+    }
+
+    // Check common locations
+    let paths = [
+        "/opt/homebrew/bin/fluidsynth",
+        "/usr/local/bin/fluidsynth",
+        "/usr/bin/fluidsynth",
+    ];
+
+    for path in paths {
+        let p = PathBuf::from(path);
+        if p.exists() {
+            return Ok(p);
+        }
+    }
+
+    Err(RenderError::FluidSynthNotFound)
+}
+
+/// Find a SoundFont file
+///
+/// `MIDI_CLI_SOUNDFONT` takes precedence over the search paths.
+pub fn find_soundfont() -> Result<PathBuf, RenderError> {
+    if let Some(path) = env_path(SOUNDFONT_ENV, std::env::var_os(SOUNDFONT_ENV))? {
+        return Ok(path);
+    }
+
+    // Check user's home directory first (~/.soundfonts/)
+    if let Some(home) = std::env::var_os("HOME") {
+        let home_path = PathBuf::from(home);
+        let user_soundfonts = [
+            home_path.join(".soundfonts/default.sf2"),
+            home_path.join(".soundfonts/GeneralUser_GS.sf2"),
+            home_path.join(".soundfonts/FluidR3_GM.sf2"),
+        ];
+        for p in user_soundfonts {
+            if p.exists() {
+                return Ok(p);
+            }
+        }
+    }
+
+    // Prioritize MIT-licensed soundfonts for clear commercial use rights
+    let paths = [
+        // Project local (preferred) - MIT licensed
+        "./soundfonts/FluidR3_GM.sf2",
+        "./soundfonts/GeneralUser_GS.sf2",
+        "./soundfonts/MuseScore_General.sf2",
+        "./soundfonts/default.sf2",
+        // macOS Homebrew - FluidR3_GM is MIT licensed
+        "/opt/homebrew/share/sounds/sf2/FluidR3_GM.sf2",
+        "/opt/homebrew/share/soundfonts/default.sf2",
+        "/usr/local/share/soundfonts/default.sf2",
+        // Linux - FluidR3_GM is MIT licensed
+        "/usr/share/sounds/sf2/FluidR3_GM.sf2",
+        "/usr/share/soundfonts/FluidR3_GM.sf2",
+        "/usr/share/soundfonts/default.sf2",
+        "/usr/share/soundfonts/freepats-general-midi.sf2",
+    ];
+
+    for path in paths {
+        let p = PathBuf::from(path);
+        if p.exists() {
+            return Ok(p);
+        }
+    }
+
+    // Fetched with --fetch-soundfont
+    if let Some(path) =
+        crate::soundfont::cache_dir().and_then(|dir| crate::soundfont::cached_soundfont(&dir))
+    {
+        return Ok(path);
+    }
+
+    Err(RenderError::SoundFontNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_split_layers_keeps_tempo_track() {
+        use crate::midi::reader::parse_midi;
+        use crate::midi::writer::midi_bytes;
+        use crate::midi::{Note, NoteSequence};
+
+        let melody = NoteSequence::new(vec![Note::new(72, 1.0, 80, 0.0)], 0, 90);
+        let bass = NoteSequence::new(vec![Note::new(36, 2.0, 70, 0.0)], 32, 90);
+        let layers = split_layers(&midi_bytes(&[melody, bass]).unwrap()).unwrap();
+
+        assert_eq!(layers.len(), 2);
+        let bass_layer = parse_midi(&layers[1]).unwrap();
+        assert_eq!(bass_layer.len(), 1);
+        assert_eq!((bass_layer[0].instrument, bass_layer[0].tempo), (32, 90));
+        assert_eq!(bass_layer[0].notes[0].pitch, 36);
+    }
+
+    #[test]
+    fn test_temp_path_is_unique_sibling() {
+        let output = Path::new("out/intro.wav");
+        let (a, b) = (temp_path(output, "render"), temp_path(output, "render"));
+        assert_ne!(a, b);
+        assert_eq!(a.parent(), output.parent());
+        let name = a.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with(&format!("intro.{}-", std::process::id())));
+        assert!(name.ends_with(".render.wav"));
+    }
+
+    #[test]
+    fn test_concurrent_renders_to_same_output() {
+        use crate::audio::{WavData, read_wav, write_wav};
+        use std::sync::Barrier;
+
+        let temp = tempfile::tempdir().unwrap();
+        let output = temp.path().join("out.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let levels = [0.25f32, 0.5];
+        let barrier = Barrier::new(levels.len());
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = levels
+                .iter()
+                .map(|&level| {
+                    let (output, barrier) = (&output, &barrier);
+                    scope.spawn(move || {
+                        finish_render(output, &RenderOptions::default(), |render_path| {
+                            barrier.wait();
+                            let data = WavData {
+                                spec,
+                                samples: vec![level; 8000],
+                            };
+                            Ok(write_wav(render_path, &data)?)
+                        })
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap().unwrap();
+            }
+        });
+
+        // One render's output, complete and unmixed
+        let samples = read_wav(&output).unwrap().samples;
+        assert_eq!(samples.len(), 8000);
+        assert!(
+            levels
+                .iter()
+                .any(|&level| samples.iter().all(|&s| (s - level).abs() < 1e-3))
+        );
+
+        // No scratch files left behind
+        let names: Vec<_> = std::fs::read_dir(temp.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["out.wav"]);
+    }
+
+    #[test]
+    fn test_output_format_from_extension() {
+        assert_eq!(
+            OutputFormat::from_path(Path::new("a.mid")),
+            OutputFormat::Midi
+        );
+        assert_eq!(
+            OutputFormat::from_path(Path::new("a.wav")),
+            OutputFormat::Wav
+        );
+        assert_eq!(
+            OutputFormat::from_path(Path::new("a.MP3")),
+            OutputFormat::Mp3
+        );
+        assert_eq!(
+            OutputFormat::from_path(Path::new("a.ogg")),
+            OutputFormat::Ogg
+        );
+        assert_eq!(OutputFormat::from_path(Path::new("a")), OutputFormat::Midi);
+    }
+
+    #[test]
+    fn test_output_paths() {
+        let out = Path::new("out/intro.mp3");
+        assert_eq!(
+            OutputFormat::Mp3.midi_path(out),
+            PathBuf::from("out/intro.mid")
+        );
+        assert_eq!(
+            OutputFormat::Mp3.wav_path(out),
+            PathBuf::from("out/intro.wav")
+        );
+        assert_eq!(
+            OutputFormat::Wav.wav_path(Path::new("a.wav")),
+            PathBuf::from("a.wav")
+        );
+        assert_eq!(
+            OutputFormat::Midi.midi_path(Path::new("a.mid")),
+            PathBuf::from("a.mid")
+        );
+    }
+
+    #[test]
+    fn test_transcode_command_mp3() {
+        let command =
+            transcode_command(Path::new("in.wav"), Path::new("out.mp3"), OutputFormat::Mp3)
+                .unwrap();
+        assert_eq!(command.get_program(), "ffmpeg");
+        assert_eq!(
+            args(&command),
+            [
+                "-y",
+                "-i",
+                "in.wav",
+                "-c:a",
+                "libmp3lame",
+                "-q:a",
+                "2",
+                "out.mp3"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transcode_command_ogg() {
+        let command =
+            transcode_command(Path::new("in.wav"), Path::new("out.ogg"), OutputFormat::Ogg)
+                .unwrap();
+        assert_eq!(
+            args(&command),
+            [
+                "-y",
+                "-i",
+                "in.wav",
+                "-c:a",
+                "libvorbis",
+                "-q:a",
+                "5",
+                "out.ogg"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_transcode_for_wav_or_midi() {
+        assert!(
+            transcode_command(Path::new("a.wav"), Path::new("a.wav"), OutputFormat::Wav).is_none()
+        );
+        assert!(
+            transcode_command(Path::new("a.wav"), Path::new("a.mid"), OutputFormat::Midi).is_none()
+        );
+    }
+
+    #[test]
+    fn test_fluidsynth_command_order() {
+        let command = fluidsynth_command(
+            Path::new("fluidsynth"),
+            Path::new("gm.sf2"),
+            Path::new("in.mid"),
+            Path::new("out.wav"),
+            &RenderOptions::default(),
+        );
+        assert_eq!(
+            args(&command),
+            [
+                "-ni", "-g", "1.0", "-r", "44100", "-F", "out.wav", "gm.sf2", "in.mid"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fluidsynth_command_gain_and_sample_rate() {
+        let options = RenderOptions {
+            gain: Some(0.35),
+            sample_rate: Some(48000),
+            ..Default::default()
+        };
+        let command = fluidsynth_command(
+            Path::new("fluidsynth"),
+            Path::new("gm.sf2"),
+            Path::new("in.mid"),
+            Path::new("out.wav"),
+            &options,
+        );
+        assert_eq!(
+            args(&command),
+            [
+                "-ni", "-g", "0.35", "-r", "48000", "-F", "out.wav", "gm.sf2", "in.mid"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_env_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let soundfont = dir.path().join("piano.sf2");
+        std::fs::write(&soundfont, "").unwrap();
+
+        let path = env_path(SOUNDFONT_ENV, Some(soundfont.clone().into_os_string())).unwrap();
+        assert_eq!(path, Some(soundfont));
+        assert_eq!(env_path(SOUNDFONT_ENV, None).unwrap(), None);
+        assert_eq!(env_path(SOUNDFONT_ENV, Some("".into())).unwrap(), None);
+
+        let err = env_path(
+            SOUNDFONT_ENV,
+            Some(dir.path().join("missing.sf2").into_os_string()),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            RenderError::EnvPathNotFound {
+                var: SOUNDFONT_ENV,
+                ..
+            }
+        ));
+        assert!(err.to_string().contains("missing.sf2"));
+    }
+}
@@ -1,43 +1,110 @@
 //! REST API handlers for the web server.
 
+use crate::analysis::{midi_info, MidiInfo};
 use crate::import::{notes_to_abc, AbcParser};
-use crate::midi::sequence::INSTRUMENT_MAP;
+use crate::midi::sequence::{resolve_instrument, suggest_instruments, Envelope, INSTRUMENT_MAP};
+use crate::midi::writer::MidiWriteError;
+use crate::midi::{write_midi, Note, NoteSequence};
+use crate::preset::{generate_mood, Key, Mood, PresetConfig};
+use crate::render::{ffmpeg_available, find_fluidsynth, find_soundfont, render_wav, RenderOptions};
 use crate::server::state::{
-    AbcImportRequest, AppState, ErrorResponse, GenerateResponse, MelodyNote, MelodyRequest,
-    PresetRequest, SavedMelody, SavedPreset,
+    generated_stem, is_safe_id, AbcImportRequest, AppState, BatchGenerateRequest, BatchItemResult, ErrorResponse, FieldError, GenerateResponse,
+    GenerationEvent, HealthResponse, MelodyNote, MelodyRequest, Page, PresetImportQuery, PresetListQuery, PresetRequest,
+    SavedMelody, SavedPreset,
 };
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     Json,
 };
+use std::collections::BTreeMap;
 use std::process::Command;
 use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
 
-/// GET /api/presets - List all saved presets.
-pub async fn list_presets(State(state): State<Arc<AppState>>) -> Json<Vec<SavedPreset>> {
+/// GET /api/presets - List saved presets, optionally filtered by mood and paged
+/// with `?mood=jazz&limit=20&offset=40`.
+pub async fn list_presets(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PresetListQuery>,
+) -> Json<Page<SavedPreset>> {
     let presets = state.presets.read().await;
-    let mut list: Vec<SavedPreset> = presets.values().cloned().collect();
+    let mut list: Vec<SavedPreset> = presets
+        .values()
+        .filter(|p| query.mood.as_ref().is_none_or(|m| p.mood.eq_ignore_ascii_case(m)))
+        .cloned()
+        .collect();
     // Sort by creation time, newest first
     list.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+    let total = list.len();
+    let items = list
+        .into_iter()
+        .skip(query.offset.unwrap_or(0))
+        .take(query.limit.unwrap_or(usize::MAX))
+        .collect();
+    Json(Page { items, total })
+}
+
+/// GET /api/presets/export - Export the whole preset library as a JSON array.
+pub async fn export_presets(State(state): State<Arc<AppState>>) -> Json<Vec<SavedPreset>> {
+    let presets = state.presets.read().await;
+    let mut list: Vec<SavedPreset> = presets.values().cloned().collect();
+    list.sort_by(|a, b| b.created_at.cmp(&a.created_at));
     Json(list)
 }
 
+/// POST /api/presets/import - Merge an exported preset array into the library.
+/// Presets whose ID already exists get a new ID, or overwrite the existing
+/// preset with `?replace=true`. Returns the presets as stored; nothing is
+/// stored if any preset is invalid (400).
+pub async fn import_presets(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PresetImportQuery>,
+    Json(incoming): Json<Vec<SavedPreset>>,
+) -> Result<Json<Vec<SavedPreset>>, (StatusCode, Json<ErrorResponse>)> {
+    for preset in &incoming {
+        // IDs become file names, so they can't contain path separators
+        if !is_safe_id(&preset.id) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Invalid preset id: {:?} (use letters, digits, '-' and '_')", preset.id),
+                }),
+            ));
+        }
+        check_preset(&preset.to_request())?;
+    }
+
+    let mut presets = state.presets.write().await;
+    let mut imported = Vec::with_capacity(incoming.len());
+    for mut preset in incoming {
+        if presets.contains_key(&preset.id) && !query.replace {
+            preset.id = uuid::Uuid::new_v4().to_string();
+        }
+        presets.insert(preset.id.clone(), preset.clone());
+        imported.push(preset);
+    }
+    drop(presets);
+
+    if let Err(e) = state.save().await {
+        eprintln!("Failed to save presets: {}", e);
+    }
+
+    Ok(Json(imported))
+}
+
 /// POST /api/presets - Create a new preset.
 pub async fn create_preset(
     State(state): State<Arc<AppState>>,
     Json(req): Json<PresetRequest>,
 ) -> Result<(StatusCode, Json<SavedPreset>), (StatusCode, Json<ErrorResponse>)> {
-    // Validate mood
-    if !is_valid_mood(&req.mood) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("Invalid mood: {}. Use /api/moods to see available moods.", req.mood),
-            }),
-        ));
-    }
+    check_preset(&req)?;
 
     let id = uuid::Uuid::new_v4().to_string();
     let preset = req.into_preset(id);
@@ -53,6 +120,30 @@ pub async fn create_preset(
     Ok((StatusCode::CREATED, Json(preset)))
 }
 
+/// Check a preset's mood exists and its fields are in range.
+fn check_preset(req: &PresetRequest) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if !is_valid_mood(&req.mood) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("Invalid mood: {}. Use /api/moods to see available moods.", req.mood),
+            }),
+        ));
+    }
+    req.validate().map_err(|errors| invalid_request("preset", &errors))
+}
+
+/// 400 response listing every field that failed validation.
+fn invalid_request(kind: &str, errors: &[FieldError]) -> (StatusCode, Json<ErrorResponse>) {
+    let details: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            error: format!("Invalid {}: {}", kind, details.join("; ")),
+        }),
+    )
+}
+
 /// GET /api/presets/:id - Get a single preset.
 pub async fn get_preset(
     State(state): State<Arc<AppState>>,
@@ -79,15 +170,7 @@ pub async fn update_preset(
     Path(id): Path<String>,
     Json(req): Json<PresetRequest>,
 ) -> Result<Json<SavedPreset>, (StatusCode, Json<ErrorResponse>)> {
-    // Validate mood
-    if !is_valid_mood(&req.mood) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("Invalid mood: {}. Use /api/moods to see available moods.", req.mood),
-            }),
-        ));
-    }
+    check_preset(&req)?;
 
     let mut presets = state.presets.write().await;
     let existing = presets.get(&id).ok_or_else(|| {
@@ -145,6 +228,39 @@ pub async fn delete_preset(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// POST /api/presets/:id/duplicate - Copy a preset under a new ID.
+pub async fn duplicate_preset(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<SavedPreset>), (StatusCode, Json<ErrorResponse>)> {
+    let mut presets = state.presets.write().await;
+    let original = presets.get(&id).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Preset not found: {}", id),
+            }),
+        )
+    })?;
+
+    let copy = SavedPreset {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: format!("{} (copy)", original.name),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        last_generated: None,
+        ..original.clone()
+    };
+
+    presets.insert(copy.id.clone(), copy.clone());
+    drop(presets);
+
+    if let Err(e) = state.save().await {
+        eprintln!("Failed to save presets: {}", e);
+    }
+
+    Ok((StatusCode::CREATED, Json(copy)))
+}
+
 /// POST /api/generate/:id - Generate audio for a preset.
 pub async fn generate_audio(
     State(state): State<Arc<AppState>>,
@@ -162,13 +278,186 @@ pub async fn generate_audio(
     })?.clone();
     drop(presets);
 
-    // Generate unique filename
-    let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string();
-    let filename = format!("{}_{}.wav", id, timestamp);
+    generate_preset(&state, preset, render_preset_wav, None)
+        .await
+        .map(Json)
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error })))
+}
+
+/// GET /api/generate/:id/stream - Generate audio for a preset, reporting
+/// progress as server-sent events: `started`, `midi_written`, `rendering`,
+/// then `done` with the audio URL (or `failed`).
+pub async fn generate_audio_stream(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, (StatusCode, Json<ErrorResponse>)> {
+    let presets = state.presets.read().await;
+    let preset = presets.get(&id).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Preset not found: {}", id),
+            }),
+        )
+    })?.clone();
+    drop(presets);
+
+    let (events, receiver) = mpsc::channel(8);
+    tokio::spawn(stream_generation(state, preset, render_preset_wav, events));
+
+    let stream = ReceiverStream::new(receiver)
+        .map(|event| Event::default().event(event.name()).json_data(&event));
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// POST /api/generate/batch - Generate several presets, rendering at most
+/// `RENDER_WORKERS` at a time across all requests. Returns each id's audio
+/// URL or error.
+pub async fn generate_batch(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<BatchGenerateRequest>,
+) -> Json<BTreeMap<String, BatchItemResult>> {
+    Json(generate_presets(state, req.preset_ids, render_preset_wav).await)
+}
+
+/// Generate presets concurrently, keyed by id (duplicate ids are generated
+/// once). Renders wait for the state's shared worker permits.
+pub async fn generate_presets(
+    state: Arc<AppState>,
+    ids: Vec<String>,
+    render: PresetRenderer,
+) -> BTreeMap<String, BatchItemResult> {
+    let mut tasks = tokio::task::JoinSet::new();
+    let mut results = BTreeMap::new();
+
+    for id in ids {
+        if results.contains_key(&id) {
+            continue;
+        }
+        let Some(preset) = state.presets.read().await.get(&id).cloned() else {
+            results.insert(id.clone(), BatchItemResult::Error { error: format!("Preset not found: {}", id) });
+            continue;
+        };
+        // Placeholder until the task reports back
+        results.insert(id.clone(), BatchItemResult::Error { error: "Generation did not finish".to_string() });
+
+        let state = state.clone();
+        tasks.spawn(async move {
+            let result = match generate_preset(&state, preset, render, None).await {
+                Ok(response) => BatchItemResult::Ok {
+                    audio_url: response.audio_url,
+                    generated_at: response.generated_at,
+                },
+                Err(error) => BatchItemResult::Error { error },
+            };
+            (id, result)
+        });
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((id, result)) = joined {
+            results.insert(id, result);
+        }
+    }
+    results
+}
+
+/// Renders a preset's MIDI file to WAV with the given options (replaceable
+/// in tests).
+pub type PresetRenderer = fn(&std::path::Path, &std::path::Path, &RenderOptions) -> Result<(), String>;
+
+fn render_preset_wav(midi_path: &std::path::Path, wav_path: &std::path::Path, options: &RenderOptions) -> Result<(), String> {
+    render_wav(midi_path, wav_path, options).map_err(|e| e.to_string())
+}
+
+/// Generate a preset to WAV, sending an event for each step.
+pub async fn stream_generation(
+    state: Arc<AppState>,
+    preset: SavedPreset,
+    render: PresetRenderer,
+    events: mpsc::Sender<GenerationEvent>,
+) {
+    let _ = events.send(GenerationEvent::Started { preset_id: preset.id.clone() }).await;
+    let event = match generate_preset(&state, preset, render, Some(&events)).await {
+        Ok(GenerateResponse { preset_id, audio_url, generated_at }) => {
+            GenerationEvent::Done { preset_id, audio_url, generated_at }
+        }
+        Err(error) => GenerationEvent::Failed { error },
+    };
+    let _ = events.send(event).await;
+}
+
+/// Generate a preset to WAV, the one path behind every generation endpoint.
+/// The blocking MIDI and render steps run off the async runtime, the render
+/// waits for one of the state's worker permits, and progress goes to
+/// `events` when given.
+async fn generate_preset(
+    state: &AppState,
+    preset: SavedPreset,
+    render: PresetRenderer,
+    events: Option<&mpsc::Sender<GenerationEvent>>,
+) -> Result<GenerateResponse, String> {
+    let notify = |event| async move {
+        if let Some(events) = events {
+            let _ = events.send(event).await;
+        }
+    };
+    let id = preset.id.clone();
+    let filename = format!("{}.wav", generated_stem(&id));
     let output_path = state.output_dir.join(&filename);
+    let midi_path = output_path.with_extension("mid");
+    let options = state.render_options(preset.duration);
+
+    let result = async {
+        let path = midi_path.clone();
+        tokio::task::spawn_blocking(move || write_preset_midi(&preset, &path))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|(_, Json(e))| e.error)?;
+        notify(GenerationEvent::MidiWritten).await;
+
+        let _permit = state.render_workers.acquire().await.map_err(|e| e.to_string())?;
+        notify(GenerationEvent::Rendering).await;
+        let (midi, wav) = (midi_path.clone(), output_path.clone());
+        tokio::task::spawn_blocking(move || render(&midi, &wav, &options))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+    .await;
+    let _ = std::fs::remove_file(&midi_path);
+
+    if let Err(error) = result {
+        eprintln!("[API ERROR] Preset generation failed: {}", error);
+        return Err(error);
+    }
+
+    eprintln!("[API] Preset generation succeeded: {}", filename);
+    state.cleanup_generated();
+
+    let generated_at = chrono::Utc::now().to_rfc3339();
+    {
+        let mut presets = state.presets.write().await;
+        if let Some(p) = presets.get_mut(&id) {
+            p.last_generated = Some(generated_at.clone());
+        }
+    }
+    let _ = state.save().await;
+
+    Ok(GenerateResponse {
+        preset_id: id,
+        audio_url: format!("/audio/{}", filename),
+        generated_at,
+    })
+}
 
-    // Build CLI command
-    let mut cmd = Command::new(std::env::current_exe().unwrap_or_else(|_| "midi-cli-rs".into()));
+/// Command that runs this executable as the CLI.
+fn cli_command() -> Command {
+    Command::new(std::env::current_exe().unwrap_or_else(|_| "midi-cli-rs".into()))
+}
+
+/// CLI command that generates a preset to `output_path` (.wav or .mid).
+fn preset_command(preset: &SavedPreset, output_path: &std::path::Path) -> Command {
+    let mut cmd = cli_command();
     cmd.arg("preset")
         .arg("-m")
         .arg(&preset.mood)
@@ -181,14 +470,18 @@ pub async fn generate_audio(
         .arg("-s")
         .arg(preset.seed.to_string())
         .arg("-o")
-        .arg(&output_path);
+        .arg(output_path);
 
     if let Some(ref key) = preset.key {
         cmd.arg("-k").arg(key);
     }
+    cmd
+}
 
+/// Run a CLI generation command, logging and reporting failures.
+fn run_generator(mut cmd: Command, label: &str) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
     // Log the command being run
-    eprintln!("[API] Running preset generation: {:?}", cmd);
+    eprintln!("[API] Running {} generation: {:?}", label.to_lowercase(), cmd);
 
     // Run generation and capture output
     let output = cmd.output().map_err(|e| {
@@ -204,7 +497,7 @@ pub async fn generate_audio(
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         let stdout = String::from_utf8_lossy(&output.stdout);
-        eprintln!("[API ERROR] Preset generation failed:");
+        eprintln!("[API ERROR] {} generation failed:", label);
         eprintln!("  Exit code: {:?}", output.status.code());
         eprintln!("  Stdout: {}", stdout);
         eprintln!("  Stderr: {}", stderr);
@@ -215,24 +508,188 @@ pub async fn generate_audio(
             }),
         ));
     }
+    Ok(())
+}
 
-    eprintln!("[API] Preset generation succeeded: {}", filename);
+/// GET /api/presets/:id/midi - Download a preset as a MIDI file.
+pub async fn get_preset_midi(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let presets = state.presets.read().await;
+    let preset = presets.get(&id).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Preset not found: {}", id),
+            }),
+        )
+    })?.clone();
+    drop(presets);
 
-    // Update last_generated timestamp
-    let generated_at = chrono::Utc::now().to_rfc3339();
-    {
-        let mut presets = state.presets.write().await;
-        if let Some(p) = presets.get_mut(&id) {
-            p.last_generated = Some(generated_at.clone());
+    let midi_path = state.output_dir.join(format!("{}.mid", id));
+    write_preset_midi(&preset, &midi_path)?;
+
+    midi_response(&midi_path, &preset.name)
+}
+
+/// Write a preset's MIDI file. Built-in moods are generated in-process;
+/// plugin moods go through the CLI.
+fn write_preset_midi(
+    preset: &SavedPreset,
+    midi_path: &std::path::Path,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if let Some(mood) = Mood::parse(&preset.mood) {
+        let config = preset_config(preset, mood);
+        config.validate().map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse { error: e.to_string() }),
+            )
+        })?;
+        let sequences = generate_mood(mood, &config);
+        write_midi(&sequences, midi_path).map_err(|e| midi_write_error(&e))
+    } else {
+        run_generator(preset_command(preset, midi_path), "Preset MIDI")
+    }
+}
+
+/// GET /api/melodies/:id/midi - Download a melody as a MIDI file.
+pub async fn get_melody_midi(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let melodies = state.melodies.read().await;
+    let melody = melodies.get(&id).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Melody not found: {}", id),
+            }),
+        )
+    })?.clone();
+    drop(melodies);
+
+    let sequence = melody_to_sequence(&melody).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+
+    let midi_path = state.output_dir.join(format!("melody_{}.mid", id));
+    write_midi(std::slice::from_ref(&sequence), &midi_path).map_err(|e| midi_write_error(&e))?;
+
+    midi_response(&midi_path, &melody.name)
+}
+
+/// Generation settings for a saved preset with a built-in mood.
+/// A seed of 0 or below picks a random seed, as on the command line.
+fn preset_config(preset: &SavedPreset, mood: Mood) -> PresetConfig {
+    let seed = if preset.seed <= 0 {
+        chrono::Utc::now().timestamp() as u64
+    } else {
+        preset.seed as u64
+    };
+    PresetConfig {
+        duration_secs: preset.duration,
+        key: preset
+            .key
+            .as_deref()
+            .and_then(Key::parse)
+            .unwrap_or_else(|| mood.default_key()),
+        intensity: preset.intensity,
+        seed,
+        tempo: preset.tempo,
+        ..Default::default()
+    }
+}
+
+fn midi_write_error(e: &MidiWriteError) -> (StatusCode, Json<ErrorResponse>) {
+    eprintln!("[API ERROR] Failed to write MIDI: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: format!("Failed to write MIDI: {}", e),
+        }),
+    )
+}
+
+/// Serve a MIDI file as a download named after the preset or melody.
+fn midi_response(
+    midi_path: &std::path::Path,
+    name: &str,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let bytes = std::fs::read(midi_path).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to read MIDI: {}", e),
+            }),
+        )
+    })?;
+
+    let stem: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    let disposition = format!("attachment; filename=\"{}.mid\"", stem);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "audio/midi".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+/// Dependency lookups used by the health check (replaceable in tests).
+pub struct Discovery {
+    pub fluidsynth: fn() -> bool,
+    pub soundfont: fn() -> Option<std::path::PathBuf>,
+    pub ffmpeg: fn() -> bool,
+}
+
+impl Discovery {
+    /// The same lookups the CLI uses when rendering.
+    pub fn system() -> Self {
+        Self {
+            fluidsynth: || find_fluidsynth().is_ok(),
+            soundfont: || find_soundfont().ok(),
+            ffmpeg: ffmpeg_available,
         }
     }
-    let _ = state.save().await;
 
-    Ok(Json(GenerateResponse {
-        preset_id: id,
-        audio_url: format!("/audio/{}", filename),
-        generated_at,
-    }))
+    /// Run the lookups and summarize them.
+    pub fn check(&self) -> HealthResponse {
+        let fluidsynth = (self.fluidsynth)();
+        let soundfont = (self.soundfont)();
+        let status = if fluidsynth && soundfont.is_some() { "ok" } else { "degraded" };
+        HealthResponse {
+            status: status.to_string(),
+            fluidsynth,
+            soundfont,
+            ffmpeg: (self.ffmpeg)(),
+        }
+    }
+}
+
+/// POST /api/inspect - Describe uploaded MIDI bytes: format, tempo, tracks,
+/// and notes per channel (the same JSON as `info --json`).
+pub async fn inspect_midi(body: axum::body::Bytes) -> Result<Json<MidiInfo>, (StatusCode, Json<ErrorResponse>)> {
+    midi_info(&body).map(Json).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: e.to_string() }),
+        )
+    })
+}
+
+/// GET /api/health - Report whether audio rendering dependencies are available.
+pub async fn health() -> Json<HealthResponse> {
+    Json(Discovery::system().check())
 }
 
 /// GET /api/moods - List available moods (built-in + plugins).
@@ -355,6 +812,8 @@ pub async fn create_melody(
     State(state): State<Arc<AppState>>,
     Json(req): Json<MelodyRequest>,
 ) -> Result<(StatusCode, Json<SavedMelody>), (StatusCode, Json<ErrorResponse>)> {
+    req.validate().map_err(|errors| invalid_request("melody", &errors))?;
+
     let id = uuid::Uuid::new_v4().to_string();
     let melody = req.into_melody(id);
 
@@ -395,6 +854,8 @@ pub async fn update_melody(
     Path(id): Path<String>,
     Json(req): Json<MelodyRequest>,
 ) -> Result<Json<SavedMelody>, (StatusCode, Json<ErrorResponse>)> {
+    req.validate().map_err(|errors| invalid_request("melody", &errors))?;
+
     let mut melodies = state.melodies.write().await;
     let existing = melodies.get(&id).ok_or_else(|| {
         (
@@ -451,6 +912,39 @@ pub async fn delete_melody(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// POST /api/melodies/:id/duplicate - Copy a melody under a new ID.
+pub async fn duplicate_melody(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<SavedMelody>), (StatusCode, Json<ErrorResponse>)> {
+    let mut melodies = state.melodies.write().await;
+    let original = melodies.get(&id).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Melody not found: {}", id),
+            }),
+        )
+    })?;
+
+    let copy = SavedMelody {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: format!("{} (copy)", original.name),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        last_generated: None,
+        ..original.clone()
+    };
+
+    melodies.insert(copy.id.clone(), copy.clone());
+    drop(melodies);
+
+    if let Err(e) = state.save().await {
+        eprintln!("Failed to save melodies: {}", e);
+    }
+
+    Ok((StatusCode::CREATED, Json(copy)))
+}
+
 /// POST /api/melodies/:id/generate - Generate audio for a melody.
 pub async fn generate_melody_audio(
     State(state): State<Arc<AppState>>,
@@ -468,33 +962,15 @@ pub async fn generate_melody_audio(
     })?.clone();
     drop(melodies);
 
-    // Generate unique filename
-    let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string();
-    let filename = format!("melody_{}_{}.wav", id, timestamp);
-    let output_path = state.output_dir.join(&filename);
-
-    // Convert notes to CLI format: "PITCH:DURATION:VELOCITY[@OFFSET],..."
-    // Rests are handled by advancing the offset without adding a note
-    let mut notes_str = String::new();
-    let mut offset = 0.0f64;
-    for note in &melody.notes {
-        if note.pitch == "rest" {
-            // For rests, just advance the offset (silence)
-            offset += note.duration;
-            continue;
-        }
-        // Only add comma separator if we already have notes
-        if !notes_str.is_empty() {
-            notes_str.push(',');
-        }
-        notes_str.push_str(&format!(
-            "{}:{}:{}@{}",
-            note.pitch, note.duration, note.velocity, offset
-        ));
-        offset += note.duration;
-    }
+    // Build the sequence in-process so the attack/decay envelope is carried
+    let sequence = melody_to_sequence(&melody).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
 
-    if notes_str.is_empty() {
+    if sequence.notes.is_empty() {
         return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -503,49 +979,32 @@ pub async fn generate_melody_audio(
         ));
     }
 
-    // Build CLI command
-    let mut cmd = Command::new(std::env::current_exe().unwrap_or_else(|_| "midi-cli-rs".into()));
-    cmd.arg("generate")
-        .arg("--notes")
-        .arg(&notes_str)
-        .arg("-i")
-        .arg(&melody.instrument)
-        .arg("-t")
-        .arg(melody.tempo.to_string())
-        .arg("-o")
-        .arg(&output_path);
-
-    // Log the command being run
-    eprintln!("[API] Running melody generation: {:?}", cmd);
-    eprintln!("[API] Notes string: {}", notes_str);
+    // Generate unique filename
+    let filename = format!("{}.wav", generated_stem(&format!("melody_{}", id)));
+    let output_path = state.output_dir.join(&filename);
+    let midi_path = output_path.with_extension("mid");
 
-    // Run generation and capture output
-    let output = cmd.output().map_err(|e| {
-        eprintln!("[API ERROR] Failed to spawn generator process: {}", e);
+    write_midi(std::slice::from_ref(&sequence), &midi_path).map_err(|e| {
+        eprintln!("[API ERROR] Failed to write melody MIDI: {}", e);
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
-                error: format!("Failed to run generator: {}", e),
+                error: format!("Failed to write MIDI: {}", e),
             }),
         )
     })?;
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        eprintln!("[API ERROR] Melody generation failed:");
-        eprintln!("  Exit code: {:?}", output.status.code());
-        eprintln!("  Stdout: {}", stdout);
-        eprintln!("  Stderr: {}", stderr);
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Audio generation failed: {}", stderr.trim()),
-            }),
-        ));
-    }
+    // Render the MIDI file with the CLI
+    let mut cmd = cli_command();
+    cmd.arg("render")
+        .arg("-i")
+        .arg(&midi_path)
+        .arg("-o")
+        .arg(&output_path);
+    run_generator(cmd, "Melody")?;
 
     eprintln!("[API] Melody generation succeeded: {}", filename);
+    state.cleanup_generated();
 
     // Update last_generated timestamp
     let generated_at = chrono::Utc::now().to_rfc3339();
@@ -564,6 +1023,40 @@ pub async fn generate_melody_audio(
     }))
 }
 
+/// Convert a saved melody into a note sequence.
+/// Notes are laid end to end; rests advance the offset without adding a note.
+/// A chord note starts with the note before it, and the next note waits for
+/// the longest note of the chord.
+/// The melody's attack/decay settings become the sequence envelope.
+pub fn melody_to_sequence(melody: &SavedMelody) -> Result<NoteSequence, String> {
+    let instrument = resolve_instrument(&melody.instrument).ok_or_else(|| {
+        match suggest_instruments(&melody.instrument)[..] {
+            [] => format!("Unknown instrument: {}", melody.instrument),
+            ref names => format!("Unknown instrument: {}. Did you mean: {}?", melody.instrument, names.join(", ")),
+        }
+    })?;
+
+    let mut notes = Vec::new();
+    let (mut start, mut next) = (0.0f64, 0.0f64);
+    for note in &melody.notes {
+        if !note.chord {
+            start = next;
+        }
+        if note.pitch != "rest" {
+            let pitch = Note::parse_pitch(&note.pitch).map_err(|e| e.to_string())?;
+            notes.push(Note::new(pitch, note.duration, note.velocity, start));
+        }
+        next = next.max(start + note.duration);
+    }
+
+    let mut sequence = NoteSequence::new(notes, instrument, melody.tempo);
+    sequence.envelope = Some(Envelope {
+        attack: melody.attack,
+        decay: melody.decay,
+    });
+    Ok(sequence)
+}
+
 /// POST /api/melodies/import/abc - Import ABC notation as a melody.
 pub async fn import_abc_melody(
     State(state): State<Arc<AppState>>,
@@ -587,6 +1080,7 @@ pub async fn import_abc_melody(
             pitch: n.pitch,
             duration: n.duration,
             velocity: n.velocity,
+            chord: false,
         })
         .collect();
 
@@ -650,10 +1144,12 @@ pub async fn export_melody_abc(
         )
     })?;
 
-    // Convert notes to format expected by notes_to_abc
+    // Convert notes to format expected by notes_to_abc; only the first note
+    // of each chord is written, so the timing stays right
     let notes: Vec<(String, f64, u8)> = melody
         .notes
         .iter()
+        .filter(|n| !n.chord)
         .map(|n| (n.pitch.clone(), n.duration, n.velocity))
         .collect();
 
@@ -665,7 +1161,7 @@ pub async fn export_melody_abc(
 pub async fn list_instruments() -> impl IntoResponse {
     Json(INSTRUMENT_MAP
         .iter()
-        .map(|(name, num)| InstrumentInfo { name, program: *num })
+        .map(|i| InstrumentInfo { name: i.name, program: i.program })
         .collect::<Vec<_>>())
 }
 
@@ -943,3 +1439,485 @@ pub fn lookup_plugin_mood(mood_name: &str) -> Option<PluginMoodInfo> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::server::state::{RetentionConfig, StorageConfig};
+
+    fn test_melody(notes: Vec<MelodyNote>) -> SavedMelody {
+        SavedMelody {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            notes,
+            key: "C".to_string(),
+            tempo: 120,
+            instrument: "violin".to_string(),
+            attack: 10,
+            decay: 90,
+            created_at: String::new(),
+            last_generated: None,
+        }
+    }
+
+    async fn response_bytes(response: Response) -> Vec<u8> {
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_preset_midi_endpoint_returns_midi() {
+        let temp = tempfile::tempdir().unwrap();
+        let state = AppState::load_or_create(Some(temp.path().to_path_buf())).unwrap();
+        let preset = PresetRequest {
+            name: "Jazz Intro".to_string(),
+            mood: "jazz".to_string(),
+            duration: 3.0,
+            key: None,
+            intensity: 50,
+            tempo: 100,
+            seed: 7,
+        }
+        .into_preset("p1".to_string());
+        state.presets.write().await.insert(preset.id.clone(), preset);
+
+        let response = get_preset_midi(State(state), Path("p1".to_string()))
+            .await
+            .map_err(|(status, _)| status)
+            .unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "audio/midi");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"Jazz_Intro.mid\""
+        );
+        assert!(response_bytes(response).await.starts_with(b"MThd"));
+    }
+
+    #[tokio::test]
+    async fn test_custom_output_dir_and_data_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let storage = StorageConfig {
+            data_dir: None,
+            data_file: Some(temp.path().join("db/melodies.json")),
+            output_dir: Some(temp.path().join("renders")),
+        };
+        let state = AppState::load_or_create_with(&storage, RetentionConfig::default(), Config::default()).unwrap();
+        assert_eq!(state.output_dir, temp.path().join("renders").canonicalize().unwrap());
+        state
+            .melodies
+            .write()
+            .await
+            .insert("test".to_string(), test_melody(vec![MelodyNote::default()]));
+
+        get_melody_midi(State(state.clone()), Path("test".to_string())).await.map_err(|(status, _)| status).unwrap();
+        assert!(temp.path().join("renders/melody_test.mid").exists());
+        state.save().await.unwrap();
+        assert!(temp.path().join("db/melodies.json").exists());
+    }
+
+    fn stub_render(midi_path: &std::path::Path, wav_path: &std::path::Path, _options: &RenderOptions) -> Result<(), String> {
+        assert!(std::fs::read(midi_path).unwrap().starts_with(b"MThd"));
+        std::fs::write(wav_path, b"RIFF").map_err(|e| e.to_string())
+    }
+
+    fn failing_render(_: &std::path::Path, _: &std::path::Path, _: &RenderOptions) -> Result<(), String> {
+        Err("FluidSynth not found".to_string())
+    }
+
+    async fn collect_events(render: PresetRenderer) -> (Arc<AppState>, Vec<GenerationEvent>, tempfile::TempDir) {
+        let temp = tempfile::tempdir().unwrap();
+        let state = AppState::load_or_create(Some(temp.path().to_path_buf())).unwrap();
+        let preset = PresetRequest {
+            name: "Calm".to_string(),
+            mood: "calm".to_string(),
+            duration: 3.0,
+            key: None,
+            intensity: 50,
+            tempo: 80,
+            seed: 3,
+        }
+        .into_preset("p1".to_string());
+        state.presets.write().await.insert(preset.id.clone(), preset.clone());
+
+        let (events, mut receiver) = mpsc::channel(8);
+        stream_generation(state.clone(), preset, render, events).await;
+        let mut received = Vec::new();
+        while let Some(event) = receiver.recv().await {
+            received.push(event);
+        }
+        (state, received, temp)
+    }
+
+    #[tokio::test]
+    async fn test_stream_generation_event_sequence() {
+        let (state, events, _temp) = collect_events(stub_render).await;
+
+        let names: Vec<&str> = events.iter().map(GenerationEvent::name).collect();
+        assert_eq!(names, ["started", "midi_written", "rendering", "done"]);
+        let GenerationEvent::Done { audio_url, .. } = &events[3] else { unreachable!() };
+        let filename = audio_url.strip_prefix("/audio/").unwrap();
+        assert!(state.output_dir.join(filename).exists());
+        assert!(!state.output_dir.join(filename).with_extension("mid").exists());
+
+        let last_generated = state.presets.read().await["p1"].last_generated.clone();
+        assert!(last_generated.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_stream_generation_reports_render_failure() {
+        let (state, events, _temp) = collect_events(failing_render).await;
+
+        let names: Vec<&str> = events.iter().map(GenerationEvent::name).collect();
+        assert_eq!(names, ["started", "midi_written", "rendering", "failed"]);
+        assert_eq!(
+            events[3],
+            GenerationEvent::Failed { error: "FluidSynth not found".to_string() }
+        );
+        assert!(state.presets.read().await["p1"].last_generated.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generate_presets_reports_each_id() {
+        let temp = tempfile::tempdir().unwrap();
+        let state = AppState::load_or_create(Some(temp.path().to_path_buf())).unwrap();
+        for (id, mood) in [("p1", "calm"), ("p2", "jazz")] {
+            let preset = PresetRequest {
+                name: id.to_string(),
+                mood: mood.to_string(),
+                duration: 2.0,
+                key: None,
+                intensity: 50,
+                tempo: 90,
+                seed: 1,
+            }
+            .into_preset(id.to_string());
+            state.presets.write().await.insert(id.to_string(), preset);
+        }
+
+        let ids = vec!["p1".to_string(), "p2".to_string()];
+        let results = generate_presets(state.clone(), ids, stub_render).await;
+        assert_eq!(results.len(), 2);
+        for (id, result) in &results {
+            let BatchItemResult::Ok { audio_url, .. } = result else { panic!("{id}: {result:?}") };
+            assert!(audio_url.starts_with(&format!("/audio/{id}_")), "{audio_url}");
+            assert!(state.output_dir.join(audio_url.strip_prefix("/audio/").unwrap()).exists());
+        }
+
+        let ids = vec!["p1".to_string(), "missing".to_string()];
+        let results = generate_presets(state, ids, stub_render).await;
+        assert!(matches!(results["p1"], BatchItemResult::Ok { .. }));
+        assert_eq!(
+            results["missing"],
+            BatchItemResult::Error { error: "Preset not found: missing".to_string() }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_preset_rejects_out_of_range_fields() {
+        let temp = tempfile::tempdir().unwrap();
+        let state = AppState::load_or_create(Some(temp.path().to_path_buf())).unwrap();
+        let req = PresetRequest {
+            name: "Broken".to_string(),
+            mood: "calm".to_string(),
+            duration: -5.0,
+            key: None,
+            intensity: 50,
+            tempo: 0,
+            seed: 1,
+        };
+
+        let (status, Json(body)) = create_preset(State(state.clone()), Json(req)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.error.starts_with("Invalid preset: duration must be between 0.1 and 300"));
+        assert!(body.error.contains("; tempo must be between 20 and 300 BPM (got 0)"));
+        assert!(state.presets.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_melody_midi_endpoint_returns_midi() {
+        let temp = tempfile::tempdir().unwrap();
+        let state = AppState::load_or_create(Some(temp.path().to_path_buf())).unwrap();
+        state
+            .melodies
+            .write()
+            .await
+            .insert("test".to_string(), test_melody(vec![MelodyNote::default()]));
+
+        let response = get_melody_midi(State(state.clone()), Path("test".to_string()))
+            .await
+            .map_err(|(status, _)| status)
+            .unwrap();
+        assert!(response_bytes(response).await.starts_with(b"MThd"));
+
+        let missing = get_melody_midi(State(state), Path("nope".to_string())).await;
+        assert_eq!(missing.err().map(|(status, _)| status), Some(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_inspect_generated_midi() {
+        let config = PresetConfig { duration_secs: 3.0, seed: 5, ..PresetConfig::default() };
+        let sequences = generate_mood(Mood::Jazz, &config);
+        let Json(info) = inspect_midi(crate::midi_bytes(&sequences).unwrap().into()).await.unwrap();
+        // A tempo track plus one per sequence
+        assert_eq!(info.tracks.len(), sequences.len() + 1);
+        assert_eq!(info.notes, sequences.iter().map(|s| s.notes.len()).sum::<usize>());
+
+        let (status, Json(body)) = inspect_midi("not midi".into()).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.error.starts_with("Invalid MIDI file"), "{}", body.error);
+    }
+
+    #[test]
+    fn test_health_reflects_discovery() {
+        let ready = Discovery {
+            fluidsynth: || true,
+            soundfont: || Some("/sf/GeneralUser_GS.sf2".into()),
+            ffmpeg: || false,
+        };
+        assert_eq!(
+            serde_json::to_value(ready.check()).unwrap(),
+            serde_json::json!({
+                "status": "ok",
+                "fluidsynth": true,
+                "soundfont": "/sf/GeneralUser_GS.sf2",
+                "ffmpeg": false,
+            })
+        );
+
+        let missing_soundfont = Discovery { fluidsynth: || true, soundfont: || None, ffmpeg: || true };
+        let report = missing_soundfont.check();
+        assert_eq!(report.status, "degraded");
+        assert_eq!((report.soundfont, report.ffmpeg), (None, true));
+    }
+
+    #[tokio::test]
+    async fn test_list_presets_filters_and_pages() {
+        let temp = tempfile::tempdir().unwrap();
+        let state = AppState::load_or_create(Some(temp.path().to_path_buf())).unwrap();
+        {
+            let mut presets = state.presets.write().await;
+            for i in 0..50 {
+                let mut preset = PresetRequest {
+                    name: format!("Preset {i}"),
+                    mood: ["jazz", "calm"][i % 2].to_string(),
+                    duration: 5.0,
+                    key: None,
+                    intensity: 50,
+                    tempo: 90,
+                    seed: i as i64,
+                }
+                .into_preset(format!("p{i}"));
+                preset.created_at = format!("2026-01-01T00:00:{i:02}Z");
+                presets.insert(preset.id.clone(), preset);
+            }
+        }
+
+        let list = |mood: Option<&str>, limit, offset| {
+            let query = PresetListQuery { mood: mood.map(String::from), limit, offset };
+            list_presets(State(state.clone()), Query(query))
+        };
+
+        let Json(all) = list(None, None, None).await;
+        assert_eq!((all.items.len(), all.total), (50, 50));
+        assert_eq!(all.items[0].id, "p49");
+
+        // 25 jazz presets (even numbers), newest first: p48, p46, ...
+        let Json(page) = list(Some("Jazz"), Some(10), Some(20)).await;
+        assert_eq!(page.total, 25);
+        let ids: Vec<&str> = page.items.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["p8", "p6", "p4", "p2", "p0"]);
+
+        let Json(past_end) = list(None, Some(20), Some(60)).await;
+        assert_eq!((past_end.items.len(), past_end.total), (0, 50));
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source = AppState::load_or_create(Some(source_dir.path().to_path_buf())).unwrap();
+        for (i, mood) in ["jazz", "calm", "eerie"].iter().enumerate() {
+            let preset = PresetRequest {
+                name: format!("Preset {i}"),
+                mood: mood.to_string(),
+                duration: 4.0 + i as f64,
+                key: Some("Am".to_string()),
+                intensity: 60,
+                tempo: 100,
+                seed: i as i64 + 1,
+            }
+            .into_preset(format!("p{i}"));
+            source.presets.write().await.insert(preset.id.clone(), preset);
+        }
+
+        let Json(exported) = export_presets(State(source)).await;
+        assert_eq!(exported.len(), 3);
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let target = AppState::load_or_create(Some(target_dir.path().to_path_buf())).unwrap();
+        let import = |presets: Vec<SavedPreset>, replace| {
+            import_presets(State(target.clone()), Query(PresetImportQuery { replace }), Json(presets))
+        };
+        let Json(stored) = import(exported.clone(), false).await.unwrap();
+        assert_eq!(stored.len(), 3);
+
+        let Json(mut reexported) = export_presets(State(target.clone())).await;
+        let mut expected = exported.clone();
+        for list in [&mut reexported, &mut expected] {
+            list.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+        assert_eq!(
+            serde_json::to_value(&reexported).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+
+        // Re-importing without replace keeps both copies under fresh IDs
+        let Json(again) = import(exported.clone(), false).await.unwrap();
+        assert!(again.iter().all(|p| !p.id.starts_with('p')));
+        assert_eq!(target.presets.read().await.len(), 6);
+
+        // With replace, existing IDs are overwritten in place
+        let mut renamed = exported.clone();
+        renamed[0].name = "Renamed".to_string();
+        let Json(replaced) = import(renamed.clone(), true).await.unwrap();
+        assert_eq!(replaced[0].id, renamed[0].id);
+        assert_eq!(target.presets.read().await.len(), 6);
+        assert_eq!(target.presets.read().await[&renamed[0].id].name, "Renamed");
+
+        // Unsafe IDs, unknown moods and out-of-range fields reject the whole import
+        let mut traversal = exported.clone();
+        traversal[1].id = "../../x".to_string();
+        let mut bad_mood = exported.clone();
+        bad_mood[2].mood = "nonexistent".to_string();
+        let mut bad_tempo = exported.clone();
+        bad_tempo[0].tempo = 0;
+        for bad in [traversal, bad_mood, bad_tempo] {
+            let (status, _) = import(bad, true).await.unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+        assert_eq!(target.presets.read().await.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_preset() {
+        let temp = tempfile::tempdir().unwrap();
+        let state = AppState::load_or_create(Some(temp.path().to_path_buf())).unwrap();
+        let (_, Json(original)) = create_preset(
+            State(state.clone()),
+            Json(PresetRequest {
+                name: "Calm Outro".to_string(),
+                mood: "calm".to_string(),
+                duration: 7.0,
+                key: Some("G".to_string()),
+                intensity: 40,
+                tempo: 72,
+                seed: 3,
+            }),
+        )
+        .await
+        .map_err(|(status, _)| status)
+        .unwrap();
+
+        let (status, Json(copy)) = duplicate_preset(State(state.clone()), Path(original.id.clone()))
+            .await
+            .map_err(|(status, _)| status)
+            .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert_ne!(copy.id, original.id);
+        assert_eq!(copy.name, "Calm Outro (copy)");
+        assert_eq!((copy.mood.as_str(), copy.seed, copy.tempo), ("calm", 3, 72));
+
+        let presets = state.presets.read().await;
+        assert_eq!(presets.len(), 2);
+        assert_eq!(presets[&original.id].name, "Calm Outro");
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_melody() {
+        let temp = tempfile::tempdir().unwrap();
+        let state = AppState::load_or_create(Some(temp.path().to_path_buf())).unwrap();
+        state
+            .melodies
+            .write()
+            .await
+            .insert("test".to_string(), test_melody(vec![MelodyNote::default()]));
+
+        let (_, Json(copy)) = duplicate_melody(State(state.clone()), Path("test".to_string()))
+            .await
+            .map_err(|(status, _)| status)
+            .unwrap();
+        assert_ne!(copy.id, "test");
+        assert_eq!(copy.name, "Test (copy)");
+        assert_eq!(copy.notes, vec![MelodyNote::default()]);
+
+        let missing = duplicate_melody(State(state.clone()), Path("nope".to_string())).await;
+        assert_eq!(missing.err().map(|(status, _)| status), Some(StatusCode::NOT_FOUND));
+
+        let melodies = state.melodies.read().await;
+        assert_eq!(melodies.len(), 2);
+        assert_eq!(melodies["test"].name, "Test");
+    }
+
+    #[test]
+    fn test_melody_to_sequence_carries_envelope() {
+        let melody = test_melody(vec![MelodyNote::default()]);
+        let seq = melody_to_sequence(&melody).unwrap();
+        assert_eq!(seq.instrument, 40);
+        assert_eq!(
+            seq.envelope,
+            Some(Envelope {
+                attack: 10,
+                decay: 90
+            })
+        );
+    }
+
+    #[test]
+    fn test_melody_to_sequence_rests_advance_offset() {
+        let melody = test_melody(vec![
+            MelodyNote {
+                pitch: "C4".to_string(),
+                duration: 1.0,
+                velocity: 80,
+                chord: false,
+            },
+            MelodyNote {
+                pitch: "rest".to_string(),
+                duration: 0.5,
+                velocity: 0,
+                chord: false,
+            },
+            MelodyNote {
+                pitch: "E4".to_string(),
+                duration: 1.0,
+                velocity: 90,
+                chord: false,
+            },
+        ]);
+        let seq = melody_to_sequence(&melody).unwrap();
+        assert_eq!(seq.notes.len(), 2);
+        assert_eq!(seq.notes[1].pitch, 64);
+        assert_eq!(seq.notes[1].offset, 1.5);
+    }
+
+    #[test]
+    fn test_melody_to_sequence_stacks_chord() {
+        let note = |pitch: &str, duration, chord| MelodyNote {
+            pitch: pitch.to_string(),
+            duration,
+            velocity: 80,
+            chord,
+        };
+        let melody = test_melody(vec![
+            note("C4", 1.0, false),
+            note("E4", 2.0, true),
+            note("G4", 1.0, true),
+            note("A4", 1.0, false),
+        ]);
+        let seq = melody_to_sequence(&melody).unwrap();
+        let offsets: Vec<f64> = seq.notes.iter().map(|n| n.offset).collect();
+        assert_eq!(offsets, [0.0, 0.0, 0.0, 2.0]);
+    }
+}
@@ -0,0 +1,132 @@
+//! Optional bearer-token auth for the API routes.
+//!
+//! When `MIDI_CLI_API_KEY` is set, every `/api` request must carry
+//! `Authorization: Bearer <key>`; others get 401. Static files and generated
+//! audio are served without a key.
+
+use crate::server::state::ErrorResponse;
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+/// Environment variable holding the API key.
+pub const API_KEY_ENV: &str = "MIDI_CLI_API_KEY";
+
+/// The key clients must present.
+#[derive(Clone, Debug)]
+pub struct ApiKey(Arc<str>);
+
+impl ApiKey {
+    pub fn new(key: impl Into<Arc<str>>) -> Self {
+        Self(key.into())
+    }
+
+    /// Key from `MIDI_CLI_API_KEY`; None (auth off) when unset or blank.
+    pub fn from_env() -> Option<Self> {
+        std::env::var(API_KEY_ENV)
+            .ok()
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .map(Self::new)
+    }
+
+    /// Whether an `Authorization` header value carries this key. Compares
+    /// every byte so timing doesn't reveal how much of a guess was right.
+    fn accepts(&self, authorization: Option<&str>) -> bool {
+        let Some(token) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+            return false;
+        };
+        let (token, key) = (token.trim().as_bytes(), self.0.as_bytes());
+        token.len() == key.len()
+            && token.iter().zip(key).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+}
+
+/// Middleware rejecting requests without the API key.
+pub async fn require_api_key(State(key): State<ApiKey>, request: Request, next: Next) -> Response {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if key.accepts(authorization) {
+        return next.run(request).await;
+    }
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        Json(ErrorResponse {
+            error: format!(
+                "Missing or invalid API key (send Authorization: Bearer <{}>)",
+                API_KEY_ENV
+            ),
+        }),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{router, state::AppState};
+    use axum::body::Body;
+    use tower::Service;
+
+    async fn status(app: &mut axum::Router, uri: &str, authorization: Option<&str>) -> StatusCode {
+        let mut request = Request::get(uri);
+        if let Some(value) = authorization {
+            request = request.header(header::AUTHORIZATION, value);
+        }
+        app.call(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_api_requires_key_when_set() {
+        let temp = tempfile::tempdir().unwrap();
+        let state = AppState::load_or_create(Some(temp.path().to_path_buf())).unwrap();
+        std::fs::write(state.output_dir.join("take.wav"), b"RIFF").unwrap();
+        let mut app = router(
+            state,
+            temp.path().to_path_buf(),
+            Some(ApiKey::new("s3cret")),
+        );
+
+        assert_eq!(
+            status(&mut app, "/api/presets", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(&mut app, "/api/presets", Some("Bearer wrong")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(&mut app, "/api/presets", Some("s3cret")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(&mut app, "/api/presets", Some("Bearer s3cret")).await,
+            StatusCode::OK
+        );
+
+        // Generated audio stays open
+        assert_eq!(
+            status(&mut app, "/audio/take.wav", None).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_open_without_key() {
+        let temp = tempfile::tempdir().unwrap();
+        let state = AppState::load_or_create(Some(temp.path().to_path_buf())).unwrap();
+        let mut app = router(state, temp.path().to_path_buf(), None);
+        assert_eq!(status(&mut app, "/api/presets", None).await, StatusCode::OK);
+    }
+}
@@ -4,40 +4,84 @@
 //! - REST API endpoints for preset CRUD and audio generation
 //! - Static files for the Yew WASM frontend
 //! - Generated audio files for playback
+//!
+//! Setting `MIDI_CLI_API_KEY` puts the REST API behind bearer-token auth.
 
 pub mod api;
+pub mod auth;
 pub mod state;
 
 use axum::{
+    Router, middleware,
     routing::{get, post},
-    Router,
 };
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 
-use state::AppState;
+use crate::config::Config;
+use auth::ApiKey;
+use state::{AppState, RetentionConfig, StorageConfig};
 
 /// Run the web server on the specified port.
 pub async fn run_server(
     port: u16,
     static_dir: PathBuf,
-    data_dir: Option<PathBuf>,
+    storage: StorageConfig,
+    retention: RetentionConfig,
+    config: Config,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let state = AppState::load_or_create(data_dir)?;
+    let state = AppState::load_or_create_with(&storage, retention, config)?;
+    state.cleanup_generated();
+
+    let api_key = ApiKey::from_env();
+    let output_dir = state.output_dir.clone();
+    let state_file = state.storage_path.clone();
+    let app = router(state, static_dir.clone(), api_key.clone());
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    eprintln!("Starting web server at http://{}", addr);
+    eprintln!("  Static files: {}", static_dir.display());
+    eprintln!("  Audio output: {}", output_dir.display());
+    eprintln!("  Data file: {}", state_file.display());
+    if api_key.is_some() {
+        eprintln!("  API key required ({} is set)", auth::API_KEY_ENV);
+    }
+    eprintln!("Open in browser to use the web UI");
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
 
+/// Build the app: the REST API under `/api` (behind `api_key` if given),
+/// generated audio under `/audio`, and the web UI from `static_dir`.
+pub fn router(
+    state: std::sync::Arc<AppState>,
+    static_dir: PathBuf,
+    api_key: Option<ApiKey>,
+) -> Router {
     // Build the API routes
     let api_routes = Router::new()
+        .route("/health", get(api::health))
         // Preset routes
         .route("/presets", get(api::list_presets).post(api::create_preset))
+        // Specific routes before parameterized routes
+        .route("/presets/export", get(api::export_presets))
+        .route("/presets/import", post(api::import_presets))
         .route(
             "/presets/:id",
             get(api::get_preset)
                 .put(api::update_preset)
                 .delete(api::delete_preset),
         )
+        .route("/presets/:id/duplicate", post(api::duplicate_preset))
+        .route("/presets/:id/midi", get(api::get_preset_midi))
+        .route("/generate/batch", post(api::generate_batch))
         .route("/generate/:id", post(api::generate_audio))
+        .route("/generate/:id/stream", get(api::generate_audio_stream))
         .route("/moods", get(api::list_moods))
         // Melody routes
         .route("/melodies", get(api::list_melodies).post(api::create_melody))
@@ -50,11 +94,20 @@ pub async fn run_server(
                 .delete(api::delete_melody),
         )
         .route("/melodies/:id/generate", post(api::generate_melody_audio))
+        .route("/melodies/:id/duplicate", post(api::duplicate_melody))
+        .route("/melodies/:id/midi", get(api::get_melody_midi))
         .route("/melodies/:id/export/abc", get(api::export_melody_abc))
         .route("/instruments", get(api::list_instruments))
+        .route("/inspect", post(api::inspect_midi))
         // Plugin routes
         .route("/plugins", get(api::list_plugins).post(api::upload_plugin))
         .route("/plugins/:name", axum::routing::delete(api::delete_plugin));
+    let api_routes = match api_key {
+        Some(key) => {
+            api_routes.route_layer(middleware::from_fn_with_state(key, auth::require_api_key))
+        }
+        None => api_routes,
+    };
 
     // CORS configuration for development
     let cors = CorsLayer::new()
@@ -64,21 +117,10 @@ pub async fn run_server(
 
     // Build the main app
     let output_dir = state.output_dir.clone();
-    let app = Router::new()
+    Router::new()
         .nest("/api", api_routes)
         .nest_service("/audio", ServeDir::new(&output_dir))
         .fallback_service(ServeDir::new(&static_dir).append_index_html_on_directories(true))
         .layer(cors)
-        .with_state(state);
-
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    eprintln!("Starting web server at http://{}", addr);
-    eprintln!("  Static files: {}", static_dir.display());
-    eprintln!("  Audio output: {}", output_dir.display());
-    eprintln!("Open in browser to use the web UI");
-
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
-
-    Ok(())
+        .with_state(state)
 }
@@ -1,10 +1,18 @@
 //! Application state and storage for the web server.
 
+use crate::config::Config;
+use crate::midi::Note;
+use crate::preset::PRESET_TEMPO_RANGE;
+use crate::render::RenderOptions;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{RwLock, Semaphore};
+
+/// Audio renders the server runs at the same time.
+pub const RENDER_WORKERS: usize = 4;
 
 /// Application state shared across all request handlers.
 pub struct AppState {
@@ -16,6 +24,59 @@ pub struct AppState {
     pub storage_path: PathBuf,
     /// Directory for generated audio files.
     pub output_dir: PathBuf,
+    /// How long generated audio is kept.
+    pub retention: RetentionConfig,
+    /// The user's config file, for render settings.
+    pub config: Config,
+    /// Permits for in-process renders, shared by all requests.
+    pub render_workers: Semaphore,
+}
+
+/// Retention policy for files in the generated audio directory.
+#[derive(Clone, Debug)]
+pub struct RetentionConfig {
+    /// Files older than this are removed.
+    pub max_age: Duration,
+    /// Number of most recent generations kept per preset or melody.
+    pub keep_latest: usize,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::from_secs(7 * 24 * 60 * 60),
+            keep_latest: 5,
+        }
+    }
+}
+
+/// Where the server keeps saved data and generated audio. Unset paths fall
+/// back to the defaults described on each field.
+#[derive(Clone, Debug, Default)]
+pub struct StorageConfig {
+    /// Directory for storage and generated audio (default: ~/.midi-cli-rs
+    /// for storage, ./generated for audio).
+    pub data_dir: Option<PathBuf>,
+    /// Storage JSON file (default: `storage.json` in the data directory).
+    pub data_file: Option<PathBuf>,
+    /// Generated audio directory (default: `generated` in the data directory).
+    pub output_dir: Option<PathBuf>,
+}
+
+impl StorageConfig {
+    /// Storage file and generated audio directory.
+    pub fn resolve(&self) -> (PathBuf, PathBuf) {
+        let (config_dir, output_dir) = match &self.data_dir {
+            Some(dir) => (dir.clone(), dir.join("generated")),
+            None => (dirs_config_dir(), PathBuf::from("generated")),
+        };
+        (
+            self.data_file
+                .clone()
+                .unwrap_or_else(|| config_dir.join("storage.json")),
+            self.output_dir.clone().unwrap_or(output_dir),
+        )
+    }
 }
 
 impl AppState {
@@ -23,16 +84,28 @@ impl AppState {
     /// If data_dir is provided, use it for both storage and generated audio.
     /// Otherwise, use ~/.midi-cli-rs for storage and ./generated for audio.
     pub fn load_or_create(data_dir: Option<PathBuf>) -> Result<Arc<Self>, std::io::Error> {
-        let (config_dir, output_dir) = if let Some(dir) = data_dir {
-            let output = dir.join("generated");
-            (dir, output)
-        } else {
-            (dirs_config_dir(), PathBuf::from("generated"))
+        let storage = StorageConfig {
+            data_dir,
+            ..StorageConfig::default()
         };
-        std::fs::create_dir_all(&config_dir)?;
-        std::fs::create_dir_all(&output_dir)?;
+        Self::load_or_create_with(&storage, RetentionConfig::default(), Config::default())
+    }
 
-        let storage_path = config_dir.join("storage.json");
+    /// Load or create state with storage locations, a retention policy for
+    /// generated audio, and the user's config.
+    pub fn load_or_create_with(
+        storage: &StorageConfig,
+        retention: RetentionConfig,
+        config: Config,
+    ) -> Result<Arc<Self>, std::io::Error> {
+        let (storage_path, output_dir) = storage.resolve();
+        if let Some(parent) = storage_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::create_dir_all(&output_dir)?;
+        // Absolute, so the CLI runs that get `-o <output_dir>/...` don't
+        // resolve it against the config's `output_dir`
+        let output_dir = output_dir.canonicalize()?;
 
         let (presets, melodies) = if storage_path.exists() {
             let content = std::fs::read_to_string(&storage_path)?;
@@ -50,9 +123,41 @@ impl AppState {
             melodies: RwLock::new(melodies),
             storage_path,
             output_dir,
+            retention,
+            config,
+            render_workers: Semaphore::new(RENDER_WORKERS),
         }))
     }
 
+    /// Render settings for a preset trimmed to `duration` seconds, with the
+    /// config file's SoundFont, FluidSynth, gain and sample rate, as the CLI
+    /// would use them.
+    pub fn render_options(&self, duration: f64) -> RenderOptions {
+        let mut options = RenderOptions {
+            target_duration: Some(duration),
+            ..Default::default()
+        };
+        self.config.apply(&mut options);
+        options
+    }
+
+    /// Remove generated audio that the retention policy no longer keeps.
+    /// Failures are logged; returns the number of files removed.
+    pub fn cleanup_generated(&self) -> usize {
+        match cleanup_generated(&self.output_dir, &self.retention, SystemTime::now()) {
+            Ok(removed) => {
+                if !removed.is_empty() {
+                    eprintln!("[API] Removed {} old generated file(s)", removed.len());
+                }
+                removed.len()
+            }
+            Err(e) => {
+                eprintln!("[API ERROR] Failed to clean up generated audio: {}", e);
+                0
+            }
+        }
+    }
+
     /// Persist all data to disk.
     pub async fn save(&self) -> Result<(), std::io::Error> {
         let presets = self.presets.read().await;
@@ -67,6 +172,106 @@ impl AppState {
     }
 }
 
+/// File types written to the generated audio directory.
+const GENERATED_EXTENSIONS: &[&str] = &["wav", "mid", "mp3", "ogg"];
+
+/// File stem for a new generation of `item`: a `_YYYYMMDD_HHMMSS`
+/// timestamp plus a random suffix, so generations started in the same
+/// second (e.g. a POST and a stream of one preset) get files of their own.
+pub fn generated_stem(item: &str) -> String {
+    let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
+    format!("{}_{}-{:08x}", item, timestamp, rand::random::<u32>())
+}
+
+/// Preset or melody a generated file belongs to: the file stem without its
+/// `_YYYYMMDD_HHMMSS` timestamp and random suffix (e.g.
+/// `melody_<id>_20260101_120000-1a2b3c4d` -> `melody_<id>`).
+fn generated_item(stem: &str) -> &str {
+    if let Some((base, suffix)) = stem.rsplit_once('-')
+        && suffix.len() == 8
+        && suffix.bytes().all(|b| b.is_ascii_hexdigit())
+        && let Some(item) = strip_timestamp(base)
+    {
+        return item;
+    }
+    strip_timestamp(stem).unwrap_or(stem)
+}
+
+/// `stem` without its trailing `_YYYYMMDD_HHMMSS` timestamp, if it has one.
+fn strip_timestamp(stem: &str) -> Option<&str> {
+    let bytes = stem.as_bytes();
+    let len = bytes.len();
+    let is_timestamp = len > 16
+        && bytes[len - 16] == b'_'
+        && bytes[len - 7] == b'_'
+        && bytes[len - 15..len - 7].iter().all(u8::is_ascii_digit)
+        && bytes[len - 6..].iter().all(u8::is_ascii_digit);
+    is_timestamp.then(|| &stem[..len - 16])
+}
+
+/// Remove generated files older than `retention.max_age`, and all but the
+/// newest `retention.keep_latest` generations of each preset or melody.
+/// Files sharing a stem (the WAV and its MIDI) count as one generation.
+/// Returns the removed paths.
+pub fn cleanup_generated(
+    dir: &Path,
+    retention: &RetentionConfig,
+    now: SystemTime,
+) -> Result<Vec<PathBuf>, std::io::Error> {
+    // item -> stem -> (newest modification time, files)
+    let mut items: HashMap<String, HashMap<String, (SystemTime, Vec<PathBuf>)>> = HashMap::new();
+
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let is_generated = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| GENERATED_EXTENSIONS.contains(&e));
+        let (Some(stem), Ok(metadata)) =
+            (path.file_stem().and_then(|s| s.to_str()), entry.metadata())
+        else {
+            continue;
+        };
+        if !is_generated || !metadata.is_file() {
+            continue;
+        }
+
+        let modified = metadata.modified()?;
+        let generation = items
+            .entry(generated_item(stem).to_string())
+            .or_default()
+            .entry(stem.to_string())
+            .or_insert((modified, Vec::new()));
+        generation.0 = generation.0.max(modified);
+        generation.1.push(path);
+    }
+
+    let mut removed = Vec::new();
+    for generations in items.into_values() {
+        let mut generations: Vec<(SystemTime, Vec<PathBuf>)> = generations.into_values().collect();
+        generations.sort_by_key(|g| std::cmp::Reverse(g.0));
+
+        for (index, (modified, files)) in generations.into_iter().enumerate() {
+            let age = now.duration_since(modified).unwrap_or_default();
+            if index >= retention.keep_latest || age > retention.max_age {
+                for file in files {
+                    // A concurrent cleanup may have got there first; any
+                    // other failure shouldn't stop the rest being removed
+                    match std::fs::remove_file(&file) {
+                        Ok(()) => removed.push(file),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(e) => {
+                            eprintln!("[API ERROR] Failed to remove {}: {}", file.display(), e)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(removed)
+}
+
 /// Get the configuration directory for midi-cli-rs.
 fn dirs_config_dir() -> PathBuf {
     if let Some(home) = std::env::var_os("HOME") {
@@ -106,6 +311,30 @@ pub struct SavedPreset {
     pub last_generated: Option<String>,
 }
 
+impl SavedPreset {
+    /// The editable fields, for validating presets that didn't arrive as a request.
+    pub fn to_request(&self) -> PresetRequest {
+        PresetRequest {
+            name: self.name.clone(),
+            mood: self.mood.clone(),
+            duration: self.duration,
+            key: self.key.clone(),
+            intensity: self.intensity,
+            tempo: self.tempo,
+            seed: self.seed,
+        }
+    }
+}
+
+/// Whether an ID is safe to use in a file name: ASCII letters, digits, `-`
+/// and `_` only.
+pub fn is_safe_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
 /// Request body for creating/updating a preset.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PresetRequest {
@@ -133,6 +362,101 @@ impl PresetRequest {
             last_generated: None,
         }
     }
+
+    /// Check the numeric fields are in range. The mood is checked by the
+    /// handlers, which know about installed plugin moods.
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if !(MIN_DURATION..=MAX_DURATION).contains(&self.duration) {
+            errors.push(FieldError::new(
+                "duration",
+                format!(
+                    "must be between {} and {} seconds (got {})",
+                    MIN_DURATION, MAX_DURATION, self.duration
+                ),
+            ));
+        }
+        check_tempo(self.tempo, &mut errors);
+        if self.intensity > 100 {
+            errors.push(FieldError::new(
+                "intensity",
+                format!("must be between 0 and 100 (got {})", self.intensity),
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Shortest preset duration in seconds.
+pub const MIN_DURATION: f64 = 0.1;
+/// Longest preset duration in seconds.
+pub const MAX_DURATION: f64 = 300.0;
+
+fn check_tempo(tempo: u16, errors: &mut Vec<FieldError>) {
+    if !PRESET_TEMPO_RANGE.contains(&tempo) {
+        errors.push(FieldError::new(
+            "tempo",
+            format!(
+                "must be between {} and {} BPM (got {})",
+                PRESET_TEMPO_RANGE.start(),
+                PRESET_TEMPO_RANGE.end(),
+                tempo
+            ),
+        ));
+    }
+}
+
+/// A request field that failed validation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldError {
+    /// Field name, e.g. "tempo" or "notes[2].pitch".
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.field, self.message)
+    }
+}
+
+/// Query parameters for listing presets.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PresetListQuery {
+    /// Only include presets with this mood (case-insensitive).
+    pub mood: Option<String>,
+    /// Maximum number of presets to return (all if omitted).
+    pub limit: Option<usize>,
+    /// Number of presets to skip.
+    pub offset: Option<usize>,
+}
+
+/// One page of a filtered list, with the total before paging.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+}
+
+/// Query parameters for importing a preset library.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PresetImportQuery {
+    /// Overwrite presets with the same ID instead of importing them under a new one.
+    #[serde(default)]
+    pub replace: bool,
 }
 
 /// A single note or rest in a melody.
@@ -144,6 +468,9 @@ pub struct MelodyNote {
     pub duration: f64,
     /// Velocity 0-127 (0 for rests).
     pub velocity: u8,
+    /// Sounds with the previous note instead of after it, building a chord.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub chord: bool,
 }
 
 impl Default for MelodyNote {
@@ -152,6 +479,7 @@ impl Default for MelodyNote {
             pitch: "C4".to_string(),
             duration: 1.0,
             velocity: 80,
+            chord: false,
         }
     }
 }
@@ -209,6 +537,35 @@ impl MelodyRequest {
             last_generated: None,
         }
     }
+
+    /// Check the melody has notes, each with a parseable pitch (or "rest")
+    /// and a positive duration, and a tempo in range.
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if self.notes.is_empty() {
+            errors.push(FieldError::new("notes", "must contain at least one note"));
+        }
+        for (i, note) in self.notes.iter().enumerate() {
+            if note.pitch != "rest" && Note::parse_pitch(&note.pitch).is_err() {
+                errors.push(FieldError::new(
+                    format!("notes[{}].pitch", i),
+                    format!("is not a note name like C4 or F#3 (got {:?})", note.pitch),
+                ));
+            }
+            if note.duration.is_nan() || note.duration <= 0.0 {
+                errors.push(FieldError::new(
+                    format!("notes[{}].duration", i),
+                    format!("must be positive (got {})", note.duration),
+                ));
+            }
+        }
+        check_tempo(self.tempo, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
 }
 
 /// Response containing a generated audio file path.
@@ -219,6 +576,68 @@ pub struct GenerateResponse {
     pub generated_at: String,
 }
 
+/// Request body for generating several presets at once.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BatchGenerateRequest {
+    pub preset_ids: Vec<String>,
+}
+
+/// Outcome of one preset in a batch generation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BatchItemResult {
+    Ok {
+        audio_url: String,
+        generated_at: String,
+    },
+    Error {
+        error: String,
+    },
+}
+
+/// Progress of a streamed preset generation, sent as server-sent events.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum GenerationEvent {
+    Started {
+        preset_id: String,
+    },
+    MidiWritten,
+    Rendering,
+    Done {
+        preset_id: String,
+        audio_url: String,
+        generated_at: String,
+    },
+    /// Named "failed" so it can't be confused with EventSource's own error event.
+    Failed {
+        error: String,
+    },
+}
+
+impl GenerationEvent {
+    /// SSE event name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Started { .. } => "started",
+            Self::MidiWritten => "midi_written",
+            Self::Rendering => "rendering",
+            Self::Done { .. } => "done",
+            Self::Failed { .. } => "failed",
+        }
+    }
+}
+
+/// Response for the health check: which rendering dependencies are present.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HealthResponse {
+    /// "ok" when audio can be rendered, "degraded" when only MIDI can be produced.
+    pub status: String,
+    pub fluidsynth: bool,
+    pub soundfont: Option<PathBuf>,
+    pub ffmpeg: bool,
+}
+
 /// Error response body.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ErrorResponse {
@@ -237,3 +656,245 @@ pub struct AbcImportRequest {
     /// Optional tempo override (uses Q: field or 120 if omitted)
     pub tempo: Option<u16>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn touch(dir: &Path, name: &str, modified: SystemTime) {
+        File::create(dir.join(name))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    fn preset_request() -> PresetRequest {
+        PresetRequest {
+            name: "Intro".to_string(),
+            mood: "calm".to_string(),
+            duration: 5.0,
+            key: None,
+            intensity: 50,
+            tempo: 90,
+            seed: 1,
+        }
+    }
+
+    fn melody_request(notes: &[(&str, f64)]) -> MelodyRequest {
+        MelodyRequest {
+            name: "Tune".to_string(),
+            notes: notes
+                .iter()
+                .map(|&(pitch, duration)| MelodyNote {
+                    pitch: pitch.to_string(),
+                    duration,
+                    velocity: 80,
+                    chord: false,
+                })
+                .collect(),
+            key: "C".to_string(),
+            tempo: 120,
+            instrument: "piano".to_string(),
+            attack: 0,
+            decay: 64,
+        }
+    }
+
+    fn invalid_fields(result: Result<(), Vec<FieldError>>) -> Vec<String> {
+        result.unwrap_err().into_iter().map(|e| e.field).collect()
+    }
+
+    #[test]
+    fn test_preset_request_in_range_is_valid() {
+        assert!(preset_request().validate().is_ok());
+        let edges = PresetRequest {
+            duration: 0.1,
+            tempo: 300,
+            intensity: 100,
+            ..preset_request()
+        };
+        assert!(edges.validate().is_ok());
+    }
+
+    #[test]
+    fn test_preset_request_duration_out_of_range() {
+        for duration in [-5.0, 0.0, 0.05, 300.5, 1e7, f64::NAN] {
+            let req = PresetRequest {
+                duration,
+                ..preset_request()
+            };
+            assert_eq!(
+                invalid_fields(req.validate()),
+                ["duration"],
+                "duration {}",
+                duration
+            );
+        }
+    }
+
+    #[test]
+    fn test_preset_request_tempo_out_of_range() {
+        for tempo in [0, 19, 301] {
+            let req = PresetRequest {
+                tempo,
+                ..preset_request()
+            };
+            assert_eq!(invalid_fields(req.validate()), ["tempo"], "tempo {}", tempo);
+        }
+    }
+
+    #[test]
+    fn test_preset_request_intensity_out_of_range() {
+        let req = PresetRequest {
+            intensity: 101,
+            ..preset_request()
+        };
+        let errors = req.validate().unwrap_err();
+        assert_eq!(
+            errors,
+            [FieldError::new(
+                "intensity",
+                "must be between 0 and 100 (got 101)"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_preset_request_reports_every_field() {
+        let req = PresetRequest {
+            duration: -5.0,
+            tempo: 0,
+            intensity: 200,
+            ..preset_request()
+        };
+        assert_eq!(
+            invalid_fields(req.validate()),
+            ["duration", "tempo", "intensity"]
+        );
+    }
+
+    #[test]
+    fn test_melody_request_validation() {
+        assert!(
+            melody_request(&[("C4", 1.0), ("rest", 0.5), ("F#3", 0.5)])
+                .validate()
+                .is_ok()
+        );
+        assert_eq!(invalid_fields(melody_request(&[]).validate()), ["notes"]);
+        assert_eq!(
+            invalid_fields(melody_request(&[("C4", 1.0), ("H9", 1.0), ("D4", 0.0)]).validate()),
+            ["notes[1].pitch", "notes[2].duration"]
+        );
+
+        let slow = MelodyRequest {
+            tempo: 10,
+            ..melody_request(&[("C4", 1.0)])
+        };
+        assert_eq!(invalid_fields(slow.validate()), ["tempo"]);
+    }
+
+    #[test]
+    fn test_generated_item_strips_timestamp() {
+        assert_eq!(generated_item("abc-123_20260101_120000"), "abc-123");
+        assert_eq!(
+            generated_item("abc-123_20260101_120000-1a2b3c4d"),
+            "abc-123"
+        );
+        assert_eq!(generated_item(&generated_stem("melody_abc")), "melody_abc");
+        assert_eq!(generated_item("abc-1a2b3c4d"), "abc-1a2b3c4d");
+        assert_eq!(generated_item("melody_abc_20260101_120000"), "melody_abc");
+        assert_eq!(generated_item("abc-123"), "abc-123");
+        assert_eq!(generated_item("take_2"), "take_2");
+    }
+
+    #[test]
+    fn test_relative_output_dir_made_absolute() {
+        let temp = tempfile::tempdir().unwrap();
+        let relative = PathBuf::from("target").join(format!("state-test-{}", std::process::id()));
+        let storage = StorageConfig {
+            data_file: Some(temp.path().join("storage.json")),
+            output_dir: Some(relative.clone()),
+            ..StorageConfig::default()
+        };
+        let state =
+            AppState::load_or_create_with(&storage, RetentionConfig::default(), Config::default())
+                .unwrap();
+        let _ = std::fs::remove_dir(&relative);
+        assert!(state.output_dir.is_absolute());
+        assert!(state.output_dir.ends_with(&relative));
+    }
+
+    #[test]
+    fn test_render_options_use_config() {
+        let temp = tempfile::tempdir().unwrap();
+        let storage = StorageConfig {
+            data_dir: Some(temp.path().to_path_buf()),
+            ..StorageConfig::default()
+        };
+        let config = Config {
+            soundfont: Some(PathBuf::from("/sf/custom.sf2")),
+            gain: Some(0.5),
+            ..Config::default()
+        };
+        let state =
+            AppState::load_or_create_with(&storage, RetentionConfig::default(), config).unwrap();
+
+        let options = state.render_options(7.5);
+        assert_eq!(options.target_duration, Some(7.5));
+        assert_eq!(options.soundfont, Some(PathBuf::from("/sf/custom.sf2")));
+        assert_eq!(options.gain, Some(0.5));
+    }
+
+    #[test]
+    fn test_cleanup_removes_expired_and_keeps_recent() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let now = SystemTime::now();
+        let retention = RetentionConfig {
+            max_age: 24 * HOUR,
+            keep_latest: 2,
+        };
+
+        // Preset a: three recent generations (WAV + MIDI), only two are kept
+        touch(dir, "a_20260101_100000.wav", now - 3 * HOUR);
+        touch(dir, "a_20260101_100000.mid", now - 3 * HOUR);
+        touch(dir, "a_20260101_110000.wav", now - 2 * HOUR);
+        touch(dir, "a_20260101_120000.wav", now - HOUR);
+        // Preset b: newest generation but past the TTL
+        touch(dir, "b_20251201_120000.wav", now - 48 * HOUR);
+        // Unrelated files are never touched
+        touch(dir, "notes.txt", now - 100 * HOUR);
+
+        let mut removed: Vec<String> = cleanup_generated(dir, &retention, now)
+            .unwrap()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        removed.sort();
+        assert_eq!(
+            removed,
+            [
+                "a_20260101_100000.mid",
+                "a_20260101_100000.wav",
+                "b_20251201_120000.wav"
+            ]
+        );
+
+        let mut left: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                "a_20260101_110000.wav",
+                "a_20260101_120000.wav",
+                "notes.txt"
+            ]
+        );
+    }
+}
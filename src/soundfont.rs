@@ -0,0 +1,312 @@
+//! SoundFont download cache
+//!
+//! `--fetch-soundfont` downloads a SoundFont once, verifies it against its
+//! SHA-256, and stores it in the user cache directory, where
+//! [`find_soundfont`](crate::render::find_soundfont) picks it up on later
+//! runs. The build's default FluidR3_GM is used unless `--soundfont-url` and
+//! `--soundfont-sha256` name another. Downloads go through `curl`, like the
+//! other external tools, and can be swapped out in tests.
+
+use crate::render::temp_path;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use thiserror::Error;
+
+/// File name of the cached SoundFont inside the cache directory
+pub const CACHED_SOUNDFONT: &str = "default.sf2";
+
+/// URL and SHA-256 of the MIT-licensed FluidR3_GM SoundFont that
+/// `--fetch-soundfont` downloads when no `--soundfont-url` is given.
+// Left unset until a verified download and its published digest are pinned
+// here; builds without it need `--soundfont-url`/`--soundfont-sha256`.
+const FLUIDR3_GM: Option<(&str, &str)> = None;
+
+/// SoundFont fetched by default: [`FLUIDR3_GM`], unless the build sets
+/// `MIDI_CLI_SOUNDFONT_URL` and `MIDI_CLI_SOUNDFONT_SHA256` to point at a
+/// mirror the packager trusts
+pub const DEFAULT_SOUNDFONT: Option<(&str, &str)> = match (
+    option_env!("MIDI_CLI_SOUNDFONT_URL"),
+    option_env!("MIDI_CLI_SOUNDFONT_SHA256"),
+) {
+    (Some(url), Some(sha256)) => Some((url, sha256)),
+    (None, None) => FLUIDR3_GM,
+    _ => panic!("set both MIDI_CLI_SOUNDFONT_URL and MIDI_CLI_SOUNDFONT_SHA256, or neither"),
+};
+
+/// Errors that can occur when fetching a SoundFont
+#[derive(Debug, Error)]
+pub enum SoundFontError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("No cache directory: set HOME or XDG_CACHE_HOME")]
+    NoCacheDir,
+
+    #[error("No default SoundFont in this build: pass --soundfont-url URL --soundfont-sha256 HEX")]
+    NoDefaultSoundFont,
+
+    #[error("--soundfont-sha256 is required to fetch {0}")]
+    MissingChecksum(String),
+
+    #[error("Invalid SHA-256 checksum: {0} (expected 64 hex digits)")]
+    InvalidChecksum(String),
+
+    #[error("curl not found (required to download SoundFonts)")]
+    CurlNotFound,
+
+    #[error("Download failed: {0}")]
+    DownloadFailed(String),
+
+    #[error("Checksum mismatch for downloaded SoundFont: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+}
+
+/// Downloads `url` to a file path
+pub type Downloader = fn(&str, &Path) -> Result<(), SoundFontError>;
+
+/// Cache directory: `$XDG_CACHE_HOME/midi-cli-rs`, else `~/.cache/midi-cli-rs`
+pub fn cache_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("midi-cli-rs"))
+}
+
+/// The cached SoundFont in `dir`, if one has been fetched
+pub fn cached_soundfont(dir: &Path) -> Option<PathBuf> {
+    Some(dir.join(CACHED_SOUNDFONT)).filter(|path| path.is_file())
+}
+
+/// Hex SHA-256 of a file
+pub fn sha256_file(path: &Path) -> Result<String, SoundFontError> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// URL and SHA-256 to fetch: the given ones override `default`, but a URL
+/// other than the default's needs its own checksum
+pub fn soundfont_source(
+    url: Option<&str>,
+    sha256: Option<&str>,
+    default: Option<(&str, &str)>,
+) -> Result<(String, String), SoundFontError> {
+    let (url, sha256) = match (url, sha256, default) {
+        (Some(url), Some(sha256), _) => (url, sha256),
+        (None, Some(sha256), Some((url, _))) => (url, sha256),
+        (Some(url), None, Some((default_url, sha256))) if url == default_url => (url, sha256),
+        (Some(url), None, _) => return Err(SoundFontError::MissingChecksum(url.to_string())),
+        (None, _, Some(default)) => default,
+        (None, _, None) => return Err(SoundFontError::NoDefaultSoundFont),
+    };
+    Ok((url.to_string(), sha256.to_string()))
+}
+
+/// Make sure the SoundFont from `url` is in `dir`, downloading it only if
+/// the cached copy is missing or doesn't match `sha256`. The download is
+/// verified before it replaces the cached file.
+pub fn fetch_soundfont(
+    dir: &Path,
+    url: &str,
+    sha256: &str,
+    download: Downloader,
+) -> Result<PathBuf, SoundFontError> {
+    let expected = sha256.trim().to_lowercase();
+    if expected.len() != 64 || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(SoundFontError::InvalidChecksum(sha256.to_string()));
+    }
+
+    let path = dir.join(CACHED_SOUNDFONT);
+    if path.is_file() && sha256_file(&path)? == expected {
+        return Ok(path);
+    }
+
+    std::fs::create_dir_all(dir)?;
+    let partial = temp_path(&path, "download");
+    let result = download(url, &partial).and_then(|()| {
+        let actual = sha256_file(&partial)?;
+        if actual != expected {
+            return Err(SoundFontError::ChecksumMismatch {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+        std::fs::rename(&partial, &path)?;
+        Ok(path)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Download with `curl`, failing on HTTP errors and following redirects
+pub fn curl_download(url: &str, path: &Path) -> Result<(), SoundFontError> {
+    let output = Command::new("curl")
+        .args([
+            "--fail",
+            "--location",
+            "--silent",
+            "--show-error",
+            "--output",
+        ])
+        .arg(path)
+        .arg(url)
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => SoundFontError::CurlNotFound,
+            _ => SoundFontError::Io(e),
+        })?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(SoundFontError::DownloadFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const CONTENT: &[u8] = b"RIFF fake soundfont";
+
+    /// SHA-256 of CONTENT
+    fn content_sha256() -> String {
+        Sha256::digest(CONTENT)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn fake_download(_url: &str, path: &Path) -> Result<(), SoundFontError> {
+        std::fs::write(path, CONTENT)?;
+        Ok(())
+    }
+
+    fn no_download(_url: &str, _path: &Path) -> Result<(), SoundFontError> {
+        Err(SoundFontError::DownloadFailed(
+            "should not download".to_string(),
+        ))
+    }
+
+    #[test]
+    fn test_fetch_downloads_once_then_uses_cache() {
+        let dir = tempdir().unwrap();
+        assert_eq!(cached_soundfont(dir.path()), None);
+
+        let first = fetch_soundfont(
+            dir.path(),
+            "https://example.invalid/sf.sf2",
+            &content_sha256(),
+            fake_download,
+        )
+        .unwrap();
+        assert_eq!(cached_soundfont(dir.path()), Some(first.clone()));
+        assert_eq!(std::fs::read(&first).unwrap(), CONTENT);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // The second call is served from the cache
+        let second = fetch_soundfont(
+            dir.path(),
+            "https://example.invalid/sf.sf2",
+            &content_sha256(),
+            no_download,
+        )
+        .unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_checksum_mismatch_leaves_no_file() {
+        let dir = tempdir().unwrap();
+        let wrong = "0".repeat(64);
+        let err = fetch_soundfont(
+            dir.path(),
+            "https://example.invalid/sf.sf2",
+            &wrong,
+            fake_download,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, SoundFontError::ChecksumMismatch { .. }));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_existing_copy_accepted_case_insensitively() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join(CACHED_SOUNDFONT), CONTENT).unwrap();
+        let path = fetch_soundfont(
+            dir.path(),
+            "https://example.invalid/sf.sf2",
+            &content_sha256().to_uppercase(),
+            no_download,
+        )
+        .unwrap();
+        assert_eq!(path, dir.path().join(CACHED_SOUNDFONT));
+    }
+
+    #[test]
+    fn test_soundfont_source_overrides_default() {
+        let default = Some(("https://example.invalid/FluidR3_GM.sf2", "aa"));
+        let source = |url, sha256| soundfont_source(url, sha256, default);
+
+        assert_eq!(
+            source(None, None).unwrap(),
+            ("https://example.invalid/FluidR3_GM.sf2".into(), "aa".into())
+        );
+        assert_eq!(source(None, Some("bb")).unwrap().1, "bb");
+        assert_eq!(
+            source(Some("https://example.invalid/FluidR3_GM.sf2"), None)
+                .unwrap()
+                .1,
+            "aa"
+        );
+        assert_eq!(
+            source(Some("https://example.invalid/other.sf2"), Some("cc"))
+                .unwrap()
+                .1,
+            "cc"
+        );
+        assert!(matches!(
+            source(Some("https://example.invalid/other.sf2"), None),
+            Err(SoundFontError::MissingChecksum(_))
+        ));
+        assert!(matches!(
+            soundfont_source(None, None, None),
+            Err(SoundFontError::NoDefaultSoundFont)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_checksum_rejected() {
+        let dir = tempdir().unwrap();
+        let err = fetch_soundfont(
+            dir.path(),
+            "https://example.invalid/sf.sf2",
+            "abc",
+            fake_download,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SoundFontError::InvalidChecksum(_)));
+    }
+
+    #[test]
+    fn test_default_soundfont_is_pinned_to_https_and_sha256() {
+        if let Some((url, sha256)) = DEFAULT_SOUNDFONT {
+            assert!(url.starts_with("https://"), "{url}");
+            assert_eq!(sha256.len(), 64, "{sha256}");
+            assert!(sha256.chars().all(|c| c.is_ascii_hexdigit()), "{sha256}");
+        }
+    }
+}
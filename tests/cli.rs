@@ -0,0 +1,880 @@
+//! End-to-end tests that run the CLI binary
+
+use std::process::Command;
+
+fn cli() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_midi-cli-rs"))
+}
+
+#[test]
+fn test_generate_to_stdout_with_dash() {
+    let output = cli()
+        .args(["generate", "--notes", "C4:1:80,E4:1:80", "-o", "-"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stdout.starts_with(b"MThd"));
+    assert_eq!(
+        midi_cli_rs::parse_midi(&output.stdout).unwrap()[0]
+            .notes
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn test_preset_to_stdout_flag() {
+    let output = cli()
+        .args(["preset", "-m", "calm", "-d", "3", "--stdout"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stdout.starts_with(b"MThd"));
+}
+
+#[test]
+fn test_stdout_rejects_stems() {
+    let output = cli()
+        .args(["preset", "-m", "calm", "--stems", "-o", "-"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_preset_rejects_out_of_range_settings() {
+    for (flag, value, message) in [
+        ("--tempo", "0", "Tempo must be 20-300"),
+        ("--intensity", "150", "Intensity must be 0-100"),
+    ] {
+        let output = cli()
+            .args(["preset", "-m", "calm", flag, value, "--stdout"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains(message),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn test_seeds_rejects_single_file_options() {
+    // Run in a tempdir so an option that slips through can't write a batch
+    // into the source tree
+    let temp = tempfile::tempdir().unwrap();
+    for extra in [
+        &["--loops", "3"][..],
+        &["--pickup", "1"],
+        &["--pad-start", "1"],
+        &["--pad-end", "1"],
+        &["--legato-fix"],
+    ] {
+        let output = cli()
+            .current_dir(temp.path())
+            .args(["preset", "-m", "calm", "--seeds", "1-2", "-o", "batch.mid"])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{extra:?} accepted");
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("cannot be used with"),
+            "{extra:?}"
+        );
+    }
+}
+
+#[test]
+fn test_generate_from_json_file() {
+    let temp = tempfile::tempdir().unwrap();
+    let json_path = temp.path().join("song.json");
+    let midi_path = temp.path().join("song.mid");
+    std::fs::write(
+        &json_path,
+        r#"{"tempo": 90, "tracks": [
+            {"instrument": "strings", "notes": [{"pitch": "C3", "duration": 4, "velocity": 50, "offset": 0}]},
+            {"instrument": "piano", "channel": 1, "name": "Melody", "notes": [
+                {"pitch": "C4", "duration": 0.5, "velocity": 80, "offset": 0},
+                {"pitch": "E4", "duration": 0.5, "velocity": 80, "offset": 0.5}
+            ]}
+        ]}"#,
+    )
+    .unwrap();
+
+    let output = cli()
+        .args(["generate", "--json-file"])
+        .arg(&json_path)
+        .arg("-o")
+        .arg(&midi_path)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let sequences = midi_cli_rs::read_midi(&midi_path).unwrap();
+    let summary: Vec<(u8, usize, u16)> = sequences
+        .iter()
+        .map(|s| (s.instrument, s.notes.len(), s.tempo))
+        .collect();
+    assert_eq!(summary, [(48, 1, 90), (0, 2, 90)]);
+    assert_eq!(sequences[1].name.as_deref(), Some("Melody"));
+}
+
+#[test]
+fn test_json_file_errors_name_the_file() {
+    let temp = tempfile::tempdir().unwrap();
+    let missing = temp.path().join("missing.json");
+    let output = cli()
+        .args(["generate", "--json-file"])
+        .arg(&missing)
+        .args(["-o", "-"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Cannot read JSON file"));
+
+    let broken = temp.path().join("broken.json");
+    std::fs::write(&broken, "{\"tracks\": [").unwrap();
+    let output = cli()
+        .args(["generate", "--json-file"])
+        .arg(&broken)
+        .args(["-o", "-"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid JSON in"));
+}
+
+#[test]
+fn test_bash_completions_list_subcommands() {
+    let output = cli().args(["completions", "bash"]).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let script = String::from_utf8(output.stdout).unwrap();
+    for subcommand in ["generate", "preset", "render", "info", "import"] {
+        assert!(script.contains(subcommand), "missing {subcommand}");
+    }
+}
+
+#[test]
+fn test_generate_loops() {
+    let output = cli()
+        .args([
+            "generate",
+            "--notes",
+            "C4:1:80,E4:1:80@1",
+            "--loops",
+            "3",
+            "-o",
+            "-",
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let notes = &midi_cli_rs::parse_midi(&output.stdout).unwrap()[0].notes;
+    let offsets: Vec<f64> = notes.iter().map(|n| n.offset).collect();
+    assert_eq!(offsets, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+}
+
+#[test]
+fn test_generate_pickup_lands_downbeat_on_bar_one() {
+    let output = cli()
+        .args([
+            "generate",
+            "--notes",
+            "G4:1:80,C5:2:90@1",
+            "--pickup",
+            "1",
+            "-o",
+            "-",
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let notes = &midi_cli_rs::parse_midi(&output.stdout).unwrap()[0].notes;
+    let offsets: Vec<f64> = notes.iter().map(|n| n.offset).collect();
+    // Bar 0 holds three beats of rest and the pickup; C5 starts bar 1
+    assert_eq!(offsets, [3.0, 4.0]);
+}
+
+#[test]
+fn test_generate_copyright_and_markers() {
+    let output = cli()
+        .args([
+            "generate",
+            "--notes",
+            "C4:4:80",
+            "--copyright",
+            "--marker",
+            "0:Intro",
+            "--marker",
+            "2:Hit",
+            "-o",
+            "-",
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let smf = midly::Smf::parse(&output.stdout).unwrap();
+    let texts: Vec<String> = smf.tracks[0]
+        .iter()
+        .filter_map(|e| match e.kind {
+            midly::TrackEventKind::Meta(
+                midly::MetaMessage::Copyright(text) | midly::MetaMessage::Marker(text),
+            ) => Some(String::from_utf8_lossy(text).into_owned()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(texts.len(), 3);
+    assert!(texts[0].starts_with("Copyright"), "{texts:?}");
+    assert_eq!(texts[1..], ["Intro", "Hit"]);
+}
+
+#[test]
+fn test_structure_markers_in_every_stem() {
+    let temp = tempfile::tempdir().unwrap();
+    let output = cli()
+        .args([
+            "preset",
+            "-m",
+            "calm",
+            "--structure",
+            "intro:1,main:2",
+            "--stems",
+            "-o",
+        ])
+        .arg(temp.path().join("song.mid"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let layer = std::fs::read(temp.path().join("song_layer1.mid")).unwrap();
+    let smf = midly::Smf::parse(&layer).unwrap();
+    let markers: Vec<String> = smf.tracks[0]
+        .iter()
+        .filter_map(|e| match e.kind {
+            midly::TrackEventKind::Meta(midly::MetaMessage::Marker(text)) => {
+                Some(String::from_utf8_lossy(text).into_owned())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(markers.len(), 2, "{markers:?}");
+}
+
+#[test]
+fn test_stats_json() {
+    let temp = tempfile::tempdir().unwrap();
+    let midi_path = temp.path().join("chord.mid");
+    let output = cli()
+        .args([
+            "generate",
+            "--notes",
+            "C4:2:80@0,E4:2:60@0,G4:2:100@0,C5:1:80@2",
+            "-t",
+            "60",
+            "-o",
+        ])
+        .arg(&midi_path)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = cli()
+        .arg("stats")
+        .arg(&midi_path)
+        .arg("--json")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["notes"], 4);
+    assert_eq!(stats["pitch_range"], serde_json::json!([60, 72]));
+    assert_eq!(stats["peak_polyphony"], 3);
+    assert_eq!(stats["duration_secs"], 3.0);
+}
+
+#[test]
+fn test_info_json() {
+    let temp = tempfile::tempdir().unwrap();
+    let midi_path = temp.path().join("melody.mid");
+    let output = cli()
+        .args([
+            "generate",
+            "--notes",
+            "C4:1:80,E4:1:80@1",
+            "-t",
+            "100",
+            "-o",
+        ])
+        .arg(&midi_path)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = cli()
+        .arg("info")
+        .arg(&midi_path)
+        .arg("--json")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(info["format"], "parallel");
+    assert_eq!(info["tempo"], 100);
+    assert_eq!(info["notes"], 2);
+    assert_eq!(info["channels"][0]["beats"], 2.0);
+}
+
+#[test]
+fn test_compare() {
+    let temp = tempfile::tempdir().unwrap();
+    let write = |name: &str, notes: &str| {
+        let path = temp.path().join(name);
+        let output = cli()
+            .args(["generate", "--notes", notes, "-o"])
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        path
+    };
+    let original = write("a.mid", "C4:1:80,E4:1:80@1,G4:1:80@2");
+    let transposed = write("b.mid", "D4:1:80,F#4:1:80@1,A4:1:80@2");
+
+    let output = cli()
+        .arg("compare")
+        .arg(&original)
+        .arg(&original)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Identical: 3 note events"));
+
+    let output = cli()
+        .arg("compare")
+        .arg(&original)
+        .arg(&transposed)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("First difference at event 0: pitch 60 -> 62"),
+        "{stdout}"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("3 of 3 note events"));
+}
+
+#[test]
+fn test_preset_dry_run_skips_rendering() {
+    let temp = tempfile::tempdir().unwrap();
+    let wav_path = temp.path().join("intro.wav");
+    let output = cli()
+        .args([
+            "preset",
+            "-m",
+            "jazz",
+            "-d",
+            "4",
+            "--soundfont",
+            "/nonexistent.sf2",
+            "--dry-run",
+            "-o",
+        ])
+        .arg(&wav_path)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["mood"], "jazz");
+    assert!(!summary["layers"].as_array().unwrap().is_empty());
+    assert!(summary["midi"].is_null());
+    assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_generate_drums_on_channel_nine() {
+    let output = cli()
+        .args([
+            "generate",
+            "--drums",
+            "--notes",
+            "kick:1:100,snare:1:90@1",
+            "-o",
+            "-",
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let smf = midly::Smf::parse(&output.stdout).unwrap();
+    let mut channels = Vec::new();
+    for event in smf.tracks.iter().flatten() {
+        if let midly::TrackEventKind::Midi { channel, message } = event.kind {
+            assert!(
+                !matches!(message, midly::MidiMessage::ProgramChange { .. }),
+                "{message:?}"
+            );
+            channels.push(channel.as_int());
+        }
+    }
+    assert!(!channels.is_empty());
+    assert!(channels.iter().all(|&c| c == 9));
+
+    let pitches: Vec<u8> = midi_cli_rs::parse_midi(&output.stdout).unwrap()[0]
+        .notes
+        .iter()
+        .map(|n| n.pitch)
+        .collect();
+    assert_eq!(pitches, [36, 38]);
+}
+
+#[test]
+fn test_config_file_defaults() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = temp.path().join("config.toml");
+    std::fs::write(
+        &config,
+        format!("tempo = 100\noutput_dir = {:?}\n", temp.path()),
+    )
+    .unwrap();
+
+    let output = cli()
+        .args(["generate", "--notes", "C4:1:80", "-o", "a.mid", "--config"])
+        .arg(&config)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        midi_cli_rs::read_midi(&temp.path().join("a.mid")).unwrap()[0].tempo,
+        100
+    );
+
+    // Flags win over the file
+    let output = cli()
+        .args([
+            "generate", "--notes", "C4:1:80", "-t", "80", "-o", "b.mid", "--config",
+        ])
+        .arg(&config)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        midi_cli_rs::read_midi(&temp.path().join("b.mid")).unwrap()[0].tempo,
+        80
+    );
+
+    let missing = temp.path().join("missing.toml");
+    let output = cli()
+        .args(["generate", "--notes", "C4:1:80", "-o", "-", "--config"])
+        .arg(&missing)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Cannot read config file"));
+}
+
+#[test]
+fn test_preset_output_template() {
+    let temp = tempfile::tempdir().unwrap();
+    let output = cli()
+        .args(["preset", "-m", "calm", "-d", "2", "--seed", "7", "-o"])
+        .arg(temp.path().join("out_{mood}_{seed}.wav"))
+        // No FluidSynth: the MIDI file is written before the render fails
+        .env(
+            "MIDI_CLI_FLUIDSYNTH",
+            temp.path().join("missing-fluidsynth"),
+        )
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("out_calm_7.mid"), "{stderr}");
+    // The MIDI goes next to the expanded WAV path
+    let files: Vec<_> = std::fs::read_dir(temp.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert_eq!(files, ["out_calm_7.mid"]);
+
+    let output = cli()
+        .args([
+            "preset",
+            "-m",
+            "calm",
+            "-d",
+            "2",
+            "--seed",
+            "7",
+            "--seed-in-filename",
+            "-o",
+        ])
+        .arg(temp.path().join("take.mid"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(temp.path().join("take_7.mid").is_file());
+}
+
+#[test]
+fn test_preset_sidecar_records_random_seed() {
+    let temp = tempfile::tempdir().unwrap();
+    let midi_path = temp.path().join("take.mid");
+    let output = cli()
+        .args([
+            "preset",
+            "-m",
+            "jazz",
+            "-d",
+            "3",
+            "--seed",
+            "0",
+            "--sidecar",
+            "-o",
+        ])
+        .arg(&midi_path)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let sidecar: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(temp.path().join("take.json")).unwrap()).unwrap();
+    let seed = sidecar["seed"].as_u64().unwrap();
+    assert!(seed > 0);
+    assert!(String::from_utf8_lossy(&output.stderr).contains(&format!("seed: {seed}")));
+    assert_eq!(sidecar["mood"], "jazz");
+    assert_eq!(sidecar["version"], env!("CARGO_PKG_VERSION"));
+    assert!(
+        sidecar["command"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|arg| arg == "--sidecar")
+    );
+}
+
+#[test]
+fn test_generate_check_warns_on_overrun() {
+    // Four beats at 60 BPM last four seconds
+    let run = |duration: &str| {
+        let output = cli()
+            .args([
+                "generate",
+                "--notes",
+                "C4:4:80",
+                "-t",
+                "60",
+                "--duration",
+                duration,
+                "--check",
+                "-o",
+                "-",
+            ])
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+    let stderr = run("3");
+    assert!(
+        stderr.contains("Warning: layer 1 (piano) ends at 4.00s, 1.00s past the requested 3.00s"),
+        "{stderr}"
+    );
+    assert!(!run("4").contains("Warning"));
+}
+
+#[test]
+fn test_padding_shifts_notes_and_extends_clip() {
+    let temp = tempfile::tempdir().unwrap();
+    let midi_path = temp.path().join("padded.mid");
+    let status = cli()
+        .args([
+            "generate",
+            "--notes",
+            "C4:1:80",
+            "-t",
+            "120",
+            "--pad-start",
+            "0.5",
+            "-o",
+        ])
+        .arg(&midi_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    // Half a second is one beat at 120 BPM
+    assert_eq!(
+        midi_cli_rs::read_midi(&midi_path).unwrap()[0].notes[0].offset,
+        1.0
+    );
+
+    let output = cli()
+        .args([
+            "preset",
+            "-m",
+            "calm",
+            "-d",
+            "5",
+            "--seed",
+            "1",
+            "--pad-start",
+            "0.5",
+            "--pad-end",
+            "1",
+        ])
+        .args(["--dry-run", "-o", "padded.wav"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["duration_secs"], 6.5);
+}
+
+#[test]
+fn test_stitch_crossfades_wav_files() {
+    use midi_cli_rs::audio::{WavData, read_wav, write_wav};
+
+    let temp = tempfile::tempdir().unwrap();
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 8000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    write_wav(
+        &temp.path().join("a.wav"),
+        &WavData {
+            spec,
+            samples: vec![0.5; 16000],
+        },
+    )
+    .unwrap();
+    write_wav(
+        &temp.path().join("b.wav"),
+        &WavData {
+            spec,
+            samples: vec![0.25; 24000],
+        },
+    )
+    .unwrap();
+
+    let status = cli()
+        .current_dir(temp.path())
+        .args([
+            "stitch",
+            "--crossfade",
+            "0.5",
+            "a.wav",
+            "b.wav",
+            "-o",
+            "combined.wav",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    // 2s + 3s - 0.5s overlap
+    let joined = read_wav(&temp.path().join("combined.wav")).unwrap();
+    assert_eq!(joined.samples.len(), 4 * 8000 + 4000);
+}
+
+#[test]
+fn test_markov_melody_reproducible_by_seed() {
+    let run = |seed: &str| {
+        let output = cli()
+            .args([
+                "generate", "--markov", "--key", "Am", "--length", "12", "--seed", seed, "-o", "-",
+            ])
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output.stdout
+    };
+    assert_eq!(run("9"), run("9"));
+    assert_ne!(run("9"), run("10"));
+}
+
+#[test]
+fn test_legato_fix_shortens_same_pitch_overlap() {
+    let temp = tempfile::tempdir().unwrap();
+    let run = |fix: bool, name: &str| {
+        let path = temp.path().join(name);
+        let mut command = cli();
+        command
+            .args(["generate", "--notes", "C4:2:80,C4:1:80@1,E4:3:80", "-o"])
+            .arg(&path);
+        if fix {
+            command.arg("--legato-fix");
+        }
+        let output = command.output().unwrap();
+        assert!(output.status.success());
+        let durations: Vec<f64> = midi_cli_rs::read_midi(&path).unwrap()[0]
+            .notes
+            .iter()
+            .map(|n| n.duration)
+            .collect();
+        (
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+            durations,
+        )
+    };
+
+    let (stderr, durations) = run(false, "raw.mid");
+    assert!(
+        stderr.contains("Warning: same-pitch notes overlap"),
+        "{stderr}"
+    );
+    assert!(durations.contains(&2.0));
+    let (stderr, mut durations) = run(true, "fixed.mid");
+    assert!(!stderr.contains("Warning"), "{stderr}");
+    durations.sort_by(f64::total_cmp);
+    assert_eq!(durations, [1.0, 1.0, 3.0]);
+}
+
+#[test]
+fn test_summary_json_matches_tracks() {
+    let temp = tempfile::tempdir().unwrap();
+    let output = cli()
+        .args([
+            "generate",
+            "--notes",
+            "C4:1:80,E4:2:80@1",
+            "-t",
+            "60",
+            "--summary-json",
+            "-o",
+        ])
+        .arg(temp.path().join("out.mid"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["layers"].as_array().unwrap().len(), 1);
+    assert_eq!(summary["layers"][0]["notes"], 2);
+    assert_eq!(summary["layers"][0]["instrument_name"], "piano");
+    assert_eq!(summary["total_seconds"], 3.0);
+
+    let output = cli()
+        .args(["preset", "-m", "jazz", "-d", "4", "--summary-json", "-o"])
+        .arg(temp.path().join("jazz.mid"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let sequences = midi_cli_rs::read_midi(&temp.path().join("jazz.mid")).unwrap();
+    assert_eq!(summary["layers"].as_array().unwrap().len(), sequences.len());
+    assert_eq!(summary["mood"], "jazz");
+
+    let output = cli()
+        .args([
+            "generate",
+            "--notes",
+            "C4:1:80",
+            "--summary-json",
+            "-o",
+            "-",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -2,6 +2,10 @@
 
 use gloo_net::http::Request;
 use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::{EventSource, MessageEvent};
 
 const API_BASE: &str = "/api";
 
@@ -34,6 +38,20 @@ pub struct PresetRequest {
     pub seed: i64,
 }
 
+/// Optional filter and paging for `list_presets`.
+#[derive(Clone, Debug, Default)]
+pub struct PresetListQuery {
+    pub mood: Option<String>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PresetPage {
+    pub items: Vec<SavedPreset>,
+    pub total: usize,
+}
+
 // ============================================================================
 // Melody types
 // ============================================================================
@@ -43,6 +61,9 @@ pub struct MelodyNote {
     pub pitch: String,
     pub duration: f64,
     pub velocity: u8,
+    /// Sounds with the previous note instead of after it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub chord: bool,
 }
 
 impl Default for MelodyNote {
@@ -51,6 +72,7 @@ impl Default for MelodyNote {
             pitch: "C4".to_string(),
             duration: 1.0,
             velocity: 80,
+            chord: false,
         }
     }
 }
@@ -61,6 +83,7 @@ impl MelodyNote {
             pitch: "rest".to_string(),
             duration,
             velocity: 0,
+            chord: false,
         }
     }
 
@@ -69,6 +92,22 @@ impl MelodyNote {
     }
 }
 
+/// Start of each note in beats: notes follow one another, and a chord note
+/// starts with the note before it (the next note waits for the longest one)
+pub fn note_offsets(notes: &[MelodyNote]) -> Vec<f64> {
+    let (mut start, mut next) = (0.0f64, 0.0f64);
+    notes
+        .iter()
+        .map(|note| {
+            if !note.chord {
+                start = next;
+            }
+            next = next.max(start + note.duration);
+            start
+        })
+        .collect()
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct SavedMelody {
     pub id: String,
@@ -112,6 +151,25 @@ impl Default for MelodyRequest {
 // Common types
 // ============================================================================
 
+/// Progress event from the streamed preset generation endpoint.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum GenerationEvent {
+    Started {
+        preset_id: String,
+    },
+    MidiWritten,
+    Rendering,
+    Done {
+        preset_id: String,
+        audio_url: String,
+        generated_at: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GenerateResponse {
     pub preset_id: String,
@@ -133,6 +191,30 @@ pub struct MoodInfo {
     pub source: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HealthInfo {
+    pub status: String,
+    pub fluidsynth: bool,
+    pub soundfont: Option<String>,
+    pub ffmpeg: bool,
+}
+
+impl HealthInfo {
+    /// Why audio generation can't work, if it can't.
+    pub fn generate_blocker(&self) -> Option<String> {
+        if !self.fluidsynth {
+            Some("FluidSynth is not installed on the server (brew install fluid-synth / apt install fluidsynth)".to_string())
+        } else if self.soundfont.is_none() {
+            Some(
+                "No SoundFont found on the server (place one at ~/.soundfonts/default.sf2)"
+                    .to_string(),
+            )
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Deserialize)]
 struct ErrorResponse {
     error: String,
@@ -199,9 +281,37 @@ impl ApiClient {
 }
 
 impl ApiClient {
+    pub async fn health() -> Result<HealthInfo, String> {
+        let response = Request::get(&format!("{}/health", API_BASE))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if response.ok() {
+            response.json().await.map_err(|e| e.to_string())
+        } else {
+            Err(format!(
+                "Failed to check server health: {}",
+                response.status()
+            ))
+        }
+    }
+
     // Preset endpoints
-    pub async fn list_presets() -> Result<Vec<SavedPreset>, String> {
+    pub async fn list_presets(query: &PresetListQuery) -> Result<PresetPage, String> {
+        let mut params: Vec<(&str, String)> = Vec::new();
+        if let Some(ref mood) = query.mood {
+            params.push(("mood", mood.clone()));
+        }
+        if let Some(limit) = query.limit {
+            params.push(("limit", limit.to_string()));
+        }
+        if let Some(offset) = query.offset {
+            params.push(("offset", offset.to_string()));
+        }
+
         let response = Request::get(&format!("{}/presets", API_BASE))
+            .query(params)
             .send()
             .await
             .map_err(|e| e.to_string())?;
@@ -256,8 +366,8 @@ impl ApiClient {
         }
     }
 
-    pub async fn generate_preset_audio(id: &str) -> Result<GenerateResponse, String> {
-        let response = Request::post(&format!("{}/generate/{}", API_BASE, id))
+    pub async fn duplicate_preset(id: &str) -> Result<SavedPreset, String> {
+        let response = Request::post(&format!("{}/presets/{}/duplicate", API_BASE, id))
             .send()
             .await
             .map_err(|e| e.to_string())?;
@@ -265,8 +375,56 @@ impl ApiClient {
         if response.ok() {
             response.json().await.map_err(|e| e.to_string())
         } else {
-            Err(Self::extract_error(response, "Generate failed").await)
+            Err(Self::extract_error(response, "Failed to duplicate preset").await)
+        }
+    }
+
+    /// Generate audio for a preset over `GET /generate/:id/stream`, calling
+    /// `on_event` for each progress event. The stream is closed after `done`
+    /// or `failed`, or when the connection drops.
+    pub fn stream_preset_generation(
+        id: &str,
+        on_event: impl Fn(GenerationEvent) + 'static,
+    ) -> Result<(), String> {
+        let source = EventSource::new(&format!("{}/generate/{}/stream", API_BASE, id))
+            .map_err(|_| "Failed to open generation stream".to_string())?;
+        let on_event = Rc::new(on_event);
+
+        for name in ["started", "midi_written", "rendering", "done", "failed"] {
+            let (source_clone, on_event) = (source.clone(), on_event.clone());
+            let listener = Closure::wrap(Box::new(move |e: MessageEvent| {
+                let data = e.data().as_string().unwrap_or_default();
+                let event =
+                    serde_json::from_str(&data).unwrap_or_else(|e| GenerationEvent::Failed {
+                        error: format!("Invalid progress event: {}", e),
+                    });
+                if matches!(
+                    event,
+                    GenerationEvent::Done { .. } | GenerationEvent::Failed { .. }
+                ) {
+                    source_clone.close();
+                }
+                on_event(event);
+            }) as Box<dyn FnMut(MessageEvent)>);
+            let _ =
+                source.add_event_listener_with_callback(name, listener.as_ref().unchecked_ref());
+            listener.forget();
         }
+
+        // Connection errors; the browser would otherwise keep reconnecting
+        let source_clone = source.clone();
+        let onerror = Closure::wrap(Box::new(move || {
+            if source_clone.ready_state() != EventSource::CLOSED {
+                source_clone.close();
+                on_event(GenerationEvent::Failed {
+                    error: "Lost connection while generating".to_string(),
+                });
+            }
+        }) as Box<dyn FnMut()>);
+        source.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+        onerror.forget();
+
+        Ok(())
     }
 
     // Melody endpoints
@@ -326,6 +484,19 @@ impl ApiClient {
         }
     }
 
+    pub async fn duplicate_melody(id: &str) -> Result<SavedMelody, String> {
+        let response = Request::post(&format!("{}/melodies/{}/duplicate", API_BASE, id))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if response.ok() {
+            response.json().await.map_err(|e| e.to_string())
+        } else {
+            Err(Self::extract_error(response, "Failed to duplicate melody").await)
+        }
+    }
+
     pub async fn generate_melody_audio(id: &str) -> Result<GenerateResponse, String> {
         let response = Request::post(&format!("{}/melodies/{}/generate", API_BASE, id))
             .send()
@@ -441,6 +612,24 @@ impl ApiClient {
         }
     }
 
+    pub async fn preset_midi(id: &str) -> Result<Vec<u8>, String> {
+        Self::fetch_midi(&format!("{}/presets/{}/midi", API_BASE, id)).await
+    }
+
+    pub async fn melody_midi(id: &str) -> Result<Vec<u8>, String> {
+        Self::fetch_midi(&format!("{}/melodies/{}/midi", API_BASE, id)).await
+    }
+
+    async fn fetch_midi(url: &str) -> Result<Vec<u8>, String> {
+        let response = Request::get(url).send().await.map_err(|e| e.to_string())?;
+
+        if response.ok() {
+            response.binary().await.map_err(|e| e.to_string())
+        } else {
+            Err(Self::extract_error(response, "Failed to download MIDI").await)
+        }
+    }
+
     pub async fn export_melody_abc(id: &str) -> Result<String, String> {
         let response = Request::get(&format!("{}/melodies/{}/export/abc", API_BASE, id))
             .send()
@@ -1,7 +1,19 @@
 //! Audio player component for playing generated WAV files.
+//!
+//! Below the player, the file's waveform is drawn on a canvas: the audio is
+//! fetched and decoded with Web Audio, reduced to one min/max pair per pixel
+//! column, and drawn as vertical bars.
 
+use gloo_net::http::Request;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{AudioBuffer, AudioContext, CanvasRenderingContext2d, HtmlCanvasElement};
 use yew::prelude::*;
 
+/// Canvas size in pixels; CSS stretches it to the card width.
+const WAVEFORM_WIDTH: u32 = 600;
+const WAVEFORM_HEIGHT: u32 = 60;
+
 /// Props for the AudioPlayer component.
 #[derive(Properties, PartialEq)]
 pub struct AudioPlayerProps {
@@ -9,14 +21,120 @@ pub struct AudioPlayerProps {
     pub src: String,
 }
 
+/// Lowest and highest sample in each of `columns` equal slices of `samples`.
+/// Slices past the end of short input are silent.
+pub fn peaks(samples: &[f32], columns: usize) -> Vec<(f32, f32)> {
+    (0..columns)
+        .map(|column| {
+            let start = column * samples.len() / columns;
+            let end = ((column + 1) * samples.len() / columns)
+                .max(start + 1)
+                .min(samples.len());
+            samples
+                .get(start..end)
+                .filter(|slice| !slice.is_empty())
+                .map_or((0.0, 0.0), |slice| {
+                    slice
+                        .iter()
+                        .fold((f32::MAX, f32::MIN), |(lo, hi), &s| (lo.min(s), hi.max(s)))
+                })
+        })
+        .collect()
+}
+
+/// Fetch and decode the audio, returning the first channel's samples.
+async fn decode_samples(src: &str) -> Result<Vec<f32>, JsValue> {
+    let bytes = Request::get(src)
+        .send()
+        .await
+        .map_err(|e| JsValue::from_str(&e.to_string()))?
+        .binary()
+        .await
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let ctx = AudioContext::new()?;
+    let data = js_sys::Uint8Array::from(bytes.as_slice()).buffer();
+    let decoded = JsFuture::from(ctx.decode_audio_data(&data)?).await;
+    let _ = ctx.close();
+    let buffer: AudioBuffer = decoded?.dyn_into()?;
+    buffer.get_channel_data(0)
+}
+
+/// Draw min/max bars centred on the canvas.
+fn draw(canvas: &HtmlCanvasElement, peaks: &[(f32, f32)]) -> Result<(), JsValue> {
+    let ctx: CanvasRenderingContext2d = canvas
+        .get_context("2d")?
+        .ok_or_else(|| JsValue::from_str("no 2d context"))?
+        .dyn_into()?;
+    let (width, height) = (canvas.width() as f64, canvas.height() as f64);
+    let middle = height / 2.0;
+
+    ctx.clear_rect(0.0, 0.0, width, height);
+    ctx.set_fill_style_str("#e94560");
+    for (x, &(lo, hi)) in peaks.iter().enumerate() {
+        let top = middle - hi.clamp(-1.0, 1.0) as f64 * middle;
+        let bottom = middle - lo.clamp(-1.0, 1.0) as f64 * middle;
+        ctx.fill_rect(x as f64, top, 1.0, (bottom - top).max(1.0));
+    }
+    Ok(())
+}
+
 /// Audio player widget wrapping an HTML5 audio element.
 #[function_component(AudioPlayer)]
 pub fn audio_player(props: &AudioPlayerProps) -> Html {
+    let canvas = use_node_ref();
+
+    {
+        let canvas = canvas.clone();
+        use_effect_with(props.src.clone(), move |src| {
+            let src = src.clone();
+            spawn_local(async move {
+                // No waveform is shown if the file can't be decoded
+                if let Ok(samples) = decode_samples(&src).await {
+                    if let Some(canvas) = canvas.cast::<HtmlCanvasElement>() {
+                        let _ = draw(&canvas, &peaks(&samples, canvas.width() as usize));
+                    }
+                }
+            });
+        });
+    }
+
     html! {
         <div class="audio-player">
             <audio controls=true autoplay=false src={props.src.clone()}>
                 {"Your browser does not support the audio element."}
             </audio>
+            <canvas
+                class="waveform"
+                ref={canvas}
+                width={WAVEFORM_WIDTH.to_string()}
+                height={WAVEFORM_HEIGHT.to_string()}
+            />
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peaks_per_column() {
+        let samples = [0.1, -0.5, 0.9, 0.2, -0.3, -0.1, 0.0, 0.4];
+        assert_eq!(
+            peaks(&samples, 4),
+            [(-0.5, 0.1), (0.2, 0.9), (-0.3, -0.1), (0.0, 0.4)]
+        );
+        assert_eq!(peaks(&samples, 1), [(-0.5, 0.9)]);
+    }
+
+    #[test]
+    fn test_peaks_of_short_or_empty_input() {
+        // More columns than samples: each sample fills a column or more
+        let wide = peaks(&[0.5, -0.5], 4);
+        assert_eq!(wide.len(), 4);
+        assert_eq!(wide[0], (0.5, 0.5));
+        assert_eq!(wide[3], (-0.5, -0.5));
+        assert_eq!(peaks(&[], 3), [(0.0, 0.0); 3]);
+    }
+}
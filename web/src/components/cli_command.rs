@@ -0,0 +1,183 @@
+//! The CLI command that reproduces a saved preset or melody, with a copy button.
+
+use crate::api::{note_offsets, SavedMelody, SavedPreset};
+use yew::prelude::*;
+
+/// Quote an argument for a POSIX shell when it needs it.
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_.,:@/#+=".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Output file named after the preset or melody.
+fn output_name(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.wav", if stem.is_empty() { "output" } else { &stem })
+}
+
+fn join(args: &[String]) -> String {
+    let mut command = "midi-cli-rs".to_string();
+    for arg in args {
+        command.push(' ');
+        command.push_str(&shell_quote(arg));
+    }
+    command
+}
+
+/// `midi-cli-rs preset ...` with the same arguments the server runs.
+pub fn preset_command(preset: &SavedPreset) -> String {
+    let mut args: Vec<String> = vec![
+        "preset".into(),
+        "-m".into(),
+        preset.mood.clone(),
+        "-d".into(),
+        preset.duration.to_string(),
+        "--intensity".into(),
+        preset.intensity.to_string(),
+        "-t".into(),
+        preset.tempo.to_string(),
+        "-s".into(),
+        preset.seed.to_string(),
+    ];
+    if let Some(ref key) = preset.key {
+        args.extend(["-k".into(), key.clone()]);
+    }
+    args.extend(["-o".into(), output_name(&preset.name)]);
+    join(&args)
+}
+
+/// `midi-cli-rs generate --notes ...` with each note at its offset.
+pub fn melody_command(melody: &SavedMelody) -> String {
+    let notes: Vec<String> = melody
+        .notes
+        .iter()
+        .zip(note_offsets(&melody.notes))
+        .filter(|(note, _)| !note.is_rest())
+        .map(|(note, offset)| {
+            format!(
+                "{}:{}:{}@{}",
+                note.pitch, note.duration, note.velocity, offset
+            )
+        })
+        .collect();
+    let args: Vec<String> = vec![
+        "generate".into(),
+        "--notes".into(),
+        notes.join(","),
+        "-i".into(),
+        melody.instrument.clone(),
+        "-t".into(),
+        melody.tempo.to_string(),
+        "-o".into(),
+        output_name(&melody.name),
+    ];
+    join(&args)
+}
+
+#[derive(Properties, PartialEq)]
+pub struct CliCommandProps {
+    pub command: String,
+}
+
+/// A command line with a button that copies it to the clipboard.
+#[function_component(CliCommand)]
+pub fn cli_command(props: &CliCommandProps) -> Html {
+    let copied = use_state(|| false);
+
+    let on_copy = {
+        let command = props.command.clone();
+        let copied = copied.clone();
+        Callback::from(move |_| {
+            if let Some(window) = web_sys::window() {
+                let _ = window.navigator().clipboard().write_text(&command);
+                copied.set(true);
+            }
+        })
+    };
+
+    html! {
+        <div class="cli-command">
+            <code>{&props.command}</code>
+            <button class="btn-secondary btn-small" onclick={on_copy} title="Copy the command to the clipboard">
+                { if *copied { "Copied" } else { "Copy" } }
+            </button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::MelodyNote;
+
+    #[test]
+    fn test_preset_command() {
+        let preset = SavedPreset {
+            id: "p1".to_string(),
+            name: "Dark Intro".to_string(),
+            mood: "suspense".to_string(),
+            duration: 5.0,
+            key: Some("Am".to_string()),
+            intensity: 70,
+            tempo: 90,
+            seed: 42,
+            created_at: String::new(),
+            last_generated: None,
+        };
+        assert_eq!(
+            preset_command(&preset),
+            "midi-cli-rs preset -m suspense -d 5 --intensity 70 -t 90 -s 42 -k Am -o Dark_Intro.wav"
+        );
+    }
+
+    #[test]
+    fn test_melody_command_places_notes_at_offsets() {
+        let note = |pitch: &str, duration, chord| MelodyNote {
+            pitch: pitch.to_string(),
+            duration,
+            velocity: 80,
+            chord,
+        };
+        let melody = SavedMelody {
+            id: "m1".to_string(),
+            name: "Tune".to_string(),
+            notes: vec![
+                note("C4", 1.0, false),
+                note("rest", 0.5, false),
+                note("E4", 1.0, false),
+                note("G4", 1.0, true),
+            ],
+            key: "C".to_string(),
+            tempo: 120,
+            instrument: "piano".to_string(),
+            attack: 0,
+            decay: 64,
+            created_at: String::new(),
+            last_generated: None,
+        };
+        assert_eq!(
+            melody_command(&melody),
+            "midi-cli-rs generate --notes C4:1:80@0,E4:1:80@1.5,G4:1:80@1.5 -i piano -t 120 -o Tune.wav"
+        );
+    }
+
+    #[test]
+    fn test_shell_quoting() {
+        assert_eq!(shell_quote("Am"), "Am");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+}
@@ -1,60 +1,64 @@
 //! Melody editor component with keyboard-driven note editing.
 
 use crate::api::{MelodyNote, MelodyRequest, SavedMelody};
+use crate::components::PianoRoll;
+use crate::synth;
+use std::ops::Range;
 use wasm_bindgen::JsCast;
-use web_sys::{HtmlInputElement, KeyboardEvent};
+use web_sys::{HtmlInputElement, KeyboardEvent, MouseEvent};
 use yew::prelude::*;
 
 const KEYS: &[&str] = &[
     "C", "Cm", "D", "Dm", "Eb", "E", "Em", "F", "Fm", "G", "Gm", "A", "Am", "Bb", "B", "Bm",
 ];
 
-/// Instruments with default octaves - must match names in INSTRUMENT_MAP (src/midi/sequence.rs)
-const INSTRUMENTS: &[(&str, u8)] = &[
+/// Instruments with default octaves and playable range (lowest, highest MIDI
+/// pitch) - must match names in INSTRUMENT_MAP (src/midi/sequence.rs)
+const INSTRUMENTS: &[(&str, u8, (u8, u8))] = &[
     // Pianos (octave 4)
-    ("piano", 4),
-    ("acoustic_piano", 4),
-    ("bright_piano", 4),
-    ("electric_piano", 4),
+    ("piano", 4, (21, 108)),
+    ("acoustic_piano", 4, (21, 108)),
+    ("bright_piano", 4, (21, 108)),
+    ("electric_piano", 4, (28, 103)),
     // Strings (varied octaves)
-    ("strings", 4),
-    ("violin", 4),
-    ("viola", 4),
-    ("cello", 3),
-    ("contrabass", 2),
-    ("tremolo_strings", 4),
-    ("pizzicato_strings", 4),
-    ("harp", 4),
+    ("strings", 4, (28, 96)),
+    ("violin", 4, (55, 103)),
+    ("viola", 4, (48, 88)),
+    ("cello", 3, (36, 76)),
+    ("contrabass", 2, (28, 67)),
+    ("tremolo_strings", 4, (28, 96)),
+    ("pizzicato_strings", 4, (28, 96)),
+    ("harp", 4, (24, 103)),
     // Woodwinds (octave 4-5)
-    ("flute", 5),
-    ("oboe", 4),
-    ("clarinet", 4),
-    ("bassoon", 2),
+    ("flute", 5, (60, 96)),
+    ("oboe", 4, (58, 91)),
+    ("clarinet", 4, (50, 94)),
+    ("bassoon", 2, (34, 75)),
     // Brass (octave 3-4)
-    ("trumpet", 4),
-    ("trombone", 3),
-    ("french_horn", 3),
-    ("tuba", 2),
+    ("trumpet", 4, (54, 84)),
+    ("trombone", 3, (40, 72)),
+    ("french_horn", 3, (34, 77)),
+    ("tuba", 2, (26, 65)),
     // Synth (octave 3-4)
-    ("synth_pad", 3),
-    ("synth_lead", 4),
-    ("pad_warm", 3),
-    ("pad_choir", 3),
+    ("synth_pad", 3, (24, 108)),
+    ("synth_lead", 4, (36, 96)),
+    ("pad_warm", 3, (24, 96)),
+    ("pad_choir", 3, (40, 84)),
     // Ambient (octave 3-4)
-    ("atmosphere", 3),
-    ("soundtrack", 4),
+    ("atmosphere", 3, (24, 108)),
+    ("soundtrack", 4, (24, 108)),
     // Guitar/Bass (octave 2-3)
-    ("acoustic_guitar", 3),
-    ("electric_guitar", 3),
-    ("bass", 2),
-    ("electric_bass", 2),
+    ("acoustic_guitar", 3, (40, 88)),
+    ("electric_guitar", 3, (40, 88)),
+    ("bass", 2, (28, 67)),
+    ("electric_bass", 2, (28, 67)),
     // Bells/Percussion (octave 4-5)
-    ("vibraphone", 4),
-    ("marimba", 4),
-    ("xylophone", 5),
-    ("tubular_bells", 4),
-    ("glockenspiel", 5),
-    ("celesta", 5),
+    ("vibraphone", 4, (53, 89)),
+    ("marimba", 4, (45, 96)),
+    ("xylophone", 5, (65, 108)),
+    ("tubular_bells", 4, (60, 77)),
+    ("glockenspiel", 5, (72, 108)),
+    ("celesta", 5, (60, 108)),
 ];
 
 const DURATIONS: &[(f64, &str)] = &[
@@ -73,6 +77,8 @@ pub struct MelodyEditorProps {
     pub on_save: Callback<MelodyRequest>,
     pub editing: Option<SavedMelody>,
     pub on_clear: Callback<()>,
+    /// Play the notes in the browser at a tempo
+    pub on_preview: Callback<(Vec<MelodyNote>, u16)>,
 }
 
 #[derive(Clone, Debug)]
@@ -84,10 +90,31 @@ struct EditorState {
     instrument: String,
     attack: u8,
     decay: u8,
-    selected_note: usize,
+    /// Selected notes; edits apply to all of them
+    selection: Range<usize>,
+    /// End of the selection that stays put when Shift+Arrow extends it
+    anchor: usize,
     insert_mode: bool,
     undo_stack: Vec<Vec<MelodyNote>>,
     redo_stack: Vec<Vec<MelodyNote>>,
+    /// Latest repeatable edit, the notes it touched, and when (ms), so a
+    /// burst of the same edit undoes in one step
+    last_edit: Option<(Edit, Range<usize>, f64)>,
+}
+
+/// Undo snapshots kept
+const UNDO_LIMIT: usize = 50;
+
+/// Same-kind edits closer together than this (ms) share an undo step
+const COALESCE_MS: f64 = 1000.0;
+
+/// Edits that are often repeated in quick succession
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Edit {
+    Octave,
+    ScaleStep,
+    Velocity,
+    Duration,
 }
 
 impl Default for EditorState {
@@ -100,16 +127,19 @@ impl Default for EditorState {
                 pitch: format!("C{}", octave),
                 duration: 1.0,
                 velocity: 80,
+                chord: false,
             }],
             key: "C".to_string(),
             tempo: 120,
             instrument,
             attack: 0,
             decay: 64,
-            selected_note: 0,
+            selection: 0..1,
+            anchor: 0,
             insert_mode: false,
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            last_edit: None,
         }
     }
 }
@@ -122,6 +152,7 @@ impl EditorState {
             pitch: format!("C{}", octave),
             duration: 1.0,
             velocity: 80,
+            chord: false,
         }
     }
 
@@ -134,6 +165,7 @@ impl EditorState {
                     pitch: format!("C{}", octave),
                     duration: 1.0,
                     velocity: 80,
+                    chord: false,
                 }]
             } else {
                 melody.notes.clone()
@@ -143,10 +175,12 @@ impl EditorState {
             instrument: melody.instrument.clone(),
             attack: melody.attack,
             decay: melody.decay,
-            selected_note: 0,
+            selection: 0..1,
+            anchor: 0,
             insert_mode: false,
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            last_edit: None,
         }
     }
 
@@ -163,28 +197,265 @@ impl EditorState {
     }
 
     fn push_undo(&mut self) {
+        self.last_edit = None;
         self.undo_stack.push(self.notes.clone());
         self.redo_stack.clear();
         // Limit undo stack size
-        if self.undo_stack.len() > 50 {
+        if self.undo_stack.len() > UNDO_LIMIT {
             self.undo_stack.remove(0);
         }
     }
 
+    /// Like `push_undo`, but a repeat of the last edit on the same notes
+    /// within `COALESCE_MS` reuses its snapshot
+    fn push_undo_coalesced(&mut self, edit: Edit, now: f64) {
+        let repeat = matches!(
+            &self.last_edit,
+            Some((last, range, at)) if *last == edit && *range == self.selection && now - at < COALESCE_MS
+        );
+        if !repeat {
+            self.push_undo();
+        }
+        self.last_edit = Some((edit, self.selection.clone(), now));
+    }
+
     fn undo(&mut self) {
+        self.last_edit = None;
         if let Some(prev) = self.undo_stack.pop() {
             self.redo_stack.push(self.notes.clone());
             self.notes = prev;
-            self.selected_note = self.selected_note.min(self.notes.len().saturating_sub(1));
+            self.clamp_selection();
         }
     }
 
     fn redo(&mut self) {
+        self.last_edit = None;
         if let Some(next) = self.redo_stack.pop() {
             self.undo_stack.push(self.notes.clone());
             self.notes = next;
-            self.selected_note = self.selected_note.min(self.notes.len().saturating_sub(1));
+            self.clamp_selection();
+        }
+    }
+
+    /// The moving end of the selection (where keyboard input lands)
+    fn cursor(&self) -> usize {
+        if self.anchor == self.selection.start {
+            self.selection.end - 1
+        } else {
+            self.selection.start
+        }
+    }
+
+    /// Select a single note
+    fn select(&mut self, idx: usize) {
+        let idx = idx.min(self.notes.len().saturating_sub(1));
+        self.selection = idx..idx + 1;
+        self.anchor = idx;
+    }
+
+    /// Extend the selection from the anchor to `idx`
+    fn select_to(&mut self, idx: usize) {
+        let idx = idx.min(self.notes.len().saturating_sub(1));
+        self.selection = self.anchor.min(idx)..self.anchor.max(idx) + 1;
+    }
+
+    /// Move the cursor by `delta` notes, extending the selection if asked
+    fn move_cursor(&mut self, delta: isize, extend: bool) {
+        let idx = self.cursor().saturating_add_signed(delta);
+        if extend {
+            self.select_to(idx);
+        } else {
+            self.select(idx);
+        }
+    }
+
+    /// Keep the selection inside the notes after they change
+    fn clamp_selection(&mut self) {
+        let last = self.notes.len().saturating_sub(1);
+        self.anchor = self.anchor.min(last);
+        self.select_to(self.cursor().min(last));
+    }
+
+    fn selected_notes_mut(&mut self) -> &mut [MelodyNote] {
+        let range = self.selection.clone();
+        &mut self.notes[range]
+    }
+
+    /// Move the note at `from` so it ends up at index `to`, selecting it
+    fn move_note(&mut self, from: usize, to: usize) {
+        let last = self.notes.len().saturating_sub(1);
+        if from > last || from == to.min(last) {
+            return;
         }
+        self.push_undo();
+        let note = self.notes.remove(from);
+        self.notes.insert(to.min(last), note);
+        self.select(to.min(last));
+    }
+
+    /// Apply an editing key pressed at `now` (ms); returns false for keys the
+    /// editor doesn't use
+    fn handle_key(&mut self, key: &str, shift: bool, ctrl: bool, now: f64) -> bool {
+        match key {
+            // Navigation
+            "Tab" => self.move_cursor(if shift { -1 } else { 1 }, false),
+            "ArrowRight" => self.move_cursor(1, shift),
+            "ArrowLeft" => self.move_cursor(-1, shift),
+
+            // Note input (a-g) at the cursor; with Shift, stack a chord note
+            // above it (the next letter up that sounds with it)
+            "a" | "b" | "c" | "d" | "e" | "f" | "g" | "A" | "B" | "C" | "D" | "E" | "F" | "G" => {
+                self.push_undo();
+                let cursor = self.cursor();
+                let octave = self
+                    .notes
+                    .get(cursor)
+                    .map_or(4, |note| extract_octave(&note.pitch));
+                let name = key.to_uppercase();
+                if shift {
+                    let below = &self.notes[cursor];
+                    let octave =
+                        if below.is_rest() || letter_index(&name) > letter_index(&below.pitch) {
+                            octave
+                        } else {
+                            (octave + 1).min(8)
+                        };
+                    let note = MelodyNote {
+                        pitch: format!("{}{}", name, octave),
+                        duration: below.duration,
+                        velocity: below.velocity,
+                        chord: true,
+                    };
+                    self.notes.insert(cursor + 1, note);
+                    self.select(cursor + 1);
+                    return true;
+                }
+                let new_pitch = format!("{}{}", name, octave);
+                if self.insert_mode {
+                    self.notes.insert(
+                        cursor + 1,
+                        MelodyNote {
+                            pitch: new_pitch,
+                            duration: 1.0,
+                            velocity: 80,
+                            chord: false,
+                        },
+                    );
+                    self.select(cursor + 1);
+                } else {
+                    self.notes[cursor].pitch = new_pitch;
+                    self.select(cursor);
+                }
+            }
+
+            // Rest
+            "r" | "R" => {
+                self.push_undo();
+                let cursor = self.cursor();
+                if self.insert_mode {
+                    self.notes.insert(cursor + 1, MelodyNote::rest(1.0));
+                    self.select(cursor + 1);
+                } else {
+                    let note = &mut self.notes[cursor];
+                    *note = MelodyNote::rest(note.duration);
+                    self.select(cursor);
+                }
+            }
+
+            // Octave up/down
+            "=" | "+" => {
+                self.push_undo_coalesced(Edit::Octave, now);
+                for note in self
+                    .selected_notes_mut()
+                    .iter_mut()
+                    .filter(|n| !n.is_rest())
+                {
+                    let octave = extract_octave(&note.pitch);
+                    if octave < 8 {
+                        note.pitch = set_octave(&note.pitch, octave + 1);
+                    }
+                }
+            }
+            "-" | "_" => {
+                self.push_undo_coalesced(Edit::Octave, now);
+                for note in self
+                    .selected_notes_mut()
+                    .iter_mut()
+                    .filter(|n| !n.is_rest())
+                {
+                    let octave = extract_octave(&note.pitch);
+                    if octave > 0 {
+                        note.pitch = set_octave(&note.pitch, octave - 1);
+                    }
+                }
+            }
+
+            // Scale movement (Shift+Up/Down), velocity (Up/Down)
+            "ArrowUp" | "ArrowDown" => {
+                self.push_undo_coalesced(
+                    if shift {
+                        Edit::ScaleStep
+                    } else {
+                        Edit::Velocity
+                    },
+                    now,
+                );
+                let up = key == "ArrowUp";
+                let scale_key = self.key.clone();
+                for note in self.selected_notes_mut() {
+                    if shift {
+                        if !note.is_rest() {
+                            note.pitch =
+                                move_scale_step(&note.pitch, if up { 1 } else { -1 }, &scale_key);
+                        }
+                    } else if up {
+                        note.velocity = (note.velocity + 10).min(127);
+                    } else {
+                        note.velocity = note.velocity.saturating_sub(10);
+                    }
+                }
+            }
+
+            // Duration ([ and ])
+            "[" | "]" => {
+                self.push_undo_coalesced(Edit::Duration, now);
+                for note in self.selected_notes_mut() {
+                    note.duration = if key == "[" {
+                        prev_duration(note.duration)
+                    } else {
+                        next_duration(note.duration)
+                    };
+                }
+            }
+
+            // Delete, always leaving at least one note
+            "Delete" | "Backspace" => {
+                if self.notes.len() > self.selection.len() {
+                    self.push_undo();
+                    let start = self.selection.start;
+                    self.notes.drain(self.selection.clone());
+                    self.select(start);
+                }
+            }
+
+            // Insert mode toggle
+            "i" | "I" if !ctrl => self.insert_mode = !self.insert_mode,
+
+            // Undo/Redo
+            "z" | "Z" if ctrl && !shift => self.undo(),
+            "z" | "Z" if ctrl && shift => self.redo(),
+            "y" | "Y" if ctrl => self.redo(),
+
+            // Add note at end
+            "Enter" => {
+                self.push_undo();
+                self.notes.push(self.new_note());
+                self.select(self.notes.len() - 1);
+            }
+
+            _ => return false,
+        }
+        true
     }
 }
 
@@ -213,6 +484,33 @@ pub fn melody_editor(props: &MelodyEditorProps) -> Html {
         });
     }
 
+    // Play the note under the cursor when it moves or its pitch changes
+    let note_preview = use_state(|| true);
+    let blip_ctx = use_mut_ref(|| None::<web_sys::AudioContext>);
+    let mounted = use_mut_ref(|| false);
+    {
+        let cursor = state.cursor();
+        let pitch = state.notes.get(cursor).map(|note| note.pitch.clone());
+        let enabled = *note_preview;
+        use_effect_with((cursor, pitch), move |(_, pitch)| {
+            // Nothing plays when the editor first opens
+            let first = !std::mem::replace(&mut *mounted.borrow_mut(), true);
+            if enabled && !first {
+                if let Some(tone) = pitch.as_deref().and_then(synth::blip) {
+                    if let Some(old) = blip_ctx.borrow_mut().take() {
+                        let _ = old.close();
+                    }
+                    *blip_ctx.borrow_mut() = synth::play(&[tone]).ok();
+                }
+            }
+        });
+    }
+
+    let on_note_preview_toggle = {
+        let note_preview = note_preview.clone();
+        Callback::from(move |_: Event| note_preview.set(!*note_preview))
+    };
+
     let on_keydown = {
         let state = state.clone();
         Callback::from(move |e: KeyboardEvent| {
@@ -229,206 +527,22 @@ pub fn melody_editor(props: &MelodyEditorProps) -> Html {
             let key = e.key();
             let shift = e.shift_key();
             let ctrl = e.ctrl_key() || e.meta_key();
-            let mut s = (*state).clone();
-
-            match key.as_str() {
-                // Navigation
-                "Tab" if !shift => {
-                    e.prevent_default();
-                    if s.selected_note < s.notes.len() - 1 {
-                        s.selected_note += 1;
-                    }
-                }
-                "Tab" if shift => {
-                    e.prevent_default();
-                    if s.selected_note > 0 {
-                        s.selected_note -= 1;
+            // Escape to blur/exit note editor
+            if key == "Escape" {
+                e.prevent_default();
+                if let Some(target) = e.target() {
+                    if let Ok(element) = target.dyn_into::<web_sys::HtmlElement>() {
+                        let _ = element.blur();
                     }
                 }
-                "ArrowRight" => {
-                    e.prevent_default();
-                    if s.selected_note < s.notes.len() - 1 {
-                        s.selected_note += 1;
-                    }
-                }
-                "ArrowLeft" => {
-                    e.prevent_default();
-                    if s.selected_note > 0 {
-                        s.selected_note -= 1;
-                    }
-                }
-
-                // Note input (a-g)
-                "a" | "b" | "c" | "d" | "e" | "f" | "g" | "A" | "B" | "C" | "D" | "E" | "F"
-                | "G" => {
-                    e.prevent_default();
-                    s.push_undo();
-                    let note_name = key.to_uppercase();
-                    let octave = if s.notes.get(s.selected_note).is_some() {
-                        extract_octave(&s.notes[s.selected_note].pitch)
-                    } else {
-                        4
-                    };
-                    let new_pitch = format!("{}{}", note_name, octave);
-                    if s.insert_mode {
-                        s.notes.insert(
-                            s.selected_note + 1,
-                            MelodyNote {
-                                pitch: new_pitch,
-                                duration: 1.0,
-                                velocity: 80,
-                            },
-                        );
-                        s.selected_note += 1;
-                    } else if let Some(note) = s.notes.get_mut(s.selected_note) {
-                        note.pitch = new_pitch;
-                    }
-                }
-
-                // Rest
-                "r" | "R" => {
-                    e.prevent_default();
-                    s.push_undo();
-                    if s.insert_mode {
-                        s.notes.insert(s.selected_note + 1, MelodyNote::rest(1.0));
-                        s.selected_note += 1;
-                    } else if let Some(note) = s.notes.get_mut(s.selected_note) {
-                        *note = MelodyNote::rest(note.duration);
-                    }
-                }
-
-                // Octave up/down
-                "=" | "+" => {
-                    e.prevent_default();
-                    s.push_undo();
-                    if let Some(note) = s.notes.get_mut(s.selected_note) {
-                        if !note.is_rest() {
-                            let octave = extract_octave(&note.pitch);
-                            if octave < 8 {
-                                note.pitch = set_octave(&note.pitch, octave + 1);
-                            }
-                        }
-                    }
-                }
-                "-" | "_" => {
-                    e.prevent_default();
-                    s.push_undo();
-                    if let Some(note) = s.notes.get_mut(s.selected_note) {
-                        if !note.is_rest() {
-                            let octave = extract_octave(&note.pitch);
-                            if octave > 0 {
-                                note.pitch = set_octave(&note.pitch, octave - 1);
-                            }
-                        }
-                    }
-                }
-
-                // Scale movement (up/down arrow with shift)
-                "ArrowUp" if shift => {
-                    e.prevent_default();
-                    s.push_undo();
-                    if let Some(note) = s.notes.get_mut(s.selected_note) {
-                        if !note.is_rest() {
-                            note.pitch = move_scale_step(&note.pitch, 1, &s.key);
-                        }
-                    }
-                }
-                "ArrowDown" if shift => {
-                    e.prevent_default();
-                    s.push_undo();
-                    if let Some(note) = s.notes.get_mut(s.selected_note) {
-                        if !note.is_rest() {
-                            note.pitch = move_scale_step(&note.pitch, -1, &s.key);
-                        }
-                    }
-                }
-
-                // Velocity adjustment
-                "ArrowUp" if !shift => {
-                    e.prevent_default();
-                    s.push_undo();
-                    if let Some(note) = s.notes.get_mut(s.selected_note) {
-                        note.velocity = (note.velocity + 10).min(127);
-                    }
-                }
-                "ArrowDown" if !shift => {
-                    e.prevent_default();
-                    s.push_undo();
-                    if let Some(note) = s.notes.get_mut(s.selected_note) {
-                        note.velocity = note.velocity.saturating_sub(10);
-                    }
-                }
-
-                // Duration ([ and ])
-                "[" => {
-                    e.prevent_default();
-                    s.push_undo();
-                    if let Some(note) = s.notes.get_mut(s.selected_note) {
-                        note.duration = prev_duration(note.duration);
-                    }
-                }
-                "]" => {
-                    e.prevent_default();
-                    s.push_undo();
-                    if let Some(note) = s.notes.get_mut(s.selected_note) {
-                        note.duration = next_duration(note.duration);
-                    }
-                }
-
-                // Delete
-                "Delete" | "Backspace" => {
-                    e.prevent_default();
-                    if s.notes.len() > 1 {
-                        s.push_undo();
-                        s.notes.remove(s.selected_note);
-                        if s.selected_note >= s.notes.len() {
-                            s.selected_note = s.notes.len() - 1;
-                        }
-                    }
-                }
-
-                // Insert mode toggle
-                "i" | "I" if !ctrl => {
-                    e.prevent_default();
-                    s.insert_mode = !s.insert_mode;
-                }
-
-                // Undo/Redo
-                "z" | "Z" if ctrl && !shift => {
-                    e.prevent_default();
-                    s.undo();
-                }
-                "z" | "Z" if ctrl && shift => {
-                    e.prevent_default();
-                    s.redo();
-                }
-                "y" | "Y" if ctrl => {
-                    e.prevent_default();
-                    s.redo();
-                }
-
-                // Add note at end
-                "Enter" => {
-                    e.prevent_default();
-                    s.push_undo();
-                    s.notes.push(s.new_note());
-                    s.selected_note = s.notes.len() - 1;
-                }
-
-                // Escape to blur/exit note editor
-                "Escape" => {
-                    e.prevent_default();
-                    if let Some(target) = e.target() {
-                        if let Ok(element) = target.dyn_into::<web_sys::HtmlElement>() {
-                            let _ = element.blur();
-                        }
-                    }
-                }
-
-                _ => {}
+                return;
             }
 
-            state.set(s);
+            let mut s = (*state).clone();
+            if s.handle_key(&key, shift, ctrl, js_sys::Date::now()) {
+                e.prevent_default();
+                state.set(s);
+            }
         })
     };
 
@@ -512,14 +626,63 @@ pub fn melody_editor(props: &MelodyEditorProps) -> Html {
         let state = state.clone();
         move |idx: usize| {
             let state = state.clone();
-            Callback::from(move |_| {
+            Callback::from(move |e: MouseEvent| {
                 let mut s = (*state).clone();
-                s.selected_note = idx;
+                if e.shift_key() {
+                    s.select_to(idx);
+                } else {
+                    s.select(idx);
+                }
                 state.set(s);
             })
         }
     };
 
+    // Dragging a cell onto another moves it to that cell's place
+    let drag_from = use_state(|| None::<usize>);
+
+    let on_drag_start = {
+        let drag_from = drag_from.clone();
+        move |idx: usize| {
+            let drag_from = drag_from.clone();
+            Callback::from(move |_: DragEvent| drag_from.set(Some(idx)))
+        }
+    };
+
+    // Accepting dragover is what lets a cell be a drop target
+    let on_drag_over = Callback::from(|e: DragEvent| e.prevent_default());
+
+    let on_drop = {
+        let state = state.clone();
+        let drag_from = drag_from.clone();
+        move |idx: usize| {
+            let state = state.clone();
+            let drag_from = drag_from.clone();
+            Callback::from(move |e: DragEvent| {
+                e.prevent_default();
+                if let Some(from) = *drag_from {
+                    let mut s = (*state).clone();
+                    s.move_note(from, idx);
+                    state.set(s);
+                }
+                drag_from.set(None);
+            })
+        }
+    };
+
+    let on_roll_select = {
+        let state = state.clone();
+        Callback::from(move |(idx, shift): (usize, bool)| {
+            let mut s = (*state).clone();
+            if shift {
+                s.select_to(idx);
+            } else {
+                s.select(idx);
+            }
+            state.set(s);
+        })
+    };
+
     let on_submit = {
         let state = state.clone();
         let on_save = props.on_save.clone();
@@ -529,6 +692,12 @@ pub fn melody_editor(props: &MelodyEditorProps) -> Html {
         })
     };
 
+    let on_preview = {
+        let state = state.clone();
+        let on_preview = props.on_preview.clone();
+        Callback::from(move |_| on_preview.emit((state.notes.clone(), state.tempo)))
+    };
+
     let on_clear = {
         let on_clear = props.on_clear.clone();
         Callback::from(move |_| on_clear.emit(()))
@@ -583,14 +752,17 @@ pub fn melody_editor(props: &MelodyEditorProps) -> Html {
                         <small>
                             {"Keys: "}
                             <kbd>{"a-g"}</kbd>{" note | "}
+                            <kbd>{"Shift+a-g"}</kbd>{" chord | "}
                             <kbd>{"r"}</kbd>{" rest | "}
                             <kbd>{"Tab"}</kbd>{" next | "}
+                            <kbd>{"Shift+←→"}</kbd>{" select | "}
                             <kbd>{"+/-"}</kbd>{" octave | "}
                             <kbd>{"[/]"}</kbd>{" duration | "}
                             <kbd>{"↑↓"}</kbd>{" velocity | "}
                             <kbd>{"Shift+↑↓"}</kbd>{" scale | "}
                             <kbd>{"i"}</kbd>{" insert | "}
                             <kbd>{"Del"}</kbd>{" delete | "}
+                            {"drag to move | "}
                             <kbd>{"Ctrl+Z/Y"}</kbd>{" undo/redo | "}
                             <kbd>{"Esc"}</kbd>{" exit"}
                         </small>
@@ -621,19 +793,26 @@ pub fn melody_editor(props: &MelodyEditorProps) -> Html {
                     onblur={on_grid_blur}
                 >
                     { for state.notes.iter().enumerate().map(|(idx, note)| {
-                        let selected = idx == state.selected_note;
-                        let class = if selected {
-                            if state.insert_mode { "note-cell selected insert-mode" } else { "note-cell selected" }
-                        } else {
-                            "note-cell"
-                        };
+                        let selected = state.selection.contains(&idx);
+                        let hint = range_hint(note, &state.instrument);
+                        let class = classes!(
+                            "note-cell",
+                            hint.is_some().then_some("out-of-range"),
+                            selected.then_some("selected"),
+                            (selected && state.insert_mode && idx == state.cursor()).then_some("insert-mode"),
+                            note.chord.then_some("chord"),
+                        );
                         let is_rest = note.is_rest();
 
                         html! {
                             <div
                                 class={class}
+                                draggable="true"
+                                ondragstart={on_drag_start(idx)}
+                                ondragover={on_drag_over.clone()}
+                                ondrop={on_drop(idx)}
                                 onclick={on_note_click(idx)}
-                                title={format!("Velocity: {}", note.velocity)}
+                                title={hint.unwrap_or_else(|| format!("Velocity: {}", note.velocity))}
                             >
                                 <div class="note-pitch">
                                     { if is_rest { "—".to_string() } else { note.pitch.clone() } }
@@ -647,6 +826,17 @@ pub fn melody_editor(props: &MelodyEditorProps) -> Html {
                     })}
                 </div>
 
+                <PianoRoll
+                    notes={state.notes.clone()}
+                    selection={state.selection.clone()}
+                    on_select={on_roll_select}
+                />
+
+                <label class="note-preview-toggle">
+                    <input type="checkbox" checked={*note_preview} onchange={on_note_preview_toggle} />
+                    {" Play notes while editing"}
+                </label>
+
                 { if state.insert_mode {
                     html! { <div class="insert-mode-indicator">{"INSERT MODE"}</div> }
                 } else {
@@ -666,7 +856,7 @@ pub fn melody_editor(props: &MelodyEditorProps) -> Html {
                     <div class="form-group">
                         <label for="melody-instrument">{"Instrument"}</label>
                         <select id="melody-instrument" onchange={on_instrument_change} value={state.instrument.clone()}>
-                            { for INSTRUMENTS.iter().map(|(name, _)| html! {
+                            { for INSTRUMENTS.iter().map(|(name, _, _)| html! {
                                 <option value={*name} selected={state.instrument == *name}>{name}</option>
                             })}
                         </select>
@@ -715,6 +905,9 @@ pub fn melody_editor(props: &MelodyEditorProps) -> Html {
                     <button type="submit" class="btn-primary">
                         { if is_editing { "Update Melody" } else { "Save Melody" } }
                     </button>
+                    <button type="button" class="btn-secondary" onclick={on_preview} title="Play in the browser">
+                        {"Preview"}
+                    </button>
                     { if is_editing {
                         html! {
                             <button type="button" class="btn-secondary" onclick={on_clear}>
@@ -791,12 +984,219 @@ fn move_scale_step(pitch: &str, steps: i32, _key: &str) -> String {
     format!("{}{}", note_names[new_idx as usize], new_octave)
 }
 
+/// Position of a pitch's letter in C D E F G A B
+fn letter_index(pitch: &str) -> usize {
+    pitch
+        .chars()
+        .next()
+        .and_then(|c| "CDEFGAB".find(c))
+        .unwrap_or(0)
+}
+
 /// Get the default octave for an instrument (bass instruments play lower).
 fn default_octave_for_instrument(instrument: &str) -> u8 {
-    for (name, octave) in INSTRUMENTS {
+    for (name, octave, _) in INSTRUMENTS {
         if *name == instrument {
             return *octave;
         }
     }
     4 // default to middle octave
 }
+
+/// Lowest and highest MIDI pitch an instrument plays well (the piano's
+/// range for instruments not in the table).
+fn instrument_range(instrument: &str) -> (u8, u8) {
+    INSTRUMENTS
+        .iter()
+        .find(|(name, _, _)| *name == instrument)
+        .map_or((21, 108), |(_, _, range)| *range)
+}
+
+/// Name of a MIDI pitch, e.g. 60 -> "C4".
+fn pitch_name(pitch: u8) -> String {
+    const NAMES: [&str; 12] = [
+        "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+    ];
+    format!("{}{}", NAMES[pitch as usize % 12], pitch as i32 / 12 - 1)
+}
+
+/// Warning for a note outside the instrument's range, suggesting how many
+/// octaves to shift it (None for rests and notes in range).
+fn range_hint(note: &MelodyNote, instrument: &str) -> Option<String> {
+    let pitch = midi_cli_rs::Note::parse_pitch(&note.pitch).ok()?;
+    let (low, high) = instrument_range(instrument);
+    let (direction, octaves, bound) = if pitch < low {
+        (
+            "up",
+            (low - pitch).div_ceil(12),
+            format!("lowest is {}", pitch_name(low)),
+        )
+    } else if pitch > high {
+        (
+            "down",
+            (pitch - high).div_ceil(12),
+            format!("highest is {}", pitch_name(high)),
+        )
+    } else {
+        return None;
+    };
+    let plural = if octaves == 1 { "" } else { "s" };
+    Some(format!(
+        "{} is out of range for {} ({}); try {} octave{} {}",
+        note.pitch, instrument, bound, octaves, plural, direction
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(pitches: &[&str]) -> EditorState {
+        let notes = pitches
+            .iter()
+            .map(|p| MelodyNote {
+                pitch: p.to_string(),
+                duration: 1.0,
+                velocity: 80,
+                chord: false,
+            })
+            .collect();
+        EditorState {
+            notes,
+            ..Default::default()
+        }
+    }
+
+    fn pitches(state: &EditorState) -> Vec<&str> {
+        state.notes.iter().map(|n| n.pitch.as_str()).collect()
+    }
+
+    #[test]
+    fn test_octave_shift_applies_to_selection() {
+        let mut state = editor(&["C4", "E4", "G4", "B4"]);
+        state.handle_key("ArrowRight", true, false, 0.0);
+        state.handle_key("ArrowRight", true, false, 0.0);
+        assert_eq!(state.selection, 0..3);
+
+        state.handle_key("+", false, false, 0.0);
+        assert_eq!(pitches(&state), ["C5", "E5", "G5", "B4"]);
+        state.handle_key("z", false, true, 0.0);
+        assert_eq!(pitches(&state), ["C4", "E4", "G4", "B4"]);
+    }
+
+    #[test]
+    fn test_selection_extends_both_ways_from_anchor() {
+        let mut state = editor(&["C4", "D4", "E4", "F4"]);
+        state.select(2);
+        state.handle_key("ArrowLeft", true, false, 0.0);
+        state.handle_key("ArrowLeft", true, false, 0.0);
+        assert_eq!(state.selection, 0..3);
+        assert_eq!(state.cursor(), 0);
+        state.handle_key("ArrowRight", true, false, 0.0);
+        state.handle_key("ArrowRight", true, false, 0.0);
+        state.handle_key("ArrowRight", true, false, 0.0);
+        assert_eq!(state.selection, 2..4);
+
+        // Plain arrows collapse it again
+        state.handle_key("ArrowLeft", false, false, 0.0);
+        assert_eq!(state.selection, 2..3);
+    }
+
+    #[test]
+    fn test_shift_letters_stack_a_chord() {
+        let mut state = editor(&["C4"]);
+        state.handle_key("E", true, false, 0.0);
+        state.handle_key("G", true, false, 0.0);
+        assert_eq!(pitches(&state), ["C4", "E4", "G4"]);
+        let chord: Vec<bool> = state.notes.iter().map(|n| n.chord).collect();
+        assert_eq!(chord, [false, true, true]);
+
+        // A letter at or below the note under it goes up an octave
+        state.handle_key("C", true, false, 0.0);
+        assert_eq!(state.notes[3].pitch, "C5");
+    }
+
+    #[test]
+    fn test_move_note_reorders_and_undoes() {
+        let mut state = editor(&["C4", "D4", "E4", "F4"]);
+        state.move_note(0, 2);
+        assert_eq!(pitches(&state), ["D4", "E4", "C4", "F4"]);
+        assert_eq!(state.selection, 2..3);
+
+        state.move_note(3, 0);
+        assert_eq!(pitches(&state), ["F4", "D4", "E4", "C4"]);
+
+        state.handle_key("z", false, true, 0.0);
+        state.handle_key("z", false, true, 0.0);
+        assert_eq!(pitches(&state), ["C4", "D4", "E4", "F4"]);
+
+        // Dropping a note on itself changes nothing and records no undo
+        state.move_note(1, 1);
+        assert!(state.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn test_rapid_edits_share_an_undo_step() {
+        let mut state = editor(&["C4", "D4"]);
+        for i in 0..10 {
+            state.handle_key("ArrowUp", false, false, i as f64 * 100.0);
+        }
+        assert_eq!(state.notes[0].velocity, 127);
+        assert_eq!(state.undo_stack.len(), 1);
+        state.handle_key("z", false, true, 1000.0);
+        assert_eq!(state.notes[0].velocity, 80);
+
+        // A pause, a different edit, or other notes start a new step
+        state.handle_key("ArrowUp", false, false, 2000.0);
+        state.handle_key("ArrowUp", false, false, 4000.0);
+        state.handle_key("]", false, false, 4100.0);
+        state.select(1);
+        state.handle_key("]", false, false, 4200.0);
+        assert_eq!(state.undo_stack.len(), 4);
+    }
+
+    #[test]
+    fn test_range_hint_flags_notes_outside_instrument() {
+        let note = |pitch: &str| MelodyNote {
+            pitch: pitch.to_string(),
+            ..Default::default()
+        };
+        let low = range_hint(&note("C1"), "tuba").unwrap();
+        assert!(low.contains("try 1 octave up"), "{low}");
+        assert!(low.contains("lowest is D1"), "{low}");
+        assert!(range_hint(&note("C6"), "tuba")
+            .unwrap()
+            .contains("octaves down"));
+        assert_eq!(range_hint(&note("C3"), "tuba"), None);
+        assert_eq!(range_hint(&MelodyNote::rest(1.0), "tuba"), None);
+
+        // Every instrument's default octave starts in range
+        for (name, octave, _) in INSTRUMENTS {
+            assert_eq!(
+                range_hint(&note(&format!("C{octave}")), name),
+                None,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_velocity_and_delete_on_selection() {
+        let mut state = editor(&["C4", "D4", "E4", "F4"]);
+        state.select(1);
+        state.select_to(2);
+        state.handle_key("ArrowUp", false, false, 0.0);
+        let velocities: Vec<u8> = state.notes.iter().map(|n| n.velocity).collect();
+        assert_eq!(velocities, [80, 90, 90, 80]);
+
+        state.handle_key("Delete", false, false, 0.0);
+        assert_eq!(pitches(&state), ["C4", "F4"]);
+        assert_eq!(state.selection, 1..2);
+
+        // The last notes can't all be deleted
+        state.select(0);
+        state.select_to(1);
+        assert!(state.handle_key("Delete", false, false, 0.0));
+        assert_eq!(state.notes.len(), 2);
+    }
+}
@@ -1,7 +1,7 @@
 //! Melody list component showing saved melodies.
 
-use crate::api::SavedMelody;
-use crate::components::AudioPlayer;
+use crate::api::{MelodyNote, SavedMelody};
+use crate::components::{melody_command, AudioPlayer, CliCommand};
 use yew::prelude::*;
 
 #[derive(Properties, PartialEq)]
@@ -9,9 +9,16 @@ pub struct MelodyListProps {
     pub melodies: Vec<SavedMelody>,
     pub on_edit: Callback<SavedMelody>,
     pub on_delete: Callback<String>,
+    pub on_duplicate: Callback<String>,
+    /// Download the MIDI file for an id.
+    pub on_download: Callback<String>,
     pub on_generate: Callback<String>,
     pub on_export_abc: Callback<String>,
+    /// Play the notes in the browser at a tempo
+    pub on_preview: Callback<(Vec<MelodyNote>, u16)>,
     pub generating: Option<String>,
+    #[prop_or_default]
+    pub generate_blocked: Option<String>,
     pub audio_urls: std::collections::HashMap<String, String>,
 }
 
@@ -50,6 +57,18 @@ pub fn melody_list(props: &MelodyListProps) -> Html {
                         Callback::from(move |_| on_delete.emit(id.clone()))
                     };
 
+                    let on_duplicate = {
+                        let on_duplicate = props.on_duplicate.clone();
+                        let id = melody_id.clone();
+                        Callback::from(move |_| on_duplicate.emit(id.clone()))
+                    };
+
+                    let on_download = {
+                        let on_download = props.on_download.clone();
+                        let id = melody_id.clone();
+                        Callback::from(move |_| on_download.emit(id.clone()))
+                    };
+
                     let on_generate = {
                         let on_generate = props.on_generate.clone();
                         let id = melody_id.clone();
@@ -62,12 +81,22 @@ pub fn melody_list(props: &MelodyListProps) -> Html {
                         Callback::from(move |_| on_export_abc.emit(id.clone()))
                     };
 
+                    let on_preview = {
+                        let on_preview = props.on_preview.clone();
+                        let (notes, tempo) = (melody.notes.clone(), melody.tempo);
+                        Callback::from(move |_| on_preview.emit((notes.clone(), tempo)))
+                    };
+
                     let note_preview: String = melody.notes
                         .iter()
                         .take(8)
-                        .map(|n| if n.pitch == "rest" { "-".to_string() } else { n.pitch.clone() })
-                        .collect::<Vec<_>>()
-                        .join(" ");
+                        .map(|n| {
+                            let pitch = if n.pitch == "rest" { "-" } else { n.pitch.as_str() };
+                            format!("{}{}", if n.chord { "+" } else { " " }, pitch)
+                        })
+                        .collect::<String>()
+                        .trim_start()
+                        .to_string();
                     let note_count = melody.notes.len();
 
                     html! {
@@ -83,11 +112,13 @@ pub fn melody_list(props: &MelodyListProps) -> Html {
                                 {note_preview}
                                 { if note_count > 8 { "..." } else { "" } }
                             </div>
+                            <CliCommand command={melody_command(melody)} />
                             <div class="preset-item-actions">
                                 <button
                                     class="btn-primary btn-small"
                                     onclick={on_generate}
-                                    disabled={is_generating}
+                                    disabled={is_generating || props.generate_blocked.is_some()}
+                                    title={props.generate_blocked.clone()}
                                 >
                                     { if is_generating {
                                         html! { <span class="loading"></span> }
@@ -95,9 +126,18 @@ pub fn melody_list(props: &MelodyListProps) -> Html {
                                         html! { "Generate" }
                                     }}
                                 </button>
+                                <button class="btn-secondary btn-small" onclick={on_preview} title="Play in the browser">
+                                    {"Preview"}
+                                </button>
                                 <button class="btn-secondary btn-small" onclick={on_edit}>
                                     {"Edit"}
                                 </button>
+                                <button class="btn-secondary btn-small" onclick={on_duplicate} title="Save a copy">
+                                    {"Duplicate"}
+                                </button>
+                                <button class="btn-secondary btn-small" onclick={on_download} title="Download the MIDI file">
+                                    {"MIDI"}
+                                </button>
                                 <button class="btn-secondary btn-small" onclick={on_export_abc} title="Export as ABC notation">
                                     {"ABC"}
                                 </button>
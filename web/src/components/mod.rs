@@ -2,16 +2,20 @@
 
 mod abc_import;
 mod audio_player;
+mod cli_command;
 mod melody_editor;
 mod melody_list;
+mod piano_roll;
 mod plugin_manager;
 mod preset_editor;
 mod preset_list;
 
 pub use abc_import::AbcImport;
 pub use audio_player::AudioPlayer;
+pub use cli_command::{melody_command, preset_command, CliCommand};
 pub use melody_editor::MelodyEditor;
 pub use melody_list::MelodyList;
+pub use piano_roll::PianoRoll;
 pub use plugin_manager::PluginManager;
 pub use preset_editor::PresetEditor;
 pub use preset_list::PresetList;
@@ -0,0 +1,189 @@
+//! Piano-roll view of a melody: one rectangle per note, placed by pitch and time.
+
+use crate::api::{note_offsets, MelodyNote};
+use midi_cli_rs::Note;
+use std::ops::Range;
+use yew::prelude::*;
+
+/// Width of one beat in pixels
+const BEAT_WIDTH: f64 = 40.0;
+
+/// Height of one semitone row in pixels
+const ROW_HEIGHT: f64 = 8.0;
+
+/// Rows shown above the highest and below the lowest note
+const PITCH_MARGIN: u8 = 2;
+
+#[derive(Properties, PartialEq)]
+pub struct PianoRollProps {
+    pub notes: Vec<MelodyNote>,
+    pub selection: Range<usize>,
+    /// Note index clicked, and whether Shift was held
+    pub on_select: Callback<(usize, bool)>,
+}
+
+/// A note's rectangle in the roll
+#[derive(Clone, Debug, PartialEq)]
+struct NoteRect {
+    /// Index into the melody's notes
+    index: usize,
+    x: f64,
+    y: f64,
+    width: f64,
+    velocity: u8,
+}
+
+/// Laid-out notes and the size of the roll
+#[derive(Debug, PartialEq)]
+struct Layout {
+    rects: Vec<NoteRect>,
+    width: f64,
+    height: f64,
+}
+
+/// Place every sounding note; rests and unreadable pitches leave a gap
+fn layout(notes: &[MelodyNote]) -> Layout {
+    let offsets = note_offsets(notes);
+    let pitched: Vec<(usize, u8)> = notes
+        .iter()
+        .enumerate()
+        .filter(|(_, note)| !note.is_rest())
+        .filter_map(|(i, note)| Note::parse_pitch(&note.pitch).ok().map(|pitch| (i, pitch)))
+        .collect();
+
+    let low = pitched
+        .iter()
+        .map(|&(_, p)| p)
+        .min()
+        .unwrap_or(60)
+        .saturating_sub(PITCH_MARGIN);
+    let high = pitched
+        .iter()
+        .map(|&(_, p)| p)
+        .max()
+        .unwrap_or(72)
+        .saturating_add(PITCH_MARGIN)
+        .min(127);
+    let beats = notes
+        .iter()
+        .zip(&offsets)
+        .map(|(n, o)| o + n.duration)
+        .fold(0.0, f64::max);
+
+    let rects = pitched
+        .into_iter()
+        .map(|(index, pitch)| NoteRect {
+            index,
+            x: offsets[index] * BEAT_WIDTH,
+            y: (high - pitch) as f64 * ROW_HEIGHT,
+            width: notes[index].duration * BEAT_WIDTH,
+            velocity: notes[index].velocity,
+        })
+        .collect();
+
+    Layout {
+        rects,
+        width: beats.max(1.0) * BEAT_WIDTH,
+        height: (high - low + 1) as f64 * ROW_HEIGHT,
+    }
+}
+
+/// Quiet notes are blue, loud ones red
+fn velocity_color(velocity: u8) -> String {
+    let hue = 220.0 - velocity.min(127) as f64 / 127.0 * 220.0;
+    format!("hsl({:.0}, 70%, 55%)", hue)
+}
+
+#[function_component(PianoRoll)]
+pub fn piano_roll(props: &PianoRollProps) -> Html {
+    let Layout {
+        rects,
+        width,
+        height,
+    } = layout(&props.notes);
+
+    html! {
+        <div class="piano-roll">
+            <svg width={width.to_string()} height={height.to_string()}>
+                { for (0..(height / ROW_HEIGHT) as usize).map(|row| html! {
+                    <rect
+                        class="piano-roll-row"
+                        x="0"
+                        y={(row as f64 * ROW_HEIGHT).to_string()}
+                        width={width.to_string()}
+                        height={ROW_HEIGHT.to_string()}
+                    />
+                })}
+                { for rects.into_iter().map(|rect| {
+                    let index = rect.index;
+                    let onclick = props.on_select.reform(move |e: MouseEvent| (index, e.shift_key()));
+                    let class = classes!("piano-roll-note", props.selection.contains(&index).then_some("selected"));
+                    html! {
+                        <rect
+                            class={class}
+                            x={rect.x.to_string()}
+                            y={rect.y.to_string()}
+                            width={(rect.width - 1.0).max(1.0).to_string()}
+                            height={ROW_HEIGHT.to_string()}
+                            fill={velocity_color(rect.velocity)}
+                            {onclick}
+                        >
+                            <title>{ format!("{} (velocity {})", props.notes[index].pitch, rect.velocity) }</title>
+                        </rect>
+                    }
+                })}
+            </svg>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(pitch: &str, duration: f64, chord: bool) -> MelodyNote {
+        MelodyNote {
+            pitch: pitch.to_string(),
+            duration,
+            velocity: 80,
+            chord,
+        }
+    }
+
+    #[test]
+    fn test_one_rect_per_sounding_note() {
+        let notes = [
+            note("C4", 1.0, false),
+            note("rest", 1.0, false),
+            note("E4", 2.0, false),
+            note("G4", 2.0, true),
+        ];
+        let Layout {
+            rects,
+            width,
+            height,
+        } = layout(&notes);
+        assert_eq!(rects.len(), 3);
+        assert_eq!(rects.iter().map(|r| r.index).collect::<Vec<_>>(), [0, 2, 3]);
+
+        // Time runs along x; the chord note shares E4's offset
+        assert_eq!(
+            (rects[0].x, rects[1].x, rects[2].x),
+            (0.0, 2.0 * BEAT_WIDTH, 2.0 * BEAT_WIDTH)
+        );
+        assert_eq!(rects[1].width, 2.0 * BEAT_WIDTH);
+        assert_eq!(width, 4.0 * BEAT_WIDTH);
+
+        // Higher pitches sit higher up; C4 to G4 plus the margins is 12 rows
+        assert!(rects[2].y < rects[1].y && rects[1].y < rects[0].y);
+        assert_eq!(height, 12.0 * ROW_HEIGHT);
+        assert_eq!(rects[0].y, 9.0 * ROW_HEIGHT);
+    }
+
+    #[test]
+    fn test_empty_melody_has_a_default_size() {
+        let layout = layout(&[note("rest", 1.0, false)]);
+        assert!(layout.rects.is_empty());
+        assert!(layout.width > 0.0 && layout.height > 0.0);
+    }
+}
@@ -1,7 +1,8 @@
 //! Preset list component showing saved presets.
 
 use crate::api::SavedPreset;
-use crate::components::AudioPlayer;
+use crate::components::{preset_command, AudioPlayer, CliCommand};
+use crate::local::is_local_mood;
 use yew::prelude::*;
 
 /// Props for the PresetList component.
@@ -13,10 +14,22 @@ pub struct PresetListProps {
     pub on_edit: Callback<SavedPreset>,
     /// Callback when delete is clicked.
     pub on_delete: Callback<String>,
+    /// Callback when duplicate is clicked.
+    pub on_duplicate: Callback<String>,
+    /// Download the MIDI file for an id.
+    pub on_download: Callback<String>,
     /// Callback when generate is clicked.
     pub on_generate: Callback<String>,
+    /// Callback when preview (in-browser playback) is clicked.
+    pub on_preview: Callback<String>,
     /// ID of preset currently being generated (if any).
     pub generating: Option<String>,
+    /// Current step of that generation (e.g. "Rendering").
+    #[prop_or_default]
+    pub generating_step: Option<String>,
+    /// Reason audio can't be generated on this server (disables Generate).
+    #[prop_or_default]
+    pub generate_blocked: Option<String>,
     /// Map of preset IDs to their generated audio URLs.
     pub audio_urls: std::collections::HashMap<String, String>,
 }
@@ -57,12 +70,31 @@ pub fn preset_list(props: &PresetListProps) -> Html {
                         Callback::from(move |_| on_delete.emit(id.clone()))
                     };
 
+                    let on_duplicate = {
+                        let on_duplicate = props.on_duplicate.clone();
+                        let id = preset_id.clone();
+                        Callback::from(move |_| on_duplicate.emit(id.clone()))
+                    };
+
+                    let on_download = {
+                        let on_download = props.on_download.clone();
+                        let id = preset_id.clone();
+                        Callback::from(move |_| on_download.emit(id.clone()))
+                    };
+
                     let on_generate = {
                         let on_generate = props.on_generate.clone();
                         let id = preset_id.clone();
                         Callback::from(move |_| on_generate.emit(id.clone()))
                     };
 
+                    let on_preview = {
+                        let on_preview = props.on_preview.clone();
+                        let id = preset_id.clone();
+                        Callback::from(move |_| on_preview.emit(id.clone()))
+                    };
+                    let can_preview = is_local_mood(&preset.mood);
+
                     html! {
                         <div class="preset-item" key={preset_id.clone()}>
                             <div class="preset-item-header">
@@ -83,21 +115,46 @@ pub fn preset_list(props: &PresetListProps) -> Html {
                                     html! {}
                                 }}
                             </div>
+                            <CliCommand command={preset_command(preset)} />
                             <div class="preset-item-actions">
                                 <button
                                     class="btn-primary btn-small"
                                     onclick={on_generate}
-                                    disabled={is_generating}
+                                    disabled={is_generating || props.generate_blocked.is_some()}
+                                    title={props.generate_blocked.clone()}
                                 >
                                     { if is_generating {
-                                        html! { <span class="loading"></span> }
+                                        html! {
+                                            <>
+                                                <span class="loading"></span>
+                                                { props.generating_step.clone().unwrap_or_default() }
+                                            </>
+                                        }
                                     } else {
                                         html! { "Generate" }
                                     }}
                                 </button>
+                                <button
+                                    class="btn-secondary btn-small"
+                                    onclick={on_preview}
+                                    disabled={!can_preview}
+                                    title={if can_preview {
+                                        "Play a quick sketch in the browser"
+                                    } else {
+                                        "Plugin moods can only be generated on the server"
+                                    }}
+                                >
+                                    {"Preview"}
+                                </button>
                                 <button class="btn-secondary btn-small" onclick={on_edit}>
                                     {"Edit"}
                                 </button>
+                                <button class="btn-secondary btn-small" onclick={on_duplicate} title="Save a copy">
+                                    {"Duplicate"}
+                                </button>
+                                <button class="btn-secondary btn-small" onclick={on_download} title="Download the MIDI file">
+                                    {"MIDI"}
+                                </button>
                                 <button class="btn-danger btn-small" onclick={on_delete}>
                                     {"Delete"}
                                 </button>
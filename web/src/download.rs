@@ -0,0 +1,88 @@
+//! Saving fetched files through the browser's download prompt.
+
+use gloo_file::{Blob, ObjectUrl};
+use wasm_bindgen::{JsCast, JsValue};
+
+/// MIME type of Standard MIDI Files.
+pub const MIDI_MIME: &str = "audio/midi";
+
+/// A file ready to hand to the browser.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Download {
+    pub file_name: String,
+    pub mime: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+/// File name for a preset or melody, matching the server's
+/// Content-Disposition naming (anything but letters, digits and '-' becomes '_').
+pub fn midi_file_name(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.mid", if stem.is_empty() { "untitled" } else { &stem })
+}
+
+/// Turn fetched MIDI bytes into a download named after `name`.
+pub fn midi_download(fetched: Result<Vec<u8>, String>, name: &str) -> Result<Download, String> {
+    let bytes = fetched?;
+    if !bytes.starts_with(b"MThd") {
+        return Err("Server did not return a MIDI file".to_string());
+    }
+    Ok(Download {
+        file_name: midi_file_name(name),
+        mime: MIDI_MIME,
+        bytes,
+    })
+}
+
+/// Save a download by clicking a temporary link to a blob URL.
+pub fn save(download: &Download) -> Result<(), JsValue> {
+    let blob = Blob::new_with_options(download.bytes.as_slice(), Some(download.mime));
+    let url = ObjectUrl::from(blob);
+
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| JsValue::from_str("no document"))?;
+    let link: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    link.set_href(&url);
+    link.set_download(&download.file_name);
+    link.click();
+    // The object URL is revoked when `url` drops; the click has already started the download
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What a successful fetch of /api/presets/:id/midi returns
+    fn fetched_midi() -> Result<Vec<u8>, String> {
+        let mut bytes = b"MThd".to_vec();
+        bytes.extend_from_slice(&[0, 0, 0, 6, 0, 1, 0, 1, 1, 224]);
+        Ok(bytes)
+    }
+
+    #[test]
+    fn test_fetched_midi_becomes_named_blob() {
+        let download = midi_download(fetched_midi(), "Dark Intro #2").unwrap();
+        assert_eq!(download.file_name, "Dark_Intro__2.mid");
+        assert_eq!(download.mime, "audio/midi");
+        assert_eq!(Ok(download.bytes), fetched_midi());
+    }
+
+    #[test]
+    fn test_failed_or_wrong_fetch_is_an_error() {
+        let failed = midi_download(Err("Failed to download MIDI: HTTP 404".to_string()), "x");
+        assert_eq!(failed.unwrap_err(), "Failed to download MIDI: HTTP 404");
+        assert!(midi_download(Ok(b"<html>".to_vec()), "x").is_err());
+        assert_eq!(midi_file_name(""), "untitled.mid");
+    }
+}
@@ -0,0 +1,77 @@
+//! In-browser generation of built-in mood presets.
+//!
+//! The core `midi` and `preset` modules are pure Rust and compile to WASM,
+//! so presets with a built-in mood can be generated and previewed without a
+//! server round-trip. Plugin moods still need the server.
+
+use crate::api::SavedPreset;
+use midi_cli_rs::{generate_mood, Key, Mood, NoteSequence, PresetConfig};
+
+/// Whether a mood can be generated in the browser.
+pub fn is_local_mood(mood: &str) -> bool {
+    Mood::parse(mood).is_some()
+}
+
+/// Generate the note sequences for a preset with a built-in mood.
+/// A seed of 0 or below picks a random seed, as on the command line.
+pub fn preset_sequences(preset: &SavedPreset) -> Result<Vec<NoteSequence>, String> {
+    let mood = Mood::parse(&preset.mood).ok_or_else(|| {
+        format!(
+            "'{}' is a plugin mood; generate it on the server",
+            preset.mood
+        )
+    })?;
+    let seed = if preset.seed <= 0 {
+        (js_sys::Date::now() / 1000.0) as u64
+    } else {
+        preset.seed as u64
+    };
+
+    let config = PresetConfig {
+        duration_secs: preset.duration,
+        key: preset
+            .key
+            .as_deref()
+            .and_then(Key::parse)
+            .unwrap_or_else(|| mood.default_key()),
+        intensity: preset.intensity.min(100),
+        seed,
+        tempo: preset.tempo,
+        ..Default::default()
+    };
+    Ok(generate_mood(mood, &config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use midi_cli_rs::midi_bytes;
+
+    fn preset(mood: &str) -> SavedPreset {
+        SavedPreset {
+            id: "p1".to_string(),
+            name: "Intro".to_string(),
+            mood: mood.to_string(),
+            duration: 5.0,
+            key: Some("Am".to_string()),
+            intensity: 60,
+            tempo: 100,
+            seed: 42,
+            created_at: String::new(),
+            last_generated: None,
+        }
+    }
+
+    #[test]
+    fn test_preset_midi_in_memory() {
+        let bytes = midi_bytes(&preset_sequences(&preset("jazz")).unwrap()).unwrap();
+        assert!(bytes.starts_with(b"MThd"));
+        assert!(bytes.len() > 100);
+    }
+
+    #[test]
+    fn test_plugin_mood_needs_server() {
+        assert!(!is_local_mood("euclidean"));
+        assert!(preset_sequences(&preset("euclidean")).is_err());
+    }
+}
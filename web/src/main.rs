@@ -2,12 +2,18 @@
 
 mod api;
 mod components;
+mod download;
+mod local;
+mod synth;
 
 mod version_info {
     include!(concat!(env!("OUT_DIR"), "/version_info.rs"));
 }
 
-use api::{AbcImportRequest, ApiClient, MelodyRequest, MoodPackInfo, PresetRequest, SavedMelody, SavedPreset};
+use api::{
+    AbcImportRequest, ApiClient, GenerationEvent, HealthInfo, MelodyNote, MelodyRequest,
+    MoodPackInfo, PresetListQuery, PresetRequest, SavedMelody, SavedPreset,
+};
 use components::{AbcImport, MelodyEditor, MelodyList, PluginManager, PresetEditor, PresetList};
 use std::collections::HashMap;
 use wasm_bindgen_futures::spawn_local;
@@ -27,7 +33,10 @@ struct AppState {
     presets: Vec<SavedPreset>,
     editing_preset: Option<SavedPreset>,
     generating_preset: Option<String>,
+    generation_step: Option<String>,
     preset_audio_urls: HashMap<String, String>,
+    /// In-browser preview currently playing (closed to stop it)
+    preview: Option<web_sys::AudioContext>,
     // Melodies
     melodies: Vec<SavedMelody>,
     editing_melody: Option<SavedMelody>,
@@ -41,6 +50,7 @@ struct AppState {
     plugins: Vec<MoodPackInfo>,
     plugins_loading: bool,
     // Common
+    health: Option<HealthInfo>,
     error: Option<String>,
     loading: bool,
 }
@@ -67,7 +77,12 @@ enum Msg {
     PresetSaved(SavedPreset),
     DeletePreset(String),
     PresetDeleted(String),
+    DuplicatePreset(String),
+    DownloadPresetMidi(String),
     GeneratePresetAudio(String),
+    PresetGenerationProgress(String),
+    PreviewPreset(String),
+    PreviewMelody(Vec<MelodyNote>, u16),
     PresetGenerationComplete(String, String),
     // Melodies
     LoadMelodies,
@@ -78,6 +93,8 @@ enum Msg {
     MelodySaved(SavedMelody),
     DeleteMelody(String),
     MelodyDeleted(String),
+    DuplicateMelody(String),
+    DownloadMelodyMidi(String),
     GenerateMelodyAudio(String),
     MelodyGenerationComplete(String, String),
     // ABC Import/Export
@@ -93,6 +110,8 @@ enum Msg {
     DeletePlugin(String),
     PluginDeleted(String),
     // Common
+    LoadHealth,
+    HealthLoaded(HealthInfo),
     Error(String),
     ClearError,
 }
@@ -109,6 +128,7 @@ impl Component for App {
         ctx.link().send_message(Msg::LoadPresets);
         ctx.link().send_message(Msg::LoadMelodies);
         ctx.link().send_message(Msg::LoadPlugins);
+        ctx.link().send_message(Msg::LoadHealth);
         Self {
             state: AppState::new(),
         }
@@ -125,8 +145,8 @@ impl Component for App {
             Msg::LoadPresets => {
                 let link = ctx.link().clone();
                 spawn_local(async move {
-                    match ApiClient::list_presets().await {
-                        Ok(presets) => link.send_message(Msg::PresetsLoaded(presets)),
+                    match ApiClient::list_presets(&PresetListQuery::default()).await {
+                        Ok(page) => link.send_message(Msg::PresetsLoaded(page.items)),
                         Err(e) => link.send_message(Msg::Error(e)),
                     }
                 });
@@ -187,24 +207,85 @@ impl Component for App {
                 }
                 true
             }
+            Msg::DuplicatePreset(id) => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    match ApiClient::duplicate_preset(&id).await {
+                        Ok(_) => link.send_message(Msg::LoadPresets),
+                        Err(e) => link.send_message(Msg::Error(e)),
+                    }
+                });
+                false
+            }
             Msg::GeneratePresetAudio(id) => {
                 self.state.generating_preset = Some(id.clone());
+                self.state.generation_step = None;
                 let link = ctx.link().clone();
-                spawn_local(async move {
-                    match ApiClient::generate_preset_audio(&id).await {
-                        Ok(response) => {
-                            link.send_message(Msg::PresetGenerationComplete(id, response.audio_url))
-                        }
-                        Err(e) => {
-                            link.send_message(Msg::Error(e));
-                            link.send_message(Msg::PresetGenerationComplete(id, String::new()));
-                        }
+                let preset_id = id.clone();
+                let result = ApiClient::stream_preset_generation(&id, move |event| match event {
+                    GenerationEvent::Started { .. } => {}
+                    GenerationEvent::MidiWritten => {
+                        link.send_message(Msg::PresetGenerationProgress("MIDI written".to_string()))
+                    }
+                    GenerationEvent::Rendering => {
+                        link.send_message(Msg::PresetGenerationProgress("Rendering".to_string()))
+                    }
+                    GenerationEvent::Done { audio_url, .. } => link
+                        .send_message(Msg::PresetGenerationComplete(preset_id.clone(), audio_url)),
+                    GenerationEvent::Failed { error } => {
+                        link.send_message(Msg::Error(error));
+                        link.send_message(Msg::PresetGenerationComplete(
+                            preset_id.clone(),
+                            String::new(),
+                        ));
                     }
                 });
+                if let Err(e) = result {
+                    ctx.link().send_message(Msg::Error(e));
+                    ctx.link()
+                        .send_message(Msg::PresetGenerationComplete(id, String::new()));
+                }
+                true
+            }
+            Msg::PreviewPreset(id) => {
+                if let Some(previous) = self.state.preview.take() {
+                    let _ = previous.close();
+                }
+                let Some(preset) = self.state.presets.iter().find(|p| p.id == id) else {
+                    return false;
+                };
+                match local::preset_sequences(preset) {
+                    Ok(sequences) => match synth::play(&synth::sequence_tones(&sequences)) {
+                        Ok(ctx) => self.state.preview = Some(ctx),
+                        Err(_) => {
+                            self.state.error =
+                                Some("Web Audio is not available in this browser".to_string())
+                        }
+                    },
+                    Err(e) => self.state.error = Some(e),
+                }
+                true
+            }
+            Msg::PreviewMelody(notes, tempo) => {
+                if let Some(previous) = self.state.preview.take() {
+                    let _ = previous.close();
+                }
+                match synth::play(&synth::melody_tones(&notes, tempo)) {
+                    Ok(ctx) => self.state.preview = Some(ctx),
+                    Err(_) => {
+                        self.state.error =
+                            Some("Web Audio is not available in this browser".to_string())
+                    }
+                }
+                true
+            }
+            Msg::PresetGenerationProgress(step) => {
+                self.state.generation_step = Some(step);
                 true
             }
             Msg::PresetGenerationComplete(id, audio_url) => {
                 self.state.generating_preset = None;
+                self.state.generation_step = None;
                 if !audio_url.is_empty() {
                     self.state.preset_audio_urls.insert(id, audio_url);
                 }
@@ -276,6 +357,16 @@ impl Component for App {
                 }
                 true
             }
+            Msg::DuplicateMelody(id) => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    match ApiClient::duplicate_melody(&id).await {
+                        Ok(_) => link.send_message(Msg::LoadMelodies),
+                        Err(e) => link.send_message(Msg::Error(e)),
+                    }
+                });
+                false
+            }
             Msg::GenerateMelodyAudio(id) => {
                 self.state.generating_melody = Some(id.clone());
                 let link = ctx.link().clone();
@@ -336,6 +427,28 @@ impl Component for App {
                 self.state.abc_import_error = Some(error);
                 true
             }
+            Msg::DownloadPresetMidi(id) => {
+                let name = self
+                    .state
+                    .presets
+                    .iter()
+                    .find(|p| p.id == id)
+                    .map(|p| p.name.clone());
+                let name = name.unwrap_or_else(|| id.clone());
+                Self::download_midi(ctx, name, async move { ApiClient::preset_midi(&id).await });
+                false
+            }
+            Msg::DownloadMelodyMidi(id) => {
+                let name = self
+                    .state
+                    .melodies
+                    .iter()
+                    .find(|m| m.id == id)
+                    .map(|m| m.name.clone());
+                let name = name.unwrap_or_else(|| id.clone());
+                Self::download_midi(ctx, name, async move { ApiClient::melody_midi(&id).await });
+                false
+            }
             Msg::ExportMelodyAbc(id) => {
                 let link = ctx.link().clone();
                 spawn_local(async move {
@@ -404,6 +517,20 @@ impl Component for App {
             }
 
             // Common handlers
+            Msg::LoadHealth => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    // An older server without /api/health just leaves generation enabled
+                    if let Ok(health) = ApiClient::health().await {
+                        link.send_message(Msg::HealthLoaded(health));
+                    }
+                });
+                false
+            }
+            Msg::HealthLoaded(health) => {
+                self.state.health = Some(health);
+                true
+            }
             Msg::Error(error) => {
                 self.state.error = Some(error);
                 self.state.loading = false;
@@ -473,6 +600,12 @@ impl Component for App {
                         html! {}
                     }}
 
+                    { if let Some(blocker) = self.generate_blocker() {
+                        html! { <div class="warning-message">{format!("Audio generation unavailable: {}", blocker)}</div> }
+                    } else {
+                        html! {}
+                    }}
+
                     { match active_tab {
                         Tab::Presets => self.view_presets_tab(ctx),
                         Tab::Melodies => self.view_melodies_tab(ctx),
@@ -506,12 +639,39 @@ impl Component for App {
 }
 
 impl App {
+    fn generate_blocker(&self) -> Option<String> {
+        self.state
+            .health
+            .as_ref()
+            .and_then(HealthInfo::generate_blocker)
+    }
+
+    /// Fetch a MIDI file and hand it to the browser as a download
+    fn download_midi(
+        ctx: &Context<Self>,
+        name: String,
+        fetch: impl std::future::Future<Output = Result<Vec<u8>, String>> + 'static,
+    ) {
+        let link = ctx.link().clone();
+        spawn_local(async move {
+            let result = download::midi_download(fetch.await, &name).and_then(|file| {
+                download::save(&file).map_err(|_| "Could not start the download".to_string())
+            });
+            if let Err(e) = result {
+                link.send_message(Msg::Error(e));
+            }
+        });
+    }
+
     fn view_presets_tab(&self, ctx: &Context<Self>) -> Html {
         let on_save = ctx.link().callback(Msg::SavePreset);
         let on_clear = ctx.link().callback(|_| Msg::ClearPresetEditor);
         let on_edit = ctx.link().callback(Msg::EditPreset);
         let on_delete = ctx.link().callback(Msg::DeletePreset);
+        let on_duplicate = ctx.link().callback(Msg::DuplicatePreset);
+        let on_download = ctx.link().callback(Msg::DownloadPresetMidi);
         let on_generate = ctx.link().callback(Msg::GeneratePresetAudio);
+        let on_preview = ctx.link().callback(Msg::PreviewPreset);
 
         html! {
             <main class="main-content">
@@ -524,8 +684,13 @@ impl App {
                     presets={self.state.presets.clone()}
                     on_edit={on_edit}
                     on_delete={on_delete}
+                    on_duplicate={on_duplicate}
+                    on_download={on_download}
                     on_generate={on_generate}
+                    on_preview={on_preview}
                     generating={self.state.generating_preset.clone()}
+                    generating_step={self.state.generation_step.clone()}
+                    generate_blocked={self.generate_blocker()}
                     audio_urls={self.state.preset_audio_urls.clone()}
                 />
             </main>
@@ -537,9 +702,14 @@ impl App {
         let on_clear = ctx.link().callback(|_| Msg::ClearMelodyEditor);
         let on_edit = ctx.link().callback(Msg::EditMelody);
         let on_delete = ctx.link().callback(Msg::DeleteMelody);
+        let on_duplicate = ctx.link().callback(Msg::DuplicateMelody);
+        let on_download = ctx.link().callback(Msg::DownloadMelodyMidi);
         let on_generate = ctx.link().callback(Msg::GenerateMelodyAudio);
         let on_export_abc = ctx.link().callback(Msg::ExportMelodyAbc);
         let on_abc_import = ctx.link().callback(Msg::ImportAbcMelody);
+        let on_preview = ctx
+            .link()
+            .callback(|(notes, tempo)| Msg::PreviewMelody(notes, tempo));
 
         html! {
             <main class="main-content">
@@ -553,14 +723,19 @@ impl App {
                     on_save={on_save}
                     editing={self.state.editing_melody.clone()}
                     on_clear={on_clear}
+                    on_preview={on_preview.clone()}
                 />
                 <MelodyList
                     melodies={self.state.melodies.clone()}
                     on_edit={on_edit}
                     on_delete={on_delete}
+                    on_duplicate={on_duplicate}
+                    on_download={on_download}
                     on_generate={on_generate}
                     on_export_abc={on_export_abc}
+                    on_preview={on_preview}
                     generating={self.state.generating_melody.clone()}
+                    generate_blocked={self.generate_blocker()}
                     audio_urls={self.state.melody_audio_urls.clone()}
                 />
             </main>
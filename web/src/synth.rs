@@ -0,0 +1,181 @@
+//! Minimal Web Audio synth for previewing note sequences in the browser.
+//!
+//! Each note becomes a triangle oscillator with a short attack and release,
+//! scheduled up front on an `AudioContext`. It is a sketch of the arrangement,
+//! not a replacement for the SoundFont render; drum tracks are skipped.
+
+use crate::api::{note_offsets, MelodyNote};
+use midi_cli_rs::{Note, NoteSequence, DRUM_CHANNEL};
+use wasm_bindgen::JsValue;
+use web_sys::{AudioContext, OscillatorType};
+
+/// Overall output level, leaving headroom for dense chords.
+const MASTER_GAIN: f32 = 0.15;
+
+/// Fade-in and fade-out time per note, in seconds (avoids clicks).
+const RAMP_SECS: f64 = 0.01;
+
+/// Length of the blip played for a note being edited, in seconds.
+const BLIP_SECS: f64 = 0.2;
+
+/// A note placed on the audio timeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tone {
+    /// Start time in seconds from the beginning of playback.
+    pub start: f64,
+    /// Length in seconds.
+    pub duration: f64,
+    /// Frequency in Hz.
+    pub frequency: f64,
+    /// Peak gain 0.0-1.0, from the note velocity.
+    pub gain: f32,
+}
+
+/// Frequency of a MIDI pitch in equal temperament (A4 = 69 = 440 Hz).
+pub fn midi_to_frequency(pitch: u8) -> f64 {
+    440.0 * 2f64.powf((pitch as f64 - 69.0) / 12.0)
+}
+
+/// Frequency of a pitch name like "C4" or "F#3"; None for rests and
+/// unreadable names.
+pub fn pitch_frequency(pitch: &str) -> Option<f64> {
+    if pitch.eq_ignore_ascii_case("rest") {
+        return None;
+    }
+    Note::parse_pitch(pitch).ok().map(midi_to_frequency)
+}
+
+/// A short tone at `pitch`, for hearing a note while editing it.
+pub fn blip(pitch: &str) -> Option<Tone> {
+    Some(Tone {
+        start: 0.0,
+        duration: BLIP_SECS,
+        frequency: pitch_frequency(pitch)?,
+        gain: 0.8,
+    })
+}
+
+/// Convert sequences to tones, following each sequence's tempo changes.
+pub fn sequence_tones(sequences: &[NoteSequence]) -> Vec<Tone> {
+    let mut tones: Vec<Tone> = sequences
+        .iter()
+        .filter(|seq| seq.channel != DRUM_CHANNEL)
+        .flat_map(|seq| {
+            seq.notes.iter().map(move |note| {
+                let start = seq.beats_to_seconds(note.offset);
+                Tone {
+                    start,
+                    duration: seq.beats_to_seconds(note.offset + note.duration) - start,
+                    frequency: midi_to_frequency(note.pitch),
+                    gain: note.velocity as f32 / 127.0,
+                }
+            })
+        })
+        .collect();
+    tones.sort_by(|a, b| a.start.total_cmp(&b.start));
+    tones
+}
+
+/// Convert editor notes to tones at `tempo` BPM; rests and unreadable
+/// pitches are silent but still take up their time.
+pub fn melody_tones(notes: &[MelodyNote], tempo: u16) -> Vec<Tone> {
+    let secs_per_beat = 60.0 / tempo.max(1) as f64;
+    let mut tones: Vec<Tone> = notes
+        .iter()
+        .zip(note_offsets(notes))
+        .filter(|(note, _)| !note.is_rest())
+        .filter_map(|(note, offset)| {
+            let pitch = Note::parse_pitch(&note.pitch).ok()?;
+            Some(Tone {
+                start: offset * secs_per_beat,
+                duration: note.duration * secs_per_beat,
+                frequency: midi_to_frequency(pitch),
+                gain: note.velocity as f32 / 127.0,
+            })
+        })
+        .collect();
+    tones.sort_by(|a, b| a.start.total_cmp(&b.start));
+    tones
+}
+
+/// Schedule tones on a new audio context and start playing.
+/// Close the returned context to stop playback early.
+pub fn play(tones: &[Tone]) -> Result<AudioContext, JsValue> {
+    let ctx = AudioContext::new()?;
+    let now = ctx.current_time();
+
+    let master = ctx.create_gain()?;
+    master.gain().set_value(MASTER_GAIN);
+    master.connect_with_audio_node(&ctx.destination())?;
+
+    for tone in tones {
+        let start = now + tone.start;
+        let end = start + tone.duration.max(RAMP_SECS);
+
+        let envelope = ctx.create_gain()?;
+        let gain = envelope.gain();
+        gain.set_value_at_time(0.0, start)?;
+        gain.linear_ramp_to_value_at_time(tone.gain, start + RAMP_SECS)?;
+        gain.set_value_at_time(tone.gain, end)?;
+        gain.linear_ramp_to_value_at_time(0.0, end + RAMP_SECS)?;
+        envelope.connect_with_audio_node(&master)?;
+
+        let oscillator = ctx.create_oscillator()?;
+        oscillator.set_type(OscillatorType::Triangle);
+        oscillator.frequency().set_value(tone.frequency as f32);
+        oscillator.connect_with_audio_node(&envelope)?;
+        oscillator.start_with_when(start)?;
+        oscillator.stop_with_when(end + RAMP_SECS)?;
+    }
+
+    Ok(ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(pitch: &str, duration: f64, chord: bool) -> MelodyNote {
+        MelodyNote {
+            pitch: pitch.to_string(),
+            duration,
+            velocity: 127,
+            chord,
+        }
+    }
+
+    #[test]
+    fn test_pitch_frequency() {
+        assert_eq!(pitch_frequency("A4"), Some(440.0));
+        assert_eq!(pitch_frequency("A5"), Some(880.0));
+        let middle_c = pitch_frequency("C4").unwrap();
+        assert!((middle_c - 261.626).abs() < 0.001, "{middle_c}");
+        let f_sharp = pitch_frequency("F#3").unwrap();
+        assert!((f_sharp - 184.997).abs() < 0.001, "{f_sharp}");
+        assert_eq!(pitch_frequency("rest"), None);
+        assert_eq!(pitch_frequency("H9"), None);
+
+        let tone = blip("A4").unwrap();
+        assert_eq!((tone.start, tone.frequency), (0.0, 440.0));
+        assert_eq!(blip("rest"), None);
+    }
+
+    #[test]
+    fn test_melody_tones_follow_tempo() {
+        // At 120 BPM a beat is half a second
+        let notes = [
+            note("A4", 1.0, false),
+            note("rest", 0.5, false),
+            note("C5", 2.0, false),
+            note("E5", 1.0, true),
+        ];
+        let tones = melody_tones(&notes, 120);
+        let times: Vec<(f64, f64)> = tones.iter().map(|t| (t.start, t.duration)).collect();
+        assert_eq!(times, [(0.0, 0.5), (0.75, 1.0), (0.75, 0.5)]);
+        assert_eq!(tones[0].frequency, 440.0);
+        assert_eq!(tones[0].gain, 1.0);
+
+        let slow = melody_tones(&notes, 60);
+        assert_eq!((slow[1].start, slow[1].duration), (1.5, 2.0));
+    }
+}